@@ -16,6 +16,7 @@ fn bench_execute(b: &mut Bencher) {
         asset_id: NATIVE_ASSET_ID.clone(),
         to:       FEE_INLET_ACCOUNT.clone(),
         value:    1u64,
+        memo:     String::new(),
     };
 
     let req = (0..1000).map(|_| TransactionRequest {
@@ -41,6 +42,7 @@ fn bench_execute_with_assets(b: &mut Bencher) {
         asset_id: NATIVE_ASSET_ID.clone(),
         to:       FEE_INLET_ACCOUNT.clone(),
         value:    1u64,
+        memo:     String::new(),
     };
 
     let req = (0..1000).map(|_| TransactionRequest {
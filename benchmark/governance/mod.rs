@@ -84,6 +84,7 @@ impl<A: Assets, SDK: ServiceSDK> GovernanceService<A, SDK> {
             asset_id: NATIVE_ASSET_ID.clone(),
             to:       tx_fee_inlet_address,
             value:    info.tx_failure_fee,
+            memo:     String::new(),
         };
 
         // Pledge the tx failure fee before executed the transaction.
@@ -106,6 +107,7 @@ impl<A: Assets, SDK: ServiceSDK> GovernanceService<A, SDK> {
             asset_id: NATIVE_ASSET_ID.clone(),
             to:       tx_fee_inlet_address,
             value:    1,
+            memo:     String::new(),
         };
 
         let res = self.asset.transfer_(&ctx, payload);
@@ -156,6 +158,7 @@ impl<A: Assets, SDK: ServiceSDK> GovernanceService<A, SDK> {
             asset_id: NATIVE_ASSET_ID.clone(),
             to:       recipient_addr,
             value:    info.miner_benefit,
+            memo:     String::new(),
         };
 
         let _ = self
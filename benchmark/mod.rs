@@ -6,7 +6,7 @@ use std::str::FromStr;
 use std::sync::Arc;
 
 use common_crypto::{Crypto, Secp256k1, Signature};
-use core_mempool::DefaultMemPoolAdapter;
+use core_mempool::{BroadcastMode, DefaultMemPoolAdapter, DEFAULT_BROADCAST_CHANNEL_SIZE};
 use core_network::{NetworkConfig, NetworkService, NetworkServiceHandle};
 use core_storage::{adapter::rocks::RocksAdapter, ImplStorage};
 use framework::binding::state::RocksTrieDB;
@@ -170,6 +170,8 @@ impl BenchmarkAdapter {
             Arc::new(MockServiceMapping {}),
             3000,
             100,
+            DEFAULT_BROADCAST_CHANNEL_SIZE,
+            BroadcastMode::Fixed,
         )
     }
 
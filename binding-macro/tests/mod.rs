@@ -10,8 +10,8 @@ use serde::{Deserialize, Serialize};
 
 use protocol::fixed_codec::FixedCodec;
 use protocol::traits::{
-    ExecutorParams, Service, ServiceResponse, ServiceSDK, StoreArray, StoreBool, StoreMap,
-    StoreString, StoreUint64,
+    ExecutorParams, Savepoint, Service, ServiceResponse, ServiceSDK, StoreArray, StoreBool,
+    StoreMap, StoreString, StoreUint64,
 };
 use protocol::types::{
     Address, Block, Hash, Receipt, ServiceContext, ServiceContextParams, SignedTransaction,
@@ -531,4 +531,12 @@ impl ServiceSDK for MockServiceSDK {
     fn get_receipt_by_hash(&self, _tx_hash: &Hash) -> Option<Receipt> {
         unimplemented!()
     }
+
+    fn stash_savepoint(&mut self, _ctx: &ServiceContext) -> Savepoint {
+        unimplemented!()
+    }
+
+    fn revert_to_savepoint(&mut self, _ctx: &ServiceContext, _savepoint: Savepoint) {
+        unimplemented!()
+    }
 }
@@ -12,12 +12,18 @@ use protocol::try_service_response;
 use protocol::types::{Address, Bytes, Hash, ServiceContext};
 
 use crate::types::{
-    ApproveEvent, ApprovePayload, Asset, AssetBalance, CreateAssetPayload, GetAllowancePayload,
-    GetAllowanceResponse, GetAssetPayload, GetBalancePayload, GetBalanceResponse,
-    InitGenesisPayload, TransferEvent, TransferFromEvent, TransferFromPayload, TransferPayload,
+    ApproveEvent, ApprovePayload, Asset, AssetBalance, CreateAssetPayload, FreezeEvent,
+    FreezePayload, GetAllowancePayload, GetAllowanceResponse, GetAssetPayload, GetBalancePayload,
+    GetBalanceResponse, InitGenesisPayload, TransferEvent, TransferFromEvent, TransferFromPayload,
+    TransferPayload,
 };
 
 pub const ASSET_SERVICE_NAME: &str = "asset";
+pub const MAX_TRANSFER_MEMO_LENGTH: usize = 256;
+const TRANSFER_MEMO_CYCLES_PER_BYTE: u64 = 100;
+/// Error code of a transfer whose sender or recipient is frozen.
+pub const FROZEN_ACCOUNT_CODE: u64 = 109;
+const RLP_ASSET_ID_HEIGHT_KEY: &str = "rlp_asset_id_height";
 
 pub trait Assets {
     fn create_(&mut self, ctx: &ServiceContext, payload: CreateAssetPayload)
@@ -99,6 +105,11 @@ impl<SDK: ServiceSDK> AssetService<SDK> {
 
     #[genesis]
     fn init_genesis(&mut self, payload: InitGenesisPayload) {
+        if let Some(height) = payload.rlp_asset_id_height {
+            self.sdk
+                .set_value(RLP_ASSET_ID_HEIGHT_KEY.to_owned(), height);
+        }
+
         let asset = Asset {
             id:     payload.id,
             name:   payload.name,
@@ -206,14 +217,16 @@ impl<SDK: ServiceSDK> AssetService<SDK> {
         payload: CreateAssetPayload,
     ) -> ServiceResponse<Asset> {
         let caller = ctx.get_caller();
-        let payload_res = serde_json::to_string(&payload);
-
-        if let Err(e) = payload_res {
-            return ServiceResponse::<Asset>::from_error(103, format!("{:?}", e));
-        }
-        let payload_str = payload_res.unwrap();
-
-        let id = Hash::digest(Bytes::from(payload_str + &caller.to_string()));
+        let rlp_asset_id_height = self
+            .sdk
+            .get_value::<_, u64>(&RLP_ASSET_ID_HEIGHT_KEY.to_owned());
+        let id = match rlp_asset_id_height {
+            Some(height) if ctx.get_current_height() >= height => payload.asset_id(&caller),
+            _ => match payload.legacy_asset_id(&caller) {
+                Ok(id) => id,
+                Err(e) => return ServiceResponse::<Asset>::from_error(103, format!("{:?}", e)),
+            },
+        };
 
         if self.assets.contains(&id) {
             return ServiceResponse::<Asset>::from_error(102, "asset id existed".to_owned());
@@ -261,11 +274,30 @@ impl<SDK: ServiceSDK> AssetService<SDK> {
         let asset_id = payload.asset_id.clone();
         let value = payload.value;
         let to = payload.to;
+        let memo = payload.memo;
+
+        if memo.len() > MAX_TRANSFER_MEMO_LENGTH {
+            return ServiceResponse::<()>::from_error(107, "memo too long".to_owned());
+        }
+
+        if !ctx.sub_cycles(memo.len() as u64 * TRANSFER_MEMO_CYCLES_PER_BYTE) {
+            return ServiceResponse::<()>::from_error(
+                3,
+                "consume cycles for memo failed: out of cycles".to_owned(),
+            );
+        }
 
         if !self.assets.contains(&payload.asset_id) {
             return ServiceResponse::<()>::from_error(101, "asset id not existed".to_owned());
         }
 
+        if self.is_frozen(&caller, &asset_id) || self.is_frozen(&to, &asset_id) {
+            return ServiceResponse::<()>::from_error(
+                FROZEN_ACCOUNT_CODE,
+                "account frozen".to_owned(),
+            );
+        }
+
         if let Err(e) = self._transfer(caller.clone(), to.clone(), asset_id.clone(), value) {
             return ServiceResponse::<()>::from_error(106, format!("{:?}", e));
         };
@@ -275,6 +307,7 @@ impl<SDK: ServiceSDK> AssetService<SDK> {
             from: caller,
             to,
             value,
+            memo,
         };
         let event_res = serde_json::to_string(&event);
 
@@ -361,6 +394,13 @@ impl<SDK: ServiceSDK> AssetService<SDK> {
             return ServiceResponse::<()>::from_error(101, "asset id not existed".to_owned());
         }
 
+        if self.is_frozen(&sender, &asset_id) || self.is_frozen(&recipient, &asset_id) {
+            return ServiceResponse::<()>::from_error(
+                FROZEN_ACCOUNT_CODE,
+                "account frozen".to_owned(),
+            );
+        }
+
         let mut sender_asset_balance: AssetBalance = self
             .sdk
             .get_account_value(&sender, &asset_id)
@@ -410,6 +450,51 @@ impl<SDK: ServiceSDK> AssetService<SDK> {
         ServiceResponse::<()>::from_succeed(())
     }
 
+    /// Freeze or unfreeze an account of an asset, a frozen account can neither
+    /// send nor receive it. Only the issuer of the asset can do this.
+    #[cycles(21_000)]
+    #[write]
+    fn freeze(&mut self, ctx: ServiceContext, payload: FreezePayload) -> ServiceResponse<()> {
+        let asset = match self.assets.get(&payload.asset_id) {
+            Some(asset) => asset,
+            None => {
+                return ServiceResponse::<()>::from_error(101, "asset id not existed".to_owned())
+            }
+        };
+
+        if ctx.get_caller() != asset.issuer {
+            return ServiceResponse::<()>::from_error(108, "only issuer can freeze".to_owned());
+        }
+
+        self.sdk
+            .set_account_value(&payload.user, frozen_key(&payload.asset_id), payload.frozen);
+
+        let event = FreezeEvent {
+            asset_id: payload.asset_id,
+            user:     payload.user,
+            frozen:   payload.frozen,
+        };
+        let event_res = serde_json::to_string(&event);
+
+        if let Err(e) = event_res {
+            return ServiceResponse::<()>::from_error(103, format!("{:?}", e));
+        };
+        let event_str = event_res.unwrap();
+        ctx.emit_event(
+            ASSET_SERVICE_NAME.to_owned(),
+            "Freeze".to_owned(),
+            event_str,
+        );
+
+        ServiceResponse::<()>::from_succeed(())
+    }
+
+    fn is_frozen(&self, user: &Address, asset_id: &Hash) -> bool {
+        self.sdk
+            .get_account_value(user, &frozen_key(asset_id))
+            .unwrap_or(false)
+    }
+
     fn _transfer(
         &mut self,
         sender: Address,
@@ -462,3 +547,11 @@ impl<SDK: ServiceSDK> AssetService<SDK> {
         Ok(())
     }
 }
+
+// Frozen flag is saved beside the balance in account, under a key derived
+// from asset id.
+fn frozen_key(asset_id: &Hash) -> Hash {
+    let mut key = b"frozen".to_vec();
+    key.extend_from_slice(asset_id.as_bytes().as_ref());
+    Hash::digest(Bytes::from(key))
+}
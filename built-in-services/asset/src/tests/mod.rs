@@ -10,16 +10,16 @@ use framework::binding::sdk::{DefaultChainQuerier, DefaultServiceSDK};
 use framework::binding::state::{GeneralServiceState, MPTTrie};
 use protocol::traits::{CommonStorage, Context, Storage};
 use protocol::types::{
-    Address, Block, BlockHeader, Hash, Proof, Receipt, ServiceContext, ServiceContextParams,
+    Address, Block, BlockHeader, Bytes, Hash, Proof, Receipt, ServiceContext, ServiceContextParams,
     SignedTransaction,
 };
 use protocol::ProtocolResult;
 
 use crate::types::{
-    ApprovePayload, CreateAssetPayload, GetAllowancePayload, GetAssetPayload, GetBalancePayload,
-    TransferFromPayload, TransferPayload,
+    ApprovePayload, CreateAssetPayload, FreezePayload, GetAllowancePayload, GetAssetPayload,
+    GetBalancePayload, InitGenesisPayload, TransferEvent, TransferFromPayload, TransferPayload,
 };
-use crate::AssetService;
+use crate::{AssetService, FROZEN_ACCOUNT_CODE, MAX_TRANSFER_MEMO_LENGTH};
 
 #[test]
 fn test_create_asset() {
@@ -56,6 +56,66 @@ fn test_create_asset() {
     assert_eq!(balance_res.asset_id, asset.id);
 }
 
+#[test]
+fn test_asset_id_stable_against_field_order() {
+    let cycles_limit = 1024 * 1024 * 1024; // 1073741824
+    let caller = Address::from_str("muta14e0lmgck835vm2dfm0w3ckv6svmez8fdgdl705").unwrap();
+    let context = mock_context(cycles_limit, caller.clone());
+
+    let mut service = new_asset_service();
+    init_genesis(&mut service, Some(1));
+
+    let payload: CreateAssetPayload =
+        serde_json::from_str(r#"{"name":"test","symbol":"TT","supply":1024}"#).unwrap();
+    let reordered: CreateAssetPayload =
+        serde_json::from_str(r#"{"supply":1024,"symbol":"TT","name":"test"}"#).unwrap();
+    // Fixed id of this payload and caller, it must never change.
+    let expected_id =
+        Hash::from_hex("0x64afe64a9d2a8136c40c3399076768b19c64acb844a27dce8240bd98dc760356")
+            .unwrap();
+    assert_eq!(payload.asset_id(&caller), expected_id);
+    assert_eq!(reordered.asset_id(&caller), expected_id);
+
+    let asset = service.create_asset(context.clone(), payload).succeed_data;
+    assert_eq!(asset.id, expected_id);
+
+    let res = service.create_asset(context, reordered);
+    assert_eq!(res.code, 102);
+}
+
+#[test]
+fn test_legacy_asset_id_before_activation() {
+    let cycles_limit = 1024 * 1024 * 1024; // 1073741824
+    let caller = Address::from_str("muta14e0lmgck835vm2dfm0w3ckv6svmez8fdgdl705").unwrap();
+    let payload = CreateAssetPayload {
+        name:   "test".to_owned(),
+        symbol: "TT".to_owned(),
+        supply: 1024,
+    };
+    let legacy_id = Hash::digest(Bytes::from(
+        r#"{"name":"test","symbol":"TT","supply":1024}"#.to_owned() + &caller.to_string(),
+    ));
+    assert_eq!(payload.legacy_asset_id(&caller).unwrap(), legacy_id);
+
+    // Without an activation height, ids are always legacy ones
+    let mut service = new_asset_service();
+    let context = mock_context_at(cycles_limit, caller.clone(), 10);
+    let asset = service.create_asset(context, payload.clone()).succeed_data;
+    assert_eq!(asset.id, legacy_id);
+
+    let mut service = new_asset_service();
+    init_genesis(&mut service, Some(2));
+
+    let context = mock_context_at(cycles_limit, caller.clone(), 1);
+    let asset = service.create_asset(context, payload.clone()).succeed_data;
+    assert_eq!(asset.id, legacy_id);
+
+    let context = mock_context_at(cycles_limit, caller.clone(), 2);
+    let asset = service.create_asset(context, payload.clone()).succeed_data;
+    assert_eq!(asset.id, payload.asset_id(&caller));
+    assert_ne!(asset.id, legacy_id);
+}
+
 #[test]
 fn test_transfer() {
     let cycles_limit = 1024 * 1024 * 1024; // 1073741824
@@ -79,6 +139,7 @@ fn test_transfer() {
         asset_id: asset.id.clone(),
         to:       to_address.clone(),
         value:    1024,
+        memo:     String::new(),
     });
 
     let balance_res = service
@@ -99,6 +160,67 @@ fn test_transfer() {
     assert_eq!(balance_res.balance, 1024);
 }
 
+#[test]
+fn test_transfer_with_memo() {
+    let cycles_limit = 1024 * 1024 * 1024; // 1073741824
+    let caller = Address::from_str("muta14e0lmgck835vm2dfm0w3ckv6svmez8fdgdl705").unwrap();
+    let context = mock_context(cycles_limit, caller.clone());
+
+    let mut service = new_asset_service();
+
+    let supply = 1024 * 1024;
+    let asset = service
+        .create_asset(context.clone(), CreateAssetPayload {
+            name: "test".to_owned(),
+            symbol: "test".to_owned(),
+            supply,
+        })
+        .succeed_data;
+
+    // test payload without memo is still accepted
+    let to_address = Address::from_str("muta15a8a9ksxe3hhjpw3l7wz7ry778qg8h9wz8y35p").unwrap();
+    let payload_str = format!(
+        r#"{{"asset_id":"{}","to":"{}","value":1024}}"#,
+        asset.id.as_hex(),
+        to_address
+    );
+    let payload: TransferPayload = serde_json::from_str(&payload_str).unwrap();
+    assert!(payload.memo.is_empty());
+
+    // test transfer with memo
+    let context = mock_context(cycles_limit, caller.clone());
+    let res = service.transfer(context.clone(), TransferPayload {
+        memo: "order 42".to_owned(),
+        ..payload.clone()
+    });
+    assert!(!res.is_error());
+
+    let events = context.get_events();
+    let event: TransferEvent = serde_json::from_str(&events.last().unwrap().data).unwrap();
+    assert_eq!(event.memo, "order 42".to_owned());
+
+    // test memo costs extra cycles
+    let no_memo_context = mock_context(cycles_limit, caller.clone());
+    service.transfer(no_memo_context.clone(), payload.clone());
+    assert!(context.get_cycles_used() > no_memo_context.get_cycles_used());
+
+    // test event without memo keeps the old format
+    let events = no_memo_context.get_events();
+    let event_data = &events.last().unwrap().data;
+    assert!(!event_data.contains("memo"));
+    let event: TransferEvent = serde_json::from_str(event_data).unwrap();
+    assert!(event.memo.is_empty());
+
+    // test memo above max length
+    let context = mock_context(cycles_limit, caller);
+    let res = service.transfer(context, TransferPayload {
+        memo: "m".repeat(MAX_TRANSFER_MEMO_LENGTH + 1),
+        ..payload
+    });
+    assert_eq!(res.code, 107);
+    assert_eq!(res.error_message, "memo too long".to_owned());
+}
+
 #[test]
 fn test_approve() {
     let cycles_limit = 1024 * 1024 * 1024; // 1073741824
@@ -196,6 +318,85 @@ fn test_transfer_from() {
     assert_eq!(balance_res.balance, 24);
 }
 
+#[test]
+fn test_freeze() {
+    let cycles_limit = 1024 * 1024 * 1024; // 1073741824
+    let caller = Address::from_str("muta14e0lmgck835vm2dfm0w3ckv6svmez8fdgdl705").unwrap();
+    let context = mock_context(cycles_limit, caller.clone());
+
+    let mut service = new_asset_service();
+
+    let asset = service
+        .create_asset(context.clone(), CreateAssetPayload {
+            name:   "test".to_owned(),
+            symbol: "test".to_owned(),
+            supply: 1024 * 1024,
+        })
+        .succeed_data;
+
+    let user = Address::from_str("muta15a8a9ksxe3hhjpw3l7wz7ry778qg8h9wz8y35p").unwrap();
+    let user_context = mock_context(cycles_limit, user.clone());
+    let transfer = |to: &Address, value| TransferPayload {
+        asset_id: asset.id.clone(),
+        to: to.clone(),
+        value,
+        memo: String::new(),
+    };
+    let freeze = |frozen| FreezePayload {
+        asset_id: asset.id.clone(),
+        user: user.clone(),
+        frozen,
+    };
+
+    let res = service.transfer(context.clone(), transfer(&user, 100));
+    assert!(!res.is_error());
+
+    // Only issuer can freeze
+    let res = service.freeze(user_context.clone(), freeze(true));
+    assert_eq!(res.code, 108);
+
+    let res = service.freeze(context.clone(), freeze(true));
+    assert!(!res.is_error());
+    let events = context.get_events();
+    let event = events.last().unwrap();
+    assert_eq!(event.name, "Freeze");
+
+    // Frozen recipient
+    let res = service.transfer(context.clone(), transfer(&user, 100));
+    assert_eq!(res.code, FROZEN_ACCOUNT_CODE);
+
+    // Frozen sender
+    let res = service.transfer(user_context.clone(), transfer(&caller, 10));
+    assert_eq!(res.code, FROZEN_ACCOUNT_CODE);
+
+    service.approve(user_context, ApprovePayload {
+        asset_id: asset.id.clone(),
+        to:       caller.clone(),
+        value:    10,
+    });
+    let res = service.transfer_from(context.clone(), TransferFromPayload {
+        asset_id:  asset.id.clone(),
+        sender:    user.clone(),
+        recipient: caller.clone(),
+        value:     10,
+    });
+    assert_eq!(res.code, FROZEN_ACCOUNT_CODE);
+
+    // Unfreeze restores transfers
+    let res = service.freeze(context.clone(), freeze(false));
+    assert!(!res.is_error());
+    let res = service.transfer(context.clone(), transfer(&user, 100));
+    assert!(!res.is_error());
+
+    let balance_res = service
+        .get_balance(context, GetBalancePayload {
+            asset_id: asset.id.clone(),
+            user,
+        })
+        .succeed_data;
+    assert_eq!(balance_res.balance, 200);
+}
+
 fn new_asset_service(
 ) -> AssetService<DefaultServiceSDK<GeneralServiceState<MemoryDB>, DefaultChainQuerier<MockStorage>>>
 {
@@ -208,7 +409,27 @@ fn new_asset_service(
     AssetService::new(sdk)
 }
 
+fn init_genesis(
+    service: &mut AssetService<
+        DefaultServiceSDK<GeneralServiceState<MemoryDB>, DefaultChainQuerier<MockStorage>>,
+    >,
+    rlp_asset_id_height: Option<u64>,
+) {
+    service.init_genesis(InitGenesisPayload {
+        id: Hash::digest(Bytes::from_static(b"genesis asset")),
+        name: "genesis".to_owned(),
+        symbol: "GA".to_owned(),
+        supply: 1024,
+        issuer: Address::from_str("muta14e0lmgck835vm2dfm0w3ckv6svmez8fdgdl705").unwrap(),
+        rlp_asset_id_height,
+    });
+}
+
 fn mock_context(cycles_limit: u64, caller: Address) -> ServiceContext {
+    mock_context_at(cycles_limit, caller, 1)
+}
+
+fn mock_context_at(cycles_limit: u64, caller: Address, height: u64) -> ServiceContext {
     let params = ServiceContextParams {
         tx_hash: None,
         nonce: None,
@@ -216,7 +437,7 @@ fn mock_context(cycles_limit: u64, caller: Address) -> ServiceContext {
         cycles_price: 1,
         cycles_used: Rc::new(RefCell::new(0)),
         caller,
-        height: 1,
+        height,
         timestamp: 0,
         service_name: "service_name".to_owned(),
         service_method: "service_method".to_owned(),
@@ -240,6 +461,19 @@ impl CommonStorage for MockStorage {
         unimplemented!()
     }
 
+    async fn contains_block(&self, _ctx: Context, _height: u64) -> ProtocolResult<bool> {
+        unimplemented!()
+    }
+
+    async fn get_blocks(
+        &self,
+        _ctx: Context,
+        _from: u64,
+        _limit: u64,
+    ) -> ProtocolResult<Vec<Block>> {
+        unimplemented!()
+    }
+
     async fn set_block(&self, _ctx: Context, _block: Block) -> ProtocolResult<()> {
         unimplemented!()
     }
@@ -296,6 +530,39 @@ impl Storage for MockStorage {
         unimplemented!()
     }
 
+    async fn get_transactions_by_hashes(
+        &self,
+        _: Context,
+        _: &[Hash],
+    ) -> ProtocolResult<Vec<Option<SignedTransaction>>> {
+        unimplemented!()
+    }
+
+    async fn get_transaction_at(
+        &self,
+        _ctx: Context,
+        _: u64,
+        _: &Hash,
+    ) -> ProtocolResult<Option<SignedTransaction>> {
+        unimplemented!()
+    }
+
+    async fn get_transaction_location(
+        &self,
+        _ctx: Context,
+        _: &Hash,
+    ) -> ProtocolResult<Option<(u64, usize)>> {
+        unimplemented!()
+    }
+
+    async fn contains_transaction(&self, _ctx: Context, _: &Hash) -> ProtocolResult<bool> {
+        unimplemented!()
+    }
+
+    async fn filter_existing(&self, _ctx: Context, _: Vec<Hash>) -> ProtocolResult<Vec<Hash>> {
+        unimplemented!()
+    }
+
     async fn get_transactions(
         &self,
         _ctx: Context,
@@ -318,6 +585,10 @@ impl Storage for MockStorage {
         unimplemented!()
     }
 
+    async fn get_block_receipts(&self, _ctx: Context, _: u64) -> ProtocolResult<Vec<Receipt>> {
+        unimplemented!()
+    }
+
     async fn get_latest_proof(&self, _ctx: Context) -> ProtocolResult<Proof> {
         unimplemented!()
     }
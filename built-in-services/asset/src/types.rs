@@ -10,11 +10,16 @@ use protocol::ProtocolResult;
 /// Payload
 #[derive(RlpFixedCodec, Deserialize, Serialize, Clone, Debug)]
 pub struct InitGenesisPayload {
-    pub id:     Hash,
-    pub name:   String,
-    pub symbol: String,
-    pub supply: u64,
-    pub issuer: Address,
+    pub id:                  Hash,
+    pub name:                String,
+    pub symbol:              String,
+    pub supply:              u64,
+    pub issuer:              Address,
+    /// From this height on, ids of created assets are derived by
+    /// `CreateAssetPayload::asset_id`, before it or if unset, by
+    /// `CreateAssetPayload::legacy_asset_id`.
+    #[serde(default)]
+    pub rlp_asset_id_height: Option<u64>,
 }
 
 #[derive(RlpFixedCodec, Deserialize, Serialize, Clone, Debug)]
@@ -24,6 +29,27 @@ pub struct CreateAssetPayload {
     pub supply: u64,
 }
 
+impl CreateAssetPayload {
+    /// Derive asset id from an explicitly ordered rlp encoding of the fields
+    /// and issuer, so the id doesn't depend on how the payload is serialized.
+    pub fn asset_id(&self, issuer: &Address) -> Hash {
+        let mut stream = rlp::RlpStream::new_list(4);
+        stream.append(&self.name);
+        stream.append(&self.symbol);
+        stream.append(&self.supply);
+        stream.append(&issuer.as_bytes().as_ref());
+
+        Hash::digest(Bytes::from(stream.out()))
+    }
+
+    /// Derive asset id from the `serde_json` encoding of the payload and
+    /// issuer, as assets created before the rlp derivation was activated.
+    pub fn legacy_asset_id(&self, issuer: &Address) -> Result<Hash, serde_json::Error> {
+        let payload_str = serde_json::to_string(self)?;
+        Ok(Hash::digest(Bytes::from(payload_str + &issuer.to_string())))
+    }
+}
+
 #[derive(RlpFixedCodec, Deserialize, Serialize, Clone, Debug)]
 pub struct GetAssetPayload {
     pub id: Hash,
@@ -34,6 +60,8 @@ pub struct TransferPayload {
     pub asset_id: Hash,
     pub to:       Address,
     pub value:    u64,
+    #[serde(default)]
+    pub memo:     String,
 }
 
 #[derive(RlpFixedCodec, Deserialize, Serialize, Clone, Debug)]
@@ -42,9 +70,16 @@ pub struct TransferEvent {
     pub from:     Address,
     pub to:       Address,
     pub value:    u64,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub memo:     String,
 }
 
-pub type ApprovePayload = TransferPayload;
+#[derive(RlpFixedCodec, Deserialize, Serialize, Clone, Debug)]
+pub struct ApprovePayload {
+    pub asset_id: Hash,
+    pub to:       Address,
+    pub value:    u64,
+}
 
 #[derive(RlpFixedCodec, Deserialize, Serialize, Clone, Debug)]
 pub struct ApproveEvent {
@@ -71,6 +106,20 @@ pub struct TransferFromEvent {
     pub value:     u64,
 }
 
+#[derive(RlpFixedCodec, Deserialize, Serialize, Clone, Debug)]
+pub struct FreezePayload {
+    pub asset_id: Hash,
+    pub user:     Address,
+    pub frozen:   bool,
+}
+
+#[derive(RlpFixedCodec, Deserialize, Serialize, Clone, Debug)]
+pub struct FreezeEvent {
+    pub asset_id: Hash,
+    pub user:     Address,
+    pub frozen:   bool,
+}
+
 #[derive(RlpFixedCodec, Deserialize, Serialize, Clone, Debug)]
 pub struct GetBalancePayload {
     pub asset_id: Hash,
@@ -10,21 +10,32 @@ use binding_macro::{cycles, genesis, service};
 use derive_more::Display;
 use rlp::{Decodable, Rlp};
 
-use common_crypto::{Crypto, Secp256k1};
+use common_crypto::{AddressVerifyError, Crypto, Secp256k1, VerifyForAddress};
 use protocol::traits::{ExecutorParams, ServiceResponse, ServiceSDK};
 use protocol::types::{Address, Bytes, Hash, ServiceContext, SignedTransaction};
+use protocol::ProtocolResult;
 
 use crate::types::{
-    Account, AddAccountPayload, ChangeMemoPayload, ChangeOwnerPayload,
+    Account, AddAccountPayload, AddressWithWeight, ChangeMemoPayload, ChangeOwnerPayload,
     GenerateMultiSigAccountPayload, GenerateMultiSigAccountResponse, GetMultiSigAccountPayload,
-    GetMultiSigAccountResponse, InitGenesisPayload, MultiSigPermission, RemoveAccountPayload,
-    RemoveAccountResult, SetAccountWeightPayload, SetThresholdPayload, SetWeightResult,
-    UpdateAccountPayload, VerifySignaturePayload, Witness,
+    GetMultiSigAccountResponse, InitGenesisPayload, MissingSignersResponse, MultiSigPermission,
+    RemoveAccountPayload, RemoveAccountResult, SetAccountWeightPayload, SetThresholdPayload,
+    SetWeightResult, UpdateAccountPayload, VerifySignaturePayload, Witness,
 };
 
 pub const MULTI_SIG_SERVICE_NAME: &str = "multi_signature";
 const MAX_MULTI_SIGNATURE_RECURSION_DEPTH: u8 = 8;
 const MAX_PERMISSION_ACCOUNTS: u8 = 16;
+/// Max bytes of rlp encoded public keys and signatures together, checked
+/// before decoding them.
+pub const MAX_WITNESS_SIZE: usize = 64 * 1024;
+const WITNESS_CYCLES_PER_BYTE: u64 = 10;
+
+/// Address of the account generated by transaction `tx_hash`, clients can
+/// compute it before sending the transaction.
+pub fn predict_account_address(tx_hash: &Hash) -> ProtocolResult<Address> {
+    Address::from_hash(Hash::digest(tx_hash.as_bytes()))
+}
 
 pub trait MultiSignature {
     fn verify_signature_(
@@ -102,6 +113,7 @@ impl<SDK: ServiceSDK> MultiSignatureService<SDK> {
             owner: payload.owner,
             threshold: payload.threshold,
             memo: payload.memo,
+            valid_until_height: None,
         };
 
         self.sdk.set_account_value(&address, 0u8, permission);
@@ -145,7 +157,15 @@ impl<SDK: ServiceSDK> MultiSignatureService<SDK> {
             None => return ServiceError::CtxMissingTxHash.into(),
         };
 
-        if let Ok(address) = Address::from_hash(Hash::digest(tx_hash.as_bytes())) {
+        if let Ok(address) = predict_account_address(&tx_hash) {
+            if self
+                .sdk
+                .get_account_value::<_, MultiSigPermission>(&address, &0u8)
+                .is_some()
+            {
+                return ServiceError::AddressCollision.into();
+            }
+
             let accounts = payload
                 .addr_with_weight
                 .iter()
@@ -171,6 +191,7 @@ impl<SDK: ServiceSDK> MultiSignatureService<SDK> {
                 owner,
                 threshold: payload.threshold,
                 memo: payload.memo,
+                valid_until_height: payload.valid_until_height,
             };
 
             self.sdk.set_account_value(&address, 0u8, permission);
@@ -205,6 +226,18 @@ impl<SDK: ServiceSDK> MultiSignatureService<SDK> {
         ctx: ServiceContext,
         payload: SignedTransaction,
     ) -> ServiceResponse<()> {
+        let witness_size = payload.pubkey.len() + payload.signature.len();
+        if witness_size > MAX_WITNESS_SIZE {
+            return ServiceError::WitnessTooLarge(witness_size).into();
+        }
+
+        if !ctx.sub_cycles(witness_size as u64 * WITNESS_CYCLES_PER_BYTE) {
+            return ServiceResponse::<()>::from_error(
+                3,
+                "consume cycles for witness failed: out of cycles".to_owned(),
+            );
+        }
+
         let pubkeys = match decode_list::<Vec<u8>>(&payload.pubkey, "public key") {
             Ok(pks) => pks,
             Err(err) => return err.into(),
@@ -215,11 +248,78 @@ impl<SDK: ServiceSDK> MultiSignatureService<SDK> {
             Err(err) => return err.into(),
         };
 
-        self._inner_verify_signature(VerifySignaturePayload {
-            tx_hash:    payload.tx_hash,
-            pubkeys:    pubkeys.into_iter().map(Bytes::from).collect::<Vec<_>>(),
-            signatures: sigs.into_iter().map(Bytes::from).collect::<Vec<_>>(),
-            sender:     payload.raw.sender,
+        self._inner_verify_signature(
+            VerifySignaturePayload {
+                tx_hash:    payload.tx_hash,
+                pubkeys:    pubkeys.into_iter().map(Bytes::from).collect::<Vec<_>>(),
+                signatures: sigs.into_iter().map(Bytes::from).collect::<Vec<_>>(),
+                sender:     payload.raw.sender,
+            },
+            ctx.get_current_height(),
+        )
+    }
+
+    /// Simulate verification of a partially signed transaction, report which
+    /// members of sender account still have to sign. Members whose signature
+    /// is absent or invalid are counted as missing.
+    #[cycles(21_000)]
+    #[read]
+    fn missing_signers(
+        &self,
+        ctx: ServiceContext,
+        payload: VerifySignaturePayload,
+    ) -> ServiceResponse<MissingSignersResponse> {
+        if payload.pubkeys.len() != payload.signatures.len() {
+            return ServiceError::PubkeyAndSignatureMismatch.into();
+        }
+
+        let permission = match self
+            .sdk
+            .get_account_value::<_, MultiSigPermission>(&payload.sender, &0u8)
+        {
+            Some(permission) => permission,
+            None => return ServiceError::AccountNotExsit.into(),
+        };
+        let height = ctx.get_current_height();
+        if permission.is_expired(height) {
+            return ServiceError::PermissionExpired.into();
+        }
+
+        let tx_hash = &payload.tx_hash;
+        let wit_map = Witness::new(payload.pubkeys, payload.signatures).into_addr_map();
+        let mut weight_acc = 0u32;
+        let mut absent = Vec::new();
+        for account in permission.accounts.iter() {
+            let signed = if !account.is_multiple {
+                wit_map.get(&account.address).map_or(false, |(pk, sig)| {
+                    !self._verify_single_signature(tx_hash, sig, pk).is_error()
+                })
+            } else {
+                !self
+                    ._verify_multi_signature(tx_hash, &wit_map, &account.address, height, 1u8)
+                    .is_error()
+            };
+
+            if signed {
+                weight_acc += account.weight as u32;
+            } else {
+                absent.push(AddressWithWeight {
+                    address: account.address.clone(),
+                    weight:  account.weight,
+                });
+            }
+        }
+
+        let missing_weight = permission.threshold.saturating_sub(weight_acc);
+        let missing_signers = if missing_weight == 0 {
+            Vec::new()
+        } else {
+            min_weight_signers(absent, missing_weight)
+        };
+
+        ServiceResponse::<MissingSignersResponse>::from_succeed(MissingSignersResponse {
+            missing_weight,
+            missing_signers,
         })
     }
 
@@ -297,6 +397,11 @@ impl<SDK: ServiceSDK> MultiSignatureService<SDK> {
                     owner: payload.owner,
                     threshold: payload.threshold,
                     memo: payload.memo,
+                    // An update without the field keeps the expiry, otherwise a
+                    // client unaware of it would make the account valid forever.
+                    valid_until_height: payload
+                        .valid_until_height
+                        .or(permission.valid_until_height),
                 });
             return ServiceResponse::<()>::from_succeed(());
         }
@@ -304,6 +409,33 @@ impl<SDK: ServiceSDK> MultiSignatureService<SDK> {
         ServiceError::AccountNotExsit.into()
     }
 
+    /// Delete the permission of a multi-signature account, only the account
+    /// itself can revoke it.
+    #[cycles(21_000)]
+    #[write]
+    fn revoke_account(
+        &mut self,
+        ctx: ServiceContext,
+        payload: GetMultiSigAccountPayload,
+    ) -> ServiceResponse<()> {
+        if ctx.get_caller() != payload.multi_sig_address {
+            return ServiceError::InvalidOwner.into();
+        }
+
+        if self
+            .sdk
+            .get_account_value::<_, MultiSigPermission>(&payload.multi_sig_address, &0u8)
+            .is_none()
+        {
+            return ServiceError::AccountNotExsit.into();
+        }
+
+        // An empty value is treated as deleted by service state
+        self.sdk
+            .set_account_value(&payload.multi_sig_address, 0u8, Bytes::new());
+        ServiceResponse::<()>::from_succeed(())
+    }
+
     #[cycles(21_000)]
     #[write]
     fn change_owner(
@@ -325,6 +457,11 @@ impl<SDK: ServiceSDK> MultiSignatureService<SDK> {
                 return ServiceError::AboveMaxRecursionDepth.into();
             }
 
+            // check new owner exists, to avoid locking the account with a wrong address
+            if payload.check_new_owner && !self._is_known_account(&permission, &payload.new_owner) {
+                return ServiceError::AccountNotExsit.into();
+            }
+
             permission.set_owner(payload.new_owner);
             self.sdk
                 .set_account_value(&payload.multi_sig_address, 0u8, permission);
@@ -493,22 +630,31 @@ impl<SDK: ServiceSDK> MultiSignatureService<SDK> {
         }
     }
 
-    fn _inner_verify_signature(&self, payload: VerifySignaturePayload) -> ServiceResponse<()> {
+    fn _inner_verify_signature(
+        &self,
+        payload: VerifySignaturePayload,
+        height: u64,
+    ) -> ServiceResponse<()> {
         if payload.pubkeys.len() != payload.signatures.len() {
             return ServiceError::PubkeyAndSignatureMismatch.into();
         }
 
         if payload.pubkeys.len() == 1 {
-            if let Ok(addr) = Address::from_pubkey_bytes(&payload.pubkeys[0]) {
-                if addr == payload.sender {
-                    return self._verify_single_signature(
-                        &payload.tx_hash,
-                        &payload.signatures[0],
-                        &payload.pubkeys[0],
-                    );
+            match Secp256k1::verify_for_address(
+                payload.tx_hash.as_slice(),
+                payload.signatures[0].as_ref(),
+                payload.pubkeys[0].as_ref(),
+                &payload.sender,
+            ) {
+                Ok(()) => return ServiceResponse::<()>::from_succeed(()),
+                Err(AddressVerifyError::InvalidPublicKey) => {
+                    return ServiceError::InvalidPublicKey.into()
                 }
-            } else {
-                return ServiceError::InvalidPublicKey.into();
+                Err(AddressVerifyError::Signature(_)) => {
+                    return ServiceError::VerifyMultiSignatureFailed.into()
+                }
+                // The key may be a member of sender multi-signature account
+                Err(AddressVerifyError::AddressMismatch { .. }) => (),
             }
         }
 
@@ -516,6 +662,7 @@ impl<SDK: ServiceSDK> MultiSignatureService<SDK> {
             &payload.tx_hash,
             &Witness::new(payload.pubkeys, payload.signatures).into_addr_map(),
             &payload.sender,
+            height,
             0u8,
         )
     }
@@ -525,6 +672,7 @@ impl<SDK: ServiceSDK> MultiSignatureService<SDK> {
         tx_hash: &Hash,
         wit_map: &HashMap<Address, (Bytes, Bytes)>,
         sender: &Address,
+        height: u64,
         recursion_depth: u8,
     ) -> ServiceResponse<()> {
         // use local variable to do DFS
@@ -544,6 +692,9 @@ impl<SDK: ServiceSDK> MultiSignatureService<SDK> {
             return ServiceError::AccountNotExsit.into();
         }
         let permission = permission.unwrap();
+        if permission.is_expired(height) {
+            return ServiceError::PermissionExpired.into();
+        }
 
         for account in permission.accounts.iter() {
             if !account.is_multiple {
@@ -553,7 +704,7 @@ impl<SDK: ServiceSDK> MultiSignatureService<SDK> {
                     }
                 }
             } else if !self
-                ._verify_multi_signature(tx_hash, wit_map, &account.address, depth_clone)
+                ._verify_multi_signature(tx_hash, wit_map, &account.address, height, depth_clone)
                 .is_error()
             {
                 weight_acc += account.weight as u32;
@@ -580,6 +731,17 @@ impl<SDK: ServiceSDK> MultiSignatureService<SDK> {
         }
     }
 
+    fn _is_known_account(&self, permission: &MultiSigPermission, address: &Address) -> bool {
+        permission
+            .accounts
+            .iter()
+            .any(|account| &account.address == address)
+            || self
+                .sdk
+                .get_account_value::<_, MultiSigPermission>(address, &0u8)
+                .is_some()
+    }
+
     fn _is_recursion_depth_overflow(&self, address: &Address, recursion_depth: u8) -> bool {
         let depth_clone = recursion_depth + 1;
         if depth_clone >= MAX_MULTI_SIGNATURE_RECURSION_DEPTH {
@@ -642,6 +804,15 @@ pub enum ServiceError {
 
     #[display(fmt = "multi signature verified failed")]
     VerifyMultiSignatureFailed,
+
+    #[display(fmt = "witness too large, {} bytes", _0)]
+    WitnessTooLarge(usize),
+
+    #[display(fmt = "generated address already has a permission")]
+    AddressCollision,
+
+    #[display(fmt = "permission expired")]
+    PermissionExpired,
 }
 
 impl ServiceError {
@@ -660,6 +831,9 @@ impl ServiceError {
             ServiceError::PubkeyAndSignatureMismatch => 111,
             ServiceError::InvalidPublicKey => 112,
             ServiceError::VerifyMultiSignatureFailed => 113,
+            ServiceError::WitnessTooLarge(_) => 114,
+            ServiceError::AddressCollision => 115,
+            ServiceError::PermissionExpired => 116,
         }
     }
 }
@@ -670,6 +844,35 @@ impl<T: Default> From<ServiceError> for ServiceResponse<T> {
     }
 }
 
+// A permission has at most `MAX_PERMISSION_ACCOUNTS` members, so all subsets
+// are checked. Fewer signers win among sets of the same weight. All of them
+// are returned if even they can't reach `need`.
+fn min_weight_signers(candidates: Vec<AddressWithWeight>, need: u32) -> Vec<AddressWithWeight> {
+    let mut best: Option<(u32, u32, usize)> = None;
+    for set in 1usize..(1 << candidates.len()) {
+        let weight = candidates
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| set & (1 << i) != 0)
+            .map(|(_, c)| c.weight as u32)
+            .sum::<u32>();
+        let key = (weight, set.count_ones(), set);
+        if weight >= need && best.map_or(true, |b| (key.0, key.1) < (b.0, b.1)) {
+            best = Some(key);
+        }
+    }
+
+    match best {
+        Some((_, _, set)) => candidates
+            .into_iter()
+            .enumerate()
+            .filter(|(i, _)| set & (1 << i) != 0)
+            .map(|(_, c)| c)
+            .collect(),
+        None => candidates,
+    }
+}
+
 fn decode_list<T: Decodable>(bytes: &[u8], ty: &str) -> Result<Vec<T>, ServiceError> {
     Rlp::new(bytes)
         .as_list()
@@ -1,11 +1,19 @@
 use std::str::FromStr;
 
+use muta_codec_derive::RlpFixedCodec;
+
+use protocol::fixed_codec::{FixedCodec, FixedCodecError};
+use protocol::ProtocolResult;
+
 use crate::types::{
-    AddAccountPayload, GenerateMultiSigAccountPayload, GetMultiSigAccountPayload,
-    MultiSigPermission, RemoveAccountPayload, SetAccountWeightPayload, SetThresholdPayload,
-    UpdateAccountPayload,
+    Account, AddAccountPayload, ChangeOwnerPayload, GenerateMultiSigAccountPayload,
+    GetMultiSigAccountPayload, MultiSigPermission, RemoveAccountPayload, SetAccountWeightPayload,
+    SetThresholdPayload, UpdateAccountPayload,
 };
 
+use crate::types::VerifySignaturePayload;
+use crate::{predict_account_address, MultiSignatureService, ServiceError};
+
 use super::*;
 
 #[test]
@@ -24,11 +32,12 @@ fn test_generate_multi_signature() {
         .collect::<Vec<_>>();
     let multi_sig_address =
         service.generate_account(context.clone(), GenerateMultiSigAccountPayload {
-            owner:            owner.clone(),
-            autonomy:         false,
-            addr_with_weight: accounts,
-            threshold:        12,
-            memo:             String::new(),
+            owner:              owner.clone(),
+            autonomy:           false,
+            addr_with_weight:   accounts,
+            threshold:          12,
+            memo:               String::new(),
+            valid_until_height: None,
         });
     assert!(multi_sig_address.is_error());
 
@@ -39,11 +48,12 @@ fn test_generate_multi_signature() {
         .collect::<Vec<_>>();
     let multi_sig_address =
         service.generate_account(context.clone(), GenerateMultiSigAccountPayload {
-            owner:            owner.clone(),
-            autonomy:         false,
-            addr_with_weight: accounts,
-            threshold:        12,
-            memo:             String::new(),
+            owner:              owner.clone(),
+            autonomy:           false,
+            addr_with_weight:   accounts,
+            threshold:          12,
+            memo:               String::new(),
+            valid_until_height: None,
         });
     assert!(multi_sig_address.is_error());
 
@@ -54,11 +64,12 @@ fn test_generate_multi_signature() {
         .collect::<Vec<_>>();
     let multi_sig_address =
         service.generate_account(context.clone(), GenerateMultiSigAccountPayload {
-            owner:            owner.clone(),
-            autonomy:         false,
-            addr_with_weight: accounts.clone(),
-            threshold:        3,
-            memo:             String::new(),
+            owner:              owner.clone(),
+            autonomy:           false,
+            addr_with_weight:   accounts.clone(),
+            threshold:          3,
+            memo:               String::new(),
+            valid_until_height: None,
         });
     assert!(!multi_sig_address.is_error());
 
@@ -73,9 +84,47 @@ fn test_generate_multi_signature() {
         accounts: to_accounts_list(accounts),
         threshold: 3,
         memo: String::new(),
+        valid_until_height: None,
     });
 }
 
+#[test]
+fn test_generate_account_address_collision() {
+    let cycles_limit = 1024 * 1024 * 1024; // 1073741824
+    let caller = Address::from_str("muta14e0lmgck835vm2dfm0w3ckv6svmez8fdgdl705").unwrap();
+    // Same tx hash derives the same address.
+    let context = mock_context(cycles_limit, caller);
+
+    let mut service = new_multi_signature_service();
+    let owner = Address::from_pubkey_bytes(gen_one_keypair().1).unwrap();
+    let payload = GenerateMultiSigAccountPayload {
+        owner:              owner.clone(),
+        autonomy:           false,
+        addr_with_weight:   gen_keypairs(4)
+            .iter()
+            .map(|pair| to_multi_sig_account(pair.1.clone()))
+            .collect::<Vec<_>>(),
+        threshold:          3,
+        memo:               String::new(),
+        valid_until_height: None,
+    };
+
+    let res = service.generate_account(context.clone(), payload.clone());
+    assert!(!res.is_error());
+    let address = res.succeed_data.address;
+
+    let mut other = payload;
+    other.owner = Address::from_pubkey_bytes(gen_one_keypair().1).unwrap();
+    let res = service.generate_account(context.clone(), other);
+    assert_eq!(res.code, 115);
+
+    // The existing permission is untouched.
+    let permission = service.get_account_from_address(context, GetMultiSigAccountPayload {
+        multi_sig_address: address,
+    });
+    assert_eq!(permission.succeed_data.permission.owner, owner);
+}
+
 #[test]
 fn test_set_threshold() {
     let cycles_limit = 1024 * 1024 * 1024; // 1073741824
@@ -90,11 +139,12 @@ fn test_set_threshold() {
         .collect::<Vec<_>>();
     let multi_sig_address = service
         .generate_account(context.clone(), GenerateMultiSigAccountPayload {
-            owner:            owner_address,
-            autonomy:         false,
-            addr_with_weight: account_pubkeys,
-            threshold:        3,
-            memo:             String::new(),
+            owner:              owner_address,
+            autonomy:           false,
+            addr_with_weight:   account_pubkeys,
+            threshold:          3,
+            memo:               String::new(),
+            valid_until_height: None,
         })
         .succeed_data
         .address;
@@ -131,11 +181,12 @@ fn test_adeption_address() {
         .collect::<Vec<_>>();
     let multi_sig_address = service
         .generate_account(context.clone(), GenerateMultiSigAccountPayload {
-            owner:            Address::default(),
-            autonomy:         true,
-            addr_with_weight: account_pubkeys,
-            threshold:        3,
-            memo:             String::new(),
+            owner:              Address::default(),
+            autonomy:           true,
+            addr_with_weight:   account_pubkeys,
+            threshold:          3,
+            memo:               String::new(),
+            valid_until_height: None,
         })
         .succeed_data
         .address;
@@ -146,6 +197,71 @@ fn test_adeption_address() {
     assert_eq!(multi_sig_address, permission.succeed_data.permission.owner);
 }
 
+#[test]
+fn test_change_owner() {
+    let cycles_limit = 1024 * 1024 * 1024; // 1073741824
+    let mut service = new_multi_signature_service();
+    let owner = gen_one_keypair();
+    let owner_address = Address::from_pubkey_bytes(owner.1).unwrap();
+    let context = mock_context(cycles_limit, owner_address.clone());
+    let gen_payload = |owner: Address| GenerateMultiSigAccountPayload {
+        owner,
+        autonomy: false,
+        addr_with_weight: gen_keypairs(4)
+            .iter()
+            .map(|pair| to_multi_sig_account(pair.1.clone()))
+            .collect::<Vec<_>>(),
+        threshold: 3,
+        memo: String::new(),
+        valid_until_height: None,
+    };
+    let multi_sig_address = service
+        .generate_account(context.clone(), gen_payload(owner_address.clone()))
+        .succeed_data
+        .address;
+    let new_owner_address = service
+        .generate_account(
+            mock_context(cycles_limit, owner_address.clone()),
+            gen_payload(owner_address),
+        )
+        .succeed_data
+        .address;
+
+    // test change owner to a non-existent account with check
+    let unknown_address = Address::from_pubkey_bytes(gen_one_keypair().1).unwrap();
+    let res = service.change_owner(context.clone(), ChangeOwnerPayload {
+        multi_sig_address: multi_sig_address.clone(),
+        new_owner:         unknown_address.clone(),
+        check_new_owner:   true,
+    });
+    assert_eq!(res.error_message, "account is not existed".to_owned());
+
+    // test change owner to a multi-signature account with check
+    let res = service.change_owner(context.clone(), ChangeOwnerPayload {
+        multi_sig_address: multi_sig_address.clone(),
+        new_owner:         new_owner_address.clone(),
+        check_new_owner:   true,
+    });
+    assert_eq!(res.error_message, "".to_owned());
+
+    let permission = service.get_account_from_address(context, GetMultiSigAccountPayload {
+        multi_sig_address: multi_sig_address.clone(),
+    });
+    assert_eq!(
+        permission.succeed_data.permission.owner,
+        new_owner_address.clone()
+    );
+
+    // test change owner to a non-existent account without check
+    let context = mock_context(cycles_limit, new_owner_address);
+    let res = service.change_owner(context, ChangeOwnerPayload {
+        multi_sig_address,
+        new_owner: unknown_address,
+        check_new_owner: false,
+    });
+    assert_eq!(res.error_message, "".to_owned());
+}
+
 #[test]
 fn test_add_account() {
     let cycles_limit = 1024 * 1024 * 1024; // 1073741824
@@ -160,11 +276,12 @@ fn test_add_account() {
         .collect::<Vec<_>>();
     let multi_sig_address = service
         .generate_account(context.clone(), GenerateMultiSigAccountPayload {
-            owner:            owner_address.clone(),
-            autonomy:         false,
-            addr_with_weight: account_pubkeys.clone(),
-            threshold:        3,
-            memo:             String::new(),
+            owner:              owner_address.clone(),
+            autonomy:           false,
+            addr_with_weight:   account_pubkeys.clone(),
+            threshold:          3,
+            memo:               String::new(),
+            valid_until_height: None,
         })
         .succeed_data
         .address;
@@ -193,10 +310,11 @@ fn test_add_account() {
     let permission =
         service.get_account_from_address(context, GetMultiSigAccountPayload { multi_sig_address });
     assert_eq!(permission.succeed_data.permission, MultiSigPermission {
-        owner:     owner_address,
-        accounts:  to_accounts_list(account_pubkeys),
-        threshold: 3,
-        memo:      String::new(),
+        owner:              owner_address,
+        accounts:           to_accounts_list(account_pubkeys),
+        threshold:          3,
+        memo:               String::new(),
+        valid_until_height: None,
     });
 }
 
@@ -214,11 +332,12 @@ fn test_update_account() {
         .collect::<Vec<_>>();
     let multi_sig_address = service
         .generate_account(context, GenerateMultiSigAccountPayload {
-            owner:            owner_address.clone(),
-            autonomy:         false,
-            addr_with_weight: account_pubkeys,
-            threshold:        4,
-            memo:             String::new(),
+            owner:              owner_address.clone(),
+            autonomy:           false,
+            addr_with_weight:   account_pubkeys,
+            threshold:          4,
+            memo:               String::new(),
+            valid_until_height: None,
         })
         .succeed_data
         .address;
@@ -231,11 +350,12 @@ fn test_update_account() {
         weight:  1u8,
     }];
     let res = service.update_account(context.clone(), UpdateAccountPayload {
-        account_address:  multi_sig_address.clone(),
-        owner:            new_owner_address.clone(),
-        addr_with_weight: account_pubkeys,
-        threshold:        1,
-        memo:             String::new(),
+        account_address:    multi_sig_address.clone(),
+        owner:              new_owner_address.clone(),
+        addr_with_weight:   account_pubkeys,
+        threshold:          1,
+        memo:               String::new(),
+        valid_until_height: None,
     });
     assert!(res.is_error());
 
@@ -245,15 +365,62 @@ fn test_update_account() {
         .map(|pair| to_multi_sig_account(pair.1.clone()))
         .collect::<Vec<_>>();
     let res = service.update_account(context, UpdateAccountPayload {
-        account_address:  multi_sig_address,
-        owner:            new_owner_address,
-        addr_with_weight: account_pubkeys,
-        threshold:        1,
-        memo:             String::new(),
+        account_address:    multi_sig_address,
+        owner:              new_owner_address,
+        addr_with_weight:   account_pubkeys,
+        threshold:          1,
+        memo:               String::new(),
+        valid_until_height: None,
     });
     assert_eq!(res.is_error(), false);
 }
 
+#[test]
+fn test_update_account_keeps_valid_until_height() {
+    let cycles_limit = 1024 * 1024 * 1024; // 1073741824
+    let mut service = new_multi_signature_service();
+    let owner_address = Address::from_pubkey_bytes(gen_one_keypair().1).unwrap();
+    let context = mock_context(cycles_limit, owner_address.clone());
+    let account_pubkeys = gen_keypairs(2)
+        .iter()
+        .map(|pair| to_multi_sig_account(pair.1.clone()))
+        .collect::<Vec<_>>();
+    let multi_sig_address = service
+        .generate_account(context.clone(), GenerateMultiSigAccountPayload {
+            owner:              owner_address.clone(),
+            autonomy:           false,
+            addr_with_weight:   account_pubkeys.clone(),
+            threshold:          2,
+            memo:               String::new(),
+            valid_until_height: Some(10),
+        })
+        .succeed_data
+        .address;
+
+    let mut update = |valid_until_height: Option<u64>| {
+        let res = service.update_account(context.clone(), UpdateAccountPayload {
+            account_address: multi_sig_address.clone(),
+            owner: owner_address.clone(),
+            addr_with_weight: account_pubkeys.clone(),
+            threshold: 2,
+            memo: String::new(),
+            valid_until_height,
+        });
+        assert!(!res.is_error());
+
+        service
+            .get_account_from_address(context.clone(), GetMultiSigAccountPayload {
+                multi_sig_address: multi_sig_address.clone(),
+            })
+            .succeed_data
+            .permission
+            .valid_until_height
+    };
+
+    assert_eq!(update(None), Some(10));
+    assert_eq!(update(Some(20)), Some(20));
+}
+
 #[test]
 fn test_set_weight() {
     let cycles_limit = 1024 * 1024 * 1024; // 1073741824
@@ -268,11 +435,12 @@ fn test_set_weight() {
         .collect::<Vec<_>>();
     let multi_sig_address = service
         .generate_account(context.clone(), GenerateMultiSigAccountPayload {
-            owner:            owner_address.clone(),
-            autonomy:         false,
-            addr_with_weight: account_pubkeys.clone(),
-            threshold:        4,
-            memo:             String::new(),
+            owner:              owner_address.clone(),
+            autonomy:           false,
+            addr_with_weight:   account_pubkeys.clone(),
+            threshold:          4,
+            memo:               String::new(),
+            valid_until_height: None,
         })
         .succeed_data
         .address;
@@ -302,10 +470,11 @@ fn test_set_weight() {
         service.get_account_from_address(context, GetMultiSigAccountPayload { multi_sig_address });
     account_pubkeys[0].weight = 2;
     assert_eq!(permission.succeed_data.permission, MultiSigPermission {
-        owner:     owner_address,
-        accounts:  to_accounts_list(account_pubkeys),
-        threshold: 4,
-        memo:      String::new(),
+        owner:              owner_address,
+        accounts:           to_accounts_list(account_pubkeys),
+        threshold:          4,
+        memo:               String::new(),
+        valid_until_height: None,
     });
 }
 
@@ -323,11 +492,12 @@ fn test_remove_account() {
         .collect::<Vec<_>>();
     let multi_sig_address = service
         .generate_account(context.clone(), GenerateMultiSigAccountPayload {
-            owner:            owner_address.clone(),
-            autonomy:         false,
-            addr_with_weight: account_pubkeys.clone(),
-            threshold:        3,
-            memo:             String::new(),
+            owner:              owner_address.clone(),
+            autonomy:           false,
+            addr_with_weight:   account_pubkeys.clone(),
+            threshold:          3,
+            memo:               String::new(),
+            valid_until_height: None,
         })
         .succeed_data
         .address;
@@ -354,9 +524,208 @@ fn test_remove_account() {
     let permission =
         service.get_account_from_address(context, GetMultiSigAccountPayload { multi_sig_address });
     assert_eq!(permission.succeed_data.permission, MultiSigPermission {
-        owner:     owner_address,
-        accounts:  to_accounts_list(account_pubkeys),
-        threshold: 3,
-        memo:      String::new(),
+        owner:              owner_address,
+        accounts:           to_accounts_list(account_pubkeys),
+        threshold:          3,
+        memo:               String::new(),
+        valid_until_height: None,
+    });
+}
+
+#[test]
+fn test_revoke_account() {
+    let cycles_limit = 1024 * 1024 * 1024; // 1073741824
+    let caller = Address::from_str("muta14e0lmgck835vm2dfm0w3ckv6svmez8fdgdl705").unwrap();
+    let mut service = new_multi_signature_service();
+    let owner = Address::from_pubkey_bytes(gen_one_keypair().1).unwrap();
+
+    let keypairs = gen_keypairs(2);
+    let accounts = keypairs
+        .iter()
+        .map(|pair| to_multi_sig_account(pair.1.clone()))
+        .collect::<Vec<_>>();
+    let multi_sig_address = service
+        .generate_account(
+            mock_context(cycles_limit, caller.clone()),
+            GenerateMultiSigAccountPayload {
+                owner,
+                autonomy: false,
+                addr_with_weight: accounts,
+                threshold: 2,
+                memo: String::new(),
+                valid_until_height: None,
+            },
+        )
+        .succeed_data
+        .address;
+
+    let verify = |service: &MultiSignatureService<_>| {
+        let tx_hash = mock_hash();
+        service._inner_verify_signature(
+            VerifySignaturePayload {
+                pubkeys: keypairs.iter().map(|pair| pair.1.clone()).collect(),
+                signatures: keypairs
+                    .iter()
+                    .map(|pair| sign(&pair.0, &tx_hash))
+                    .collect(),
+                sender: multi_sig_address.clone(),
+                tx_hash,
+            },
+            1,
+        )
+    };
+    assert!(!verify(&service).is_error());
+
+    // only the account itself can revoke
+    let payload = GetMultiSigAccountPayload {
+        multi_sig_address: multi_sig_address.clone(),
+    };
+    let res = service.revoke_account(mock_context(cycles_limit, caller), payload.clone());
+    assert!(res.is_error());
+    assert!(!verify(&service).is_error());
+
+    let res = service.revoke_account(
+        mock_context(cycles_limit, multi_sig_address.clone()),
+        payload.clone(),
+    );
+    assert!(!res.is_error());
+
+    let res = verify(&service);
+    assert_eq!(res.code, ServiceError::AccountNotExsit.code());
+
+    let res = service.get_account_from_address(
+        mock_context(cycles_limit, multi_sig_address.clone()),
+        payload,
+    );
+    assert!(res.is_error());
+}
+
+#[test]
+fn test_decode_permission_without_valid_until_height() {
+    // Permission as stored before `valid_until_height` was added.
+    #[derive(RlpFixedCodec)]
+    struct OldPermission {
+        owner:     Address,
+        accounts:  Vec<Account>,
+        threshold: u32,
+        memo:      String,
+    }
+
+    let owner = Address::from_pubkey_bytes(gen_one_keypair().1).unwrap();
+    let accounts = gen_keypairs(2)
+        .iter()
+        .map(|pair| to_multi_sig_account(pair.1.clone()))
+        .map(|a| Account {
+            address:     a.address,
+            weight:      a.weight,
+            is_multiple: false,
+        })
+        .collect::<Vec<_>>();
+    let old = OldPermission {
+        owner:     owner.clone(),
+        accounts:  accounts.clone(),
+        threshold: 2,
+        memo:      "old".to_owned(),
+    };
+
+    let old_bytes = old.encode_fixed().unwrap();
+    let permission = MultiSigPermission::decode_fixed(old_bytes.clone()).unwrap();
+    assert_eq!(permission, MultiSigPermission {
+        owner,
+        accounts,
+        threshold: 2,
+        memo: "old".to_owned(),
+        valid_until_height: None,
+    });
+    assert!(!permission.is_expired(std::u64::MAX));
+
+    // Without expiry it's encoded byte for byte as before
+    let bytes = permission.encode_fixed().unwrap();
+    assert_eq!(bytes, old_bytes);
+    assert_eq!(rlp::Rlp::new(bytes.as_ref()).item_count().unwrap(), 4);
+
+    let permission = MultiSigPermission {
+        valid_until_height: Some(10),
+        ..permission
+    };
+    let bytes = permission.encode_fixed().unwrap();
+    assert_eq!(rlp::Rlp::new(bytes.as_ref()).item_count().unwrap(), 5);
+    let decoded = MultiSigPermission::decode_fixed(bytes).unwrap();
+    assert_eq!(decoded, permission);
+}
+
+#[test]
+fn test_permission_valid_until_height() {
+    let cycles_limit = 1024 * 1024 * 1024; // 1073741824
+    let caller = Address::from_str("muta14e0lmgck835vm2dfm0w3ckv6svmez8fdgdl705").unwrap();
+    let mut service = new_multi_signature_service();
+    let owner = Address::from_pubkey_bytes(gen_one_keypair().1).unwrap();
+
+    let keypairs = gen_keypairs(2);
+    let accounts = keypairs
+        .iter()
+        .map(|pair| to_multi_sig_account(pair.1.clone()))
+        .collect::<Vec<_>>();
+    let multi_sig_address = service
+        .generate_account(
+            mock_context(cycles_limit, caller),
+            GenerateMultiSigAccountPayload {
+                owner,
+                autonomy: false,
+                addr_with_weight: accounts,
+                threshold: 2,
+                memo: String::new(),
+                valid_until_height: Some(10),
+            },
+        )
+        .succeed_data
+        .address;
+
+    let verify = |height: u64| {
+        let tx_hash = mock_hash();
+        service._inner_verify_signature(
+            VerifySignaturePayload {
+                pubkeys: keypairs.iter().map(|pair| pair.1.clone()).collect(),
+                signatures: keypairs
+                    .iter()
+                    .map(|pair| sign(&pair.0, &tx_hash))
+                    .collect(),
+                sender: multi_sig_address.clone(),
+                tx_hash,
+            },
+            height,
+        )
+    };
+
+    assert!(!verify(9).is_error());
+    assert!(!verify(10).is_error());
+
+    let res = verify(11);
+    assert_eq!(
+        res.error_message,
+        ServiceError::PermissionExpired.to_string()
+    );
+}
+
+#[test]
+fn test_predict_account_address() {
+    let cycles_limit = 1024 * 1024 * 1024; // 1073741824
+    let caller = Address::from_str("muta14e0lmgck835vm2dfm0w3ckv6svmez8fdgdl705").unwrap();
+    let context = mock_context(cycles_limit, caller);
+    let predicted = predict_account_address(&context.get_tx_hash().unwrap()).unwrap();
+
+    let mut service = new_multi_signature_service();
+    let accounts = gen_keypairs(2)
+        .iter()
+        .map(|pair| to_multi_sig_account(pair.1.clone()))
+        .collect::<Vec<_>>();
+    let res = service.generate_account(context, GenerateMultiSigAccountPayload {
+        owner:              Address::from_pubkey_bytes(gen_one_keypair().1).unwrap(),
+        autonomy:           false,
+        addr_with_weight:   accounts,
+        threshold:          2,
+        memo:               String::new(),
+        valid_until_height: None,
     });
+    assert_eq!(res.succeed_data.address, predicted);
 }
@@ -1,6 +1,11 @@
 use std::str::FromStr;
 
-use crate::types::{GenerateMultiSigAccountPayload, VerifySignaturePayload};
+use protocol::types::{RawTransaction, SignedTransaction, TransactionRequest};
+
+use crate::types::{
+    GenerateMultiSigAccountPayload, MissingSignersResponse, VerifySignaturePayload,
+};
+use crate::MAX_WITNESS_SIZE;
 
 use super::*;
 
@@ -21,11 +26,12 @@ fn test_recursion_verify_signature() {
         .generate_account(
             mock_context(cycles_limit, caller.clone()),
             GenerateMultiSigAccountPayload {
-                owner:            owner.clone(),
-                autonomy:         false,
-                addr_with_weight: init_multi_sig_account,
-                threshold:        4,
-                memo:             String::new(),
+                owner:              owner.clone(),
+                autonomy:           false,
+                addr_with_weight:   init_multi_sig_account,
+                threshold:          4,
+                memo:               String::new(),
+                valid_until_height: None,
             },
         )
         .succeed_data
@@ -51,6 +57,7 @@ fn test_recursion_verify_signature() {
                 addr_with_weight: multi_sig_account,
                 threshold: 4,
                 memo: String::new(),
+                valid_until_height: None,
             },
         )
         .succeed_data
@@ -69,12 +76,15 @@ fn test_recursion_verify_signature() {
 
     assert_eq!(pks.len(), sigs.len());
 
-    let res = service._inner_verify_signature(VerifySignaturePayload {
-        pubkeys: pks,
-        signatures: sigs,
-        sender: sender_new,
-        tx_hash,
-    });
+    let res = service._inner_verify_signature(
+        VerifySignaturePayload {
+            pubkeys: pks,
+            signatures: sigs,
+            sender: sender_new,
+            tx_hash,
+        },
+        1,
+    );
 
     assert_eq!(res.is_error(), false);
 }
@@ -101,11 +111,12 @@ fn test_recursion_depth() {
         .generate_account(
             mock_context(cycles_limit, caller.clone()),
             GenerateMultiSigAccountPayload {
-                owner:            owner.clone(),
-                autonomy:         false,
-                addr_with_weight: init_multi_sig_account,
-                threshold:        4,
-                memo:             String::new(),
+                owner:              owner.clone(),
+                autonomy:           false,
+                addr_with_weight:   init_multi_sig_account,
+                threshold:          4,
+                memo:               String::new(),
+                valid_until_height: None,
             },
         )
         .succeed_data
@@ -127,11 +138,12 @@ fn test_recursion_depth() {
         let res = service.generate_account(
             mock_context(cycles_limit, caller.clone()),
             GenerateMultiSigAccountPayload {
-                owner:            owner.clone(),
-                autonomy:         false,
-                addr_with_weight: multi_sig_account,
-                threshold:        4,
-                memo:             String::new(),
+                owner:              owner.clone(),
+                autonomy:           false,
+                addr_with_weight:   multi_sig_account,
+                threshold:          4,
+                memo:               String::new(),
+                valid_until_height: None,
             },
         );
 
@@ -150,7 +162,108 @@ fn test_recursion_depth() {
             }],
             threshold: 1,
             memo: String::new(),
+            valid_until_height: None,
         },
     );
     assert!(res.is_error());
 }
+
+#[test]
+fn test_missing_signers() {
+    let cycles_limit = 1024 * 1024 * 1024; // 1073741824
+    let caller = Address::from_str("muta14e0lmgck835vm2dfm0w3ckv6svmez8fdgdl705").unwrap();
+    let mut service = new_multi_signature_service();
+    let owner = Address::from_pubkey_bytes(gen_one_keypair().1).unwrap();
+
+    let keypairs = gen_keypairs(4);
+    let accounts = keypairs
+        .iter()
+        .zip([1u8, 2, 3, 4].iter())
+        .map(|(pair, &weight)| AddressWithWeight {
+            address: Address::from_pubkey_bytes(pair.1.clone()).unwrap(),
+            weight,
+        })
+        .collect::<Vec<_>>();
+
+    let sender = service
+        .generate_account(
+            mock_context(cycles_limit, caller.clone()),
+            GenerateMultiSigAccountPayload {
+                owner,
+                autonomy: false,
+                addr_with_weight: accounts.clone(),
+                threshold: 6,
+                memo: String::new(),
+                valid_until_height: None,
+            },
+        )
+        .succeed_data
+        .address;
+
+    let ctx = mock_context(cycles_limit, caller);
+    let tx_hash = ctx.get_tx_hash().unwrap();
+    let witness = |signed: &[usize]| VerifySignaturePayload {
+        tx_hash:    tx_hash.clone(),
+        pubkeys:    signed.iter().map(|&i| keypairs[i].1.clone()).collect(),
+        signatures: signed
+            .iter()
+            .map(|&i| sign(&keypairs[i].0, &tx_hash))
+            .collect(),
+        sender:     sender.clone(),
+    };
+
+    // Weight 1 + 2 signed, the weight 4 one signed another hash
+    let mut payload = witness(&[0, 1]);
+    payload.pubkeys.push(keypairs[3].1.clone());
+    payload.signatures.push(sign(&keypairs[3].0, &mock_hash()));
+    let res = service.missing_signers(ctx.clone(), payload);
+    assert!(!res.is_error());
+    assert_eq!(res.succeed_data, MissingSignersResponse {
+        missing_weight:  3,
+        missing_signers: vec![accounts[2].clone()],
+    });
+
+    let res = service.missing_signers(ctx.clone(), witness(&[0]));
+    assert_eq!(res.succeed_data.missing_weight, 5);
+    // Weight 2 + 3 is the least covering 5, rather than the heaviest one
+    assert_eq!(res.succeed_data.missing_signers, vec![
+        accounts[1].clone(),
+        accounts[2].clone()
+    ]);
+
+    // Already satisfied
+    let res = service.missing_signers(ctx, witness(&[2, 3]));
+    assert_eq!(res.succeed_data, MissingSignersResponse::default());
+}
+
+#[test]
+fn test_verify_signature_witness_too_large() {
+    let cycles_limit = 1024 * 1024 * 1024; // 1073741824
+    let caller = Address::from_str("muta14e0lmgck835vm2dfm0w3ckv6svmez8fdgdl705").unwrap();
+    let service = new_multi_signature_service();
+    let ctx = mock_context(cycles_limit, caller.clone());
+
+    let stx = SignedTransaction {
+        raw:       RawTransaction {
+            chain_id: mock_hash(),
+            cycles_price: 1,
+            cycles_limit,
+            nonce: mock_hash(),
+            request: TransactionRequest {
+                method:       String::new(),
+                service_name: String::new(),
+                payload:      String::new(),
+            },
+            timeout: 0,
+            sender: caller,
+        },
+        tx_hash:   ctx.get_tx_hash().unwrap(),
+        pubkey:    get_random_bytes(MAX_WITNESS_SIZE),
+        signature: get_random_bytes(1),
+    };
+
+    let res = service.verify_signature(ctx.clone(), stx);
+    assert_eq!(res.code, 114);
+    // rejected before charging for the witness
+    assert_eq!(ctx.get_cycles_used(), 21_000);
+}
@@ -32,11 +32,14 @@ pub struct InitGenesisPayload {
 
 #[derive(RlpFixedCodec, Deserialize, Serialize, Clone, Debug, PartialEq, Eq)]
 pub struct GenerateMultiSigAccountPayload {
-    pub owner:            Address,
-    pub autonomy:         bool,
-    pub addr_with_weight: Vec<AddressWithWeight>,
-    pub threshold:        u32,
-    pub memo:             String,
+    pub owner:              Address,
+    pub autonomy:           bool,
+    pub addr_with_weight:   Vec<AddressWithWeight>,
+    pub threshold:          u32,
+    pub memo:               String,
+    /// The permission expires after this height, never if `None`.
+    #[serde(default)]
+    pub valid_until_height: Option<u64>,
 }
 
 #[derive(RlpFixedCodec, Deserialize, Serialize, Clone, Debug, Default)]
@@ -52,6 +55,15 @@ pub struct VerifySignaturePayload {
     pub sender:     Address,
 }
 
+/// Weight still required to reach the threshold, and the absent members
+/// with the least total weight covering it. A multiple member is listed by
+/// its own account address.
+#[derive(RlpFixedCodec, Deserialize, Serialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct MissingSignersResponse {
+    pub missing_weight:  u32,
+    pub missing_signers: Vec<AddressWithWeight>,
+}
+
 #[derive(RlpFixedCodec, Deserialize, Serialize, Clone, Debug)]
 pub struct GetMultiSigAccountPayload {
     pub multi_sig_address: Address,
@@ -66,6 +78,11 @@ pub struct GetMultiSigAccountResponse {
 pub struct ChangeOwnerPayload {
     pub multi_sig_address: Address,
     pub new_owner:         Address,
+    // If set, the new owner must be an existing multi-signature account or one
+    // of the accounts of this multi-signature account. Leave it unset to skip
+    // the check, e.g. for an address that has never been used on chain.
+    #[serde(default)]
+    pub check_new_owner:   bool,
 }
 
 #[derive(RlpFixedCodec, Deserialize, Serialize, Clone, Debug)]
@@ -101,22 +118,97 @@ pub struct SetThresholdPayload {
 
 #[derive(RlpFixedCodec, Deserialize, Serialize, Clone, Debug)]
 pub struct UpdateAccountPayload {
-    pub account_address:  Address,
-    pub owner:            Address,
-    pub addr_with_weight: Vec<AddressWithWeight>,
-    pub threshold:        u32,
-    pub memo:             String,
+    pub account_address:    Address,
+    pub owner:              Address,
+    pub addr_with_weight:   Vec<AddressWithWeight>,
+    pub threshold:          u32,
+    pub memo:               String,
+    #[serde(default)]
+    pub valid_until_height: Option<u64>,
 }
 
-#[derive(RlpFixedCodec, Deserialize, Serialize, Clone, Debug, Default, PartialEq, Eq)]
+#[derive(Deserialize, Serialize, Clone, Debug, Default, PartialEq, Eq)]
 pub struct MultiSigPermission {
-    pub owner:     Address,
-    pub accounts:  Vec<Account>,
-    pub threshold: u32,
-    pub memo:      String,
+    pub owner:              Address,
+    pub accounts:           Vec<Account>,
+    pub threshold:          u32,
+    pub memo:               String,
+    #[serde(default)]
+    pub valid_until_height: Option<u64>,
+}
+
+// Encoded the same as the derived codec, but permissions stored before
+// `valid_until_height` was added are lists of 4 items, they decode to `None`.
+// A permission without expiry is still encoded that way, so that state written
+// by blocks before stays the same when they are executed again.
+impl rlp::Decodable for MultiSigPermission {
+    fn decode(rlp: &rlp::Rlp) -> Result<Self, rlp::DecoderError> {
+        fn decode_item<T: FixedCodec>(bytes: Vec<u8>) -> Result<T, rlp::DecoderError> {
+            T::decode_fixed(Bytes::from(bytes))
+                .map_err(|_| rlp::DecoderError::Custom("decode fixed error"))
+        }
+
+        let accounts = rlp
+            .list_at::<Vec<u8>>(1)?
+            .into_iter()
+            .map(decode_item)
+            .collect::<Result<Vec<Account>, _>>()?;
+        let valid_until_height = if rlp.item_count()? > 4 {
+            decode_item(rlp.val_at(4)?)?
+        } else {
+            None
+        };
+
+        Ok(MultiSigPermission {
+            owner: decode_item(rlp.val_at(0)?)?,
+            accounts,
+            threshold: decode_item(rlp.val_at(2)?)?,
+            memo: decode_item(rlp.val_at(3)?)?,
+            valid_until_height,
+        })
+    }
+}
+
+impl rlp::Encodable for MultiSigPermission {
+    fn rlp_append(&self, s: &mut rlp::RlpStream) {
+        fn encode_item<T: FixedCodec>(item: &T) -> Vec<u8> {
+            item.encode_fixed()
+                .expect("fixed_codec not supported")
+                .to_vec()
+        }
+
+        let accounts = self.accounts.iter().map(encode_item).collect::<Vec<_>>();
+
+        match self.valid_until_height {
+            Some(_) => s.begin_list(5),
+            None => s.begin_list(4),
+        };
+        s.append(&encode_item(&self.owner));
+        s.append_list::<Vec<u8>, _>(&accounts);
+        s.append(&encode_item(&self.threshold));
+        s.append(&encode_item(&self.memo));
+        if self.valid_until_height.is_some() {
+            s.append(&encode_item(&self.valid_until_height));
+        }
+    }
+}
+
+impl FixedCodec for MultiSigPermission {
+    fn encode_fixed(&self) -> ProtocolResult<Bytes> {
+        Ok(Bytes::from(rlp::encode(self)))
+    }
+
+    fn decode_fixed(bytes: Bytes) -> ProtocolResult<Self> {
+        Ok(rlp::decode(bytes.as_ref()).map_err(FixedCodecError::from)?)
+    }
 }
 
 impl MultiSigPermission {
+    pub fn is_expired(&self, height: u64) -> bool {
+        self.valid_until_height
+            .map_or(false, |until| height > until)
+    }
+
     pub fn get_account(&self, addr: &Address) -> Option<Account> {
         for account in self.accounts.iter() {
             if &account.address == addr {
@@ -126,6 +126,19 @@ impl CommonStorage for MockStorage {
         unimplemented!()
     }
 
+    async fn contains_block(&self, _ctx: Context, _height: u64) -> ProtocolResult<bool> {
+        unimplemented!()
+    }
+
+    async fn get_blocks(
+        &self,
+        _ctx: Context,
+        _from: u64,
+        _limit: u64,
+    ) -> ProtocolResult<Vec<Block>> {
+        unimplemented!()
+    }
+
     async fn get_block_header(
         &self,
         _ctx: Context,
@@ -183,6 +196,39 @@ impl Storage for MockStorage {
         unimplemented!()
     }
 
+    async fn get_transactions_by_hashes(
+        &self,
+        _: Context,
+        _: &[Hash],
+    ) -> ProtocolResult<Vec<Option<SignedTransaction>>> {
+        unimplemented!()
+    }
+
+    async fn get_transaction_at(
+        &self,
+        _ctx: Context,
+        _: u64,
+        _: &Hash,
+    ) -> ProtocolResult<Option<SignedTransaction>> {
+        unimplemented!()
+    }
+
+    async fn get_transaction_location(
+        &self,
+        _ctx: Context,
+        _: &Hash,
+    ) -> ProtocolResult<Option<(u64, usize)>> {
+        unimplemented!()
+    }
+
+    async fn contains_transaction(&self, _ctx: Context, _: &Hash) -> ProtocolResult<bool> {
+        unimplemented!()
+    }
+
+    async fn filter_existing(&self, _ctx: Context, _: Vec<Hash>) -> ProtocolResult<Vec<Hash>> {
+        unimplemented!()
+    }
+
     async fn insert_receipts(
         &self,
         _: Context,
@@ -205,6 +251,10 @@ impl Storage for MockStorage {
         unimplemented!()
     }
 
+    async fn get_block_receipts(&self, _ctx: Context, _: u64) -> ProtocolResult<Vec<Receipt>> {
+        unimplemented!()
+    }
+
     async fn update_latest_proof(&self, _: Context, _: Proof) -> ProtocolResult<()> {
         unimplemented!()
     }
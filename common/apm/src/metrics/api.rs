@@ -9,6 +9,13 @@ make_auto_flush_static_metric! {
     pub label_enum RequestKind {
         send_transaction,
         get_block,
+        get_transactions,
+        get_blocks_since,
+        get_latest_height,
+        chain_info,
+        get_validators,
+        get_vote_participation,
+        get_block_receipts,
     }
 
     pub label_enum SendTransactionResult {
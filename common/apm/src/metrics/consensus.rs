@@ -47,6 +47,12 @@ lazy_static! {
         &["type", "result"]
     )
     .unwrap();
+    pub static ref CONSENSUS_VIEW_CHANGE_COUNTER_VEC: IntCounterVec = register_int_counter_vec!(
+        "muta_consensus_view_change_total",
+        "Total number of consensus view change by reason",
+        &["reason"]
+    )
+    .unwrap();
     pub static ref CONSENSUS_TIME_HISTOGRAM_VEC: HistogramVec = register_histogram_vec!(
         "muta_consensus_time_cost_seconds",
         "Consensus process time cost",
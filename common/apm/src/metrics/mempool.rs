@@ -1,6 +1,7 @@
 use crate::metrics::{
-    auto_flush_from, exponential_buckets, make_auto_flush_static_metric, register_histogram_vec,
-    register_int_counter_vec, register_int_gauge, HistogramVec, IntCounterVec, IntGauge,
+    auto_flush_from, exponential_buckets, make_auto_flush_static_metric, register_histogram,
+    register_histogram_vec, register_int_counter, register_int_counter_vec, register_int_gauge,
+    Histogram, HistogramVec, IntCounter, IntCounterVec, IntGauge,
 };
 
 use lazy_static::lazy_static;
@@ -72,6 +73,41 @@ lazy_static! {
     .expect("mempool current size");
     pub static ref MEMPOOL_LEN_GAUGE: IntGauge =
         register_int_gauge!("muta_mempool_tx_count", "Tx len in mempool").unwrap();
+    pub static ref MEMPOOL_BROADCAST_DROPPED_COUNTER: IntCounter = register_int_counter!(
+        "muta_mempool_broadcast_dropped_total",
+        "The transactions dropped from broadcast because of a full channel"
+    )
+    .unwrap();
+    pub static ref MEMPOOL_INSERT_TX_ORIGIN_COUNTER_VEC: IntCounterVec = register_int_counter_vec!(
+        "muta_mempool_insert_tx_total",
+        "The transactions inserted into mempool by origin",
+        &["origin"]
+    )
+    .expect("mempool insert tx origin counter");
+    pub static ref MEMPOOL_PACKAGE_TX_ORIGIN_COUNTER_VEC: IntCounterVec =
+        register_int_counter_vec!(
+            "muta_mempool_package_tx_total",
+            "The transactions packaged as order transactions by origin",
+            &["origin"]
+        )
+        .expect("mempool package tx origin counter");
+    pub static ref MEMPOOL_TX_PENDING_SECONDS: Histogram = register_histogram!(
+        "muta_mempool_tx_pending_seconds",
+        "Time a transaction stays in mempool before it is committed",
+        exponential_buckets(0.5, 2.0, 12).expect("mempool tx pending exponential")
+    )
+    .expect("mempool tx pending time");
+    pub static ref MEMPOOL_TX_EVICTED_PENDING_SECONDS: Histogram = register_histogram!(
+        "muta_mempool_tx_evicted_pending_seconds",
+        "Time a transaction stays in mempool before it is evicted",
+        exponential_buckets(0.5, 2.0, 12).expect("mempool tx evicted pending exponential")
+    )
+    .expect("mempool tx evicted pending time");
+    pub static ref MEMPOOL_WRONG_CHAIN_TX_COUNTER: IntCounter = register_int_counter!(
+        "muta_mempool_wrong_chain_tx_total",
+        "The transactions rejected because of a wrong chain id"
+    )
+    .unwrap();
 }
 
 lazy_static! {
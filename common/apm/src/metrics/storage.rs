@@ -61,6 +61,13 @@ lazy_static! {
         &["cf"]
     )
     .unwrap();
+    pub static ref STORAGE_LATEST_BLOCK_CACHE_COUNTER_VEC: IntCounterVec =
+        register_int_counter_vec!(
+            "muta_storage_latest_block_cache_total",
+            "Storage latest block cache hit and miss number",
+            &["result"]
+        )
+        .unwrap();
 }
 
 lazy_static! {
@@ -88,6 +95,13 @@ pub fn on_storage_put_state(duration: Duration, size: i64) {
     STORAGE_PUT_CF_BYTES_COUNTER.state.inc_by(size);
 }
 
+pub fn on_latest_block_cache(hit: bool) {
+    let result = if hit { "hit" } else { "miss" };
+    STORAGE_LATEST_BLOCK_CACHE_COUNTER_VEC
+        .with_label_values(&[result])
+        .inc();
+}
+
 pub fn on_storage_get_cf(sc: StorageCategory, duration: Duration, keys: i64) {
     let seconds = duration_to_sec(duration);
 
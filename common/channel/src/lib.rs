@@ -1,7 +1,48 @@
+use futures::channel::mpsc::Receiver;
+
+/// Drain items already in a channel in one call.
+pub trait TryRecvBatch<T> {
+    /// Pull up to `max` items already in channel without waiting, an empty
+    /// vec if none is ready.
+    fn try_recv_batch(&mut self, max: usize) -> Vec<T>;
+}
+
+impl<T> TryRecvBatch<T> for Receiver<T> {
+    fn try_recv_batch(&mut self, max: usize) -> Vec<T> {
+        let mut items = Vec::new();
+        while items.len() < max {
+            match self.try_next() {
+                Ok(Some(item)) => items.push(item),
+                // Empty or closed
+                _ => break,
+            }
+        }
+        items
+    }
+}
+
 #[cfg(test)]
 mod tests {
+    use futures::channel::mpsc::channel;
+
+    use super::TryRecvBatch;
+
     #[test]
-    fn it_works() {
-        assert_eq!(2 + 2, 4);
+    fn test_try_recv_batch() {
+        let (mut tx, mut rx) = channel(10);
+        assert!(rx.try_recv_batch(10).is_empty());
+
+        for i in 0..5 {
+            tx.try_send(i).unwrap();
+        }
+        assert_eq!(rx.try_recv_batch(10), vec![0, 1, 2, 3, 4]);
+        assert!(rx.try_recv_batch(10).is_empty());
+
+        for i in 0..5 {
+            tx.try_send(i).unwrap();
+        }
+        assert_eq!(rx.try_recv_batch(3), vec![0, 1, 2]);
+        drop(tx);
+        assert_eq!(rx.try_recv_batch(10), vec![3, 4]);
     }
 }
@@ -42,6 +42,40 @@ pub fn parse<T: de::DeserializeOwned>(name: &str) -> Result<T, ParseError> {
     }
 }
 
+/// Parse the base config, then the override config, and deserialize the
+/// override merged onto the base. Both names are resolved like `parse`.
+///
+/// Merge semantics:
+/// - a table is merged key by key, recursively;
+/// - any other value of the override, arrays included, replaces the one of the
+///   base, arrays are never appended.
+pub fn parse_with_overrides<T: de::DeserializeOwned>(
+    base: &str,
+    overrides: &str,
+) -> Result<T, ParseError> {
+    let mut base: toml::Value = parse(base)?;
+    let overrides: toml::Value = parse(overrides)?;
+
+    merge(&mut base, overrides);
+    Ok(base.try_into()?)
+}
+
+fn merge(base: &mut toml::Value, overrides: toml::Value) {
+    match (base, overrides) {
+        (toml::Value::Table(base), toml::Value::Table(overrides)) => {
+            for (key, value) in overrides.into_iter() {
+                match base.get_mut(&key) {
+                    Some(base_value) => merge(base_value, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, overrides) => *base = overrides,
+    }
+}
+
 #[derive(Debug)]
 pub enum ParseError {
     IO(io::Error),
@@ -81,10 +115,23 @@ impl From<reqwest::Error> for ParseError {
 
 #[cfg(test)]
 mod tests {
-    use super::{parse, parse_file, parse_http, parse_reader};
+    use super::{merge, parse, parse_file, parse_http, parse_reader, parse_with_overrides};
     use serde_derive::Deserialize;
     use stringreader::StringReader;
 
+    #[derive(Debug, Deserialize)]
+    struct NodeConfig {
+        name:    String,
+        network: NetworkConfig,
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct NetworkConfig {
+        listening_address: String,
+        max_connected:     u64,
+        bootstraps:        Vec<String>,
+    }
+
     #[derive(Debug, Deserialize)]
     struct Config {
         global_string: Option<String>,
@@ -103,6 +150,56 @@ mod tests {
         assert_eq!(config.global_int, Some(42));
     }
 
+    #[test]
+    fn test_merge() {
+        let mut base: toml::Value = toml::from_str(
+            r#"
+        name = "base"
+        [network]
+        listening_address = "0.0.0.0:1337"
+        max_connected = 10
+        bootstraps = ["a", "b"]
+    "#,
+        )
+        .unwrap();
+        let overrides: toml::Value = toml::from_str(
+            r#"
+        name = "node1"
+        [network]
+        max_connected = 20
+        bootstraps = ["c"]
+    "#,
+        )
+        .unwrap();
+
+        merge(&mut base, overrides);
+        let config: NodeConfig = base.try_into().unwrap();
+        // scalar is overridden
+        assert_eq!(config.name, "node1");
+        // nested table is merged
+        assert_eq!(config.network.listening_address, "0.0.0.0:1337");
+        assert_eq!(config.network.max_connected, 20);
+        // array is replaced
+        assert_eq!(config.network.bootstraps, vec!["c".to_owned()]);
+    }
+
+    #[test]
+    fn test_parse_with_overrides() {
+        let dir = std::env::temp_dir();
+        let base = dir.join("test_parse_with_overrides_base.toml");
+        let overrides = dir.join("test_parse_with_overrides_node.toml");
+        std::fs::write(&base, "global_string = \"Best Food\"\nglobal_int = 42\n").unwrap();
+        std::fs::write(&overrides, "global_int = 43\n").unwrap();
+
+        let config: Config =
+            parse_with_overrides(base.to_str().unwrap(), overrides.to_str().unwrap()).unwrap();
+        assert_eq!(config.global_string, Some(String::from("Best Food")));
+        assert_eq!(config.global_int, Some(43));
+
+        std::fs::remove_file(base).unwrap();
+        std::fs::remove_file(overrides).unwrap();
+    }
+
     #[ignore]
     #[test]
     fn test_parse_file() {
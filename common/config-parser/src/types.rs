@@ -5,22 +5,40 @@ use std::path::PathBuf;
 use serde_derive::Deserialize;
 
 use core_consensus::{DEFAULT_OVERLORD_GAP, DEFAULT_SYNC_TXS_CHUNK_SIZE};
-use core_mempool::{DEFAULT_BROADCAST_TXS_INTERVAL, DEFAULT_BROADCAST_TXS_SIZE};
+use core_mempool::{
+    DEFAULT_BROADCAST_CHANNEL_SIZE, DEFAULT_BROADCAST_TXS_INTERVAL, DEFAULT_BROADCAST_TXS_SIZE,
+};
+use core_storage::DEFAULT_BLOCK_CACHE_SIZE;
 use protocol::types::Hex;
 
 #[derive(Debug, Deserialize)]
 pub struct ConfigGraphQL {
-    pub listening_address:   SocketAddr,
-    pub graphql_uri:         String,
-    pub graphiql_uri:        String,
+    pub listening_address:      SocketAddr,
+    pub graphql_uri:            String,
+    pub graphiql_uri:           String,
     #[serde(default)]
-    pub workers:             usize,
+    pub workers:                usize,
     #[serde(default)]
-    pub maxconn:             usize,
+    pub maxconn:                usize,
     #[serde(default)]
-    pub max_payload_size:    usize,
-    pub tls:                 Option<ConfigGraphQLTLS>,
-    pub enable_dump_profile: Option<bool>,
+    pub max_payload_size:       usize,
+    #[serde(default)]
+    pub max_batch_query_size:   usize,
+    #[serde(default)]
+    pub max_batch_request_size: usize,
+    #[serde(default)]
+    pub max_query_cost:         u64,
+    #[serde(default)]
+    pub max_query_depth:        usize,
+    #[serde(default)]
+    pub max_aliases:            usize,
+    #[serde(default)]
+    pub ready_commit_timeout:   u64,
+    pub tls:                    Option<ConfigGraphQLTLS>,
+    pub enable_dump_profile:    Option<bool>,
+    #[serde(default)]
+    pub maintenance_mode:       bool,
+    pub admin_token:            Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -72,6 +90,8 @@ pub struct ConfigConsensus {
     pub overlord_gap:        usize,
     #[serde(default = "default_sync_txs_chunk_size")]
     pub sync_txs_chunk_size: usize,
+    #[serde(default)]
+    pub min_block_interval:  u64,
 }
 
 fn default_broadcast_txs_size() -> usize {
@@ -82,6 +102,10 @@ fn default_broadcast_txs_interval() -> u64 {
     DEFAULT_BROADCAST_TXS_INTERVAL
 }
 
+fn default_broadcast_channel_size() -> usize {
+    DEFAULT_BROADCAST_CHANNEL_SIZE
+}
+
 #[derive(Debug, Deserialize)]
 pub struct ConfigMempool {
     pub pool_size: u64,
@@ -90,22 +114,62 @@ pub struct ConfigMempool {
     pub broadcast_txs_size:     usize,
     #[serde(default = "default_broadcast_txs_interval")]
     pub broadcast_txs_interval: u64,
+    #[serde(default = "default_broadcast_channel_size")]
+    pub broadcast_channel_size: usize,
+    pub adaptive_broadcast:     Option<ConfigMempoolAdaptiveBroadcast>,
+    #[serde(default)]
+    pub enable_wal:             bool,
+    pub future_block_tolerance: Option<u64>,
+    #[serde(default)]
+    pub dedup_window:           u64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ConfigMempoolAdaptiveBroadcast {
+    pub max_txs_size: usize,
+    pub min_interval: u64,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct ConfigExecutor {
-    pub light:             bool,
-    pub triedb_cache_size: usize,
+    pub light:                     bool,
+    pub triedb_cache_size:         usize,
+    #[serde(default)]
+    pub min_cycles_price:          u64,
+    #[serde(default)]
+    pub min_cycles_price_height:   Option<u64>,
+    #[serde(default)]
+    pub sender_blacklist:          Vec<String>,
+    #[serde(default)]
+    pub storage_cycles_per_byte:   u64,
+    #[serde(default)]
+    pub storage_cycles_height:     Option<u64>,
+    #[serde(default)]
+    pub parallel_exec:             bool,
+    #[serde(default)]
+    pub out_of_cycles_code_height: Option<u64>,
+}
+
+fn default_block_cache_size() -> usize {
+    DEFAULT_BLOCK_CACHE_SIZE
 }
 
 #[derive(Debug, Deserialize)]
 pub struct ConfigRocksDB {
-    pub max_open_files: i32,
+    pub max_open_files:   i32,
+    #[serde(default)]
+    pub block_checksum:   bool,
+    #[serde(default = "default_block_cache_size")]
+    pub block_cache_size: usize,
 }
 
 impl Default for ConfigRocksDB {
     fn default() -> Self {
-        Self { max_open_files: 64 }
+        Self {
+            max_open_files:   64,
+            block_checksum:   false,
+            block_cache_size: default_block_cache_size(),
+        }
     }
 }
 
@@ -184,6 +248,12 @@ impl Config {
         path_state
     }
 
+    pub fn data_path_for_mempool_wal(&self) -> PathBuf {
+        let mut path_state = self.data_path.clone();
+        path_state.push("mempool_wal");
+        path_state
+    }
+
     pub fn data_path_for_consensus_wal(&self) -> PathBuf {
         let mut path_state = self.data_path.clone();
         path_state.push("consensus_wal");
@@ -1,5 +1,17 @@
 #![feature(test)]
 
+mod scheme;
+mod shamir;
+
+use std::convert::TryFrom;
+
+use derive_more::Display;
+use lru::LruCache;
+use parking_lot::RwLock;
+use subtle::ConstantTimeEq;
+
+use protocol::types::Address;
+
 pub use ophelia::HashValue;
 pub use ophelia::{
     BlsSignatureVerify, Crypto, Error, PrivateKey, PublicKey, Signature, ToBlsPublicKey,
@@ -10,6 +22,342 @@ pub use ophelia_secp256k1::{
     Secp256k1, Secp256k1PrivateKey, Secp256k1PublicKey, Secp256k1Signature,
 };
 
+pub use scheme::{
+    SchemeError, SchemeRegistry, Secp256k1Scheme, SignatureScheme, SECP256K1_SCHEME_ID,
+};
+pub use shamir::{recover_secret, split_secret, Share, ShareError};
+
+/// Compare two byte slices in time independent of their content, use it
+/// instead of `==` when one side is a secret or an untrusted signature/hash.
+/// Only the lengths may leak, slices of different lengths are never equal.
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.ct_eq(b).into()
+}
+
+/// Sign without any system randomness, the same message and private key
+/// always produce the same signature.
+pub trait DeterministicSign: Crypto {
+    fn sign_deterministic(msg: &[u8], priv_key: &[u8]) -> Result<Self::Signature, Error>;
+}
+
+impl DeterministicSign for Secp256k1 {
+    /// The nonce is derived from the message and the private key as described
+    /// in RFC 6979, which is the default nonce function of libsecp256k1.
+    fn sign_deterministic(msg: &[u8], priv_key: &[u8]) -> Result<Self::Signature, Error> {
+        <Secp256k1 as Crypto>::sign_message(msg, priv_key)
+    }
+}
+
+/// Convert public keys between compressed and uncompressed form. Both forms
+/// are accepted as input.
+pub trait PubkeyCompression: Crypto {
+    fn compress_pubkey(pub_key: &[u8]) -> Result<[u8; 33], Error>;
+
+    fn decompress_pubkey(pub_key: &[u8]) -> Result<[u8; 65], Error>;
+}
+
+impl PubkeyCompression for Secp256k1 {
+    fn compress_pubkey(pub_key: &[u8]) -> Result<[u8; 33], Error> {
+        let pub_key = Secp256k1PublicKey::try_from(pub_key)?;
+
+        let mut compressed = [0u8; 33];
+        compressed.copy_from_slice(&pub_key.to_bytes());
+        Ok(compressed)
+    }
+
+    fn decompress_pubkey(pub_key: &[u8]) -> Result<[u8; 65], Error> {
+        let pub_key = Secp256k1PublicKey::try_from(pub_key)?;
+
+        let mut uncompressed = [0u8; 65];
+        uncompressed.copy_from_slice(&pub_key.to_uncompressed_bytes());
+        Ok(uncompressed)
+    }
+}
+
+/// Verify a signature together with the address its public key derives to,
+/// instead of checking the signature and the sender address separately.
+pub trait VerifyForAddress: Crypto {
+    fn verify_for_address(
+        msg: &[u8],
+        sig: &[u8],
+        pub_key: &[u8],
+        expected: &Address,
+    ) -> Result<(), AddressVerifyError>;
+}
+
+impl VerifyForAddress for Secp256k1 {
+    fn verify_for_address(
+        msg: &[u8],
+        sig: &[u8],
+        pub_key: &[u8],
+        expected: &Address,
+    ) -> Result<(), AddressVerifyError> {
+        let actual = Address::from_pubkey_bytes(pub_key)
+            .map_err(|_| AddressVerifyError::InvalidPublicKey)?;
+
+        <Secp256k1 as Crypto>::verify_signature(msg, sig, pub_key)
+            .map_err(AddressVerifyError::Signature)?;
+
+        if !constant_time_eq(actual.as_slice(), expected.as_slice()) {
+            return Err(AddressVerifyError::AddressMismatch {
+                expected: expected.clone(),
+                actual,
+            });
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Display)]
+pub enum AddressVerifyError {
+    #[display(fmt = "invalid public key")]
+    InvalidPublicKey,
+
+    #[display(fmt = "signature verification failed: {:?}", _0)]
+    Signature(Error),
+
+    /// Signature is valid, but signed by another address.
+    #[display(fmt = "signature is for address {:?}, expect {:?}", actual, expected)]
+    AddressMismatch {
+        expected: Address,
+        actual:   Address,
+    },
+}
+
+impl std::error::Error for AddressVerifyError {}
+
+/// Bitmaps come from the network, so only the recently used aggregations
+/// are kept.
+const AGGREGATE_CACHE_SIZE: usize = 128;
+
+/// Public keys of the validator set in order, with the aggregated key of
+/// each participating subset cached by its bitmap. Bit `i` of the bitmap,
+/// counted from the most significant bit of the first byte, selects the
+/// `i`th validator. The cache is dropped when the validator set changes.
+pub struct ValidatorKeySet {
+    inner: RwLock<KeySetInner>,
+}
+
+struct KeySetInner {
+    pub_keys: Vec<BlsPublicKey>,
+    cache:    LruCache<Vec<u8>, BlsPublicKey>,
+}
+
+impl Default for ValidatorKeySet {
+    fn default() -> Self {
+        ValidatorKeySet::new(Vec::new())
+    }
+}
+
+impl ValidatorKeySet {
+    pub fn new(pub_keys: Vec<BlsPublicKey>) -> Self {
+        ValidatorKeySet {
+            inner: RwLock::new(KeySetInner {
+                pub_keys,
+                cache: LruCache::new(AGGREGATE_CACHE_SIZE),
+            }),
+        }
+    }
+
+    /// Replace the validator set and invalidate all cached aggregations.
+    pub fn update(&self, pub_keys: Vec<BlsPublicKey>) {
+        let mut inner = self.inner.write();
+        inner.pub_keys = pub_keys;
+        inner.cache.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.inner.read().pub_keys.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Return the aggregated public key of validators selected by bitmap.
+    /// Returns `None` if no validator is selected or a bit beyond the
+    /// validator set is set.
+    pub fn aggregate(&self, bitmap: &[u8]) -> Option<BlsPublicKey> {
+        let bitmap = bitmap.to_vec();
+        let mut inner = self.inner.write();
+        if let Some(key) = inner.cache.get(&bitmap) {
+            return Some(key.clone());
+        }
+
+        let len = inner.pub_keys.len();
+        let mut selected = Vec::new();
+        for (i, byte) in bitmap.iter().enumerate() {
+            for bit in 0..8 {
+                if byte & (0x80 >> bit) == 0 {
+                    continue;
+                }
+                let index = i * 8 + bit;
+                if index >= len {
+                    return None;
+                }
+                selected.push(inner.pub_keys[index].clone());
+            }
+        }
+        if selected.is_empty() {
+            return None;
+        }
+
+        let key = BlsPublicKey::aggregate(selected);
+        inner.cache.put(bitmap, key.clone());
+        Some(key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::OsRng;
+    use rand::RngCore;
+
+    use super::*;
+
+    #[test]
+    fn test_constant_time_eq() {
+        assert!(constant_time_eq(b"", b""));
+        assert!(constant_time_eq(b"muta", b"muta"));
+        assert!(!constant_time_eq(b"muta", b"mutb"));
+        assert!(!constant_time_eq(b"muta", b"mut"));
+        assert!(!constant_time_eq(b"", b"m"));
+    }
+
+    fn gen_bls_pub_keys(size: usize) -> Vec<BlsPublicKey> {
+        let common_ref: BlsCommonReference = "muta".into();
+        (0..size)
+            .map(|_| {
+                let mut seed = [0u8; 32];
+                OsRng.fill_bytes(&mut seed);
+                BlsPrivateKey::try_from([&[0u8; 16], seed.as_ref()].concat().as_ref())
+                    .unwrap()
+                    .pub_key(&common_ref)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_validator_key_set() {
+        let pub_keys = gen_bls_pub_keys(10);
+        let key_set = ValidatorKeySet::new(pub_keys.clone());
+        assert_eq!(key_set.len(), 10);
+
+        // validators 0, 2 and 9
+        let bitmap = [0b1010_0000, 0b0100_0000];
+        let expect = BlsPublicKey::aggregate(vec![
+            pub_keys[0].clone(),
+            pub_keys[2].clone(),
+            pub_keys[9].clone(),
+        ]);
+        assert_eq!(key_set.aggregate(&bitmap), Some(expect.clone()));
+        assert_eq!(key_set.aggregate(&bitmap), Some(expect));
+
+        assert_eq!(key_set.aggregate(&[0, 0]), None);
+        assert_eq!(key_set.aggregate(&[0, 0b0010_0000]), None);
+
+        let new_keys = gen_bls_pub_keys(10);
+        key_set.update(new_keys.clone());
+        let expect = BlsPublicKey::aggregate(vec![
+            new_keys[0].clone(),
+            new_keys[2].clone(),
+            new_keys[9].clone(),
+        ]);
+        assert_eq!(key_set.aggregate(&bitmap), Some(expect));
+    }
+
+    #[test]
+    fn test_validator_key_set_cache_bounded() {
+        let key_set = ValidatorKeySet::new(gen_bls_pub_keys(16));
+
+        for i in 0..=(u16::max_value() >> 6) {
+            let bitmap = (i + 1).to_be_bytes();
+            assert!(key_set.aggregate(&bitmap).is_some());
+        }
+        assert_eq!(key_set.inner.read().cache.len(), AGGREGATE_CACHE_SIZE);
+    }
+
+    #[test]
+    fn test_secp256k1_sign_deterministic() {
+        let priv_key = Secp256k1PrivateKey::generate(&mut OsRng);
+        let pub_key = priv_key.pub_key();
+        let mut msg = [0u8; 32];
+        OsRng.fill_bytes(&mut msg);
+
+        let priv_key = priv_key.to_bytes();
+        let sig_1 = Secp256k1::sign_deterministic(&msg, &priv_key).unwrap();
+        let sig_2 = Secp256k1::sign_deterministic(&msg, &priv_key).unwrap();
+        assert_eq!(sig_1.to_bytes(), sig_2.to_bytes());
+
+        assert!(Secp256k1::verify_signature(&msg, &sig_1.to_bytes(), &pub_key.to_bytes()).is_ok());
+    }
+
+    #[test]
+    fn test_secp256k1_verify_for_address() {
+        let priv_key = Secp256k1PrivateKey::generate(&mut OsRng);
+        let pub_key = priv_key.pub_key().to_bytes();
+        let address = Address::from_pubkey_bytes(&pub_key).unwrap();
+        let mut msg = [0u8; 32];
+        OsRng.fill_bytes(&mut msg);
+        let sig = Secp256k1::sign_message(&msg, &priv_key.to_bytes())
+            .unwrap()
+            .to_bytes();
+
+        assert!(Secp256k1::verify_for_address(&msg, &sig, &pub_key, &address).is_ok());
+
+        let other_priv_key = Secp256k1PrivateKey::generate(&mut OsRng);
+        let wrong_sig = Secp256k1::sign_message(&msg, &other_priv_key.to_bytes())
+            .unwrap()
+            .to_bytes();
+        match Secp256k1::verify_for_address(&msg, &wrong_sig, &pub_key, &address) {
+            Err(AddressVerifyError::Signature(_)) => (),
+            _ => panic!("should be signature error"),
+        }
+
+        let other_address =
+            Address::from_pubkey_bytes(other_priv_key.pub_key().to_bytes()).unwrap();
+        match Secp256k1::verify_for_address(&msg, &sig, &pub_key, &other_address) {
+            Err(AddressVerifyError::AddressMismatch { expected, actual }) => {
+                assert_eq!(expected, other_address);
+                assert_eq!(actual, address);
+            }
+            _ => panic!("should be address mismatch"),
+        }
+
+        match Secp256k1::verify_for_address(&msg, &sig, &pub_key[1..], &address) {
+            Err(AddressVerifyError::InvalidPublicKey) => (),
+            _ => panic!("should be invalid public key"),
+        }
+    }
+
+    #[test]
+    fn test_secp256k1_compress_pubkey() {
+        let pub_key = Secp256k1PrivateKey::generate(&mut OsRng).pub_key();
+        let compressed = pub_key.to_bytes();
+        let uncompressed = pub_key.to_uncompressed_bytes();
+
+        let decompressed = Secp256k1::decompress_pubkey(&compressed).unwrap();
+        assert_eq!(&decompressed[..], uncompressed.as_ref());
+        assert_eq!(
+            &Secp256k1::compress_pubkey(&decompressed).unwrap()[..],
+            compressed.as_ref()
+        );
+
+        // already in target form
+        assert_eq!(
+            &Secp256k1::compress_pubkey(&compressed).unwrap()[..],
+            compressed.as_ref()
+        );
+        assert_eq!(
+            &Secp256k1::decompress_pubkey(&uncompressed).unwrap()[..],
+            uncompressed.as_ref()
+        );
+
+        assert!(Secp256k1::compress_pubkey(&compressed[1..]).is_err());
+        assert!(Secp256k1::decompress_pubkey(&[0u8; 65]).is_err());
+    }
+}
+
 #[rustfmt::skip]
 /// Bench in Intel(R) Core(TM) i7-4770HQ CPU @ 2.20GHz (8 x 2200)
 /// test benches::bench_4_aggregated_sig         ... bench:      20,325 ns/iter (+/- 1,251)
@@ -448,4 +796,59 @@ mod benches {
                 .unwrap();
         })
     }
+
+    fn bench_64_aggregated_verify(b: &mut Bencher, cached: bool) {
+        let common_ref: BlsCommonReference = gen_common_ref().as_str().into();
+        let vote_msg = HashValue::try_from(
+            Hash::digest(Bytes::from(rlp::encode(&mock_vote())))
+                .as_bytes()
+                .as_ref(),
+        )
+        .unwrap();
+
+        let mut priv_pub_keys = Vec::new();
+        let mut signatures = Vec::new();
+        gen_key_pair_sigs(
+            64,
+            &mut priv_pub_keys,
+            &mut signatures,
+            &vote_msg,
+            &common_ref,
+        );
+
+        let sigs_pubkeys = signatures
+            .iter()
+            .zip(priv_pub_keys.iter())
+            .map(|(sig, key_pair)| (sig.clone(), key_pair.1.clone()))
+            .collect::<Vec<_>>();
+        let aggragated_sig = BlsSignature::combine(sigs_pubkeys);
+        let pub_keys = priv_pub_keys
+            .iter()
+            .map(|key_pair| key_pair.1.clone())
+            .collect::<Vec<_>>();
+        let key_set = ValidatorKeySet::new(pub_keys.clone());
+        let bitmap = [0xffu8; 8];
+
+        b.iter(move || {
+            let aggregated_key = if cached {
+                key_set.aggregate(&bitmap).unwrap()
+            } else {
+                BlsPublicKey::aggregate(pub_keys.clone())
+            };
+            aggragated_sig
+                .clone()
+                .verify(&vote_msg, &aggregated_key, &common_ref)
+                .unwrap();
+        })
+    }
+
+    #[bench]
+    fn bench_64_aggregated_verify_uncached(b: &mut Bencher) {
+        bench_64_aggregated_verify(b, false)
+    }
+
+    #[bench]
+    fn bench_64_aggregated_verify_cached(b: &mut Bencher) {
+        bench_64_aggregated_verify(b, true)
+    }
 }
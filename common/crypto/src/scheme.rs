@@ -0,0 +1,217 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use derive_more::Display;
+
+use protocol::Bytes;
+
+use crate::{Crypto, Error, Secp256k1, Signature};
+
+/// Scheme id of secp256k1, registered by default.
+pub const SECP256K1_SCHEME_ID: u8 = 0;
+
+/// A signature algorithm identified by a scheme byte, so that a witness can
+/// tell which one it's signed with. Messages are 32 bytes hashes, keys and
+/// signatures are in the scheme's own byte encoding.
+pub trait SignatureScheme: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    fn sign(&self, msg: &[u8], priv_key: &[u8]) -> Result<Bytes, SchemeError>;
+
+    fn verify(&self, msg: &[u8], sig: &[u8], pub_key: &[u8]) -> Result<(), SchemeError>;
+
+    /// Recover the public key which produced the signature.
+    fn recover(&self, msg: &[u8], sig: &[u8]) -> Result<Bytes, SchemeError>;
+}
+
+#[derive(Debug, Display)]
+pub enum SchemeError {
+    #[display(fmt = "unknown signature scheme {}", _0)]
+    UnknownScheme(u8),
+
+    #[display(fmt = "signature scheme {} doesn't support public key recovery", _0)]
+    RecoverUnsupported(&'static str),
+
+    #[display(fmt = "{:?}", _0)]
+    Crypto(Error),
+}
+
+impl std::error::Error for SchemeError {}
+
+impl From<Error> for SchemeError {
+    fn from(err: Error) -> Self {
+        SchemeError::Crypto(err)
+    }
+}
+
+/// Secp256k1 signatures are 64 bytes compact ones without recovery id, so
+/// public key can't be recovered from them.
+pub struct Secp256k1Scheme;
+
+impl SignatureScheme for Secp256k1Scheme {
+    fn name(&self) -> &'static str {
+        "secp256k1"
+    }
+
+    fn sign(&self, msg: &[u8], priv_key: &[u8]) -> Result<Bytes, SchemeError> {
+        let sig = <Secp256k1 as Crypto>::sign_message(msg, priv_key)?;
+        Ok(sig.to_bytes())
+    }
+
+    fn verify(&self, msg: &[u8], sig: &[u8], pub_key: &[u8]) -> Result<(), SchemeError> {
+        <Secp256k1 as Crypto>::verify_signature(msg, sig, pub_key)?;
+        Ok(())
+    }
+
+    fn recover(&self, _msg: &[u8], _sig: &[u8]) -> Result<Bytes, SchemeError> {
+        Err(SchemeError::RecoverUnsupported(self.name()))
+    }
+}
+
+/// Signature schemes keyed by scheme id. Build it once on startup, then
+/// dispatch on the scheme byte of a witness.
+pub struct SchemeRegistry {
+    schemes: HashMap<u8, Arc<dyn SignatureScheme>>,
+}
+
+impl Default for SchemeRegistry {
+    fn default() -> Self {
+        let mut registry = SchemeRegistry::empty();
+        registry.register(SECP256K1_SCHEME_ID, Arc::new(Secp256k1Scheme));
+        registry
+    }
+}
+
+impl SchemeRegistry {
+    pub fn empty() -> Self {
+        SchemeRegistry {
+            schemes: HashMap::new(),
+        }
+    }
+
+    /// Register a scheme under `id`, return the one it replaces if any.
+    pub fn register(
+        &mut self,
+        id: u8,
+        scheme: Arc<dyn SignatureScheme>,
+    ) -> Option<Arc<dyn SignatureScheme>> {
+        self.schemes.insert(id, scheme)
+    }
+
+    pub fn get(&self, id: u8) -> Result<&Arc<dyn SignatureScheme>, SchemeError> {
+        self.schemes.get(&id).ok_or(SchemeError::UnknownScheme(id))
+    }
+
+    pub fn sign(&self, id: u8, msg: &[u8], priv_key: &[u8]) -> Result<Bytes, SchemeError> {
+        self.get(id)?.sign(msg, priv_key)
+    }
+
+    pub fn verify(
+        &self,
+        id: u8,
+        msg: &[u8],
+        sig: &[u8],
+        pub_key: &[u8],
+    ) -> Result<(), SchemeError> {
+        self.get(id)?.verify(msg, sig, pub_key)
+    }
+
+    pub fn recover(&self, id: u8, msg: &[u8], sig: &[u8]) -> Result<Bytes, SchemeError> {
+        self.get(id)?.recover(msg, sig)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::OsRng;
+    use rand::RngCore;
+
+    use crate::{PrivateKey, PublicKey, Secp256k1PrivateKey, ToPublicKey};
+
+    use super::*;
+
+    const MOCK_SCHEME_ID: u8 = 0xff;
+
+    /// Signature is the public key, which is the private key itself,
+    /// followed by the message.
+    struct MockScheme;
+
+    impl SignatureScheme for MockScheme {
+        fn name(&self) -> &'static str {
+            "mock"
+        }
+
+        fn sign(&self, msg: &[u8], priv_key: &[u8]) -> Result<Bytes, SchemeError> {
+            Ok(Bytes::from([priv_key, msg].concat()))
+        }
+
+        fn verify(&self, msg: &[u8], sig: &[u8], pub_key: &[u8]) -> Result<(), SchemeError> {
+            if sig == [pub_key, msg].concat().as_slice() {
+                Ok(())
+            } else {
+                Err(Error::msg("mock signature mismatch").into())
+            }
+        }
+
+        fn recover(&self, msg: &[u8], sig: &[u8]) -> Result<Bytes, SchemeError> {
+            if sig.len() < msg.len() || !sig.ends_with(msg) {
+                return Err(Error::msg("mock signature mismatch").into());
+            }
+            Ok(Bytes::copy_from_slice(&sig[..sig.len() - msg.len()]))
+        }
+    }
+
+    #[test]
+    fn test_default_secp256k1_scheme() {
+        let registry = SchemeRegistry::default();
+        let priv_key = Secp256k1PrivateKey::generate(&mut OsRng);
+        let pub_key = priv_key.pub_key().to_bytes();
+        let mut msg = [0u8; 32];
+        OsRng.fill_bytes(&mut msg);
+
+        let sig = registry
+            .sign(SECP256K1_SCHEME_ID, &msg, &priv_key.to_bytes())
+            .unwrap();
+        assert!(registry
+            .verify(SECP256K1_SCHEME_ID, &msg, &sig, &pub_key)
+            .is_ok());
+        assert!(registry
+            .verify(SECP256K1_SCHEME_ID, &[0u8; 32], &sig, &pub_key)
+            .is_err());
+        match registry.recover(SECP256K1_SCHEME_ID, &msg, &sig) {
+            Err(SchemeError::RecoverUnsupported("secp256k1")) => (),
+            _ => panic!("should not support recovery"),
+        }
+    }
+
+    #[test]
+    fn test_register_mock_scheme() {
+        let mut registry = SchemeRegistry::default();
+        let msg = [1u8; 32];
+        match registry.sign(MOCK_SCHEME_ID, &msg, b"key") {
+            Err(SchemeError::UnknownScheme(MOCK_SCHEME_ID)) => (),
+            _ => panic!("should be unknown scheme"),
+        }
+
+        assert!(registry
+            .register(MOCK_SCHEME_ID, Arc::new(MockScheme))
+            .is_none());
+        assert_eq!(registry.get(MOCK_SCHEME_ID).unwrap().name(), "mock");
+
+        let sig = registry.sign(MOCK_SCHEME_ID, &msg, b"key").unwrap();
+        assert!(registry.verify(MOCK_SCHEME_ID, &msg, &sig, b"key").is_ok());
+        assert!(registry
+            .verify(MOCK_SCHEME_ID, &msg, &sig, b"other")
+            .is_err());
+        assert_eq!(
+            registry.recover(MOCK_SCHEME_ID, &msg, &sig).unwrap(),
+            Bytes::from_static(b"key")
+        );
+
+        // secp256k1 is still there
+        assert_eq!(
+            registry.get(SECP256K1_SCHEME_ID).unwrap().name(),
+            "secp256k1"
+        );
+    }
+}
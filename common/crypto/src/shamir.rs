@@ -0,0 +1,267 @@
+//! Shamir's secret sharing of private keys, so that operators can keep shares
+//! of a key in different places and recover it from any `threshold` of them.
+//!
+//! Each byte of the key is shared separately over GF(2^8), the reduction
+//! polynomial is x^8 + x^4 + x^3 + x + 1 as in AES. Field arithmetic on
+//! secret bytes runs without branches or table lookups, and share values are
+//! zeroed on drop.
+
+use std::collections::HashSet;
+use std::convert::TryFrom;
+
+use derive_more::Display;
+use rand::rngs::OsRng;
+use rand::RngCore;
+use zeroize::Zeroize;
+
+use crate::{Error, PrivateKey, Secp256k1PrivateKey};
+
+const SECRET_LEN: usize = 32;
+
+/// One share of a secret. `index` is the x coordinate, never zero, and
+/// `threshold` is the number of shares required to recover the secret.
+#[derive(Clone, PartialEq, Eq)]
+pub struct Share {
+    pub index:     u8,
+    pub threshold: u8,
+    pub value:     [u8; SECRET_LEN],
+}
+
+impl Drop for Share {
+    fn drop(&mut self) {
+        self.value.zeroize();
+    }
+}
+
+#[derive(Debug, Display)]
+pub enum ShareError {
+    #[display(
+        fmt = "threshold {} should be between 1 and share number {}",
+        threshold,
+        n
+    )]
+    InvalidThreshold { n: u8, threshold: u8 },
+
+    #[display(fmt = "{} shares are given, {} are required", actual, threshold)]
+    NotEnoughShares { threshold: u8, actual: usize },
+
+    #[display(fmt = "shares are split with different thresholds")]
+    ThresholdMismatch,
+
+    #[display(fmt = "zero or duplicate share index {}", _0)]
+    InvalidIndex(u8),
+
+    #[display(fmt = "{:?}", _0)]
+    Crypto(Error),
+}
+
+impl std::error::Error for ShareError {}
+
+impl From<Error> for ShareError {
+    fn from(err: Error) -> Self {
+        ShareError::Crypto(err)
+    }
+}
+
+/// Split `key` into `n` shares, any `threshold` of them recover it.
+pub fn split_secret(
+    key: &Secp256k1PrivateKey,
+    n: u8,
+    threshold: u8,
+) -> Result<Vec<Share>, ShareError> {
+    if threshold == 0 || threshold > n {
+        return Err(ShareError::InvalidThreshold { n, threshold });
+    }
+
+    let secret = key.to_bytes();
+    let mut shares = (1..=n)
+        .map(|index| Share {
+            index,
+            threshold,
+            value: [0u8; SECRET_LEN],
+        })
+        .collect::<Vec<_>>();
+
+    // Coefficients of the polynomial, the constant term is the secret byte.
+    let mut coefficients = vec![0u8; threshold as usize];
+    for (i, byte) in secret.iter().enumerate() {
+        coefficients[0] = *byte;
+        OsRng.fill_bytes(&mut coefficients[1..]);
+
+        for share in shares.iter_mut() {
+            share.value[i] = eval_polynomial(&coefficients, share.index);
+        }
+    }
+    coefficients.zeroize();
+
+    Ok(shares)
+}
+
+/// Recover the key from at least `threshold` shares of it.
+pub fn recover_secret(shares: &[Share]) -> Result<Secp256k1PrivateKey, ShareError> {
+    let threshold = match shares.first() {
+        Some(share) => share.threshold,
+        None => {
+            return Err(ShareError::NotEnoughShares {
+                threshold: 1,
+                actual:    0,
+            })
+        }
+    };
+    if shares.iter().any(|share| share.threshold != threshold) {
+        return Err(ShareError::ThresholdMismatch);
+    }
+    if shares.len() < threshold as usize {
+        return Err(ShareError::NotEnoughShares {
+            threshold,
+            actual: shares.len(),
+        });
+    }
+
+    let mut indexes = HashSet::new();
+    for share in shares.iter() {
+        if share.index == 0 || !indexes.insert(share.index) {
+            return Err(ShareError::InvalidIndex(share.index));
+        }
+    }
+
+    // Lagrange interpolation at x = 0, subtraction is xor in GF(2^8).
+    let mut secret = [0u8; SECRET_LEN];
+    for (j, share) in shares.iter().enumerate() {
+        let mut basis = 1u8;
+        for (m, other) in shares.iter().enumerate() {
+            if m != j {
+                basis = gf_mul(basis, gf_div(other.index, other.index ^ share.index));
+            }
+        }
+
+        for (byte, value) in secret.iter_mut().zip(share.value.iter()) {
+            *byte ^= gf_mul(*value, basis);
+        }
+    }
+
+    let key = Secp256k1PrivateKey::try_from(&secret[..]);
+    secret.zeroize();
+    Ok(key?)
+}
+
+fn eval_polynomial(coefficients: &[u8], x: u8) -> u8 {
+    coefficients
+        .iter()
+        .rev()
+        .fold(0u8, |acc, coefficient| gf_mul(acc, x) ^ coefficient)
+}
+
+/// Constant time, bits are selected by masks rather than branches.
+fn gf_mul(mut a: u8, mut b: u8) -> u8 {
+    let mut product = 0u8;
+    for _ in 0..8 {
+        product ^= a & (b & 1).wrapping_neg();
+        let carry = (a >> 7).wrapping_neg();
+        a = (a << 1) ^ (0x1b & carry);
+        b >>= 1;
+    }
+    product
+}
+
+/// `b` must not be zero. The inverse of `b` is `b^254` since `b^255 == 1`.
+fn gf_div(a: u8, b: u8) -> u8 {
+    let mut inverse = 1u8;
+    for _ in 0..254 {
+        inverse = gf_mul(inverse, b);
+    }
+    gf_mul(a, inverse)
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::OsRng;
+
+    use crate::{PrivateKey, Secp256k1PrivateKey};
+
+    use super::{gf_div, gf_mul, recover_secret, split_secret, ShareError};
+
+    // Shift and add with a branch on each bit, as a reference for `gf_mul`.
+    fn gf_mul_reference(mut a: u8, mut b: u8) -> u8 {
+        let mut product = 0u8;
+        while b != 0 {
+            if b & 1 != 0 {
+                product ^= a;
+            }
+            let carry = a & 0x80;
+            a <<= 1;
+            if carry != 0 {
+                a ^= 0x1b;
+            }
+            b >>= 1;
+        }
+        product
+    }
+
+    #[test]
+    fn test_gf_mul() {
+        for a in 0..=255u8 {
+            for b in 0..=255u8 {
+                assert_eq!(gf_mul(a, b), gf_mul_reference(a, b));
+            }
+        }
+        // Example from FIPS-197
+        assert_eq!(gf_mul(0x57, 0x83), 0xc1);
+    }
+
+    #[test]
+    fn test_gf_div() {
+        for a in 1..=255u8 {
+            assert_eq!(gf_div(a, a), 1);
+            assert_eq!(gf_div(gf_mul(a, 0x53), 0x53), a);
+        }
+    }
+
+    #[test]
+    fn test_recover_from_threshold_shares() {
+        let key = Secp256k1PrivateKey::generate(&mut OsRng);
+        let shares = split_secret(&key, 5, 3).unwrap();
+        assert_eq!(shares.len(), 5);
+
+        for picked in [[0, 1, 2], [0, 2, 4], [4, 3, 1]].iter() {
+            let picked = picked
+                .iter()
+                .map(|i| shares[*i].clone())
+                .collect::<Vec<_>>();
+            let recovered = recover_secret(&picked).unwrap();
+            assert_eq!(recovered.to_bytes(), key.to_bytes());
+        }
+
+        let recovered = recover_secret(&shares).unwrap();
+        assert_eq!(recovered.to_bytes(), key.to_bytes());
+    }
+
+    #[test]
+    fn test_recover_below_threshold() {
+        let key = Secp256k1PrivateKey::generate(&mut OsRng);
+        let shares = split_secret(&key, 5, 3).unwrap();
+
+        match recover_secret(&shares[..2]) {
+            Err(ShareError::NotEnoughShares { threshold, actual }) => {
+                assert_eq!(threshold, 3);
+                assert_eq!(actual, 2);
+            }
+            _ => panic!("should require 3 shares"),
+        }
+
+        let dup = vec![shares[0].clone(), shares[1].clone(), shares[0].clone()];
+        match recover_secret(&dup) {
+            Err(ShareError::InvalidIndex(index)) => assert_eq!(index, 1),
+            _ => panic!("should reject duplicate share"),
+        }
+    }
+
+    #[test]
+    fn test_split_invalid_threshold() {
+        let key = Secp256k1PrivateKey::generate(&mut OsRng);
+
+        assert!(split_secret(&key, 3, 4).is_err());
+        assert!(split_secret(&key, 3, 0).is_err());
+        assert_eq!(split_secret(&key, 1, 1).unwrap().len(), 1);
+    }
+}
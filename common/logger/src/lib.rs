@@ -21,6 +21,9 @@ pub use json::array;
 pub use json::object;
 use log4rs::append::file::FileAppender;
 
+/// Log target of audit records, they go to `audit.log` only.
+pub const AUDIT_TARGET: &str = "audit";
+
 // Example
 // ```rust
 //     let json_obj = json!({
@@ -59,6 +62,7 @@ pub fn init<S: ::std::hash::BuildHasher>(
 
     let muta_roller_pat = log_path.join("{date}.muta.{timestamp}.log");
     let metrics_roller_pat = log_path.join("{date}.metrics.{timestamp}.log");
+    let audit_roller_pat = log_path.join("{date}.audit.{timestamp}.log");
 
     let file_appender = {
         let size_trigger = SizeTrigger::new(file_size_limit);
@@ -91,6 +95,19 @@ pub fn init<S: ::std::hash::BuildHasher>(
             .unwrap()
     };
 
+    let audit_appender = {
+        let size_trigger = SizeTrigger::new(file_size_limit);
+        let roller = DateFixedWindowRoller::builder()
+            .build(&audit_roller_pat.to_string_lossy())
+            .unwrap();
+        let policy = CompoundPolicy::new(Box::new(size_trigger), Box::new(roller));
+
+        RollingFileAppender::builder()
+            .encoder(Box::new(JsonEncoder::new()))
+            .build(log_path.join("audit.log"), Box::new(policy))
+            .unwrap()
+    };
+
     let mut root_builder = Root::builder();
     if log_to_console {
         root_builder = root_builder.appender("console");
@@ -111,6 +128,12 @@ pub fn init<S: ::std::hash::BuildHasher>(
         },
     );
 
+    // Audit records are always kept, no matter what the root filter is.
+    let audit_logger = Logger::builder().additive(false).appender("audit").build(
+        AUDIT_TARGET,
+        convert_level(filter.as_ref()).max(LevelFilter::Info),
+    );
+
     let cli_logger = Logger::builder()
         .additive(false)
         .appender("cli")
@@ -122,7 +145,9 @@ pub fn init<S: ::std::hash::BuildHasher>(
         .appender(Appender::builder().build("file", Box::new(file_appender)))
         .appender(Appender::builder().build("metrics", Box::new(metrics_appender)))
         .appender(Appender::builder().build("cli", Box::new(cli_file_appender)))
+        .appender(Appender::builder().build("audit", Box::new(audit_appender)))
         .logger(metrics_logger)
+        .logger(audit_logger)
         .logger(cli_logger);
 
     for (module, level) in &modules_level {
@@ -160,14 +185,23 @@ pub fn metrics(name: &str, mut content: JsonValue) {
     });
 }
 
+// Usage:
+// audit("asset_transfer", &ctx, common_logger::object!{"caller" =>
+// caller.to_string(), "asset_id" => asset_id.as_hex()})
+pub fn audit(event: &str, ctx: &Context, mut fields: JsonValue) {
+    put_context_fields(ctx, &mut fields);
+
+    log::info!(target: AUDIT_TARGET, "{}", {
+        fields["event"] = event.into();
+        fields
+    });
+}
+
 // Usage:
 // log(Level::Info, "network", "netw0001", &ctx, common_logger::object!{"music"
 // : "beautiful world"})
 pub fn log(level: Level, module: &str, event: &str, ctx: &Context, mut msg: JsonValue) {
-    if let Some(trace_ctx) = trace_context(ctx) {
-        msg["trace_id"] = trace_ctx.trace_id.to_string().into();
-        msg["span_id"] = trace_ctx.span_id.into();
-    }
+    put_context_fields(ctx, &mut msg);
 
     log::log!(target: module, level, "{}", {
         msg["event"] = event.into();
@@ -175,6 +209,18 @@ pub fn log(level: Level, module: &str, event: &str, ctx: &Context, mut msg: Json
     });
 }
 
+fn put_context_fields(ctx: &Context, fields: &mut JsonValue) {
+    if let Some(trace_ctx) = trace_context(ctx) {
+        fields["trace_id"] = trace_ctx.trace_id.to_string().into();
+        fields["span_id"] = trace_ctx.span_id.into();
+    }
+
+    // NOTE: Set by core_api::State::new_context for graphql requests.
+    if let Some(request_id) = ctx.get::<String>("request_id") {
+        fields["request_id"] = request_id.as_str().into();
+    }
+}
+
 #[derive(Debug, Clone, Copy)]
 struct TraceContext {
     trace_id: TraceId,
@@ -217,4 +263,16 @@ mod tests {
         assert_eq!(json["msg"], "asset_01");
         assert_eq!(json["is_connected"], true);
     }
+
+    #[test]
+    fn test_request_id_field() {
+        let mut fields = json!({"height", 1});
+        put_context_fields(&Context::new(), &mut fields);
+        assert!(fields["request_id"].is_null());
+
+        let ctx = Context::new().with_value::<String>("request_id", "req-42".to_owned());
+        put_context_fields(&ctx, &mut fields);
+        assert_eq!(fields["request_id"], "req-42");
+        assert_eq!(fields["height"], 1);
+    }
 }
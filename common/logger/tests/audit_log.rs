@@ -0,0 +1,39 @@
+use std::collections::HashMap;
+use std::fs;
+
+use creep::Context;
+
+#[test]
+fn test_audit_log() {
+    let log_path = std::env::temp_dir().join(format!("muta-audit-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&log_path);
+
+    // Root filter is stricter than audit records.
+    common_logger::init(
+        "warn".to_owned(),
+        false,
+        false,
+        true,
+        false,
+        log_path.clone(),
+        1024 * 1024,
+        HashMap::new(),
+    );
+
+    common_logger::audit(
+        "asset_transfer",
+        &Context::new(),
+        common_logger::object! {"asset_id" => "0x01", "value" => 100},
+    );
+    log::warn!("not an audit record");
+    log::logger().flush();
+
+    let audit_log = fs::read_to_string(log_path.join("audit.log")).unwrap();
+    let main_log = fs::read_to_string(log_path.join("muta.log")).unwrap();
+    assert!(audit_log.contains("asset_transfer"));
+    assert!(!audit_log.contains("not an audit record"));
+    assert!(!main_log.contains("asset_transfer"));
+    assert!(main_log.contains("not an audit record"));
+
+    fs::remove_dir_all(log_path).unwrap();
+}
@@ -43,6 +43,20 @@ impl Merkle {
     }
 }
 
+/// Verify that `leaf` is included in the tree of `root`, with the proof from
+/// `Merkle::get_proof_by_input_index`.
+pub fn verify_proof(root: &Hash, leaf: &Hash, proof: &[ProofNode]) -> bool {
+    let computed = proof.iter().fold(leaf.clone(), |acc, node| {
+        if node.is_right {
+            merge(&acc, &node.hash)
+        } else {
+            merge(&node.hash, &acc)
+        }
+    });
+
+    &computed == root
+}
+
 fn merge(left: &Hash, right: &Hash) -> Hash {
     let left = left.as_bytes();
     let right = right.as_bytes();
@@ -53,6 +67,34 @@ fn merge(left: &Hash, right: &Hash) -> Hash {
     Hash::digest(Bytes::from(root))
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hashes(size: usize) -> Vec<Hash> {
+        (0..size)
+            .map(|i| Hash::digest(Bytes::from(i.to_string())))
+            .collect()
+    }
+
+    #[test]
+    fn test_verify_proof() {
+        for size in 1..=9 {
+            let leaves = hashes(size);
+            let merkle = Merkle::from_hashes(leaves.clone());
+            let root = merkle.get_root_hash().unwrap();
+
+            for (index, leaf) in leaves.iter().enumerate() {
+                let proof = merkle.get_proof_by_input_index(index).unwrap();
+                assert!(verify_proof(&root, leaf, &proof));
+
+                let other = Hash::digest(Bytes::from("other"));
+                assert!(!verify_proof(&root, &other, &proof));
+            }
+        }
+    }
+}
+
 #[rustfmt::skip]
 /// Bench in Intel(R) Core(TM) i7-4770HQ CPU @2.20GHz (8 x 2200):
 /// test benches::bench_merkle_1000_hashes  ... bench:   1,167,080 ns/iter (+/- 108,462)
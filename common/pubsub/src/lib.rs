@@ -1,3 +1,7 @@
+pub mod register;
+
+pub use register::{PubSubError, Publish, Subscription, TopicMetrics, TopicRegister};
+
 #[cfg(test)]
 mod tests {
     #[test]
@@ -0,0 +1,519 @@
+use std::any::Any;
+use std::collections::{HashMap, VecDeque};
+use std::error::Error;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use derive_more::Display;
+use futures::channel::mpsc::{self, Receiver, Sender};
+use futures::future::poll_fn;
+use futures::stream::{Stream, StreamExt};
+
+/// Message counters of a topic.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct TopicMetrics {
+    /// Messages sent to the topic.
+    pub published:   u64,
+    /// Copies of messages buffered to subscribers.
+    pub delivered:   u64,
+    /// Messages buffered but not yet received by the slowest subscriber.
+    pub max_backlog: usize,
+}
+
+/// Receive end of a subscription. It counts buffered messages not yet
+/// received, which is reported as backlog.
+pub struct Subscription<T> {
+    rx:      Receiver<T>,
+    backlog: Arc<AtomicUsize>,
+}
+
+impl<T> Stream for Subscription<T> {
+    type Item = T;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        let polled = self.rx.poll_next_unpin(cx);
+        if let Poll::Ready(Some(_)) = polled {
+            self.backlog.fetch_sub(1, Ordering::SeqCst);
+        }
+
+        polled
+    }
+}
+
+struct Subscriber<T> {
+    tx:      Sender<T>,
+    backlog: Arc<AtomicUsize>,
+}
+
+/// Publish end of a topic. Every message is cloned to all subscribers, each
+/// one has its own bounded buffer.
+///
+/// With retention, the last messages are also kept for subscribers joining
+/// later to replay. They are kept for the lifetime of the topic even if
+/// nobody subscribes, so memory grows with retention times message size.
+pub struct Publish<T> {
+    subscribers: Vec<Subscriber<T>>,
+    published:   u64,
+    delivered:   u64,
+    retention:   usize,
+    retained:    VecDeque<T>,
+}
+
+impl<T: Clone> Default for Publish<T> {
+    fn default() -> Self {
+        Publish::new()
+    }
+}
+
+impl<T: Clone> Publish<T> {
+    pub fn new() -> Self {
+        Publish::with_retention(0)
+    }
+
+    /// Keep the last `retention` messages for `subscribe_replay`, zero
+    /// retains nothing.
+    pub fn with_retention(retention: usize) -> Self {
+        Publish {
+            subscribers: Vec::new(),
+            published: 0,
+            delivered: 0,
+            retention,
+            retained: VecDeque::with_capacity(retention),
+        }
+    }
+
+    /// Register a new subscriber which buffers at most `buffer` messages.
+    pub fn subscribe(&mut self, buffer: usize) -> Subscription<T> {
+        let (tx, rx) = mpsc::channel(buffer);
+        let backlog = Arc::new(AtomicUsize::new(0));
+        self.subscribers.push(Subscriber {
+            tx,
+            backlog: Arc::clone(&backlog),
+        });
+
+        Subscription { rx, backlog }
+    }
+
+    /// Like `subscribe`, but retained messages are received first. The buffer
+    /// is enlarged to hold them, so none of them is missed.
+    pub fn subscribe_replay(&mut self, buffer: usize) -> Subscription<T> {
+        let (mut tx, rx) = mpsc::channel(buffer + self.retained.len());
+        let backlog = Arc::new(AtomicUsize::new(0));
+        for msg in self.retained.iter() {
+            backlog.fetch_add(1, Ordering::SeqCst);
+            if tx.try_send(msg.clone()).is_err() {
+                backlog.fetch_sub(1, Ordering::SeqCst);
+            }
+        }
+
+        self.subscribers.push(Subscriber {
+            tx,
+            backlog: Arc::clone(&backlog),
+        });
+
+        Subscription { rx, backlog }
+    }
+
+    pub fn subscriber_count(&self) -> usize {
+        self.subscribers.len()
+    }
+
+    pub fn metrics(&self) -> TopicMetrics {
+        TopicMetrics {
+            published:   self.published,
+            delivered:   self.delivered,
+            max_backlog: self
+                .subscribers
+                .iter()
+                .map(|s| s.backlog.load(Ordering::SeqCst))
+                .max()
+                .unwrap_or(0),
+        }
+    }
+
+    /// Send message to subscribers without waiting. Subscribers whose buffer
+    /// is full miss this message, dropped subscribers are removed.
+    pub fn try_send(&mut self, msg: T) -> Result<(), PubSubError> {
+        let mut alive = Vec::with_capacity(self.subscribers.len());
+        let mut missed = 0;
+        self.published += 1;
+        self.retain(&msg);
+
+        for mut sub in self.subscribers.drain(..) {
+            // Count before sending, the message may be received right away
+            sub.backlog.fetch_add(1, Ordering::SeqCst);
+            match sub.tx.try_send(msg.clone()) {
+                Ok(()) => {
+                    self.delivered += 1;
+                    alive.push(sub);
+                }
+                Err(e) if e.is_full() => {
+                    sub.backlog.fetch_sub(1, Ordering::SeqCst);
+                    missed += 1;
+                    alive.push(sub);
+                }
+                Err(_) => (),
+            }
+        }
+
+        self.subscribers = alive;
+
+        if missed > 0 {
+            return Err(PubSubError::Full { missed });
+        }
+
+        Ok(())
+    }
+
+    /// Send message to subscribers, wait for buffer capacity instead of
+    /// dropping the message.
+    ///
+    /// Subscribers are served one by one, so with differing capacities this
+    /// completes once the slowest subscriber has room for the message. Dropped
+    /// subscribers are removed, it completes immediately if no subscriber is
+    /// left.
+    pub async fn send(&mut self, msg: T) -> Result<(), PubSubError> {
+        let mut alive = Vec::with_capacity(self.subscribers.len());
+        self.published += 1;
+        self.retain(&msg);
+
+        for mut sub in self.subscribers.drain(..) {
+            if poll_fn(|cx| sub.tx.poll_ready(cx)).await.is_err() {
+                continue;
+            }
+
+            sub.backlog.fetch_add(1, Ordering::SeqCst);
+            if sub.tx.start_send(msg.clone()).is_ok() {
+                self.delivered += 1;
+                alive.push(sub);
+            }
+        }
+
+        self.subscribers = alive;
+        Ok(())
+    }
+
+    fn retain(&mut self, msg: &T) {
+        if self.retention == 0 {
+            return;
+        }
+
+        if self.retained.len() == self.retention {
+            self.retained.pop_front();
+        }
+        self.retained.push_back(msg.clone());
+    }
+}
+
+/// Type erased topic in register.
+trait Topic: Send {
+    fn metrics(&self) -> TopicMetrics;
+
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+impl<T: Clone + Send + 'static> Topic for Publish<T> {
+    fn metrics(&self) -> TopicMetrics {
+        Publish::metrics(self)
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+/// Named topics, each topic is a `Publish` of its own message type.
+///
+/// Besides subscribing to an exact topic, one can subscribe to every topic
+/// starting with a prefix. Topics are not typed by the register, so all topics
+/// matched by a prefix subscription must carry the same `T` as the
+/// subscription: messages of other types are silently skipped for it.
+#[derive(Default)]
+pub struct TopicRegister {
+    topics:   HashMap<String, Box<dyn Topic>>,
+    prefixes: Vec<(String, Box<dyn Any + Send>)>,
+}
+
+impl TopicRegister {
+    pub fn new() -> Self {
+        TopicRegister::default()
+    }
+
+    /// Subscribe to `topic`. Returns `TypeMismatch` if the topic was already
+    /// registered with another message type.
+    pub fn subscribe<T: Clone + Send + 'static>(
+        &mut self,
+        topic: String,
+        buffer: usize,
+    ) -> Result<Subscription<T>, PubSubError> {
+        Ok(self.topic_mut::<T>(topic)?.subscribe(buffer))
+    }
+
+    /// Keep the last `retention` messages of `topic` for `subscribe_replay`,
+    /// including those published before anyone subscribes. Returns
+    /// `TypeMismatch` if the topic was already registered with another
+    /// message type. See `Publish` for memory cost.
+    pub fn set_retention<T: Clone + Send + 'static>(
+        &mut self,
+        topic: String,
+        retention: usize,
+    ) -> Result<(), PubSubError> {
+        let publish = self.topic_mut::<T>(topic)?;
+        publish.retention = retention;
+        while publish.retained.len() > retention {
+            publish.retained.pop_front();
+        }
+        Ok(())
+    }
+
+    /// Like `subscribe`, but messages retained by `set_retention` are
+    /// received first, before live ones.
+    pub fn subscribe_replay<T: Clone + Send + 'static>(
+        &mut self,
+        topic: String,
+        buffer: usize,
+    ) -> Result<Subscription<T>, PubSubError> {
+        Ok(self.topic_mut::<T>(topic)?.subscribe_replay(buffer))
+    }
+
+    /// Subscribe to every topic starting with `prefix`, including topics
+    /// registered later.
+    pub fn subscribe_prefix<T: Clone + Send + 'static>(
+        &mut self,
+        prefix: String,
+        buffer: usize,
+    ) -> Subscription<T> {
+        let mut publish = Publish::<T>::new();
+        let rx = publish.subscribe(buffer);
+        self.prefixes.push((prefix, Box::new(publish)));
+
+        rx
+    }
+
+    /// Route message to subscribers of `topic` and to prefix subscribers
+    /// matching it, without waiting. See `Publish::try_send`.
+    pub fn publish<T: Clone + Send + 'static>(
+        &mut self,
+        topic: &str,
+        msg: T,
+    ) -> Result<(), PubSubError> {
+        let mut missed = 0;
+
+        if let Some(publish) = self.topics.get_mut(topic) {
+            let publish = publish
+                .as_any_mut()
+                .downcast_mut::<Publish<T>>()
+                .ok_or_else(|| PubSubError::TypeMismatch {
+                    topic: topic.to_owned(),
+                })?;
+
+            if let Err(PubSubError::Full { missed: n }) = publish.try_send(msg.clone()) {
+                missed += n;
+            }
+        }
+
+        for (prefix, publish) in self.prefixes.iter_mut() {
+            if !topic.starts_with(prefix.as_str()) {
+                continue;
+            }
+
+            if let Some(publish) = publish.downcast_mut::<Publish<T>>() {
+                if let Err(PubSubError::Full { missed: n }) = publish.try_send(msg.clone()) {
+                    missed += n;
+                }
+            }
+        }
+
+        if missed > 0 {
+            return Err(PubSubError::Full { missed });
+        }
+
+        Ok(())
+    }
+
+    fn topic_mut<T: Clone + Send + 'static>(
+        &mut self,
+        topic: String,
+    ) -> Result<&mut Publish<T>, PubSubError> {
+        let publish = self
+            .topics
+            .entry(topic.clone())
+            .or_insert_with(|| Box::new(Publish::<T>::new()));
+
+        publish
+            .as_any_mut()
+            .downcast_mut::<Publish<T>>()
+            .ok_or(PubSubError::TypeMismatch { topic })
+    }
+
+    /// Metrics of subscribers to exactly `topic`, prefix subscriptions are
+    /// not included. All zero for a topic never subscribed.
+    pub fn topic_metrics(&self, topic: &str) -> TopicMetrics {
+        self.topics
+            .get(topic)
+            .map(|publish| publish.metrics())
+            .unwrap_or_default()
+    }
+}
+
+#[derive(Debug, Display)]
+pub enum PubSubError {
+    #[display(fmt = "{} subscribers are full, message missed", missed)]
+    Full { missed: usize },
+
+    #[display(fmt = "topic {} is registered with another message type", topic)]
+    TypeMismatch { topic: String },
+}
+
+impl Error for PubSubError {}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use futures::StreamExt;
+    use futures_timer::Delay;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_try_send_full_subscriber() {
+        let mut publish = Publish::new();
+        let mut rx = publish.subscribe(0);
+
+        assert!(publish.try_send(1).is_ok());
+        match publish.try_send(2) {
+            Err(PubSubError::Full { missed }) => assert_eq!(missed, 1),
+            _ => panic!("should be full"),
+        }
+
+        assert_eq!(rx.next().await, Some(1));
+
+        drop(rx);
+        assert!(publish.try_send(3).is_ok());
+        assert_eq!(publish.subscriber_count(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_send_wait_for_slow_subscriber() {
+        let mut publish = Publish::new();
+        let mut slow_rx = publish.subscribe(0);
+        let fast_rx = publish.subscribe(16);
+
+        let consumer = tokio::spawn(async move {
+            let mut received = Vec::new();
+            while let Some(msg) = slow_rx.next().await {
+                Delay::new(Duration::from_millis(10)).await;
+                received.push(msg);
+            }
+            received
+        });
+
+        for i in 0..10 {
+            publish.send(i).await.unwrap();
+        }
+        drop(publish);
+
+        assert_eq!(consumer.await.unwrap(), (0..10).collect::<Vec<_>>());
+        let fast = fast_rx.collect::<Vec<_>>().await;
+        assert_eq!(fast, (0..10).collect::<Vec<_>>());
+    }
+
+    #[tokio::test]
+    async fn test_subscribe_prefix() {
+        let mut register = TopicRegister::new();
+        let mut vote_rx = register
+            .subscribe::<String>("broadcast_vote".to_owned(), 4)
+            .unwrap();
+        let mut broadcast_rx = register.subscribe_prefix::<String>("broadcast_".to_owned(), 4);
+
+        register
+            .publish("broadcast_vote", "vote".to_owned())
+            .unwrap();
+        register
+            .publish("broadcast_block", "block".to_owned())
+            .unwrap();
+        register.publish("sync_block", "sync".to_owned()).unwrap();
+
+        assert_eq!(vote_rx.next().await, Some("vote".to_owned()));
+        assert_eq!(broadcast_rx.next().await, Some("vote".to_owned()));
+        assert_eq!(broadcast_rx.next().await, Some("block".to_owned()));
+
+        drop(register);
+        assert_eq!(vote_rx.next().await, None);
+        assert_eq!(broadcast_rx.next().await, None);
+
+        let mut register = TopicRegister::new();
+        let _rx = register.subscribe::<u64>("height".to_owned(), 1).unwrap();
+        match register.publish("height", "1".to_owned()) {
+            Err(PubSubError::TypeMismatch { topic }) => assert_eq!(topic, "height"),
+            _ => panic!("should be type mismatch"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_topic_metrics() {
+        let mut register = TopicRegister::new();
+        let mut fast_rx = register.subscribe::<u64>("height".to_owned(), 16).unwrap();
+        let mut slow_rx = register.subscribe::<u64>("height".to_owned(), 2).unwrap();
+
+        for i in 0..5u64 {
+            let _ = register.publish("height", i);
+        }
+
+        // The slow subscriber misses messages once its buffer is full
+        let metrics = register.topic_metrics("height");
+        assert_eq!(metrics.published, 5);
+        assert_eq!(metrics.delivered, 5 + 3);
+        assert_eq!(metrics.max_backlog, 5);
+
+        for _ in 0..5 {
+            fast_rx.next().await.unwrap();
+        }
+        slow_rx.next().await.unwrap();
+        assert_eq!(register.topic_metrics("height").max_backlog, 2);
+
+        assert_eq!(register.topic_metrics("unknown"), TopicMetrics::default());
+    }
+
+    #[tokio::test]
+    async fn test_subscribe_replay() {
+        let mut register = TopicRegister::new();
+        register
+            .set_retention::<u64>("height".to_owned(), 3)
+            .unwrap();
+
+        for i in 0..5u64 {
+            register.publish("height", i).unwrap();
+        }
+
+        let mut live_rx = register.subscribe::<u64>("height".to_owned(), 4).unwrap();
+        let mut replay_rx = register
+            .subscribe_replay::<u64>("height".to_owned(), 1)
+            .unwrap();
+        register.publish("height", 5u64).unwrap();
+
+        // Retained messages go first, then live ones
+        for i in 2..=5u64 {
+            assert_eq!(replay_rx.next().await, Some(i));
+        }
+        assert_eq!(live_rx.next().await, Some(5));
+
+        // Nothing retained without retention
+        register.publish("block", 1u64).unwrap();
+        let _rx = register.subscribe::<u64>("block".to_owned(), 1).unwrap();
+        register.publish("block", 2u64).unwrap();
+        let mut replay_rx = register
+            .subscribe_replay::<u64>("block".to_owned(), 1)
+            .unwrap();
+        register.publish("block", 3u64).unwrap();
+        assert_eq!(replay_rx.next().await, Some(3));
+
+        match register.set_retention::<String>("height".to_owned(), 1) {
+            Err(PubSubError::TypeMismatch { topic }) => assert_eq!(topic, "height"),
+            _ => panic!("should be type mismatch"),
+        }
+    }
+}
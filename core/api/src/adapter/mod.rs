@@ -4,12 +4,13 @@ use std::sync::Arc;
 use async_trait::async_trait;
 use derive_more::Display;
 
+use common_crypto::{Crypto, Secp256k1};
 use protocol::traits::{
-    APIAdapter, Context, ExecutorFactory, ExecutorParams, MemPool, ServiceMapping, ServiceResponse,
-    Storage,
+    APIAdapter, Context, ExecutorConfig, ExecutorFactory, ExecutorParams, MemPool, ServiceMapping,
+    ServiceResponse, Storage,
 };
 use protocol::types::{
-    Address, Block, BlockHeader, Hash, Receipt, SignedTransaction, TransactionRequest,
+    Address, Block, BlockHeader, Bytes, Hash, Receipt, SignedTransaction, TransactionRequest,
 };
 use protocol::{ProtocolError, ProtocolErrorKind, ProtocolResult};
 
@@ -24,6 +25,18 @@ pub enum APIError {
 
     #[display(fmt = "not found")]
     NotFound,
+
+    #[display(fmt = "too many items in one request, max {}, got {}", max, actual)]
+    ExceedBatchLimit { max: usize, actual: usize },
+
+    #[display(fmt = "transaction {:?} is already committed", _0)]
+    AlreadyCommitted(Hash),
+
+    #[display(fmt = "invalid cancel signature")]
+    InvalidCancelSignature,
+
+    #[display(fmt = "transaction {:?} is not sent by {:?}", tx_hash, caller)]
+    NotTransactionOwner { tx_hash: Hash, caller: Address },
 }
 
 impl std::error::Error for APIError {}
@@ -34,11 +47,49 @@ impl From<APIError> for ProtocolError {
     }
 }
 
+/// The message to sign for cancelling a pending transaction, it's prefixed
+/// so that a transaction signature can't be reused as a cancel signature.
+pub fn cancel_tx_message(tx_hash: &Hash) -> Hash {
+    Hash::digest(Bytes::from(
+        [b"cancel".as_ref(), tx_hash.as_slice()].concat(),
+    ))
+}
+
+/// Check that the pending transaction can be cancelled by the owner of
+/// `pubkey`. A committed transaction can't be cancelled.
+fn check_cancel(
+    tx_hash: &Hash,
+    committed: bool,
+    pending: Option<&SignedTransaction>,
+    pubkey: &[u8],
+    signature: &[u8],
+) -> Result<(), APIError> {
+    if committed {
+        return Err(APIError::AlreadyCommitted(tx_hash.clone()));
+    }
+    let stx = pending.ok_or(APIError::NotFound)?;
+
+    let msg = cancel_tx_message(tx_hash);
+    Secp256k1::verify_signature(msg.as_slice(), signature, pubkey)
+        .map_err(|_| APIError::InvalidCancelSignature)?;
+
+    let caller =
+        Address::from_pubkey_bytes(pubkey).map_err(|_| APIError::InvalidCancelSignature)?;
+    if caller != stx.raw.sender {
+        return Err(APIError::NotTransactionOwner {
+            tx_hash: tx_hash.clone(),
+            caller,
+        });
+    }
+    Ok(())
+}
+
 pub struct DefaultAPIAdapter<EF, M, S, DB, Mapping> {
     mempool:         Arc<M>,
     storage:         Arc<S>,
     trie_db:         Arc<DB>,
     service_mapping: Arc<Mapping>,
+    executor_config: ExecutorConfig,
 
     pin_ef: PhantomData<EF>,
 }
@@ -62,9 +113,15 @@ impl<
             storage,
             trie_db,
             service_mapping,
+            executor_config: ExecutorConfig::default(),
             pin_ef: PhantomData,
         }
     }
+
+    /// Executor settings of the node, used to run queries.
+    pub fn set_executor_config(&mut self, config: ExecutorConfig) {
+        self.executor_config = config;
+    }
 }
 
 #[async_trait]
@@ -84,6 +141,37 @@ impl<
         self.mempool.insert(ctx, signed_tx).await
     }
 
+    async fn cancel_transaction(
+        &self,
+        ctx: Context,
+        tx_hash: Hash,
+        pubkey: Bytes,
+        signature: Bytes,
+    ) -> ProtocolResult<()> {
+        let committed = self
+            .storage
+            .get_transaction_by_hash(ctx.clone(), &tx_hash)
+            .await?
+            .is_some();
+        // Storage is checked first, so what mempool returns is pending.
+        let pending = if committed {
+            None
+        } else {
+            self.mempool
+                .get_full_txs(ctx.clone(), None, &[tx_hash.clone()])
+                .await
+                .ok()
+                .and_then(|mut txs| txs.pop())
+        };
+        check_cancel(&tx_hash, committed, pending.as_ref(), &pubkey, &signature)?;
+
+        if !self.mempool.remove(ctx, tx_hash.clone()).await? {
+            // Flushed after the check
+            return Err(APIError::AlreadyCommitted(tx_hash).into());
+        }
+        Ok(())
+    }
+
     async fn get_block_by_height(
         &self,
         ctx: Context,
@@ -95,6 +183,20 @@ impl<
         }
     }
 
+    async fn get_blocks_since(
+        &self,
+        ctx: Context,
+        from: u64,
+        limit: u64,
+    ) -> ProtocolResult<Vec<Block>> {
+        self.storage.get_blocks(ctx, from, limit).await
+    }
+
+    async fn get_latest_height(&self, ctx: Context) -> ProtocolResult<u64> {
+        let header = self.storage.get_latest_block_header(ctx).await?;
+        Ok(header.height)
+    }
+
     async fn get_block_header_by_height(
         &self,
         ctx: Context,
@@ -131,12 +233,39 @@ impl<
         }
     }
 
+    async fn get_receipts_by_hashes(
+        &self,
+        ctx: Context,
+        height: u64,
+        tx_hashes: Vec<Hash>,
+    ) -> ProtocolResult<Vec<Option<Receipt>>> {
+        self.storage.get_receipts(ctx, height, tx_hashes).await
+    }
+
+    async fn get_block_receipts(&self, ctx: Context, height: u64) -> ProtocolResult<Vec<Receipt>> {
+        self.storage.get_block_receipts(ctx, height).await
+    }
+
     async fn get_transaction_by_hash(
         &self,
         ctx: Context,
         tx_hash: Hash,
+        height: Option<u64>,
     ) -> ProtocolResult<Option<SignedTransaction>> {
-        self.storage.get_transaction_by_hash(ctx, &tx_hash).await
+        match height {
+            Some(height) => self.storage.get_transaction_at(ctx, height, &tx_hash).await,
+            None => self.storage.get_transaction_by_hash(ctx, &tx_hash).await,
+        }
+    }
+
+    async fn get_transactions_by_hashes(
+        &self,
+        ctx: Context,
+        tx_hashes: Vec<Hash>,
+    ) -> ProtocolResult<Vec<Option<SignedTransaction>>> {
+        self.storage
+            .get_transactions_by_hashes(ctx, &tx_hashes)
+            .await
     }
 
     async fn query_service(
@@ -160,6 +289,7 @@ impl<
             Arc::clone(&self.trie_db),
             Arc::clone(&self.storage),
             Arc::clone(&self.service_mapping),
+            &self.executor_config,
         )?;
 
         let params = ExecutorParams {
@@ -176,3 +306,93 @@ impl<
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryFrom;
+
+    use common_crypto::{PrivateKey, PublicKey, Secp256k1PrivateKey, Signature, ToPublicKey};
+    use protocol::types::RawTransaction;
+
+    use super::*;
+
+    fn mock_stx(sender: Address) -> SignedTransaction {
+        let raw = RawTransaction {
+            chain_id: Hash::digest(Bytes::from_static(b"chain")),
+            cycles_price: 1,
+            cycles_limit: 100,
+            nonce: Hash::digest(Bytes::from_static(b"nonce")),
+            request: TransactionRequest {
+                service_name: "asset".to_owned(),
+                method:       "transfer".to_owned(),
+                payload:      "".to_owned(),
+            },
+            timeout: 10,
+            sender,
+        };
+        SignedTransaction {
+            tx_hash: Hash::digest(Bytes::from_static(b"tx")),
+            raw,
+            pubkey: Bytes::new(),
+            signature: Bytes::new(),
+        }
+    }
+
+    fn sign_cancel(priv_key: &Secp256k1PrivateKey, tx_hash: &Hash) -> (Bytes, Bytes) {
+        let signature =
+            Secp256k1::sign_message(cancel_tx_message(tx_hash).as_slice(), &priv_key.to_bytes())
+                .unwrap();
+        (priv_key.pub_key().to_bytes(), signature.to_bytes())
+    }
+
+    #[test]
+    fn test_cancel_pending_transaction() {
+        let priv_key = Secp256k1PrivateKey::try_from([1u8; 32].as_ref()).unwrap();
+        let sender = Address::from_pubkey_bytes(priv_key.pub_key().to_bytes()).unwrap();
+        let stx = mock_stx(sender);
+        let (pubkey, signature) = sign_cancel(&priv_key, &stx.tx_hash);
+
+        assert!(check_cancel(&stx.tx_hash, false, Some(&stx), &pubkey, &signature).is_ok());
+
+        // signed by others
+        let other_key = Secp256k1PrivateKey::try_from([2u8; 32].as_ref()).unwrap();
+        let (other_pubkey, other_signature) = sign_cancel(&other_key, &stx.tx_hash);
+        match check_cancel(
+            &stx.tx_hash,
+            false,
+            Some(&stx),
+            &other_pubkey,
+            &other_signature,
+        ) {
+            Err(APIError::NotTransactionOwner { .. }) => (),
+            _ => panic!("should not be transaction owner"),
+        }
+
+        // transaction signature can't be used to cancel
+        let tx_signature = Secp256k1::sign_message(stx.tx_hash.as_slice(), &priv_key.to_bytes())
+            .unwrap()
+            .to_bytes();
+        match check_cancel(&stx.tx_hash, false, Some(&stx), &pubkey, &tx_signature) {
+            Err(APIError::InvalidCancelSignature) => (),
+            _ => panic!("should be invalid cancel signature"),
+        }
+
+        match check_cancel(&stx.tx_hash, false, None, &pubkey, &signature) {
+            Err(APIError::NotFound) => (),
+            _ => panic!("should be not found"),
+        }
+    }
+
+    #[test]
+    fn test_cancel_committed_transaction() {
+        let priv_key = Secp256k1PrivateKey::try_from([1u8; 32].as_ref()).unwrap();
+        let sender = Address::from_pubkey_bytes(priv_key.pub_key().to_bytes()).unwrap();
+        let stx = mock_stx(sender);
+        let (pubkey, signature) = sign_cancel(&priv_key, &stx.tx_hash);
+
+        match check_cancel(&stx.tx_hash, true, None, &pubkey, &signature) {
+            Err(APIError::AlreadyCommitted(tx_hash)) => assert_eq!(tx_hash, stx.tx_hash),
+            _ => panic!("should be already committed"),
+        }
+    }
+}
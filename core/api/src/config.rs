@@ -1,6 +1,8 @@
 use std::net::SocketAddr;
 use std::path::PathBuf;
 
+use protocol::types::Hash;
+
 #[derive(Debug, Clone)]
 pub struct GraphQLConfig {
     pub listening_address: SocketAddr,
@@ -20,9 +22,47 @@ pub struct GraphQLConfig {
     // The default value is 1024 * 1024, which is 1MB.
     pub max_payload_size: usize,
 
+    // Set the max number of items that can be queried in one batch request,
+    // such as `getTransactions`.
+    pub max_batch_query_size: usize,
+
+    // Set the max number of requests in one batched request, which is a json
+    // array of graphql requests.
+    pub max_batch_request_size: usize,
+
+    // Set the max cost of one query, fields in the query are counted before
+    // execution, see `cost.rs` for how the cost is calculated.
+    pub max_query_cost: u64,
+
+    // Set the max nesting depth of fields and the max number of aliases in
+    // one query, checked before execution along with the cost.
+    pub max_query_depth: usize,
+    pub max_aliases:     usize,
+
+    // Readiness check reports not ready if the node hasn't committed a block
+    // for this many seconds.
+    pub ready_commit_timeout: u64,
+
     pub tls: Option<GraphQLTLS>,
 
     pub enable_dump_profile: bool,
+
+    // Mutations are rejected in maintenance mode. It can be toggled at
+    // runtime through `/maintenance`, which is only served with an admin
+    // token.
+    pub maintenance_mode: bool,
+    pub admin_token:      Option<String>,
+
+    // Reported by `chainInfo`, so that clients can check they are talking to
+    // the expected chain.
+    pub chain_info: Option<ChainInfo>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ChainInfo {
+    pub chain_id:     Hash,
+    pub version:      String,
+    pub genesis_hash: Hash,
 }
 
 #[derive(Debug, Clone)]
@@ -38,13 +78,22 @@ impl Default for GraphQLConfig {
                 .parse()
                 .expect("Unable to parse socket address"),
 
-            graphql_uri:         "/graphql".to_owned(),
-            graphiql_uri:        "/graphiql".to_owned(),
-            workers:             num_cpus::get(),
-            maxconn:             25000,
-            max_payload_size:    1024 * 1024, // 1MB
-            tls:                 None,
-            enable_dump_profile: false,
+            graphql_uri:            "/graphql".to_owned(),
+            graphiql_uri:           "/graphiql".to_owned(),
+            workers:                num_cpus::get(),
+            maxconn:                25000,
+            max_payload_size:       1024 * 1024, // 1MB
+            max_batch_query_size:   100,
+            max_batch_request_size: 10,
+            max_query_cost:         10_000,
+            max_query_depth:        32,
+            max_aliases:            100,
+            ready_commit_timeout:   60,
+            tls:                    None,
+            enable_dump_profile:    false,
+            maintenance_mode:       false,
+            admin_token:            None,
+            chain_info:             None,
         }
     }
 }
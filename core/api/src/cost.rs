@@ -0,0 +1,855 @@
+//! A rough complexity analysis of GraphQL queries, it runs before execution
+//! so that an expensive query is rejected without touching storage.
+//!
+//! Every field costs 1. Children of a field with a batch argument are charged
+//! once per item, for example `getBlocksSince(limit: 10) { ... }` costs 10
+//! times its selection, a batch of no item is still charged once. Fragment
+//! spreads are charged at each use, but every fragment is only analyzed once.
+//!
+//! The shape of a query, nesting depth and alias count, is checked as well.
+//! It is much cheaper than cost analysis and catches pathological queries
+//! such as deeply nested fragments or thousands of aliased fields.
+//!
+//! Juniper doesn't expose its parser, so the query is parsed here with a
+//! small parser which only keeps what the analysis needs: fields, batch
+//! arguments, fragments and aliases. It is parsed once for both checks.
+
+use std::collections::HashMap;
+use std::error::Error;
+
+use derive_more::Display;
+
+/// Arguments which make a field resolve to many items.
+const BATCH_ARGUMENTS: &[&str] = &["limit", "txHashes"];
+
+/// Fields resolving to `max_batch_query_size` items if `limit` is left out.
+const BATCH_FIELDS: &[&str] = &["getBlocksSince", "getBlockReceipts"];
+
+/// Fragments spreading each other deeper than this are rejected.
+const MAX_FRAGMENT_DEPTH: usize = 16;
+
+/// Selection sets and values nested deeper than this are rejected while
+/// parsing, before recursion could overflow the stack.
+const MAX_PARSE_DEPTH: usize = 128;
+
+#[derive(Debug, Display, PartialEq, Eq)]
+pub enum CostError {
+    #[display(fmt = "parse query failed, {}", _0)]
+    Parse(String),
+
+    #[display(fmt = "query cost {} exceeds the limit {}", cost, max)]
+    ExceedLimit { cost: u64, max: u64 },
+
+    #[display(fmt = "query depth {} exceeds the limit {}", depth, max)]
+    TooDeep { depth: usize, max: usize },
+
+    #[display(fmt = "query has {} aliases, exceeds the limit {}", aliases, max)]
+    TooManyAliases { aliases: usize, max: usize },
+}
+
+impl Error for CostError {}
+
+/// Parse a query for `check_shape` and `check_cost`.
+pub fn parse_query(query: &str) -> Result<Document<'_>, CostError> {
+    let tokens = tokenize(query)?;
+    Parser {
+        tokens,
+        pos: 0,
+        depth: 0,
+        aliases: 0,
+    }
+    .parse_document()
+}
+
+impl<'a> Document<'a> {
+    /// Return the cost of the query if it doesn't exceed `max_cost`. An
+    /// argument given by variable, or a batch field without `limit`, counts
+    /// as `max_batch_query_size` items. Analysis stops as soon as the cost
+    /// exceeds `max_cost`.
+    pub fn check_cost(&self, max_batch_query_size: usize, max_cost: u64) -> Result<u64, CostError> {
+        let mut analyzer = Analyzer::new(&self.fragments, max_batch_query_size as u64, max_cost);
+
+        let mut cost = 0u64;
+        for selections in self.operations.iter() {
+            let (operation_cost, _) = analyzer.selections_cost(selections, 0)?;
+            cost = cost.saturating_add(operation_cost);
+
+            if cost > max_cost {
+                return Err(CostError::ExceedLimit {
+                    cost,
+                    max: max_cost,
+                });
+            }
+        }
+        Ok(cost)
+    }
+
+    /// Check that the query nests no deeper than `max_depth` and has no more
+    /// than `max_aliases` aliases. A top level field is at depth 1, fragment
+    /// spreads are expanded in place.
+    pub fn check_shape(&self, max_depth: usize, max_aliases: usize) -> Result<(), CostError> {
+        if self.aliases > max_aliases {
+            return Err(CostError::TooManyAliases {
+                aliases: self.aliases,
+                max:     max_aliases,
+            });
+        }
+
+        let mut analyzer = Analyzer::new(&self.fragments, 0, 0);
+
+        let mut depth = 0;
+        for selections in self.operations.iter() {
+            let (operation_depth, _) = analyzer.selections_depth(selections, 0)?;
+            depth = depth.max(operation_depth);
+        }
+
+        if depth > max_depth {
+            return Err(CostError::TooDeep {
+                depth,
+                max: max_depth,
+            });
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token<'a> {
+    Name(&'a str),
+    Int(u64),
+    Punct(char),
+    Spread,
+    // Strings and floats, their value doesn't matter.
+    Other,
+}
+
+fn tokenize(query: &str) -> Result<Vec<Token<'_>>, CostError> {
+    let bytes = query.as_bytes();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let c = bytes[i];
+        match c {
+            b' ' | b'\t' | b'\n' | b'\r' | b',' => i += 1,
+            // Unicode BOM is ignored as whitespace.
+            0xef if query[i..].starts_with('\u{feff}') => i += 3,
+            b'#' => {
+                while i < bytes.len() && bytes[i] != b'\n' {
+                    i += 1;
+                }
+            }
+            b'"' => {
+                i += 1;
+                while i < bytes.len() && bytes[i] != b'"' {
+                    if bytes[i] == b'\\' {
+                        i += 1;
+                    }
+                    i += 1;
+                }
+                if i >= bytes.len() {
+                    return Err(CostError::Parse("unterminated string".to_owned()));
+                }
+                i += 1;
+                tokens.push(Token::Other);
+            }
+            b'.' => {
+                if !query[i..].starts_with("...") {
+                    return Err(CostError::Parse("unexpected '.'".to_owned()));
+                }
+                i += 3;
+                tokens.push(Token::Spread);
+            }
+            b'{' | b'}' | b'(' | b')' | b'[' | b']' | b':' | b'$' | b'@' | b'=' | b'!' | b'|'
+            | b'&' => {
+                i += 1;
+                tokens.push(Token::Punct(c as char));
+            }
+            b'-' | b'0'..=b'9' => {
+                let start = i;
+                i += 1;
+                while i < bytes.len()
+                    && (bytes[i].is_ascii_alphanumeric() || bytes[i] == b'.' || bytes[i] == b'-')
+                {
+                    i += 1;
+                }
+                match query[start..i].parse::<u64>() {
+                    Ok(n) => tokens.push(Token::Int(n)),
+                    Err(_) => tokens.push(Token::Other),
+                }
+            }
+            _ if c == b'_' || c.is_ascii_alphabetic() => {
+                let start = i;
+                while i < bytes.len() && (bytes[i] == b'_' || bytes[i].is_ascii_alphanumeric()) {
+                    i += 1;
+                }
+                tokens.push(Token::Name(&query[start..i]));
+            }
+            _ => {
+                return Err(CostError::Parse(format!(
+                    "unexpected character {:?}",
+                    c as char
+                )))
+            }
+        }
+    }
+
+    Ok(tokens)
+}
+
+enum ArgValue {
+    Int(u64),
+    List(usize),
+    Variable,
+    Other,
+}
+
+enum Selection<'a> {
+    Field {
+        batch:    Option<ArgValue>,
+        children: Vec<Selection<'a>>,
+    },
+    Spread(&'a str),
+    Inline(Vec<Selection<'a>>),
+}
+
+pub struct Document<'a> {
+    operations: Vec<Vec<Selection<'a>>>,
+    fragments:  HashMap<&'a str, Vec<Selection<'a>>>,
+    aliases:    usize,
+}
+
+struct Parser<'a> {
+    tokens:  Vec<Token<'a>>,
+    pos:     usize,
+    depth:   usize,
+    aliases: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<&Token<'a>> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Result<Token<'a>, CostError> {
+        let token = self
+            .tokens
+            .get(self.pos)
+            .cloned()
+            .ok_or_else(|| CostError::Parse("unexpected end of query".to_owned()))?;
+        self.pos += 1;
+        Ok(token)
+    }
+
+    fn expect(&mut self, punct: char) -> Result<(), CostError> {
+        match self.next()? {
+            Token::Punct(c) if c == punct => Ok(()),
+            t => Err(CostError::Parse(format!("expect {:?}, got {:?}", punct, t))),
+        }
+    }
+
+    fn name(&mut self) -> Result<&'a str, CostError> {
+        match self.next()? {
+            Token::Name(name) => Ok(name),
+            t => Err(CostError::Parse(format!("expect name, got {:?}", t))),
+        }
+    }
+
+    fn is_punct(&self, punct: char) -> bool {
+        self.peek() == Some(&Token::Punct(punct))
+    }
+
+    fn enter(&mut self) -> Result<(), CostError> {
+        self.depth += 1;
+        if self.depth > MAX_PARSE_DEPTH {
+            return Err(CostError::TooDeep {
+                depth: self.depth,
+                max:   MAX_PARSE_DEPTH,
+            });
+        }
+        Ok(())
+    }
+
+    fn leave(&mut self) {
+        self.depth -= 1;
+    }
+
+    fn parse_document(mut self) -> Result<Document<'a>, CostError> {
+        let mut document = Document {
+            operations: Vec::new(),
+            fragments:  HashMap::new(),
+            aliases:    0,
+        };
+
+        while self.peek().is_some() {
+            if self.is_punct('{') {
+                document.operations.push(self.selection_set()?);
+                continue;
+            }
+
+            match self.name()? {
+                "fragment" => {
+                    let name = self.name()?;
+                    self.name()?; // on
+                    self.name()?; // type condition
+                    self.skip_directives()?;
+                    let selections = self.selection_set()?;
+                    document.fragments.insert(name, selections);
+                }
+                "query" | "mutation" | "subscription" => {
+                    while !self.is_punct('{') {
+                        if self.is_punct('(') {
+                            self.skip_balanced('(', ')')?;
+                        } else {
+                            self.next()?;
+                        }
+                    }
+                    document.operations.push(self.selection_set()?);
+                }
+                name => {
+                    return Err(CostError::Parse(format!("unexpected {:?}", name)));
+                }
+            }
+        }
+
+        document.aliases = self.aliases;
+        Ok(document)
+    }
+
+    fn selection_set(&mut self) -> Result<Vec<Selection<'a>>, CostError> {
+        self.expect('{')?;
+        self.enter()?;
+        let mut selections = Vec::new();
+
+        while !self.is_punct('}') {
+            if self.peek() == Some(&Token::Spread) {
+                self.next()?;
+                match self.peek() {
+                    Some(Token::Name("on")) => {
+                        self.next()?;
+                        self.name()?;
+                        self.skip_directives()?;
+                        selections.push(Selection::Inline(self.selection_set()?));
+                    }
+                    Some(Token::Name(_)) => {
+                        let name = self.name()?;
+                        self.skip_directives()?;
+                        selections.push(Selection::Spread(name));
+                    }
+                    _ => {
+                        self.skip_directives()?;
+                        selections.push(Selection::Inline(self.selection_set()?));
+                    }
+                }
+                continue;
+            }
+
+            let mut name = self.name()?;
+            if self.is_punct(':') {
+                // alias
+                self.next()?;
+                name = self.name()?;
+                self.aliases += 1;
+            }
+
+            let mut batch = None;
+            if self.is_punct('(') {
+                self.next()?;
+                while !self.is_punct(')') {
+                    let arg = self.name()?;
+                    self.expect(':')?;
+                    let value = self.value()?;
+                    if BATCH_ARGUMENTS.contains(&arg) {
+                        batch = Some(value);
+                    }
+                }
+                self.next()?;
+            }
+            if batch.is_none() && BATCH_FIELDS.contains(&name) {
+                batch = Some(ArgValue::Other);
+            }
+            self.skip_directives()?;
+
+            let children = if self.is_punct('{') {
+                self.selection_set()?
+            } else {
+                Vec::new()
+            };
+            selections.push(Selection::Field { batch, children });
+        }
+        self.next()?;
+        self.leave();
+
+        Ok(selections)
+    }
+
+    fn value(&mut self) -> Result<ArgValue, CostError> {
+        match self.next()? {
+            Token::Int(n) => Ok(ArgValue::Int(n)),
+            Token::Punct('$') => {
+                self.name()?;
+                Ok(ArgValue::Variable)
+            }
+            Token::Punct('[') => {
+                self.enter()?;
+                let mut len = 0;
+                while !self.is_punct(']') {
+                    self.value()?;
+                    len += 1;
+                }
+                self.next()?;
+                self.leave();
+                Ok(ArgValue::List(len))
+            }
+            Token::Punct('{') => {
+                self.enter()?;
+                while !self.is_punct('}') {
+                    self.name()?;
+                    self.expect(':')?;
+                    self.value()?;
+                }
+                self.next()?;
+                self.leave();
+                Ok(ArgValue::Other)
+            }
+            Token::Name(_) | Token::Other => Ok(ArgValue::Other),
+            t => Err(CostError::Parse(format!("unexpected value {:?}", t))),
+        }
+    }
+
+    fn skip_directives(&mut self) -> Result<(), CostError> {
+        while self.is_punct('@') {
+            self.next()?;
+            self.name()?;
+            if self.is_punct('(') {
+                self.skip_balanced('(', ')')?;
+            }
+        }
+        Ok(())
+    }
+
+    fn skip_balanced(&mut self, open: char, close: char) -> Result<(), CostError> {
+        self.expect(open)?;
+        let mut depth = 1;
+        while depth > 0 {
+            match self.next()? {
+                Token::Punct(c) if c == open => depth += 1,
+                Token::Punct(c) if c == close => depth -= 1,
+                _ => (),
+            }
+        }
+        Ok(())
+    }
+}
+
+struct Analyzer<'a, 'b> {
+    fragments: &'b HashMap<&'a str, Vec<Selection<'a>>>,
+    max_batch: u64,
+    max_cost:  u64,
+    // Cost and depth of each analyzed fragment with the longest fragment
+    // chain it starts, so that a fragment spread many times isn't expanded
+    // again.
+    costs:     HashMap<&'a str, (u64, usize)>,
+    depths:    HashMap<&'a str, (usize, usize)>,
+}
+
+impl<'a, 'b> Analyzer<'a, 'b> {
+    fn new(
+        fragments: &'b HashMap<&'a str, Vec<Selection<'a>>>,
+        max_batch: u64,
+        max_cost: u64,
+    ) -> Self {
+        Analyzer {
+            fragments,
+            max_batch,
+            max_cost,
+            costs: HashMap::new(),
+            depths: HashMap::new(),
+        }
+    }
+
+    /// Return the cost of selections and the longest fragment chain in them.
+    fn selections_cost(
+        &mut self,
+        selections: &[Selection<'a>],
+        fragment_depth: usize,
+    ) -> Result<(u64, usize), CostError> {
+        if fragment_depth > MAX_FRAGMENT_DEPTH {
+            return Err(CostError::TooDeep {
+                depth: fragment_depth,
+                max:   MAX_FRAGMENT_DEPTH,
+            });
+        }
+
+        let mut cost = 0u64;
+        let mut chain = 0;
+        for selection in selections.iter() {
+            let selection_cost = match selection {
+                Selection::Field { batch, children } => {
+                    let items = match batch {
+                        None => 1,
+                        Some(ArgValue::Int(n)) => (*n).min(self.max_batch),
+                        Some(ArgValue::List(len)) => (*len as u64).min(self.max_batch),
+                        Some(ArgValue::Variable) | Some(ArgValue::Other) => self.max_batch,
+                    };
+                    let (children_cost, children_chain) =
+                        self.selections_cost(children, fragment_depth)?;
+                    chain = chain.max(children_chain);
+                    1u64.saturating_add(items.max(1).saturating_mul(children_cost))
+                }
+                Selection::Spread(name) => {
+                    let (fragment_cost, fragment_chain) =
+                        self.fragment_cost(name, fragment_depth)?;
+                    chain = chain.max(fragment_chain + 1);
+                    fragment_cost
+                }
+                Selection::Inline(children) => {
+                    let (children_cost, children_chain) =
+                        self.selections_cost(children, fragment_depth)?;
+                    chain = chain.max(children_chain);
+                    children_cost
+                }
+            };
+
+            // The cost of selections is never less than their share of the
+            // query cost, so it's safe to give up early.
+            cost = cost.saturating_add(selection_cost);
+            if cost > self.max_cost {
+                return Err(CostError::ExceedLimit {
+                    cost,
+                    max: self.max_cost,
+                });
+            }
+        }
+
+        Ok((cost, chain))
+    }
+
+    fn fragment_cost(
+        &mut self,
+        name: &'a str,
+        fragment_depth: usize,
+    ) -> Result<(u64, usize), CostError> {
+        if let Some(&(cost, chain)) = self.costs.get(name) {
+            check_fragment_chain(fragment_depth, chain)?;
+            return Ok((cost, chain));
+        }
+
+        let fragments = self.fragments;
+        let fragment = fragments
+            .get(name)
+            .ok_or_else(|| CostError::Parse(format!("unknown fragment {:?}", name)))?;
+        let measured = self.selections_cost(fragment, fragment_depth + 1)?;
+        self.costs.insert(name, measured);
+
+        Ok(measured)
+    }
+
+    /// Return the depth of selections and the longest fragment chain in
+    /// them.
+    fn selections_depth(
+        &mut self,
+        selections: &[Selection<'a>],
+        fragment_depth: usize,
+    ) -> Result<(usize, usize), CostError> {
+        // Fragment chains nest fields no matter what they select, a chain
+        // too long is as pathological as a deep query.
+        if fragment_depth > MAX_FRAGMENT_DEPTH {
+            return Err(CostError::TooDeep {
+                depth: fragment_depth,
+                max:   MAX_FRAGMENT_DEPTH,
+            });
+        }
+
+        let mut depth = 0;
+        let mut chain = 0;
+        for selection in selections.iter() {
+            let selection_depth = match selection {
+                Selection::Field { children, .. } => {
+                    let (children_depth, children_chain) =
+                        self.selections_depth(children, fragment_depth)?;
+                    chain = chain.max(children_chain);
+                    1 + children_depth
+                }
+                Selection::Spread(name) => {
+                    let (spread_depth, fragment_chain) =
+                        self.fragment_depth(name, fragment_depth)?;
+                    chain = chain.max(fragment_chain + 1);
+                    spread_depth
+                }
+                Selection::Inline(children) => {
+                    let (children_depth, children_chain) =
+                        self.selections_depth(children, fragment_depth)?;
+                    chain = chain.max(children_chain);
+                    children_depth
+                }
+            };
+            depth = depth.max(selection_depth);
+        }
+
+        Ok((depth, chain))
+    }
+
+    fn fragment_depth(
+        &mut self,
+        name: &'a str,
+        fragment_depth: usize,
+    ) -> Result<(usize, usize), CostError> {
+        if let Some(&(depth, chain)) = self.depths.get(name) {
+            check_fragment_chain(fragment_depth, chain)?;
+            return Ok((depth, chain));
+        }
+
+        let fragments = self.fragments;
+        let fragment = fragments
+            .get(name)
+            .ok_or_else(|| CostError::Parse(format!("unknown fragment {:?}", name)))?;
+        let measured = self.selections_depth(fragment, fragment_depth + 1)?;
+        self.depths.insert(name, measured);
+
+        Ok(measured)
+    }
+}
+
+/// A fragment analyzed before is spread again at `fragment_depth`, check that
+/// the fragment chain it starts isn't too long from there.
+fn check_fragment_chain(fragment_depth: usize, chain: usize) -> Result<(), CostError> {
+    let depth = fragment_depth + 1 + chain;
+    if depth > MAX_FRAGMENT_DEPTH {
+        return Err(CostError::TooDeep {
+            depth,
+            max: MAX_FRAGMENT_DEPTH,
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MAX_BATCH: usize = 100;
+
+    fn check_query_cost(query: &str, max_batch: usize, max_cost: u64) -> Result<u64, CostError> {
+        parse_query(query)?.check_cost(max_batch, max_cost)
+    }
+
+    fn check_query_shape(
+        query: &str,
+        max_depth: usize,
+        max_aliases: usize,
+    ) -> Result<(), CostError> {
+        parse_query(query)?.check_shape(max_depth, max_aliases)
+    }
+
+    // Every fragment spreads the next one `width` times, expanding them all
+    // takes `width ^ MAX_FRAGMENT_DEPTH` steps.
+    fn fan_out_query(width: usize) -> String {
+        let mut query = "{ ...F1 }".to_owned();
+        for i in 1..MAX_FRAGMENT_DEPTH {
+            let spreads = vec![format!("...F{}", i + 1); width].join(" ");
+            query += &format!(" fragment F{} on Query {{ {} }}", i, spreads);
+        }
+        query += &format!(
+            " fragment F{} on Query {{ getLatestHeight }}",
+            MAX_FRAGMENT_DEPTH
+        );
+        query
+    }
+
+    #[test]
+    fn test_query_cost_under_budget() {
+        let query = r#"
+            query {
+                getBlock(height: "0x1") { header { height prevHash } }
+                getTransactions(txHashes: ["0x01", "0x02"]) { txHash }
+            }
+        "#;
+        // getBlock 1 + header 1 + 2 fields, getTransactions 1 + 2 * txHash
+        assert_eq!(check_query_cost(query, MAX_BATCH, 10), Ok(7));
+    }
+
+    #[test]
+    fn test_query_cost_over_budget() {
+        let query = r#"
+            query blocks($from: Uint64!) {
+                getBlocksSince(from: $from, limit: 50) { ...BlockFields }
+            }
+            fragment BlockFields on BlocksSince {
+                blocks { header { height stateRoot } orderedTxHashes }
+            }
+        "#;
+        // getBlocksSince 1 + 50 * (blocks 1 + header 3 + orderedTxHashes 1)
+        assert_eq!(
+            check_query_cost(query, MAX_BATCH, 250),
+            Err(CostError::ExceedLimit {
+                cost: 251,
+                max:  250,
+            })
+        );
+
+        // limit given by variable counts as max batch size
+        let query = r#"{ getBlocksSince(from: "0x0", limit: $limit) { blocks { hash } } }"#;
+        assert_eq!(
+            check_query_cost(query, MAX_BATCH, 100),
+            Err(CostError::ExceedLimit {
+                cost: 201,
+                max:  100,
+            })
+        );
+    }
+
+    #[test]
+    fn test_query_cost_parse_error() {
+        let query = "{ getBlock { header ";
+        assert!(matches!(
+            check_query_cost(query, MAX_BATCH, 10),
+            Err(CostError::Parse(_))
+        ));
+    }
+
+    #[test]
+    fn test_query_cost_batch_field_without_limit() {
+        // Resolved with limit of max batch size
+        let query = r#"{ getBlocksSince(from: "0x0") { blocks { hash } } }"#;
+        assert_eq!(
+            check_query_cost(query, MAX_BATCH, 100),
+            Err(CostError::ExceedLimit {
+                cost: 201,
+                max:  100,
+            })
+        );
+
+        let query = r#"{ r: getBlockReceipts(height: "0x1", limit: 2) { total } }"#;
+        assert_eq!(check_query_cost(query, MAX_BATCH, 100), Ok(3));
+    }
+
+    #[test]
+    fn test_query_non_ascii() {
+        let query = "\u{feff}{ getLatestHeight }";
+        assert_eq!(check_query_cost(query, MAX_BATCH, 10), Ok(1));
+
+        let query = r#"{ getBlock(height: "é") { hash } } é"#;
+        assert!(matches!(
+            check_query_cost(query, MAX_BATCH, 10),
+            Err(CostError::Parse(_))
+        ));
+    }
+
+    #[test]
+    fn test_query_too_deep() {
+        let query = r#"
+            { getBlock { header { ...Validators } } }
+            fragment Validators on BlockHeader {
+                validators { ... on Validator { address } }
+            }
+        "#;
+        // getBlock, header, validators, address
+        assert_eq!(check_query_shape(query, 4, 10), Ok(()));
+        assert_eq!(
+            check_query_shape(query, 3, 10),
+            Err(CostError::TooDeep { depth: 4, max: 3 })
+        );
+    }
+
+    #[test]
+    fn test_query_fragment_chain_too_deep() {
+        let mut query = "{ getLatestHeight ...F0 }".to_owned();
+        for i in 0..=MAX_FRAGMENT_DEPTH {
+            query += &format!(" fragment F{} on Query {{ ...F{} }}", i, i + 1);
+        }
+        query += &format!(
+            " fragment F{} on Query {{ __typename }}",
+            MAX_FRAGMENT_DEPTH + 1
+        );
+
+        let too_deep = CostError::TooDeep {
+            depth: MAX_FRAGMENT_DEPTH + 1,
+            max:   MAX_FRAGMENT_DEPTH,
+        };
+        assert_eq!(check_query_shape(&query, 32, 10), Err(too_deep));
+        assert_eq!(
+            check_query_cost(&query, MAX_BATCH, 1000),
+            Err(CostError::TooDeep {
+                depth: MAX_FRAGMENT_DEPTH + 1,
+                max:   MAX_FRAGMENT_DEPTH,
+            })
+        );
+
+        // A fragment spreading itself is caught the same way
+        let query = "{ ...F } fragment F on Query { getLatestHeight ...F }";
+        assert!(matches!(
+            check_query_shape(query, 32, 10),
+            Err(CostError::TooDeep { .. })
+        ));
+    }
+
+    #[test]
+    fn test_query_cost_fragment_fan_out() {
+        let query = fan_out_query(10);
+        assert!(matches!(
+            check_query_cost(&query, MAX_BATCH, 10_000),
+            Err(CostError::ExceedLimit { max: 10_000, .. })
+        ));
+
+        // Each fragment is analyzed once even without a limit to stop at
+        assert_eq!(
+            check_query_cost(&query, MAX_BATCH, u64::MAX),
+            Ok(10u64.pow(MAX_FRAGMENT_DEPTH as u32 - 1))
+        );
+    }
+
+    #[test]
+    fn test_query_shape_fragment_fan_out() {
+        let query = fan_out_query(10);
+        // Fragments nest no field, only getLatestHeight counts
+        assert_eq!(check_query_shape(&query, 1, 10), Ok(()));
+
+        let query = query.replace("getLatestHeight", "getBlock { hash }");
+        assert_eq!(
+            check_query_shape(&query, 1, 10),
+            Err(CostError::TooDeep { depth: 2, max: 1 })
+        );
+    }
+
+    #[test]
+    fn test_query_nested_too_deep_to_parse() {
+        let depth = MAX_PARSE_DEPTH + 1;
+        let too_deep = CostError::TooDeep {
+            depth,
+            max: MAX_PARSE_DEPTH,
+        };
+
+        let query = format!("{}{}", "{ a ".repeat(depth), "}".repeat(depth));
+        assert_eq!(parse_query(&query).err(), Some(too_deep));
+
+        let query = format!(
+            "{{ getTransactions(txHashes: {}{}) {{ txHash }} }}",
+            "[".repeat(depth),
+            "]".repeat(depth)
+        );
+        assert!(matches!(
+            parse_query(&query),
+            Err(CostError::TooDeep { .. })
+        ));
+    }
+
+    #[test]
+    fn test_query_too_many_aliases() {
+        let query = r#"
+            {
+                a: getBlock(height: "0x1") { hash }
+                b: getBlock(height: "0x2") { hash }
+                c: getBlock(height: "0x3") { h: hash }
+            }
+        "#;
+        assert_eq!(check_query_shape(query, 10, 4), Ok(()));
+        assert_eq!(
+            check_query_shape(query, 10, 3),
+            Err(CostError::TooManyAliases {
+                aliases: 4,
+                max:     3,
+            })
+        );
+    }
+}
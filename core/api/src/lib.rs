@@ -1,41 +1,103 @@
 pub mod adapter;
 pub mod config;
+mod cost;
 mod schema;
 
 use std::cmp;
+use std::collections::HashMap;
 use std::convert::TryFrom;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
-use actix_web::{web, App, Error, FromRequest, HttpResponse, HttpServer};
+use actix_web::error::ErrorBadRequest;
+use actix_web::{web, App, Error, FromRequest, HttpRequest, HttpResponse, HttpServer};
 use futures::executor::block_on;
-use juniper::http::GraphQLRequest;
-use juniper::FieldResult;
+use juniper::http::{GraphQLRequest, GraphQLResponse};
+use juniper::{graphql_value, FieldError, FieldResult};
 use lazy_static::lazy_static;
 use openssl::ssl::{SslAcceptor, SslFiletype, SslMethod};
 
 use common_crypto::{
-    HashValue, PrivateKey, PublicKey, Secp256k1PrivateKey, Signature, ToPublicKey,
+    constant_time_eq, HashValue, PrivateKey, PublicKey, Secp256k1PrivateKey, Signature, ToPublicKey,
 };
 
 use protocol::fixed_codec::FixedCodec;
-use protocol::traits::{APIAdapter, Context};
+use protocol::traits::{APIAdapter, CommitStatus, Context};
+use protocol::types::PageRequest;
+use protocol::{ProtocolError, ProtocolResult};
 
+use crate::adapter::APIError;
 use crate::config::GraphQLConfig;
+use crate::cost::parse_query;
 use crate::schema::{
-    to_signed_transaction, to_transaction, Address, Block, Bytes, Hash, InputRawTransaction,
-    InputTransactionEncryption, Receipt, ServiceResponse, SignedTransaction, Uint64,
+    to_signed_transaction, to_transaction, validate_input_transaction, Address, Block,
+    BlockReceipts, BlocksSince, Bytes, ChainInfo, Hash, InputRawTransaction,
+    InputTransactionEncryption, Receipt, ServiceResponse, SignedTransaction, TransactionProof,
+    Uint64, Validator, VoteParticipation,
 };
 
 lazy_static! {
     static ref GRAPHIQL_HTML: &'static str = include_str!("../source/graphiql.html");
 }
 
+/// Header to correlate a request with its logs, echoed back in response.
+pub const REQUEST_ID_HEADER: &str = "X-Request-Id";
+// NOTE: common_logger reads it by the same key.
+const REQUEST_ID_KEY: &str = "request_id";
+
+/// Header carrying the admin token, required by admin endpoints.
+pub const ADMIN_TOKEN_HEADER: &str = "X-Admin-Token";
+
+/// Returns the latest commit status of consensus, used by readiness check.
+pub type CommitStatusFn = Arc<dyn Fn() -> CommitStatus + Send + Sync>;
+
+/// Returns the validator set of the latest committed height.
+pub type ValidatorsFn = Arc<dyn Fn() -> Vec<protocol::types::Validator> + Send + Sync>;
+
+/// Returns the vote participation rate of each validator over recent heights.
+pub type ParticipationFn = Arc<dyn Fn() -> Vec<(protocol::types::Address, f64)> + Send + Sync>;
+
 // This is accessible as state in Tide, and as executor context in Juniper.
 #[derive(Clone)]
 struct State {
-    adapter: Arc<Box<dyn APIAdapter>>,
-    schema:  Arc<Schema>,
+    adapter:                Arc<Box<dyn APIAdapter>>,
+    schema:                 Arc<Schema>,
+    max_batch_query_size:   usize,
+    max_batch_request_size: usize,
+    max_query_cost:         u64,
+    max_query_depth:        usize,
+    max_aliases:            usize,
+    commit_status:          Option<CommitStatusFn>,
+    validators:             Option<ValidatorsFn>,
+    participation:          Option<ParticipationFn>,
+    ready_commit_timeout:   Duration,
+    chain_info:             Option<config::ChainInfo>,
+    request_id:             Option<String>,
+    // Shared by all workers, mutations are rejected while it is set.
+    maintenance:            Arc<AtomicBool>,
+    admin_token:            Option<String>,
+}
+
+impl State {
+    /// Context passed down by resolvers, carries the request id for logs.
+    fn new_context(&self) -> Context {
+        match &self.request_id {
+            Some(id) => Context::new().with_value::<String>(REQUEST_ID_KEY, id.clone()),
+            None => Context::new(),
+        }
+    }
+
+    /// Mutations are unavailable in maintenance mode, queries still work.
+    fn check_maintenance(&self) -> FieldResult<()> {
+        if self.maintenance.load(Ordering::SeqCst) {
+            return Err(FieldError::new(
+                "node is in maintenance, try again later",
+                graphql_value!({ "code": 503 }),
+            ));
+        }
+        Ok(())
+    }
 }
 
 // We define `Query` unit struct here. GraphQL queries will refer to this
@@ -46,9 +108,16 @@ struct Query;
 // Switch to async/await fn https://github.com/graphql-rust/juniper/issues/2
 #[juniper::graphql_object(Context = State)]
 impl Query {
-    #[graphql(name = "getBlock", description = "Get the block")]
-    async fn get_block(state_ctx: &State, height: Option<Uint64>) -> FieldResult<Option<Block>> {
-        let ctx = Context::new();
+    #[graphql(
+        name = "getBlock",
+        description = "Get the block, receipts are embedded if includeReceipts is true"
+    )]
+    async fn get_block(
+        state_ctx: &State,
+        height: Option<Uint64>,
+        include_receipts: Option<bool>,
+    ) -> FieldResult<Option<Block>> {
+        let ctx = state_ctx.new_context();
         let inst = Instant::now();
         common_apm::metrics::api::API_REQUEST_COUNTER_VEC_STATIC
             .get_block
@@ -85,6 +154,38 @@ impl Query {
             }
         };
 
+        let block = match opt_block {
+            Some(block) => block,
+            None => return Ok(None),
+        };
+
+        // Receipts of a huge block are truncated, the rest can be fetched by
+        // getReceipt.
+        let mut receipts = None;
+        if include_receipts.unwrap_or(false) {
+            let max = state_ctx.max_batch_query_size;
+            let truncated = block.ordered_tx_hashes.len() > max;
+            let hashes = block.ordered_tx_hashes.iter().take(max).cloned().collect();
+
+            match state_ctx
+                .adapter
+                .get_receipts_by_hashes(ctx.clone(), block.header.height, hashes)
+                .await
+            {
+                Ok(opt_receipts) => {
+                    receipts = Some((opt_receipts.into_iter().flatten().collect(), truncated))
+                }
+                Err(err) => {
+                    common_apm::metrics::api::API_REQUEST_RESULT_COUNTER_VEC_STATIC
+                        .get_block
+                        .failure
+                        .inc();
+
+                    return Err(err.into());
+                }
+            }
+        }
+
         common_apm::metrics::api::API_REQUEST_RESULT_COUNTER_VEC_STATIC
             .get_block
             .success
@@ -93,32 +194,321 @@ impl Query {
             .get_block
             .observe(common_apm::metrics::duration_to_sec(inst.elapsed()));
 
-        Ok(opt_block.map(Block::from))
+        let block = Block::from(block);
+        Ok(Some(match receipts {
+            Some((receipts, truncated)) => block.with_receipts(receipts, truncated),
+            None => block,
+        }))
+    }
+
+    #[graphql(
+        name = "getBlocksSince",
+        description = "Get consecutive blocks start from the height"
+    )]
+    async fn get_blocks_since(
+        state_ctx: &State,
+        from: Uint64,
+        limit: Option<i32>,
+    ) -> FieldResult<BlocksSince> {
+        let ctx = state_ctx.new_context();
+        let inst = Instant::now();
+        common_apm::metrics::api::API_REQUEST_COUNTER_VEC_STATIC
+            .get_blocks_since
+            .inc();
+
+        let from = match from.try_into_u64() {
+            Ok(from) => from,
+            Err(err) => {
+                common_apm::metrics::api::API_REQUEST_RESULT_COUNTER_VEC_STATIC
+                    .get_blocks_since
+                    .failure
+                    .inc();
+
+                return Err(err.into());
+            }
+        };
+        let max_limit = state_ctx.max_batch_query_size as u64;
+        let limit = match limit {
+            Some(limit) => cmp::min(cmp::max(limit, 0) as u64, max_limit),
+            None => max_limit,
+        };
+
+        let blocks = match state_ctx
+            .adapter
+            .get_blocks_since(ctx.clone(), from, limit)
+            .await
+        {
+            Ok(blocks) => blocks,
+            Err(err) => {
+                common_apm::metrics::api::API_REQUEST_RESULT_COUNTER_VEC_STATIC
+                    .get_blocks_since
+                    .failure
+                    .inc();
+
+                return Err(err.into());
+            }
+        };
+
+        common_apm::metrics::api::API_REQUEST_RESULT_COUNTER_VEC_STATIC
+            .get_blocks_since
+            .success
+            .inc();
+        common_apm::metrics::api::API_REQUEST_TIME_HISTOGRAM_STATIC
+            .get_blocks_since
+            .observe(common_apm::metrics::duration_to_sec(inst.elapsed()));
+
+        let next_height = from + blocks.len() as u64;
+        Ok(BlocksSince {
+            blocks:      blocks.into_iter().map(Block::from).collect(),
+            next_height: Uint64::from(next_height),
+        })
     }
 
-    #[graphql(name = "getTransaction", description = "Get the transaction by hash")]
+    #[graphql(
+        name = "chainInfo",
+        description = "Get the chain id, node version and genesis block hash, clients can check \
+                       them before sending transactions"
+    )]
+    async fn chain_info(state_ctx: &State) -> FieldResult<ChainInfo> {
+        let inst = Instant::now();
+        common_apm::metrics::api::API_REQUEST_COUNTER_VEC_STATIC
+            .chain_info
+            .inc();
+
+        let info = match &state_ctx.chain_info {
+            Some(info) => info.clone(),
+            None => {
+                common_apm::metrics::api::API_REQUEST_RESULT_COUNTER_VEC_STATIC
+                    .chain_info
+                    .failure
+                    .inc();
+
+                let err: ProtocolError = APIError::NotFound.into();
+                return Err(err.into());
+            }
+        };
+
+        common_apm::metrics::api::API_REQUEST_RESULT_COUNTER_VEC_STATIC
+            .chain_info
+            .success
+            .inc();
+        common_apm::metrics::api::API_REQUEST_TIME_HISTOGRAM_STATIC
+            .chain_info
+            .observe(common_apm::metrics::duration_to_sec(inst.elapsed()));
+
+        Ok(ChainInfo::from(info))
+    }
+
+    #[graphql(
+        name = "getValidators",
+        description = "Get the validator set of the latest committed height"
+    )]
+    async fn get_validators(state_ctx: &State) -> FieldResult<Vec<Validator>> {
+        let inst = Instant::now();
+        common_apm::metrics::api::API_REQUEST_COUNTER_VEC_STATIC
+            .get_validators
+            .inc();
+
+        let validators = match &state_ctx.validators {
+            Some(validators) => validators(),
+            None => {
+                common_apm::metrics::api::API_REQUEST_RESULT_COUNTER_VEC_STATIC
+                    .get_validators
+                    .failure
+                    .inc();
+
+                let err: ProtocolError = APIError::NotFound.into();
+                return Err(err.into());
+            }
+        };
+
+        common_apm::metrics::api::API_REQUEST_RESULT_COUNTER_VEC_STATIC
+            .get_validators
+            .success
+            .inc();
+        common_apm::metrics::api::API_REQUEST_TIME_HISTOGRAM_STATIC
+            .get_validators
+            .observe(common_apm::metrics::duration_to_sec(inst.elapsed()));
+
+        Ok(validators.into_iter().map(Validator::from).collect())
+    }
+
+    #[graphql(
+        name = "getVoteParticipation",
+        description = "Get the share of recent heights each validator voted in, a delinquent validator has a low rate"
+    )]
+    async fn get_vote_participation(state_ctx: &State) -> FieldResult<Vec<VoteParticipation>> {
+        let inst = Instant::now();
+        common_apm::metrics::api::API_REQUEST_COUNTER_VEC_STATIC
+            .get_vote_participation
+            .inc();
+
+        let participation = match &state_ctx.participation {
+            Some(participation) => participation(),
+            None => {
+                common_apm::metrics::api::API_REQUEST_RESULT_COUNTER_VEC_STATIC
+                    .get_vote_participation
+                    .failure
+                    .inc();
+
+                let err: ProtocolError = APIError::NotFound.into();
+                return Err(err.into());
+            }
+        };
+
+        common_apm::metrics::api::API_REQUEST_RESULT_COUNTER_VEC_STATIC
+            .get_vote_participation
+            .success
+            .inc();
+        common_apm::metrics::api::API_REQUEST_TIME_HISTOGRAM_STATIC
+            .get_vote_participation
+            .observe(common_apm::metrics::duration_to_sec(inst.elapsed()));
+
+        Ok(participation
+            .into_iter()
+            .map(VoteParticipation::from)
+            .collect())
+    }
+
+    #[graphql(
+        name = "getLatestHeight",
+        description = "Get the height of the latest block"
+    )]
+    async fn get_latest_height(state_ctx: &State) -> FieldResult<Uint64> {
+        let ctx = state_ctx.new_context();
+        let inst = Instant::now();
+        common_apm::metrics::api::API_REQUEST_COUNTER_VEC_STATIC
+            .get_latest_height
+            .inc();
+
+        let height = match state_ctx.adapter.get_latest_height(ctx).await {
+            Ok(height) => height,
+            Err(err) => {
+                common_apm::metrics::api::API_REQUEST_RESULT_COUNTER_VEC_STATIC
+                    .get_latest_height
+                    .failure
+                    .inc();
+
+                return Err(err.into());
+            }
+        };
+
+        common_apm::metrics::api::API_REQUEST_RESULT_COUNTER_VEC_STATIC
+            .get_latest_height
+            .success
+            .inc();
+        common_apm::metrics::api::API_REQUEST_TIME_HISTOGRAM_STATIC
+            .get_latest_height
+            .observe(common_apm::metrics::duration_to_sec(inst.elapsed()));
+
+        Ok(Uint64::from(height))
+    }
+
+    #[graphql(
+        name = "getTransaction",
+        description = "Get the transaction by hash, if height is given, only the one included in block at that height"
+    )]
     async fn get_transaction(
         state_ctx: &State,
         tx_hash: Hash,
+        height: Option<Uint64>,
     ) -> FieldResult<Option<SignedTransaction>> {
-        let ctx = Context::new();
+        let ctx = state_ctx.new_context();
 
         let hash = protocol::types::Hash::from_hex(&tx_hash.as_hex())?;
+        let height = match height {
+            Some(id) => Some(id.try_into_u64()?),
+            None => None,
+        };
 
         let opt_stx = state_ctx
             .adapter
-            .get_transaction_by_hash(ctx.clone(), hash)
+            .get_transaction_by_hash(ctx.clone(), hash, height)
             .await?;
 
         Ok(opt_stx.map(SignedTransaction::from))
     }
 
+    #[graphql(
+        name = "getTransactions",
+        description = "Get the transactions by hashes, the result is in the same order as the hashes"
+    )]
+    async fn get_transactions(
+        state_ctx: &State,
+        tx_hashes: Vec<Hash>,
+    ) -> FieldResult<Vec<Option<SignedTransaction>>> {
+        let ctx = state_ctx.new_context();
+        let inst = Instant::now();
+        common_apm::metrics::api::API_REQUEST_COUNTER_VEC_STATIC
+            .get_transactions
+            .inc();
+
+        if tx_hashes.len() > state_ctx.max_batch_query_size {
+            common_apm::metrics::api::API_REQUEST_RESULT_COUNTER_VEC_STATIC
+                .get_transactions
+                .failure
+                .inc();
+
+            let err: ProtocolError = APIError::ExceedBatchLimit {
+                max:    state_ctx.max_batch_query_size,
+                actual: tx_hashes.len(),
+            }
+            .into();
+            return Err(err.into());
+        }
+
+        let hashes = match tx_hashes
+            .iter()
+            .map(|tx_hash| protocol::types::Hash::from_hex(&tx_hash.as_hex()))
+            .collect::<Result<Vec<_>, _>>()
+        {
+            Ok(hashes) => hashes,
+            Err(err) => {
+                common_apm::metrics::api::API_REQUEST_RESULT_COUNTER_VEC_STATIC
+                    .get_transactions
+                    .failure
+                    .inc();
+
+                return Err(err.into());
+            }
+        };
+
+        let opt_stxs = match state_ctx
+            .adapter
+            .get_transactions_by_hashes(ctx.clone(), hashes)
+            .await
+        {
+            Ok(opt_stxs) => opt_stxs,
+            Err(err) => {
+                common_apm::metrics::api::API_REQUEST_RESULT_COUNTER_VEC_STATIC
+                    .get_transactions
+                    .failure
+                    .inc();
+
+                return Err(err.into());
+            }
+        };
+
+        common_apm::metrics::api::API_REQUEST_RESULT_COUNTER_VEC_STATIC
+            .get_transactions
+            .success
+            .inc();
+        common_apm::metrics::api::API_REQUEST_TIME_HISTOGRAM_STATIC
+            .get_transactions
+            .observe(common_apm::metrics::duration_to_sec(inst.elapsed()));
+
+        Ok(opt_stxs
+            .into_iter()
+            .map(|opt_stx| opt_stx.map(SignedTransaction::from))
+            .collect())
+    }
+
     #[graphql(
         name = "getReceipt",
         description = "Get the receipt by transaction hash"
     )]
     async fn get_receipt(state_ctx: &State, tx_hash: Hash) -> FieldResult<Option<Receipt>> {
-        let ctx = Context::new();
+        let ctx = state_ctx.new_context();
 
         let hash = protocol::types::Hash::from_hex(&tx_hash.as_hex())?;
 
@@ -130,6 +520,136 @@ impl Query {
         Ok(opt_receipt.map(Receipt::from))
     }
 
+    #[graphql(
+        name = "getBlockReceipts",
+        description = "Get receipts of the block in transaction order by page, at most \
+                       maxBatchQuerySize receipts a page"
+    )]
+    async fn get_block_receipts(
+        state_ctx: &State,
+        height: Uint64,
+        offset: Option<i32>,
+        limit: Option<i32>,
+    ) -> FieldResult<Option<BlockReceipts>> {
+        let ctx = state_ctx.new_context();
+        let inst = Instant::now();
+        common_apm::metrics::api::API_REQUEST_COUNTER_VEC_STATIC
+            .get_block_receipts
+            .inc();
+
+        let height = match height.try_into_u64() {
+            Ok(height) => height,
+            Err(err) => {
+                common_apm::metrics::api::API_REQUEST_RESULT_COUNTER_VEC_STATIC
+                    .get_block_receipts
+                    .failure
+                    .inc();
+
+                return Err(err.into());
+            }
+        };
+
+        let opt_block = match state_ctx
+            .adapter
+            .get_block_by_height(ctx.clone(), Some(height))
+            .await
+        {
+            Ok(opt_block) => opt_block,
+            Err(err) => {
+                common_apm::metrics::api::API_REQUEST_RESULT_COUNTER_VEC_STATIC
+                    .get_block_receipts
+                    .failure
+                    .inc();
+
+                return Err(err.into());
+            }
+        };
+
+        let block = match opt_block {
+            Some(block) => block,
+            None => return Ok(None),
+        };
+
+        // Storage returns receipts of the block in key order, page them in
+        // transaction order.
+        let mut receipts = match state_ctx
+            .adapter
+            .get_block_receipts(ctx.clone(), height)
+            .await
+        {
+            Ok(receipts) => receipts
+                .into_iter()
+                .map(|receipt| (receipt.tx_hash.clone(), receipt))
+                .collect::<HashMap<_, _>>(),
+            Err(err) => {
+                common_apm::metrics::api::API_REQUEST_RESULT_COUNTER_VEC_STATIC
+                    .get_block_receipts
+                    .failure
+                    .inc();
+
+                return Err(err.into());
+            }
+        };
+
+        let total = block.ordered_tx_hashes.len();
+        let max_limit = state_ctx.max_batch_query_size;
+        let requested = match limit {
+            Some(limit) => cmp::max(limit, 0) as usize,
+            None => max_limit,
+        };
+        let start = cmp::min(cmp::max(offset.unwrap_or(0), 0) as usize, total);
+        let end = cmp::min(start + cmp::min(requested, max_limit), total);
+        let truncated = requested > max_limit && end < total;
+
+        common_apm::metrics::api::API_REQUEST_RESULT_COUNTER_VEC_STATIC
+            .get_block_receipts
+            .success
+            .inc();
+        common_apm::metrics::api::API_REQUEST_TIME_HISTOGRAM_STATIC
+            .get_block_receipts
+            .observe(common_apm::metrics::duration_to_sec(inst.elapsed()));
+
+        Ok(Some(BlockReceipts {
+            receipts: block.ordered_tx_hashes[start..end]
+                .iter()
+                .filter_map(|tx_hash| receipts.remove(tx_hash))
+                .map(Receipt::from)
+                .collect(),
+            total: Uint64::from(total as u64),
+            truncated,
+        }))
+    }
+
+    #[graphql(
+        name = "getTransactionProof",
+        description = "Get the merkle proof that a transaction is included in its block"
+    )]
+    async fn get_transaction_proof(
+        state_ctx: &State,
+        tx_hash: Hash,
+    ) -> FieldResult<Option<TransactionProof>> {
+        let ctx = state_ctx.new_context();
+
+        let hash = protocol::types::Hash::from_hex(&tx_hash.as_hex())?;
+
+        // Receipt records the height of block including the transaction
+        let height = match state_ctx
+            .adapter
+            .get_receipt_by_tx_hash(ctx.clone(), hash.clone())
+            .await?
+        {
+            Some(receipt) => receipt.height,
+            None => return Ok(None),
+        };
+
+        let opt_block = state_ctx
+            .adapter
+            .get_block_by_height(ctx.clone(), Some(height))
+            .await?;
+
+        Ok(opt_block.and_then(|block| TransactionProof::build(block, &hash)))
+    }
+
     #[graphql(name = "queryService", description = "query service")]
     async fn query_service(
         state_ctx: &State,
@@ -140,13 +660,15 @@ impl Query {
         service_name: String,
         method: String,
         payload: String,
+        offset: Option<Uint64>,
+        limit: Option<Uint64>,
     ) -> FieldResult<ServiceResponse> {
-        let ctx = Context::new();
+        let ctx = state_ctx.new_context();
 
         let height = match height {
             Some(id) => id.try_into_u64()?,
             None => {
-                block_on(state_ctx.adapter.get_block_by_height(Context::new(), None))?
+                block_on(state_ctx.adapter.get_block_by_height(ctx.clone(), None))?
                     .expect("Always not none")
                     .header
                     .height
@@ -163,6 +685,7 @@ impl Query {
         };
 
         let address: protocol::types::Address = caller.to_str().parse()?;
+        let payload = paged_payload(payload, offset, limit)?;
 
         let exec_resp = state_ctx
             .adapter
@@ -191,13 +714,22 @@ impl Mutation {
         input_raw: InputRawTransaction,
         input_encryption: InputTransactionEncryption,
     ) -> FieldResult<Hash> {
-        let ctx = Context::new();
+        state_ctx.check_maintenance()?;
+        let ctx = state_ctx.new_context();
 
         let inst = Instant::now();
         common_apm::metrics::api::API_REQUEST_COUNTER_VEC_STATIC
             .send_transaction
             .inc();
 
+        if let Err(err) = validate_input_transaction(&input_raw, &input_encryption) {
+            common_apm::metrics::api::API_REQUEST_RESULT_COUNTER_VEC_STATIC
+                .send_transaction
+                .failure
+                .inc();
+            return Err(err.into_field_error());
+        }
+
         let stx = to_signed_transaction(input_raw, input_encryption)?;
         let tx_hash = stx.tx_hash.clone();
 
@@ -220,6 +752,30 @@ impl Mutation {
         Ok(Hash::from(tx_hash))
     }
 
+    #[graphql(
+        name = "cancelTransaction",
+        description = "Cancel a pending transaction, signature is signed over the cancel message of the transaction by its sender"
+    )]
+    async fn cancel_transaction(
+        state_ctx: &State,
+        tx_hash: Hash,
+        pubkey: Bytes,
+        signature: Bytes,
+    ) -> FieldResult<bool> {
+        state_ctx.check_maintenance()?;
+        let ctx = state_ctx.new_context();
+
+        let hash = protocol::types::Hash::from_hex(&tx_hash.as_hex())?;
+        let pubkey = protocol::Bytes::from(pubkey.to_vec()?);
+        let signature = protocol::Bytes::from(signature.to_vec()?);
+
+        state_ctx
+            .adapter
+            .cancel_transaction(ctx, hash, pubkey, signature)
+            .await?;
+        Ok(true)
+    }
+
     #[graphql(
         name = "unsafeSendTransaction",
         deprecated = "DON'T use it in production! This is just for development."
@@ -229,7 +785,8 @@ impl Mutation {
         input_raw: InputRawTransaction,
         input_privkey: Bytes,
     ) -> FieldResult<Hash> {
-        let ctx = Context::new();
+        state_ctx.check_maintenance()?;
+        let ctx = state_ctx.new_context();
 
         let raw_tx = to_transaction(input_raw)?;
         let tx_hash = protocol::types::Hash::digest(raw_tx.encode_fixed()?);
@@ -258,24 +815,159 @@ impl Mutation {
 // well, the whole GraphQL schema.
 type Schema = juniper::RootNode<'static, Query, Mutation>;
 
+/// Pass paging arguments to the service through payload, the payload is left
+/// untouched if neither is given.
+fn paged_payload(
+    payload: String,
+    offset: Option<Uint64>,
+    limit: Option<Uint64>,
+) -> ProtocolResult<String> {
+    if offset.is_none() && limit.is_none() {
+        return Ok(payload);
+    }
+
+    let mut page = PageRequest::default();
+    if let Some(offset) = offset {
+        page.offset = offset.try_into_u64()?;
+    }
+    if let Some(limit) = limit {
+        page.limit = limit.try_into_u64()?;
+    }
+    page.merge_into_payload(&payload)
+}
+
 async fn graphiql() -> HttpResponse {
     HttpResponse::Ok()
         .content_type("text/html; charset=utf-8")
         .body(GRAPHIQL_HTML.to_owned())
 }
 
+// A json array is a batch of requests, results are returned in the same order.
 async fn graphql(
+    req: HttpRequest,
     st: web::Data<State>,
-    data: web::Json<GraphQLRequest>,
+    data: web::Json<serde_json::Value>,
 ) -> Result<HttpResponse, Error> {
-    let result = data.execute_async(&st.schema, &st).await;
-    let res = Ok::<_, serde_json::error::Error>(serde_json::to_string(&result)?)?;
+    let request_id = req
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|id| id.to_str().ok())
+        .filter(|id| !id.is_empty())
+        .map(str::to_owned)
+        .unwrap_or_else(|| format!("{:016x}", rand::random::<u64>()));
+    let st = State {
+        request_id: Some(request_id.clone()),
+        ..st.get_ref().clone()
+    };
+
+    let res = match data.into_inner() {
+        serde_json::Value::Array(requests) if requests.len() > st.max_batch_request_size => {
+            let error: FieldError = FieldError::new(
+                format!(
+                    "batch of {} requests exceeds max {}",
+                    requests.len(),
+                    st.max_batch_request_size
+                ),
+                graphql_value!(None),
+            );
+            serde_json::to_string(&GraphQLResponse::error(error))?
+        }
+        serde_json::Value::Array(requests) => {
+            let mut results = Vec::with_capacity(requests.len());
+            for request in requests {
+                let request = serde_json::from_value(request).map_err(ErrorBadRequest)?;
+                results.push(execute_request(&st, &request).await?);
+            }
+            serde_json::to_string(&results)?
+        }
+        request => {
+            let request = serde_json::from_value(request).map_err(ErrorBadRequest)?;
+            serde_json::to_string(&execute_request(&st, &request).await?)?
+        }
+    };
 
     Ok(HttpResponse::Ok()
         .content_type("application/json")
+        .header(REQUEST_ID_HEADER, request_id)
         .body(res))
 }
 
+async fn execute_request(
+    st: &State,
+    data: &GraphQLRequest,
+) -> Result<serde_json::Value, serde_json::Error> {
+    // Query string of request is private, take it from serialized request.
+    let request = serde_json::to_value(data)?;
+    let query = request["query"].as_str().unwrap_or_default();
+
+    // A query our analyzer can't parse is rejected as well, otherwise it would
+    // run without checks.
+    let checked = parse_query(query).and_then(|document| {
+        document.check_shape(st.max_query_depth, st.max_aliases)?;
+        document.check_cost(st.max_batch_query_size, st.max_query_cost)
+    });
+    match checked {
+        Ok(_) => {
+            let result = data.execute_async(&st.schema, st).await;
+            serde_json::to_value(&result)
+        }
+        Err(err) => {
+            let error: FieldError = FieldError::new(err.to_string(), graphql_value!(None));
+            serde_json::to_value(&GraphQLResponse::error(error))
+        }
+    }
+}
+
+async fn ready(st: web::Data<State>) -> HttpResponse {
+    let status = st.commit_status.as_ref().map(|f| f());
+    match check_ready(status, st.ready_commit_timeout) {
+        Ok(()) => HttpResponse::Ok().body("ready"),
+        Err(reason) => HttpResponse::ServiceUnavailable().body(reason),
+    }
+}
+
+/// Shows maintenance mode on GET, sets it from the `enabled` query parameter
+/// on POST. Only registered when an admin token is configured.
+async fn maintenance(req: HttpRequest, st: web::Data<State>) -> HttpResponse {
+    let authorized = match (&st.admin_token, req.headers().get(ADMIN_TOKEN_HEADER)) {
+        (Some(token), Some(given)) => constant_time_eq(token.as_bytes(), given.as_bytes()),
+        _ => false,
+    };
+    if !authorized {
+        return HttpResponse::Unauthorized().body("invalid admin token");
+    }
+
+    if req.method() == actix_web::http::Method::POST {
+        let enabled = url::form_urlencoded::parse(req.query_string().as_bytes())
+            .find(|(key, _)| key == "enabled")
+            .map(|(_, value)| value.parse::<bool>());
+        match enabled {
+            Some(Ok(enabled)) => {
+                st.maintenance.store(enabled, Ordering::SeqCst);
+                log::warn!("[api]: maintenance mode set to {}", enabled);
+            }
+            _ => return HttpResponse::BadRequest().body("enabled should be true or false"),
+        }
+    }
+
+    HttpResponse::Ok().json(serde_json::json!({
+        "maintenance": st.maintenance.load(Ordering::SeqCst)
+    }))
+}
+
+/// Not ready if the node hasn't committed a block within `timeout`. Always
+/// ready without a commit status source.
+fn check_ready(status: Option<CommitStatus>, timeout: Duration) -> Result<(), String> {
+    match status {
+        Some(status) if status.committed_at.elapsed() > timeout => Err(format!(
+            "no commit for {}s, last committed height {}",
+            status.committed_at.elapsed().as_secs(),
+            status.height
+        )),
+        _ => Ok(()),
+    }
+}
+
 async fn metrics() -> HttpResponse {
     let metrics_data = match common_apm::metrics::all_metrics() {
         Ok(data) => data,
@@ -407,12 +1099,31 @@ mod profile {
     }
 }
 
-pub async fn start_graphql<Adapter: APIAdapter + 'static>(cfg: GraphQLConfig, adapter: Adapter) {
+pub async fn start_graphql<Adapter: APIAdapter + 'static>(
+    cfg: GraphQLConfig,
+    adapter: Adapter,
+    commit_status: Option<CommitStatusFn>,
+    validators: Option<ValidatorsFn>,
+    participation: Option<ParticipationFn>,
+) {
     let schema = Schema::new(Query, Mutation);
 
     let state = State {
         adapter: Arc::new(Box::new(adapter)),
-        schema:  Arc::new(schema),
+        schema: Arc::new(schema),
+        max_batch_query_size: cfg.max_batch_query_size,
+        max_batch_request_size: cfg.max_batch_request_size,
+        max_query_cost: cfg.max_query_cost,
+        max_query_depth: cfg.max_query_depth,
+        max_aliases: cfg.max_aliases,
+        commit_status,
+        validators,
+        participation,
+        ready_commit_timeout: Duration::from_secs(cfg.ready_commit_timeout),
+        chain_info: cfg.chain_info.clone(),
+        request_id: None,
+        maintenance: Arc::new(AtomicBool::new(cfg.maintenance_mode)),
+        admin_token: cfg.admin_token.clone(),
     };
 
     let path_graphql_uri = cfg.graphql_uri.to_owned();
@@ -422,6 +1133,7 @@ pub async fn start_graphql<Adapter: APIAdapter + 'static>(cfg: GraphQLConfig, ad
     let add_listening_address = cfg.listening_address;
     let max_payload_size = cfg.max_payload_size;
     let enable_dump_profile = cfg.enable_dump_profile;
+    let enable_maintenance = cfg.admin_token.is_some();
 
     // Start http server
     let server = HttpServer::new(move || {
@@ -429,13 +1141,24 @@ pub async fn start_graphql<Adapter: APIAdapter + 'static>(cfg: GraphQLConfig, ad
             .data(state.clone())
             .service(
                 web::resource(&path_graphql_uri)
-                    .app_data(web::Json::<GraphQLRequest>::configure(|cfg| {
+                    .app_data(web::Json::<serde_json::Value>::configure(|cfg| {
                         cfg.limit(max_payload_size)
                     }))
                     .route(web::post().to(graphql)),
             )
             .service(web::resource(&path_graphiql_uri).route(web::get().to(graphiql)))
-            .service(web::resource("/metrics").route(web::get().to(metrics)));
+            .service(web::resource("/metrics").route(web::get().to(metrics)))
+            .service(web::resource("/ready").route(web::get().to(ready)));
+
+        let app = if enable_maintenance {
+            app.service(
+                web::resource("/maintenance")
+                    .route(web::get().to(maintenance))
+                    .route(web::post().to(maintenance)),
+            )
+        } else {
+            app
+        };
 
         if enable_dump_profile {
             app.service(web::resource("/dump_profile").route(web::get().to(profile::dump_profile)))
@@ -471,3 +1194,643 @@ pub async fn start_graphql<Adapter: APIAdapter + 'static>(cfg: GraphQLConfig, ad
             .unwrap()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{http, test};
+    use async_trait::async_trait;
+
+    use protocol::traits::ServiceResponse;
+    use protocol::types::{self, BlockHeader};
+
+    use super::*;
+
+    struct MockAPIAdapter;
+
+    #[async_trait]
+    impl APIAdapter for MockAPIAdapter {
+        async fn insert_signed_txs(
+            &self,
+            _: Context,
+            _: types::SignedTransaction,
+        ) -> ProtocolResult<()> {
+            unimplemented!()
+        }
+
+        async fn cancel_transaction(
+            &self,
+            _: Context,
+            _: types::Hash,
+            _: types::Bytes,
+            _: types::Bytes,
+        ) -> ProtocolResult<()> {
+            Ok(())
+        }
+
+        async fn get_block_by_height(
+            &self,
+            _: Context,
+            height: Option<u64>,
+        ) -> ProtocolResult<Option<types::Block>> {
+            Ok(height
+                .filter(|h| *h == 1)
+                .map(|_| mock_block(mock_tx_hashes())))
+        }
+
+        async fn get_blocks_since(
+            &self,
+            _: Context,
+            _: u64,
+            _: u64,
+        ) -> ProtocolResult<Vec<types::Block>> {
+            unimplemented!()
+        }
+
+        async fn get_latest_height(&self, _: Context) -> ProtocolResult<u64> {
+            Ok(16)
+        }
+
+        async fn get_block_header_by_height(
+            &self,
+            _: Context,
+            _: Option<u64>,
+        ) -> ProtocolResult<Option<BlockHeader>> {
+            unimplemented!()
+        }
+
+        async fn get_receipt_by_tx_hash(
+            &self,
+            _: Context,
+            _: types::Hash,
+        ) -> ProtocolResult<Option<types::Receipt>> {
+            unimplemented!()
+        }
+
+        async fn get_receipts_by_hashes(
+            &self,
+            _: Context,
+            height: u64,
+            tx_hashes: Vec<types::Hash>,
+        ) -> ProtocolResult<Vec<Option<types::Receipt>>> {
+            Ok(tx_hashes
+                .into_iter()
+                .map(|tx_hash| Some(mock_receipt(height, tx_hash)))
+                .collect())
+        }
+
+        async fn get_block_receipts(
+            &self,
+            _: Context,
+            height: u64,
+        ) -> ProtocolResult<Vec<types::Receipt>> {
+            // Not in transaction order, like storage
+            Ok(mock_tx_hashes()
+                .into_iter()
+                .rev()
+                .map(|tx_hash| mock_receipt(height, tx_hash))
+                .collect())
+        }
+
+        async fn get_transaction_by_hash(
+            &self,
+            _: Context,
+            _: types::Hash,
+            _: Option<u64>,
+        ) -> ProtocolResult<Option<types::SignedTransaction>> {
+            unimplemented!()
+        }
+
+        async fn get_transactions_by_hashes(
+            &self,
+            _: Context,
+            tx_hashes: Vec<types::Hash>,
+        ) -> ProtocolResult<Vec<Option<types::SignedTransaction>>> {
+            let known = mock_tx_hashes();
+            Ok(tx_hashes
+                .into_iter()
+                .map(|tx_hash| Some(tx_hash).filter(|h| known.contains(h)).map(mock_stx))
+                .collect())
+        }
+
+        async fn query_service(
+            &self,
+            _: Context,
+            _: u64,
+            _: u64,
+            _: u64,
+            _: types::Address,
+            _: String,
+            _: String,
+            _: String,
+        ) -> ProtocolResult<ServiceResponse<String>> {
+            unimplemented!()
+        }
+    }
+
+    fn mock_tx_hashes() -> Vec<types::Hash> {
+        (0u8..5)
+            .map(|i| types::Hash::digest(types::Bytes::from(vec![i])))
+            .collect()
+    }
+
+    fn mock_block(tx_hashes: Vec<types::Hash>) -> types::Block {
+        let header = BlockHeader {
+            chain_id:                       types::Hash::default(),
+            height:                         1,
+            exec_height:                    1,
+            prev_hash:                      types::Hash::default(),
+            timestamp:                      0,
+            order_root:                     types::Hash::default(),
+            order_signed_transactions_hash: types::Hash::default(),
+            confirm_root:                   vec![],
+            state_root:                     types::Hash::default(),
+            receipt_root:                   vec![],
+            cycles_used:                    vec![],
+            proposer:                       types::Address::default(),
+            proof:                          types::Proof {
+                height:     0,
+                round:      0,
+                block_hash: types::Hash::default(),
+                signature:  types::Bytes::new(),
+                bitmap:     types::Bytes::new(),
+            },
+            validator_version:              0,
+            validators:                     vec![],
+        };
+
+        types::Block {
+            header,
+            ordered_tx_hashes: tx_hashes,
+        }
+    }
+
+    fn mock_stx(tx_hash: types::Hash) -> types::SignedTransaction {
+        types::SignedTransaction {
+            raw: types::RawTransaction {
+                chain_id:     types::Hash::default(),
+                cycles_price: 1,
+                cycles_limit: 1,
+                nonce:        types::Hash::default(),
+                request:      types::TransactionRequest {
+                    method:       "transfer".to_owned(),
+                    service_name: "asset".to_owned(),
+                    payload:      "".to_owned(),
+                },
+                timeout:      1,
+                sender:       types::Address::default(),
+            },
+            tx_hash,
+            pubkey: types::Bytes::new(),
+            signature: types::Bytes::new(),
+        }
+    }
+
+    fn mock_receipt(height: u64, tx_hash: types::Hash) -> types::Receipt {
+        types::Receipt {
+            state_root: types::Hash::default(),
+            height,
+            tx_hash,
+            cycles_used: 1,
+            events: vec![],
+            response: types::ReceiptResponse {
+                service_name: "asset".to_owned(),
+                method:       "transfer".to_owned(),
+                response:     ServiceResponse::from_succeed("".to_owned()),
+            },
+        }
+    }
+
+    fn mock_state() -> State {
+        let cfg = GraphQLConfig::default();
+        State {
+            adapter:                Arc::new(Box::new(MockAPIAdapter)),
+            schema:                 Arc::new(Schema::new(Query, Mutation)),
+            max_batch_query_size:   cfg.max_batch_query_size,
+            max_batch_request_size: 2,
+            max_query_cost:         cfg.max_query_cost,
+            max_query_depth:        cfg.max_query_depth,
+            max_aliases:            cfg.max_aliases,
+            commit_status:          None,
+            validators:             Some(Arc::new(|| {
+                vec![types::Validator {
+                    pub_key:        types::Bytes::from_static(b"validator"),
+                    propose_weight: 1,
+                    vote_weight:    2,
+                }]
+            })),
+            participation:          Some(Arc::new(|| {
+                let address = types::Address::from_hash(types::Hash::digest(
+                    types::Bytes::from_static(b"validator"),
+                ))
+                .unwrap();
+                vec![(address, 0.5)]
+            })),
+            ready_commit_timeout:   Duration::from_secs(cfg.ready_commit_timeout),
+            chain_info:             Some(config::ChainInfo {
+                chain_id:     types::Hash::digest(types::Bytes::from_static(b"chain")),
+                version:      "v0.2.1".to_owned(),
+                genesis_hash: types::Hash::digest(types::Bytes::from_static(b"genesis")),
+            }),
+            request_id:             None,
+            maintenance:            Arc::new(AtomicBool::new(false)),
+            admin_token:            Some("admin".to_owned()),
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_batched_graphql() {
+        let mut app = test::init_service(
+            App::new()
+                .data(mock_state())
+                .route("/graphql", web::post().to(graphql)),
+        )
+        .await;
+
+        let batch = serde_json::json!([
+            { "query": "{ getLatestHeight }" },
+            { "query": "{ __typename }" },
+        ]);
+        let req = test::TestRequest::post()
+            .uri("/graphql")
+            .set_json(&batch)
+            .to_request();
+        let results: serde_json::Value = test::read_response_json(&mut app, req).await;
+        assert_eq!(results[0]["data"]["getLatestHeight"], "0x0000000000000010");
+        assert_eq!(results[1]["data"]["__typename"], "Query");
+
+        // A single request still gets a single result
+        let req = test::TestRequest::post()
+            .uri("/graphql")
+            .set_json(&batch[1])
+            .to_request();
+        let result: serde_json::Value = test::read_response_json(&mut app, req).await;
+        assert_eq!(result["data"]["__typename"], "Query");
+
+        let too_many = serde_json::json!([batch[0], batch[0], batch[1]]);
+        let req = test::TestRequest::post()
+            .uri("/graphql")
+            .set_json(&too_many)
+            .to_request();
+        let result: serde_json::Value = test::read_response_json(&mut app, req).await;
+        assert!(result["errors"][0]["message"]
+            .as_str()
+            .unwrap()
+            .contains("exceeds max 2"));
+    }
+
+    #[actix_rt::test]
+    async fn test_chain_info() {
+        let state = mock_state();
+        let chain_info = state.chain_info.clone().unwrap();
+        let mut app = test::init_service(
+            App::new()
+                .data(state)
+                .route("/graphql", web::post().to(graphql)),
+        )
+        .await;
+
+        let query = serde_json::json!({ "query": "{ chainInfo { chainId version genesisHash } }" });
+        let req = test::TestRequest::post()
+            .uri("/graphql")
+            .set_json(&query)
+            .to_request();
+        let result: serde_json::Value = test::read_response_json(&mut app, req).await;
+        let info = &result["data"]["chainInfo"];
+        assert_eq!(info["chainId"], chain_info.chain_id.as_hex());
+        assert_eq!(info["version"], "v0.2.1");
+        assert_eq!(info["genesisHash"], chain_info.genesis_hash.as_hex());
+    }
+
+    #[actix_rt::test]
+    async fn test_get_validators() {
+        let mut app = test::init_service(
+            App::new()
+                .data(mock_state())
+                .route("/graphql", web::post().to(graphql)),
+        )
+        .await;
+
+        let query = serde_json::json!({
+            "query": "{ getValidators { pubkey proposeWeight voteWeight } }"
+        });
+        let req = test::TestRequest::post()
+            .uri("/graphql")
+            .set_json(&query)
+            .to_request();
+        let result: serde_json::Value = test::read_response_json(&mut app, req).await;
+        let validators = result["data"]["getValidators"].as_array().unwrap();
+        assert_eq!(validators.len(), 1);
+        assert_eq!(validators[0]["proposeWeight"], 1);
+        assert_eq!(validators[0]["voteWeight"], 2);
+    }
+
+    #[actix_rt::test]
+    async fn test_reject_unchecked_query() {
+        let mut state = mock_state();
+        state.max_query_cost = 10;
+        let mut app = test::init_service(
+            App::new()
+                .data(state)
+                .route("/graphql", web::post().to(graphql)),
+        )
+        .await;
+
+        for (query, error) in [
+            // Not understood by the analyzer
+            (
+                "{ getValidators { voteWeight } } \u{00e9}",
+                "parse query failed",
+            ),
+            // Default limit is max batch size
+            (
+                r#"{ getBlocksSince(from: "0x0") { blocks { hash } } }"#,
+                "exceeds the limit",
+            ),
+        ]
+        .iter()
+        {
+            let req = test::TestRequest::post()
+                .uri("/graphql")
+                .set_json(&serde_json::json!({ "query": query }))
+                .to_request();
+            let result: serde_json::Value = test::read_response_json(&mut app, req).await;
+            assert!(result["data"].is_null());
+            assert!(result["errors"][0]["message"]
+                .as_str()
+                .unwrap()
+                .contains(error));
+        }
+    }
+
+    #[actix_rt::test]
+    async fn test_get_vote_participation() {
+        let mut app = test::init_service(
+            App::new()
+                .data(mock_state())
+                .route("/graphql", web::post().to(graphql)),
+        )
+        .await;
+
+        let query = serde_json::json!({ "query": "{ getVoteParticipation { address rate } }" });
+        let req = test::TestRequest::post()
+            .uri("/graphql")
+            .set_json(&query)
+            .to_request();
+        let result: serde_json::Value = test::read_response_json(&mut app, req).await;
+        let participation = result["data"]["getVoteParticipation"].as_array().unwrap();
+        assert_eq!(participation.len(), 1);
+        assert_eq!(participation[0]["rate"], 0.5);
+    }
+
+    #[actix_rt::test]
+    async fn test_get_block_receipts() {
+        let mut state = mock_state();
+        state.max_batch_query_size = 3;
+        let mut app = test::init_service(
+            App::new()
+                .data(state)
+                .route("/graphql", web::post().to(graphql)),
+        )
+        .await;
+
+        let tx_hashes = mock_tx_hashes()
+            .into_iter()
+            .map(|h| h.as_hex())
+            .collect::<Vec<_>>();
+        macro_rules! page {
+            ($query: expr) => {{
+                let query = serde_json::json!({ "query": $query });
+                let req = test::TestRequest::post()
+                    .uri("/graphql")
+                    .set_json(&query)
+                    .to_request();
+                let result: serde_json::Value = test::read_response_json(&mut app, req).await;
+                result["data"]["getBlockReceipts"].clone()
+            }};
+        }
+
+        let receipts = page!(
+            r#"{ getBlockReceipts(height: "0x1", offset: 1, limit: 2) {
+                receipts { txHash } total truncated
+            } }"#
+        );
+        assert_eq!(receipts["total"], "0x0000000000000005");
+        assert_eq!(receipts["truncated"], false);
+        assert_eq!(receipts["receipts"].as_array().unwrap().len(), 2);
+        assert_eq!(receipts["receipts"][0]["txHash"], tx_hashes[1]);
+        assert_eq!(receipts["receipts"][1]["txHash"], tx_hashes[2]);
+
+        // Capped by max batch query size.
+        let receipts = page!(
+            r#"{ getBlockReceipts(height: "0x1", limit: 10) { receipts { txHash } truncated } }"#
+        );
+        assert_eq!(receipts["truncated"], true);
+        assert_eq!(receipts["receipts"].as_array().unwrap().len(), 3);
+
+        // The last page.
+        let receipts = page!(
+            r#"{ getBlockReceipts(height: "0x1", offset: 3, limit: 10) {
+                receipts { txHash } truncated
+            } }"#
+        );
+        assert_eq!(receipts["truncated"], false);
+        assert_eq!(receipts["receipts"][1]["txHash"], tx_hashes[4]);
+
+        let receipts = page!(r#"{ getBlockReceipts(height: "0x2") { total } }"#);
+        assert!(receipts.is_null());
+    }
+
+    #[actix_rt::test]
+    async fn test_get_transactions() {
+        let mut state = mock_state();
+        state.max_batch_query_size = 3;
+        let mut app = test::init_service(
+            App::new()
+                .data(state)
+                .route("/graphql", web::post().to(graphql)),
+        )
+        .await;
+
+        let tx_hashes = mock_tx_hashes();
+        macro_rules! get_transactions {
+            ($hashes: expr) => {{
+                let hashes = $hashes
+                    .iter()
+                    .map(|h| format!(r#""{}""#, h.as_hex()))
+                    .collect::<Vec<_>>()
+                    .join(",");
+                let query = serde_json::json!({
+                    "query": format!("{{ getTransactions(txHashes: [{}]) {{ txHash }} }}", hashes)
+                });
+                let req = test::TestRequest::post()
+                    .uri("/graphql")
+                    .set_json(&query)
+                    .to_request();
+                let result: serde_json::Value = test::read_response_json(&mut app, req).await;
+                result
+            }};
+        }
+
+        // In request order, unknown hashes are null.
+        let unknown = types::Hash::digest(types::Bytes::from_static(b"unknown"));
+        let result = get_transactions!([&tx_hashes[2], &unknown, &tx_hashes[0]]);
+        let stxs = result["data"]["getTransactions"].as_array().unwrap();
+        assert_eq!(stxs.len(), 3);
+        assert_eq!(stxs[0]["txHash"], tx_hashes[2].as_hex());
+        assert!(stxs[1].is_null());
+        assert_eq!(stxs[2]["txHash"], tx_hashes[0].as_hex());
+
+        let result = get_transactions!(tx_hashes[..4]);
+        assert!(result["data"].is_null());
+        assert!(result["errors"][0]["message"]
+            .as_str()
+            .unwrap()
+            .contains("ExceedBatchLimit"));
+    }
+
+    #[actix_rt::test]
+    async fn test_maintenance_mode() {
+        let mut app = test::init_service(
+            App::new()
+                .data(mock_state())
+                .route("/graphql", web::post().to(graphql))
+                .service(
+                    web::resource("/maintenance")
+                        .route(web::get().to(maintenance))
+                        .route(web::post().to(maintenance)),
+                ),
+        )
+        .await;
+
+        let tx_hash = types::Hash::digest(types::Bytes::from_static(b"tx")).as_hex();
+        let cancel = format!(
+            r#"mutation {{ cancelTransaction(txHash: "{}", pubkey: "0x01", signature: "0x02") }}"#,
+            tx_hash
+        );
+        let mutation = serde_json::json!({ "query": cancel });
+        let query = serde_json::json!({ "query": "{ getValidators { voteWeight } }" });
+        macro_rules! post {
+            ($body: expr) => {{
+                let req = test::TestRequest::post()
+                    .uri("/graphql")
+                    .set_json(&$body)
+                    .to_request();
+                let result: serde_json::Value = test::read_response_json(&mut app, req).await;
+                result
+            }};
+        }
+        macro_rules! set_maintenance {
+            ($uri: expr, $token: expr) => {{
+                let req = test::TestRequest::post()
+                    .uri($uri)
+                    .header(ADMIN_TOKEN_HEADER, $token)
+                    .to_request();
+                test::call_service(&mut app, req).await
+            }};
+        }
+
+        assert_eq!(post!(mutation)["data"]["cancelTransaction"], true);
+
+        let resp = set_maintenance!("/maintenance?enabled=true", "guess");
+        assert_eq!(resp.status(), http::StatusCode::UNAUTHORIZED);
+        let resp = set_maintenance!("/maintenance?enabled=yes", "admin");
+        assert_eq!(resp.status(), http::StatusCode::BAD_REQUEST);
+        let resp = set_maintenance!("/maintenance?enabled=true", "admin");
+        assert_eq!(resp.status(), http::StatusCode::OK);
+
+        let result = post!(mutation);
+        assert!(result["data"].is_null());
+        assert_eq!(result["errors"][0]["extensions"]["code"], 503);
+        assert_eq!(post!(query)["data"]["getValidators"][0]["voteWeight"], 2);
+
+        let req = test::TestRequest::get()
+            .uri("/maintenance")
+            .header(ADMIN_TOKEN_HEADER, "admin")
+            .to_request();
+        let status: serde_json::Value = test::read_response_json(&mut app, req).await;
+        assert_eq!(status["maintenance"], true);
+
+        set_maintenance!("/maintenance?enabled=false", "admin");
+        assert_eq!(post!(mutation)["data"]["cancelTransaction"], true);
+    }
+
+    #[actix_rt::test]
+    async fn test_request_id() {
+        let mut app = test::init_service(
+            App::new()
+                .data(mock_state())
+                .route("/graphql", web::post().to(graphql)),
+        )
+        .await;
+        let query = serde_json::json!({ "query": "{ __typename }" });
+
+        let req = test::TestRequest::post()
+            .uri("/graphql")
+            .header(REQUEST_ID_HEADER, "req-42")
+            .set_json(&query)
+            .to_request();
+        let resp = test::call_service(&mut app, req).await;
+        assert_eq!(resp.headers().get(REQUEST_ID_HEADER).unwrap(), "req-42");
+
+        let req = test::TestRequest::post()
+            .uri("/graphql")
+            .set_json(&query)
+            .to_request();
+        let resp = test::call_service(&mut app, req).await;
+        let generated = resp.headers().get(REQUEST_ID_HEADER).unwrap();
+        assert!(!generated.is_empty());
+
+        // Resolvers pass it down in context, where logger picks it up
+        let state = State {
+            request_id: Some("req-42".to_owned()),
+            ..mock_state()
+        };
+        let ctx = state.new_context();
+        assert_eq!(ctx.get::<String>(REQUEST_ID_KEY).unwrap(), "req-42");
+    }
+
+    #[test]
+    fn test_check_ready() {
+        let timeout = Duration::from_secs(60);
+        assert!(check_ready(None, timeout).is_ok());
+
+        let recent = CommitStatus {
+            height:       10,
+            committed_at: Instant::now(),
+        };
+        assert!(check_ready(Some(recent), timeout).is_ok());
+
+        let stale = CommitStatus {
+            height:       10,
+            committed_at: Instant::now() - Duration::from_secs(61),
+        };
+        assert!(check_ready(Some(stale), timeout).is_err());
+    }
+
+    #[test]
+    fn test_paged_payload() {
+        let payload = r#"{"asset_id": "0x01"}"#.to_owned();
+        assert_eq!(paged_payload(payload.clone(), None, None).unwrap(), payload);
+
+        let paged = paged_payload(payload, Some(Uint64::from(20)), None).unwrap();
+        let value: serde_json::Value = serde_json::from_str(&paged).unwrap();
+        assert_eq!(value["asset_id"], "0x01");
+        assert_eq!(value["offset"], 20);
+        assert_eq!(value["limit"], protocol::types::DEFAULT_PAGE_LIMIT);
+
+        // A paged list of mock service, sliced by threaded arguments
+        let all: Vec<u64> = (0..50).collect();
+        let paged = paged_payload(
+            "".to_owned(),
+            Some(Uint64::from(45)),
+            Some(Uint64::from(10)),
+        );
+        let page: PageRequest = serde_json::from_str(&paged.unwrap()).unwrap();
+        let resp = protocol::types::ServicePage::from_slice(&all, page);
+        assert_eq!(resp.items, (45..50).collect::<Vec<_>>());
+        assert_eq!(resp.total, 50);
+        assert_eq!(resp.next_cursor, None);
+
+        assert!(paged_payload("[]".to_owned(), None, Some(Uint64::from(1))).is_err());
+    }
+}
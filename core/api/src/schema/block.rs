@@ -1,7 +1,7 @@
 use protocol::fixed_codec::FixedCodec;
 use protocol::types::Hash as PHash;
 
-use crate::schema::{Address, Bytes, Hash, MerkleRoot, Uint64};
+use crate::schema::{Address, Bytes, Hash, MerkleRoot, Receipt, Uint64};
 
 #[derive(juniper::GraphQLObject, Clone)]
 #[graphql(
@@ -12,11 +12,39 @@ use crate::schema::{Address, Bytes, Hash, MerkleRoot, Uint64};
 )]
 pub struct Block {
     #[graphql(description = "The header section of a block")]
-    header:            BlockHeader,
+    header:             BlockHeader,
     #[graphql(description = "The body section of a block")]
-    ordered_tx_hashes: Vec<Hash>,
+    ordered_tx_hashes:  Vec<Hash>,
     #[graphql(description = "Hash of the block")]
-    hash:              Hash,
+    hash:               Hash,
+    #[graphql(
+        description = "Receipts of transactions in the block, only present if requested by includeReceipts"
+    )]
+    receipts:           Option<Vec<Receipt>>,
+    #[graphql(description = "Whether receipts of some transactions are left out")]
+    receipts_truncated: bool,
+}
+
+impl Block {
+    /// Embed receipts of the block, `truncated` tells that only receipts of
+    /// part of the transactions are given.
+    pub fn with_receipts(
+        mut self,
+        receipts: Vec<protocol::types::Receipt>,
+        truncated: bool,
+    ) -> Self {
+        self.receipts = Some(receipts.into_iter().map(Receipt::from).collect());
+        self.receipts_truncated = truncated;
+        self
+    }
+}
+
+#[derive(juniper::GraphQLObject, Clone)]
+#[graphql(description = "Consecutive blocks and the height to continue from")]
+pub struct BlocksSince {
+    pub blocks:      Vec<Block>,
+    #[graphql(description = "The height to start the next query from")]
+    pub next_height: Uint64,
 }
 
 #[derive(juniper::GraphQLObject, Clone)]
@@ -72,6 +100,13 @@ pub struct Validator {
     pub vote_weight:    i32,
 }
 
+#[derive(juniper::GraphQLObject, Clone)]
+#[graphql(description = "Share of recent heights a validator voted in")]
+pub struct VoteParticipation {
+    pub address: Address,
+    pub rate:    f64,
+}
+
 impl From<protocol::types::BlockHeader> for BlockHeader {
     fn from(block_header: protocol::types::BlockHeader) -> Self {
         BlockHeader {
@@ -113,16 +148,18 @@ impl From<protocol::types::BlockHeader> for BlockHeader {
 impl From<protocol::types::Block> for Block {
     fn from(block: protocol::types::Block) -> Self {
         Block {
-            header:            BlockHeader::from(block.header.clone()),
-            ordered_tx_hashes: block
+            header:             BlockHeader::from(block.header.clone()),
+            ordered_tx_hashes:  block
                 .ordered_tx_hashes
                 .clone()
                 .into_iter()
                 .map(MerkleRoot::from)
                 .collect(),
-            hash:              Hash::from(PHash::digest(
+            hash:               Hash::from(PHash::digest(
                 block.header.encode_fixed().expect("rlp encode never fail"),
             )),
+            receipts:           None,
+            receipts_truncated: false,
         }
     }
 }
@@ -143,8 +180,100 @@ impl From<protocol::types::Validator> for Validator {
     fn from(validator: protocol::types::Validator) -> Self {
         Validator {
             pubkey:         Bytes::from(validator.pub_key),
-            propose_weight: validator.vote_weight as i32,
+            propose_weight: validator.propose_weight as i32,
             vote_weight:    validator.vote_weight as i32,
         }
     }
 }
+
+impl From<(protocol::types::Address, f64)> for VoteParticipation {
+    fn from((address, rate): (protocol::types::Address, f64)) -> Self {
+        VoteParticipation {
+            address: Address::from(address),
+            rate,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use protocol::traits::ServiceResponse;
+    use protocol::types::{
+        Address as PAddress, Block as PBlock, BlockHeader as PBlockHeader, Proof as PProof,
+        Receipt as PReceipt, ReceiptResponse as PReceiptResponse,
+    };
+    use protocol::Bytes as PBytes;
+
+    use super::*;
+
+    fn mock_block(tx_hashes: Vec<PHash>) -> PBlock {
+        let header = PBlockHeader {
+            chain_id:                       PHash::default(),
+            height:                         1,
+            exec_height:                    1,
+            prev_hash:                      PHash::default(),
+            timestamp:                      0,
+            order_root:                     PHash::default(),
+            order_signed_transactions_hash: PHash::default(),
+            confirm_root:                   vec![],
+            state_root:                     PHash::default(),
+            receipt_root:                   vec![],
+            cycles_used:                    vec![],
+            proposer:                       PAddress::default(),
+            proof:                          PProof {
+                height:     0,
+                round:      0,
+                block_hash: PHash::default(),
+                signature:  PBytes::new(),
+                bitmap:     PBytes::new(),
+            },
+            validator_version:              0,
+            validators:                     vec![],
+        };
+
+        PBlock {
+            header,
+            ordered_tx_hashes: tx_hashes,
+        }
+    }
+
+    fn mock_receipt(tx_hash: PHash) -> PReceipt {
+        PReceipt {
+            state_root: PHash::default(),
+            height: 1,
+            tx_hash,
+            cycles_used: 1,
+            events: vec![],
+            response: PReceiptResponse {
+                service_name: "asset".to_owned(),
+                method:       "transfer".to_owned(),
+                response:     ServiceResponse::from_succeed("".to_owned()),
+            },
+        }
+    }
+
+    #[test]
+    fn test_block_with_receipts() {
+        let tx_hashes = (0u8..3)
+            .map(|i| PHash::digest(PBytes::from(vec![i])))
+            .collect::<Vec<_>>();
+
+        let block = Block::from(mock_block(tx_hashes.clone()));
+        assert!(block.receipts.is_none());
+        assert!(!block.receipts_truncated);
+
+        let receipts = tx_hashes.into_iter().map(mock_receipt).collect::<Vec<_>>();
+        let block = Block::from(mock_block(vec![])).with_receipts(receipts.clone(), false);
+        let embedded = block.receipts.unwrap();
+        assert_eq!(embedded.len(), 3);
+        assert_eq!(
+            embedded[2].tx_hash.as_hex(),
+            Hash::from(receipts[2].tx_hash.clone()).as_hex()
+        );
+        assert!(!block.receipts_truncated);
+
+        let block = Block::from(mock_block(vec![])).with_receipts(receipts[..1].to_vec(), true);
+        assert_eq!(block.receipts.unwrap().len(), 1);
+        assert!(block.receipts_truncated);
+    }
+}
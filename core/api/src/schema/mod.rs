@@ -9,11 +9,11 @@ use std::num::ParseIntError;
 
 use protocol::{ProtocolError, ProtocolErrorKind, ProtocolResult};
 
-pub use block::{Block, BlockHeader};
-pub use receipt::{Event, Receipt, ReceiptResponse};
+pub use block::{Block, BlockHeader, BlocksSince, Validator, VoteParticipation};
+pub use receipt::{BlockReceipts, Event, Receipt, ReceiptResponse};
 pub use transaction::{
-    to_signed_transaction, to_transaction, InputRawTransaction, InputTransactionEncryption,
-    SignedTransaction,
+    to_signed_transaction, to_transaction, validate_input_transaction, InputRawTransaction,
+    InputTransactionEncryption, ProofNode, SignedTransaction, TransactionProof,
 };
 
 #[derive(juniper::GraphQLObject, Clone)]
@@ -33,6 +33,26 @@ impl From<protocol::traits::ServiceResponse<String>> for ServiceResponse {
     }
 }
 
+#[derive(juniper::GraphQLObject, Clone)]
+#[graphql(description = "Identity of the chain served by the node")]
+pub struct ChainInfo {
+    pub chain_id:     Hash,
+    #[graphql(description = "Version of the node")]
+    pub version:      String,
+    #[graphql(description = "Hash of the genesis block header")]
+    pub genesis_hash: Hash,
+}
+
+impl From<crate::config::ChainInfo> for ChainInfo {
+    fn from(info: crate::config::ChainInfo) -> Self {
+        Self {
+            chain_id:     Hash::from(info.chain_id),
+            version:      info.version,
+            genesis_hash: Hash::from(info.genesis_hash),
+        }
+    }
+}
+
 #[derive(juniper::GraphQLScalarValue, Clone)]
 #[graphql(description = "The output digest of Keccak hash function")]
 pub struct Hash(String);
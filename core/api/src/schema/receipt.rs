@@ -10,6 +10,17 @@ pub struct Receipt {
     pub response:    ReceiptResponse,
 }
 
+#[derive(juniper::GraphQLObject, Clone)]
+#[graphql(description = "A page of receipts of a block")]
+pub struct BlockReceipts {
+    #[graphql(description = "Receipts in the order of transactions of the block")]
+    pub receipts:  Vec<Receipt>,
+    #[graphql(description = "Number of receipts of the block")]
+    pub total:     Uint64,
+    #[graphql(description = "The page is cut short by the server side limit")]
+    pub truncated: bool,
+}
+
 #[derive(juniper::GraphQLObject, Clone)]
 pub struct Event {
     pub service: String,
@@ -1,6 +1,11 @@
+use std::str::FromStr;
+
+use derive_more::Display;
+use juniper::{graphql_value, FieldError};
+
 use protocol::ProtocolResult;
 
-use crate::schema::{Address, Bytes, Hash, SchemaError, Uint64};
+use crate::schema::{Address, Bytes, Hash, MerkleRoot, SchemaError, Uint64};
 
 #[derive(juniper::GraphQLObject, Clone)]
 pub struct SignedTransaction {
@@ -37,6 +42,47 @@ impl From<protocol::types::SignedTransaction> for SignedTransaction {
     }
 }
 
+#[derive(juniper::GraphQLObject, Clone)]
+#[graphql(
+    description = "Merkle proof of a transaction in the ordered transactions of a block, verify it against orderRoot"
+)]
+pub struct TransactionProof {
+    pub height:     Uint64,
+    pub order_root: MerkleRoot,
+    pub tx_hash:    Hash,
+    pub proof:      Vec<ProofNode>,
+}
+
+#[derive(juniper::GraphQLObject, Clone)]
+pub struct ProofNode {
+    pub is_right: bool,
+    pub hash:     Hash,
+}
+
+impl TransactionProof {
+    /// Build the proof from ordered transaction hashes of `block`, None if
+    /// `tx_hash` is not one of them. The order root is the one committed in
+    /// block header, which the proof verifies against.
+    pub fn build(block: protocol::types::Block, tx_hash: &protocol::types::Hash) -> Option<Self> {
+        let index = block.ordered_tx_hashes.iter().position(|h| h == tx_hash)?;
+        let merkle = common_merkle::Merkle::from_hashes(block.ordered_tx_hashes);
+        let proof = merkle.get_proof_by_input_index(index)?;
+
+        Some(TransactionProof {
+            height:     Uint64::from(block.header.height),
+            order_root: MerkleRoot::from(block.header.order_root),
+            tx_hash:    Hash::from(tx_hash.clone()),
+            proof:      proof
+                .into_iter()
+                .map(|node| ProofNode {
+                    is_right: node.is_right,
+                    hash:     Hash::from(node.hash),
+                })
+                .collect(),
+        })
+    }
+}
+
 // #####################
 // GraphQLInputObject
 // #####################
@@ -82,6 +128,83 @@ pub struct InputTransactionEncryption {
     pub signature: Bytes,
 }
 
+#[derive(Debug, Display)]
+#[display(fmt = "invalid {}: {}", field, reason)]
+pub struct InputError {
+    pub field:  &'static str,
+    pub reason: String,
+}
+
+impl InputError {
+    fn new(field: &'static str, reason: &str) -> Self {
+        InputError {
+            field,
+            reason: reason.to_owned(),
+        }
+    }
+
+    pub fn into_field_error(self) -> FieldError {
+        let field = self.field;
+        FieldError::new(self.to_string(), graphql_value!({ "field": field }))
+    }
+}
+
+/// Check user input up front, so that a malformed field is reported by its
+/// name instead of failing somewhere deep in the stack.
+pub fn validate_input_transaction(
+    raw: &InputRawTransaction,
+    encryption: &InputTransactionEncryption,
+) -> Result<(), InputError> {
+    validate_hash("chainId", &raw.chain_id)?;
+    validate_hash("nonce", &raw.nonce)?;
+    validate_uint64("timeout", &raw.timeout)?;
+    let cycles_limit = validate_uint64("cyclesLimit", &raw.cycles_limit)?;
+    let cycles_price = validate_uint64("cyclesPrice", &raw.cycles_price)?;
+    if cycles_limit == 0 {
+        return Err(InputError::new("cyclesLimit", "should be greater than 0"));
+    }
+    if cycles_limit.checked_mul(cycles_price).is_none() {
+        return Err(InputError::new(
+            "cyclesPrice",
+            "cyclesLimit * cyclesPrice overflows u64",
+        ));
+    }
+    validate_address("sender", &raw.sender)?;
+
+    validate_hash("txHash", &encryption.tx_hash)?;
+    validate_bytes("pubkey", &encryption.pubkey)?;
+    validate_bytes("signature", &encryption.signature)?;
+    Ok(())
+}
+
+fn validate_hash(field: &'static str, hash: &Hash) -> Result<(), InputError> {
+    protocol::types::Hash::from_hex(&hash.as_hex())
+        .map(|_| ())
+        .map_err(|_| InputError::new(field, "expect 0x-prefixed hex of 32 bytes"))
+}
+
+fn validate_uint64(field: &'static str, n: &Uint64) -> Result<u64, InputError> {
+    n.clone()
+        .try_into_u64()
+        .map_err(|_| InputError::new(field, "expect 0x-prefixed hex of u64"))
+}
+
+fn validate_bytes(field: &'static str, bytes: &Bytes) -> Result<(), InputError> {
+    match bytes.to_vec() {
+        Ok(v) if !v.is_empty() => Ok(()),
+        Ok(_) => Err(InputError::new(field, "should not be empty")),
+        Err(_) => Err(InputError::new(field, "expect 0x-prefixed hex")),
+    }
+}
+
+fn validate_address(field: &'static str, address: &Address) -> Result<(), InputError> {
+    protocol::types::Address::from_str(address.to_str())
+        .ok()
+        .and_then(|addr| protocol::types::Address::from_bytes(addr.as_bytes()).ok())
+        .map(|_| ())
+        .ok_or_else(|| InputError::new(field, "expect bech32 address of 20 bytes"))
+}
+
 pub fn to_signed_transaction(
     raw: InputRawTransaction,
     encryption: InputTransactionEncryption,
@@ -113,3 +236,135 @@ pub fn to_transaction(raw: InputRawTransaction) -> ProtocolResult<protocol::type
         sender:       raw.sender.to_str().parse()?,
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mock_input() -> (InputRawTransaction, InputTransactionEncryption) {
+        let hash = Hash("0x".to_owned() + &"ab".repeat(32));
+        let raw = InputRawTransaction {
+            chain_id:     hash.clone(),
+            cycles_limit: Uint64("0xffff".to_owned()),
+            cycles_price: Uint64("0x1".to_owned()),
+            nonce:        hash.clone(),
+            timeout:      Uint64("0x14".to_owned()),
+            service_name: "asset".to_owned(),
+            method:       "transfer".to_owned(),
+            payload:      "".to_owned(),
+            sender:       Address("muta14e0lmgck835vm2dfm0w3ckv6svmez8fdgdl705".to_owned()),
+        };
+        let encryption = InputTransactionEncryption {
+            tx_hash:   hash,
+            pubkey:    Bytes("0x02".to_owned()),
+            signature: Bytes("0x03".to_owned()),
+        };
+        (raw, encryption)
+    }
+
+    fn invalid_field(
+        raw: &InputRawTransaction,
+        encryption: &InputTransactionEncryption,
+    ) -> &'static str {
+        validate_input_transaction(raw, encryption)
+            .unwrap_err()
+            .field
+    }
+
+    #[test]
+    fn test_validate_input_transaction() {
+        let (raw, encryption) = mock_input();
+        assert!(validate_input_transaction(&raw, &encryption).is_ok());
+
+        let mut bad_raw = raw.clone();
+        bad_raw.chain_id = Hash("0xabcd".to_owned());
+        assert_eq!(invalid_field(&bad_raw, &encryption), "chainId");
+
+        let mut bad_raw = raw.clone();
+        bad_raw.nonce = Hash("ab".repeat(32));
+        assert_eq!(invalid_field(&bad_raw, &encryption), "nonce");
+
+        let mut bad_raw = raw.clone();
+        bad_raw.timeout = Uint64("0x1ffffffffffffffff".to_owned());
+        assert_eq!(invalid_field(&bad_raw, &encryption), "timeout");
+
+        let mut bad_raw = raw.clone();
+        bad_raw.cycles_limit = Uint64("0x0".to_owned());
+        assert_eq!(invalid_field(&bad_raw, &encryption), "cyclesLimit");
+
+        let mut bad_raw = raw.clone();
+        bad_raw.cycles_price = Uint64("0xffffffffffffffff".to_owned());
+        assert_eq!(invalid_field(&bad_raw, &encryption), "cyclesPrice");
+
+        let mut bad_raw = raw.clone();
+        bad_raw.sender = Address("muta1qqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqqq".to_owned());
+        assert_eq!(invalid_field(&bad_raw, &encryption), "sender");
+
+        let mut bad_encryption = encryption.clone();
+        bad_encryption.pubkey = Bytes("0xzz".to_owned());
+        assert_eq!(invalid_field(&raw, &bad_encryption), "pubkey");
+
+        let mut bad_encryption = encryption;
+        bad_encryption.signature = Bytes("0x".to_owned());
+        assert_eq!(invalid_field(&raw, &bad_encryption), "signature");
+    }
+
+    fn mock_block(ordered_tx_hashes: Vec<protocol::types::Hash>) -> protocol::types::Block {
+        let order_root = common_merkle::Merkle::from_hashes(ordered_tx_hashes.clone())
+            .get_root_hash()
+            .unwrap();
+        let header = protocol::types::BlockHeader {
+            chain_id: protocol::types::Hash::default(),
+            height: 1,
+            exec_height: 1,
+            prev_hash: protocol::types::Hash::default(),
+            timestamp: 0,
+            order_root,
+            order_signed_transactions_hash: protocol::types::Hash::default(),
+            confirm_root: vec![],
+            state_root: protocol::types::Hash::default(),
+            receipt_root: vec![],
+            cycles_used: vec![],
+            proposer: protocol::types::Address::default(),
+            proof: protocol::types::Proof {
+                height:     0,
+                round:      0,
+                block_hash: protocol::types::Hash::default(),
+                signature:  protocol::Bytes::new(),
+                bitmap:     protocol::Bytes::new(),
+            },
+            validator_version: 0,
+            validators: vec![],
+        };
+
+        protocol::types::Block {
+            header,
+            ordered_tx_hashes,
+        }
+    }
+
+    #[test]
+    fn test_transaction_proof() {
+        let hashes = (0u8..5)
+            .map(|i| protocol::types::Hash::digest(protocol::Bytes::from(vec![i])))
+            .collect::<Vec<_>>();
+        let block = mock_block(hashes.clone());
+        let missing = protocol::types::Hash::digest(protocol::Bytes::from(vec![9u8]));
+        assert!(TransactionProof::build(block.clone(), &missing).is_none());
+
+        let root = block.header.order_root.clone();
+        let tx_hash = hashes[3].clone();
+        let proof = TransactionProof::build(block, &tx_hash).unwrap();
+        assert_eq!(proof.order_root.as_hex(), Hash::from(root.clone()).as_hex());
+
+        let nodes = proof
+            .proof
+            .iter()
+            .map(|node| common_merkle::ProofNode {
+                is_right: node.is_right,
+                hash:     protocol::types::Hash::from_hex(&node.hash.as_hex()).unwrap(),
+            })
+            .collect::<Vec<_>>();
+        assert!(common_merkle::verify_proof(&root, &tx_hash, &nodes));
+    }
+}
@@ -2,6 +2,7 @@ use std::error::Error;
 
 use derive_more::{Display, From};
 
+use protocol::types::MerkleRoot;
 use protocol::{ProtocolError, ProtocolErrorKind};
 
 #[derive(Debug, Display, From)]
@@ -32,6 +33,29 @@ pub enum CliError {
 
     #[display(fmt = "genesis.toml is missing")]
     MissingGenesis,
+
+    #[display(fmt = "invalid replay range from {} to {}", _0, _1)]
+    ReplayRange(u64, u64),
+
+    #[from(ignore)]
+    #[display(fmt = "executed state root for height {} not found", _0)]
+    StateRootNotFound(u64),
+
+    #[from(ignore)]
+    #[display(fmt = "transactions of block {} not found", _0)]
+    TransactionsNotFound(u64),
+
+    #[display(
+        fmt = "state root mismatch at height {}, expect {:?}, got {:?}",
+        height,
+        expected,
+        actual
+    )]
+    StateRootMismatch {
+        height:   u64,
+        expected: MerkleRoot,
+        actual:   MerkleRoot,
+    },
 }
 
 impl Error for CliError {}
@@ -11,19 +11,29 @@ use std::sync::Arc;
 
 use clap::ArgMatches;
 use common_config_parser::types::Config;
+use core_consensus::util::digest_signed_transactions;
 use core_consensus::wal::ConsensusWal;
 use core_consensus::SignedTxsWAL;
 use core_storage::adapter::rocks::RocksAdapter;
 use core_storage::ImplStorage;
-use protocol::traits::{Context, MaintenanceStorage, ServiceMapping};
-use protocol::types::{Block, Genesis, SignedTransaction};
-use protocol::ProtocolResult;
+use framework::binding::state::RocksTrieDB;
+use framework::executor::ServiceExecutorFactory;
+use protocol::traits::{
+    Context, ExecutorConfig, ExecutorFactory, ExecutorParams, MaintenanceStorage, ServiceMapping,
+    Storage, StorageCategory,
+};
+use protocol::types::{Block, Genesis, MerkleRoot, SignedTransaction};
+use protocol::{Bytes, ProtocolResult};
+use serde_derive::{Deserialize, Serialize};
 
 use crate::error::CliError;
 
 const PLEASE_CONFIRM: &str =
     "Please use -y to confirm modification and DO BACK UP YOUR DB DATA AND WAL";
 
+const RAW_CATEGORIES: &str =
+    "one of block, block_header, receipt, signed_transaction, wal and hash_height";
+
 pub struct CliConfig {
     pub app_name:      &'static str,
     pub version:       &'static str,
@@ -40,6 +50,7 @@ where
     pub config:          Config,
     pub genesis:         Option<Genesis>,
     pub service_mapping: Arc<Mapping>,
+    pub version:         &'static str,
 }
 
 impl<'a, Mapping> Cli<'a, Mapping>
@@ -53,7 +64,8 @@ where
     ) {
         let cli = Self::new(service_mapping, cli_config, target_commands);
         if let Err(e) = cli.start() {
-            log::error!("{:?}", e)
+            log::error!("{:?}", e);
+            std::process::exit(1);
         }
     }
 
@@ -62,6 +74,7 @@ where
         cli_config: CliConfig,
         target_commands: Option<Vec<&str>>,
     ) -> Self {
+        let version = cli_config.version;
         let matches = Self::generate_matches(cli_config, target_commands);
 
         let config_path = matches.value_of("config").expect("missing config path");
@@ -89,6 +102,7 @@ where
             config,
             genesis,
             service_mapping: Arc::new(service_mapping),
+            version,
         }
     }
 
@@ -110,7 +124,8 @@ where
             ("run", Some(_sub_cmd)) => {
                 log::info!("run subcommand run");
                 if let Some(genesis) = self.genesis {
-                    let muta = run::Muta::new(self.config, genesis, self.service_mapping);
+                    let muta = run::Muta::new(self.config, genesis, self.service_mapping)
+                        .with_version(self.version);
                     muta.run()
                 } else {
                     log::error!("genesis.toml is missing");
@@ -139,10 +154,23 @@ where
                 let maintenance_cli = self.generate_maintenance_cli();
                 maintenance_cli.start()
             }
+
+            ("raw", Some(_sub_cmd)) => {
+                log::info!("run subcommand raw");
+                let maintenance_cli = self.generate_maintenance_cli();
+                maintenance_cli.start()
+            }
+
+            ("replay", Some(_sub_cmd)) => {
+                log::info!("run subcommand replay");
+                let maintenance_cli = self.generate_maintenance_cli();
+                maintenance_cli.start()
+            }
             _ => {
                 log::info!("run without any subcommand, default to run");
                 if let Some(genesis) = self.genesis {
-                    let muta = run::Muta::new(self.config, genesis, self.service_mapping);
+                    let muta = run::Muta::new(self.config, genesis, self.service_mapping)
+                        .with_version(self.version);
                     muta.run()
                 } else {
                     log::error!("genesis.toml is missing");
@@ -183,6 +211,7 @@ where
                         clap::SubCommand::with_name("set")
                             .arg(clap::Arg::with_name("BLOCK_HEIGHT").required(true))
                             .arg(clap::Arg::with_name("confirm").short("y").help("confirm to take effect"))
+                            .arg(clap::Arg::with_name("compact").short("c").help("compact storage to reclaim space of removed blocks"))
                             .about("set the latest block")
                     )
                     .subcommand(
@@ -228,6 +257,16 @@ where
                                 clap::SubCommand::with_name("get")
                                     .about("get mempool wal")
                                     .arg(clap::Arg::with_name("BLOCK_HEIGHT").required(true)),
+                            )
+                            .subcommand(
+                                clap::SubCommand::with_name("export")
+                                    .about("export all mempool wal to [FILE] in JSON format")
+                                    .arg(clap::Arg::with_name("FILE").required(true).help("path")),
+                            )
+                            .subcommand(
+                                clap::SubCommand::with_name("import")
+                                    .about("import mempool wal from [FILE], skip committed and expired transactions")
+                                    .arg(clap::Arg::with_name("FILE").required(true).help("path")),
                             ),
                     )
                     .subcommand(
@@ -253,6 +292,30 @@ where
                             .about("restore db from [FROM] place")
                             .arg(clap::Arg::with_name("FROM").required(true).help("path")),
                     ),
+            )
+            .subcommand(
+                clap::SubCommand::with_name("raw")
+                    .about("APIs for raw storage bytes, bypassing schemas, for advanced recovery only")
+                    .subcommand(
+                        clap::SubCommand::with_name("get")
+                            .arg(clap::Arg::with_name("CATEGORY").required(true).help(RAW_CATEGORIES))
+                            .arg(clap::Arg::with_name("KEY").required(true).help("hex encoded key"))
+                            .about("get hex encoded raw value of [KEY] in [CATEGORY]"),
+                    )
+                    .subcommand(
+                        clap::SubCommand::with_name("put")
+                            .arg(clap::Arg::with_name("CATEGORY").required(true).help(RAW_CATEGORIES))
+                            .arg(clap::Arg::with_name("KEY").required(true).help("hex encoded key"))
+                            .arg(clap::Arg::with_name("VALUE").required(true).help("hex encoded value"))
+                            .arg(clap::Arg::with_name("confirm").short("y").help("confirm to take effect"))
+                            .about("DANGEROUS, put raw [VALUE] of [KEY] in [CATEGORY], a malformed value breaks the node"),
+                    ),
+            )
+            .subcommand(
+                clap::SubCommand::with_name("replay")
+                    .about("re-execute blocks from [FROM] to [TO] on a copy of state db and compare state roots")
+                    .arg(clap::Arg::with_name("FROM").required(true).help("start height"))
+                    .arg(clap::Arg::with_name("TO").required(true).help("end height")),
             );
         match cmds {
             Some(cmds) => app.get_matches_from(cmds),
@@ -304,7 +367,7 @@ where
 pub struct MaintenanceCli<'a, Mapping, S>
 where
     Mapping: 'static + ServiceMapping,
-    S: 'static + MaintenanceStorage,
+    S: 'static + MaintenanceStorage + Storage,
 {
     pub matches:         ArgMatches<'a>,
     pub config:          Config,
@@ -317,7 +380,7 @@ where
 impl<'a, Mapping, S> MaintenanceCli<'a, Mapping, S>
 where
     Mapping: 'static + ServiceMapping,
-    S: 'static + MaintenanceStorage,
+    S: 'static + MaintenanceStorage + Storage,
 {
     pub fn new(
         matches: ArgMatches<'a>,
@@ -343,6 +406,8 @@ where
             ("block", Some(sub_cmd)) => self.block(sub_cmd),
             ("wal", Some(sub_cmd)) => self.wal(sub_cmd),
             ("backup", Some(sub_cmd)) => self.backup(sub_cmd),
+            ("raw", Some(sub_cmd)) => self.raw(sub_cmd),
+            ("replay", Some(sub_cmd)) => self.replay(sub_cmd),
             _ => Err(CliError::UnsupportedCommand.into()),
         }
     }
@@ -363,7 +428,10 @@ where
                 }
 
                 match u64::from_str_radix(height, 10) {
-                    Ok(height) => rt.block_on(async move { self.latest_block_set(height).await }),
+                    Ok(height) => {
+                        let compact = cmd.is_present("compact");
+                        rt.block_on(async move { self.latest_block_set(height, compact).await })
+                    }
                     Err(_e) => Err(CliError::Parse.into()),
                 }
             }
@@ -381,7 +449,8 @@ where
         }
     }
 
-    pub async fn latest_block_set(&self, height: u64) -> ProtocolResult<()> {
+    /// Blocks above `height` are removed, and compacted away if `compact`.
+    pub async fn latest_block_set(&self, height: u64, compact: bool) -> ProtocolResult<()> {
         let last = self.storage.get_latest_block(Context::new()).await?;
 
         let block = self.block_get(height).await?;
@@ -400,13 +469,30 @@ where
 
         // now remove 'future' blocks
         for idx in RangeInclusive::new(height + 1, last.header.height) {
-            self.storage.remove_block(Context::new(), idx).await?
+            self.storage.remove_block_full(Context::new(), idx).await?
         }
         log::info!(
             "latest_block set, remove blocks from {} to {}",
             height + 1,
             last.header.height
         );
+
+        if compact {
+            for category in [
+                StorageCategory::Block,
+                StorageCategory::BlockHeader,
+                StorageCategory::SignedTransaction,
+                StorageCategory::Receipt,
+                StorageCategory::HashHeight,
+            ]
+            .iter()
+            {
+                self.storage
+                    .compact_range(Context::new(), *category, None, None)
+                    .await?;
+            }
+            log::info!("latest_block set, storage compacted");
+        }
         Ok(())
     }
 
@@ -477,6 +563,44 @@ where
         Ok(())
     }
 
+    pub fn raw(&self, sub_cmd: &ArgMatches) -> ProtocolResult<()> {
+        let mut rt = tokio::runtime::Runtime::new().expect("new tokio runtime");
+
+        match sub_cmd.subcommand() {
+            ("get", Some(cmd)) => {
+                let category =
+                    parse_category(cmd.value_of("CATEGORY").expect("missing [CATEGORY]"))?;
+                let key = parse_hex(cmd.value_of("KEY").expect("missing [KEY]"))?;
+
+                let res = rt.block_on(self.storage.get_raw(Context::new(), category, key))?;
+                match res {
+                    Some(value) => log::info!("raw get: 0x{}", hex::encode(value)),
+                    None => log::info!("raw value not found"),
+                }
+                Ok(())
+            }
+
+            ("put", Some(cmd)) => {
+                let confirm = cmd.is_present("confirm");
+                if !confirm {
+                    log::info!("{}", PLEASE_CONFIRM);
+                    return Ok(());
+                }
+
+                let category =
+                    parse_category(cmd.value_of("CATEGORY").expect("missing [CATEGORY]"))?;
+                let key = parse_hex(cmd.value_of("KEY").expect("missing [KEY]"))?;
+                let value = parse_hex(cmd.value_of("VALUE").expect("missing [VALUE]"))?;
+
+                rt.block_on(self.storage.put_raw(Context::new(), category, key, value))?;
+                log::info!("raw put successfully");
+                Ok(())
+            }
+
+            _ => Err(CliError::Grammar.into()),
+        }
+    }
+
     pub fn wal(&self, sub_cmd: &ArgMatches) -> ProtocolResult<()> {
         match sub_cmd.subcommand() {
             ("mempool", Some(cmd)) => match cmd.subcommand() {
@@ -501,6 +625,17 @@ where
                     self.wal_txs_get(height)?;
                     Ok(())
                 }
+                ("export", Some(cmd)) => {
+                    let file = cmd.value_of("FILE").expect("missing [FILE]");
+                    self.wal_txs_export(file)?;
+                    Ok(())
+                }
+                ("import", Some(cmd)) => {
+                    let file = cmd.value_of("FILE").expect("missing [FILE]");
+                    let mut rt = tokio::runtime::Runtime::new().expect("new tokio runtime");
+                    rt.block_on(self.wal_txs_import(file))?;
+                    Ok(())
+                }
                 _ => Err(CliError::Grammar.into()),
             },
 
@@ -549,7 +684,81 @@ where
     pub fn wal_txs_get(&self, height: u64) -> ProtocolResult<Vec<SignedTransaction>> {
         let res = self.txs_wal.load_by_height(height);
         log::info!("wal_txs_get: {:?}", res);
-        Ok(res)
+        res
+    }
+
+    /// Export transactions of all heights, return the number of exported
+    /// transactions.
+    pub fn wal_txs_export<P: AsRef<Path>>(&self, to: P) -> ProtocolResult<usize> {
+        let mut entries = Vec::new();
+        for height in self.txs_wal.available_height()? {
+            entries.push(WalTxsEntry {
+                height,
+                txs: self.txs_wal.load_by_height(height)?,
+            });
+        }
+        entries.sort_by_key(|entry| entry.height);
+
+        let count = entries.iter().map(|entry| entry.txs.len()).sum();
+        let json = serde_json::to_string(&entries).map_err(CliError::JSONFormat)?;
+        fs::write(to.as_ref(), json).map_err(CliError::IO)?;
+
+        log::info!(
+            "wal_txs_export: {} transactions to {:?}",
+            count,
+            to.as_ref().to_str()
+        );
+        Ok(count)
+    }
+
+    /// Import transactions exported by `wal_txs_export`. Transactions of
+    /// committed heights, already committed or timed out on this node are
+    /// skipped. Return the number of imported and skipped transactions.
+    pub async fn wal_txs_import<P: AsRef<Path>>(&self, from: P) -> ProtocolResult<(usize, usize)> {
+        let json = fs::read_to_string(from.as_ref()).map_err(CliError::IO)?;
+        let entries =
+            serde_json::from_str::<Vec<WalTxsEntry>>(&json).map_err(CliError::JSONFormat)?;
+        let latest_height = self
+            .storage
+            .get_latest_block_header(Context::new())
+            .await?
+            .height;
+
+        let (mut imported, mut skipped) = (0, 0);
+        for entry in entries.into_iter() {
+            if entry.height <= latest_height {
+                skipped += entry.txs.len();
+                continue;
+            }
+
+            let mut txs = Vec::with_capacity(entry.txs.len());
+            for stx in entry.txs.into_iter() {
+                let committed = self
+                    .storage
+                    .get_transaction_by_hash(Context::new(), &stx.tx_hash)
+                    .await?
+                    .is_some();
+
+                if committed || stx.raw.timeout < latest_height {
+                    skipped += 1;
+                } else {
+                    txs.push(stx);
+                }
+            }
+
+            if !txs.is_empty() {
+                imported += txs.len();
+                let txs_hash = digest_signed_transactions(&txs)?;
+                self.txs_wal.save(entry.height, txs_hash, txs)?;
+            }
+        }
+
+        log::info!(
+            "wal_txs_import: {} transactions imported, {} skipped",
+            imported,
+            skipped
+        );
+        Ok((imported, skipped))
     }
 
     pub fn wal_consensus_clear(&self) -> ProtocolResult<()> {
@@ -580,6 +789,11 @@ where
     pub fn backup_save<P: AsRef<Path>>(&self, to: P) -> ProtocolResult<()> {
         let to = to.as_ref();
         let data_path = self.config.data_path.as_path();
+
+        // Memtables not flushed yet would be missing from the copy
+        let mut rt = tokio::runtime::Runtime::new().expect("new tokio runtime");
+        rt.block_on(self.storage.sync(Context::new()))?;
+
         fs_extra::dir::remove(to).map_err(CliError::IO2)?;
         fs_extra::dir::copy(data_path, to, &fs_extra::dir::CopyOptions {
             overwrite:    true,
@@ -611,4 +825,181 @@ where
         log::info!("backup_restore successfully to: {:?}", from.to_str());
         Ok(())
     }
+
+    pub fn replay(&self, sub_cmd: &ArgMatches) -> ProtocolResult<()> {
+        let from = sub_cmd.value_of("FROM").expect("missing [FROM]");
+        let to = sub_cmd.value_of("TO").expect("missing [TO]");
+
+        let from = u64::from_str_radix(from, 10).map_err(|_| CliError::Parse)?;
+        let to = u64::from_str_radix(to, 10).map_err(|_| CliError::Parse)?;
+
+        let mut rt = tokio::runtime::Runtime::new().expect("new tokio runtime");
+        rt.block_on(async move { self.replay_range(from, to).await })
+    }
+
+    /// Re-execute blocks in `[from, to]` and compare the computed state roots
+    /// with stored ones. Execution runs on a copy of state db, so the node data
+    /// is left untouched.
+    pub async fn replay_range(&self, from: u64, to: u64) -> ProtocolResult<()> {
+        let latest = self
+            .storage
+            .get_latest_block(Context::new())
+            .await?
+            .header
+            .height;
+        if from == 0 || from > to || to > latest {
+            return Err(CliError::ReplayRange(from, to).into());
+        }
+
+        let state_root = self
+            .executed_state_root(from - 1, latest)
+            .await?
+            .ok_or(CliError::StateRootNotFound(from - 1))?;
+
+        let temp_path = std::env::temp_dir().join(format!("muta-replay-{}", std::process::id()));
+        if temp_path.exists() {
+            fs_extra::dir::remove(&temp_path).map_err(CliError::IO2)?;
+        }
+        fs_extra::dir::copy(
+            self.config.data_path_for_state(),
+            &temp_path,
+            &fs_extra::dir::CopyOptions {
+                overwrite:    true,
+                skip_exist:   false,
+                buffer_size:  64000, // 64kb
+                copy_inside:  true,
+                content_only: false,
+                depth:        0,
+            },
+        )
+        .map_err(CliError::IO2)?;
+
+        let res = self
+            .replay_on(&temp_path, state_root, from, to, latest)
+            .await;
+        fs_extra::dir::remove(&temp_path).map_err(CliError::IO2)?;
+        res
+    }
+
+    async fn replay_on(
+        &self,
+        state_path: &Path,
+        mut state_root: MerkleRoot,
+        from: u64,
+        to: u64,
+        latest: u64,
+    ) -> ProtocolResult<()> {
+        let trie_db = Arc::new(RocksTrieDB::new(
+            state_path,
+            self.config.executor.light,
+            self.config.rocksdb.max_open_files,
+            self.config.executor.triedb_cache_size,
+        )?);
+
+        // Same executor settings as consensus, they affect execution results.
+        // Sender blacklist is only checked by mempool.
+        let executor_config = ExecutorConfig {
+            min_cycles_price:          self.config.executor.min_cycles_price,
+            min_cycles_price_height:   self.config.executor.min_cycles_price_height,
+            sender_blacklist:          Default::default(),
+            storage_cycles_per_byte:   self.config.executor.storage_cycles_per_byte,
+            storage_cycles_height:     self.config.executor.storage_cycles_height,
+            parallel_exec:             self.config.executor.parallel_exec,
+            out_of_cycles_code_height: self.config.executor.out_of_cycles_code_height,
+        };
+
+        for height in RangeInclusive::new(from, to) {
+            let block = self
+                .block_get(height)
+                .await?
+                .ok_or(CliError::BlockNotFound(height))?;
+            let txs = self
+                .storage
+                .get_transactions(Context::new(), height, &block.ordered_tx_hashes)
+                .await?
+                .into_iter()
+                .collect::<Option<Vec<_>>>()
+                .ok_or(CliError::TransactionsNotFound(height))?;
+
+            let mut executor = ServiceExecutorFactory::from_root(
+                state_root.clone(),
+                Arc::clone(&trie_db),
+                Arc::clone(&self.storage),
+                Arc::clone(&self.service_mapping),
+                &executor_config,
+            )?;
+            let params = ExecutorParams {
+                state_root: state_root.clone(),
+                height,
+                timestamp: block.header.timestamp,
+                cycles_limit: u64::max_value(),
+                proposer: block.header.proposer.clone(),
+            };
+            state_root = executor.exec(Context::new(), &params, &txs)?.state_root;
+
+            match self.executed_state_root(height, latest).await? {
+                Some(expected) if expected != state_root => {
+                    return Err(CliError::StateRootMismatch {
+                        height,
+                        expected,
+                        actual: state_root,
+                    }
+                    .into());
+                }
+                Some(_) => log::info!("replay height {}, state root matched", height),
+                None => log::info!("replay height {}, no stored state root to compare", height),
+            }
+        }
+
+        log::info!("replay from {} to {} successfully", from, to);
+        Ok(())
+    }
+
+    /// State root after executing `height`, it is recorded by the first block
+    /// whose `exec_height` reaches `height`. None if execution of `height` was
+    /// skipped over by the following proposals.
+    async fn executed_state_root(
+        &self,
+        height: u64,
+        latest: u64,
+    ) -> ProtocolResult<Option<MerkleRoot>> {
+        for idx in RangeInclusive::new(height + 1, latest) {
+            let block = self
+                .block_get(idx)
+                .await?
+                .ok_or(CliError::BlockNotFound(idx))?;
+
+            if block.header.exec_height == height {
+                return Ok(Some(block.header.state_root));
+            } else if block.header.exec_height > height {
+                return Ok(None);
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// Transactions saved in mempool wal of one height, the export file is a JSON
+/// array of them.
+#[derive(Serialize, Deserialize, Debug)]
+struct WalTxsEntry {
+    height: u64,
+    txs:    Vec<SignedTransaction>,
+}
+
+fn parse_category(category: &str) -> Result<StorageCategory, CliError> {
+    match category {
+        "block" => Ok(StorageCategory::Block),
+        "block_header" => Ok(StorageCategory::BlockHeader),
+        "receipt" => Ok(StorageCategory::Receipt),
+        "signed_transaction" => Ok(StorageCategory::SignedTransaction),
+        "wal" => Ok(StorageCategory::Wal),
+        "hash_height" => Ok(StorageCategory::HashHeight),
+        _ => Err(CliError::Parse),
+    }
+}
+
+fn parse_hex(s: &str) -> Result<Bytes, CliError> {
+    let s = s.trim_start_matches("0x");
+    hex::decode(s).map(Bytes::from).map_err(|_| CliError::Parse)
 }
@@ -3,8 +3,11 @@ mod service_mapping;
 use std::path::PathBuf;
 use std::str::FromStr;
 
-use protocol::traits::{CommonStorage, Context};
-use protocol::types::{Block, BlockHeader, Bytes, Hash, Proof};
+use protocol::traits::{CommonStorage, Context, Storage};
+use protocol::types::{
+    Address, Block, BlockHeader, Bytes, Hash, Proof, RawTransaction, SignedTransaction,
+    TransactionRequest,
+};
 use protocol::ProtocolResult;
 
 use crate::{Cli, CliConfig};
@@ -15,6 +18,7 @@ const SAVE_DIR: &str = "./free-space/save";
 const DATA_DIR: &str = "./free-space/data";
 const CONFIG_PATH: &str = "./src/tests/config.toml";
 const GENESIS_PATH: &str = "./src/tests/genesis.toml";
+const WAL_EXPORT_PATH: &str = "./free-space/mempool_wal.json";
 
 #[test]
 fn test_lineally() {
@@ -40,6 +44,10 @@ fn test_lineally() {
     prepare();
     block_set();
     clean();
+
+    prepare();
+    wal_export_import();
+    clean();
 }
 
 fn save_restore() {
@@ -255,7 +263,7 @@ fn latest_set() {
     if let ("latest_block", Some(sub_cmd)) = maintenance_cli.matches.subcommand() {
         if let ("set", Some(_cmd)) = sub_cmd.subcommand() {
             let mut rt = tokio::runtime::Runtime::new().expect("new tokio runtime");
-            let res = rt.block_on(async move { maintenance_cli.latest_block_set(10).await });
+            let res = rt.block_on(async move { maintenance_cli.latest_block_set(10, true).await });
             assert!(res.is_ok());
         } else {
             panic!()
@@ -269,6 +277,97 @@ fn latest_set() {
     println!("tested latest_set");
 }
 
+fn wal_export_import() {
+    println!("test wal_export_import");
+    let cmd = vec![
+        "muta-chain",
+        "--config",
+        CONFIG_PATH,
+        "--genesis",
+        GENESIS_PATH,
+        "wal",
+        "mempool",
+        "list",
+    ];
+
+    let maintenance_cli = Cli::new(
+        DefaultServiceMapping {},
+        CliConfig {
+            app_name:      "Rodents",
+            version:       "Big Cheek",
+            author:        "Hamsters",
+            config_path:   "./cofnig.toml",
+            genesis_patch: "./genesis.toml",
+        },
+        Some(cmd),
+    )
+    .generate_maintenance_cli();
+
+    // latest height is 23
+    let valid = mock_stx(1, 30);
+    let expired = mock_stx(2, 10);
+    let committed = mock_stx(3, 30);
+    let stale = mock_stx(4, 30);
+
+    let mut rt = tokio::runtime::Runtime::new().expect("new tokio runtime");
+    rt.block_on(
+        maintenance_cli
+            .storage
+            .insert_transactions(Context::new(), 23, vec![committed.clone()]),
+    )
+    .expect("wal_export_import, insert transactions fails");
+
+    let txs_wal = &maintenance_cli.txs_wal;
+    txs_wal
+        .save(24, Hash::digest(Bytes::from_static(b"24")), vec![
+            valid.clone(),
+            expired,
+            committed,
+        ])
+        .expect("wal_export_import, save wal fails");
+    txs_wal
+        .save(20, Hash::digest(Bytes::from_static(b"20")), vec![stale])
+        .expect("wal_export_import, save wal fails");
+
+    let exported = maintenance_cli
+        .wal_txs_export(WAL_EXPORT_PATH)
+        .expect("wal_export_import, export fails");
+    assert_eq!(exported, 4);
+
+    txs_wal
+        .remove_all()
+        .expect("wal_export_import, clear wal fails");
+    let (imported, skipped) = rt
+        .block_on(maintenance_cli.wal_txs_import(WAL_EXPORT_PATH))
+        .expect("wal_export_import, import fails");
+    assert_eq!((imported, skipped), (1, 3));
+
+    assert_eq!(txs_wal.available_height().unwrap(), vec![24]);
+    assert_eq!(txs_wal.load_by_height(24).unwrap(), vec![valid]);
+    println!("tested wal_export_import");
+}
+
+fn mock_stx(seed: u8, timeout: u64) -> SignedTransaction {
+    SignedTransaction {
+        raw:       RawTransaction {
+            chain_id: Default::default(),
+            nonce: Hash::digest(Bytes::from(vec![seed])),
+            timeout,
+            cycles_price: 1,
+            cycles_limit: 100,
+            request: TransactionRequest {
+                service_name: "test".to_owned(),
+                method:       "test".to_owned(),
+                payload:      "test".to_owned(),
+            },
+            sender: Address::from_bytes(Bytes::from(vec![seed; 20])).unwrap(),
+        },
+        tx_hash:   Hash::digest(Bytes::from(vec![seed; 2])),
+        pubkey:    Bytes::new(),
+        signature: Bytes::new(),
+    }
+}
+
 // test functional methods list below
 
 fn prepare() {
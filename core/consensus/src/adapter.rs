@@ -5,8 +5,8 @@ use std::sync::Arc;
 use std::time::Instant;
 
 use async_trait::async_trait;
-use overlord::types::{Node, OverlordMsg, Vote, VoteType};
-use overlord::{extract_voters, Crypto, OverlordHandler};
+use overlord::types::{Node, OverlordMsg};
+use overlord::OverlordHandler;
 use parking_lot::RwLock;
 use tokio::sync::mpsc::error::TrySendError;
 use tokio::sync::mpsc::{channel, Receiver, Sender};
@@ -17,9 +17,10 @@ use common_merkle::Merkle;
 use core_network::{PeerId, PeerIdExt};
 
 use protocol::traits::{
-    CommonConsensusAdapter, ConsensusAdapter, Context, ExecutorFactory, ExecutorParams,
-    ExecutorResp, Gossip, MemPool, MessageTarget, MixedTxHashes, Network, PeerTrust, Priority, Rpc,
-    ServiceMapping, Storage, SynchronizationAdapter, TrustFeedback,
+    CommonConsensusAdapter, ConsensusAdapter, Context, ExecutorConfig, ExecutorFactory,
+    ExecutorParams, ExecutorResp, Gossip, MemPool, MessageTarget, MixedTxHashes, Network,
+    PeerTrust, Priority, ProposalSelector, Rpc, ServiceMapping, Storage, SynchronizationAdapter,
+    TrustFeedback,
 };
 use protocol::types::{
     Address, Block, BlockHeader, Bytes, Hash, Hex, MerkleRoot, Metadata, Proof, Receipt,
@@ -35,10 +36,20 @@ use crate::message::{
     BROADCAST_HEIGHT, RPC_SYNC_PULL_BLOCK, RPC_SYNC_PULL_PROOF, RPC_SYNC_PULL_TXS,
 };
 use crate::status::{ExecutedInfo, StatusAgent};
-use crate::util::{convert_hex_to_bls_pubkeys, ExecuteInfo, OverlordCrypto};
+use crate::util::{
+    verify_block_proof, verify_proof_signature, verify_proof_weight, ExecuteInfo, OverlordCrypto,
+};
 use crate::BlockHeaderField::{PreviousBlockHash, ProofHash, Proposer};
-use crate::BlockProofField::{BitMap, HashMismatch, HeightMismatch, Signature, WeightNotFound};
-use crate::{BlockHeaderField, BlockProofField, ConsensusError};
+use crate::{BlockHeaderField, ConsensusError};
+
+/// Propose the transactions exactly as mempool packages them.
+pub struct DefaultProposalSelector;
+
+impl ProposalSelector for DefaultProposalSelector {
+    fn select(&self, _ctx: Context, _height: u64, txs: MixedTxHashes) -> MixedTxHashes {
+        txs
+    }
+}
 
 pub struct OverlordConsensusAdapter<
     EF: ExecutorFactory<DB, S, Mapping>,
@@ -55,9 +66,11 @@ pub struct OverlordConsensusAdapter<
     service_mapping:  Arc<Mapping>,
     overlord_handler: RwLock<Option<OverlordHandler<FixedPill>>>,
 
-    exec_queue:  Sender<ExecuteInfo>,
-    exec_demons: Option<ExecDemons<S, DB, EF, Mapping>>,
-    crypto:      Arc<OverlordCrypto>,
+    exec_queue:        Sender<ExecuteInfo>,
+    exec_demons:       Option<ExecDemons<S, DB, EF, Mapping>>,
+    crypto:            Arc<OverlordCrypto>,
+    proposal_selector: Box<dyn ProposalSelector>,
+    executor_config:   ExecutorConfig,
 }
 
 #[async_trait]
@@ -75,11 +88,16 @@ where
     async fn get_txs_from_mempool(
         &self,
         ctx: Context,
-        _height: u64,
+        height: u64,
         cycle_limit: u64,
         tx_num_limit: u64,
     ) -> ProtocolResult<MixedTxHashes> {
-        self.mempool.package(ctx, cycle_limit, tx_num_limit).await
+        let txs = self
+            .mempool
+            .package(ctx.clone(), cycle_limit, tx_num_limit)
+            .await?;
+
+        Ok(self.proposal_selector.select(ctx, height, txs))
     }
 
     #[muta_apm::derive::tracing_span(kind = "consensus.adapter")]
@@ -257,6 +275,7 @@ where
             Arc::clone(&self.trie_db),
             Arc::clone(&self.storage),
             Arc::clone(&self.service_mapping),
+            &self.executor_config,
         )?;
         let inst = Instant::now();
         let resp = executor.exec(ctx, params, txs)?;
@@ -468,6 +487,7 @@ where
             Arc::clone(&self.trie_db),
             Arc::clone(&self.storage),
             Arc::clone(&self.service_mapping),
+            &ExecutorConfig::default(),
         )?;
 
         let caller = Address::from_hash(Hash::digest(protocol::address_hrp().as_str()))?;
@@ -628,35 +648,10 @@ where
         proof: &Proof,
     ) -> ProtocolResult<()> {
         // the block 0 has no proof, which is consensus-ed by community, not by chain
-
         if block_header.height == 0 {
             return Ok(());
         };
 
-        if block_header.height != proof.height {
-            log::error!(
-                "[consensus] verify_proof, block_header.height: {}, proof.height: {}",
-                block_header.height,
-                proof.height
-            );
-            return Err(ConsensusError::VerifyProof(
-                block_header.height,
-                HeightMismatch(block_header.height, proof.height),
-            )
-            .into());
-        }
-
-        let blockhash = Hash::digest(block_header.encode_fixed()?);
-
-        if blockhash != proof.block_hash {
-            log::error!(
-                "[consensus] verify_proof, blockhash: {:?}, proof.block_hash: {:?}",
-                blockhash,
-                proof.block_hash
-            );
-            return Err(ConsensusError::VerifyProof(block_header.height, HashMismatch).into());
-        }
-
         let previous_block_header = self
             .get_block_header_by_height(ctx.clone(), block_header.height - 1)
             .await
@@ -676,68 +671,17 @@ where
             previous_block_header.proposer,
         )?;
 
-        let mut authority_list = metadata
-            .verifier_list
-            .iter()
-            .map(|v| Node {
-                address:        v.pub_key.decode(),
-                propose_weight: v.propose_weight,
-                vote_weight:    v.vote_weight,
-            })
-            .collect::<Vec<Node>>();
-
-        let signed_voters = extract_voters(&mut authority_list, &proof.bitmap).map_err(|_| {
-            log::error!("[consensus] extract_voters fails, bitmap error");
-            ConsensusError::VerifyProof(block_header.height, BitMap)
-        })?;
-
-        let vote = Vote {
-            height:     proof.height,
-            round:      proof.round,
-            vote_type:  VoteType::Precommit,
-            block_hash: proof.block_hash.as_bytes(),
-        };
-
-        let weight_map = authority_list
-            .iter()
-            .map(|node| (node.address.clone(), node.vote_weight))
-            .collect::<HashMap<overlord::types::Address, u32>>();
-        self.verify_proof_weight(
-            ctx.clone(),
-            block_header.height,
-            weight_map,
-            signed_voters.clone(),
+        verify_block_proof(&self.crypto, block_header, proof, &metadata.verifier_list).map_err(
+            |e| {
+                log::error!(
+                    "[consensus] verify_proof error {}, block_header: {:?}, proof: {:?}",
+                    e,
+                    block_header,
+                    proof
+                );
+                e
+            },
         )?;
-
-        let vote_hash = self.crypto.hash(Bytes::from(rlp::encode(&vote)));
-        let hex_pubkeys = metadata
-            .verifier_list
-            .iter()
-            .filter_map(|v| {
-                if signed_voters.contains(&v.pub_key.decode()) {
-                    Some(v.bls_pub_key.clone())
-                } else {
-                    None
-                }
-            })
-            .collect::<Vec<_>>();
-
-        self.verify_proof_signature(
-            ctx.clone(),
-            block_header.height,
-            vote_hash.clone(),
-            proof.signature.clone(),
-            hex_pubkeys,
-        ).map_err(|e| {
-            log::error!("[consensus] verify_proof_signature error, height {}, vote: {:?}, vote_hash:{:?}, sig:{:?}, signed_voter:{:?}",
-            block_header.height,
-            vote,
-            vote_hash,
-            proof.signature,
-            signed_voters,
-            );
-            e
-        })?;
         Ok(())
     }
 
@@ -750,17 +694,14 @@ where
         aggregated_signature_bytes: Bytes,
         vote_keys: Vec<Hex>,
     ) -> ProtocolResult<()> {
-        let mut pub_keys = Vec::new();
-        for hex in vote_keys.into_iter() {
-            pub_keys.push(convert_hex_to_bls_pubkeys(hex)?)
-        }
-
-        self.crypto
-            .inner_verify_aggregated_signature(vote_hash, pub_keys, aggregated_signature_bytes)
-            .map_err(|e| {
-                log::error!("[consensus] verify_proof_signature error: {}", e);
-                ConsensusError::VerifyProof(block_height, Signature).into()
-            })
+        verify_proof_signature(
+            &self.crypto,
+            block_height,
+            vote_hash,
+            aggregated_signature_bytes,
+            vote_keys,
+        )?;
+        Ok(())
     }
 
     #[muta_apm::derive::tracing_span(kind = "consensus.adapter")]
@@ -771,42 +712,7 @@ where
         weight_map: HashMap<Bytes, u32>,
         signed_voters: Vec<Bytes>,
     ) -> ProtocolResult<()> {
-        let total_validator_weight: u64 = weight_map.iter().map(|pair| u64::from(*pair.1)).sum();
-
-        let mut accumulator = 0u64;
-        for signed_voter_address in signed_voters {
-            if weight_map.contains_key(signed_voter_address.as_ref()) {
-                let weight = weight_map
-                    .get(signed_voter_address.as_ref())
-                    .ok_or(ConsensusError::VerifyProof(block_height, WeightNotFound))
-                    .map_err(|e| {
-                        log::error!(
-                            "[consensus] verify_proof_weight,signed_voter_address: {:?}",
-                            signed_voter_address
-                        );
-                        e
-                    })?;
-                accumulator += u64::from(*(weight));
-            } else {
-                log::error!(
-                    "[consensus] verify_proof_weight, weight not found, signed_voter_address: {:?}",
-                    signed_voter_address
-                );
-                return Err(
-                    ConsensusError::VerifyProof(block_height, BlockProofField::Validator).into(),
-                );
-            }
-        }
-
-        if 3 * accumulator <= 2 * total_validator_weight {
-            log::error!(
-                "[consensus] verify_proof_weight, accumulator: {}, total: {}",
-                accumulator,
-                total_validator_weight
-            );
-
-            return Err(ConsensusError::VerifyProof(block_height, BlockProofField::Weight).into());
-        }
+        verify_proof_weight(block_height, &weight_map, &signed_voters)?;
         Ok(())
     }
 }
@@ -849,11 +755,26 @@ where
             exec_queue,
             exec_demons,
             crypto,
+            proposal_selector: Box::new(DefaultProposalSelector),
+            executor_config: ExecutorConfig::default(),
         };
 
         Ok(adapter)
     }
 
+    pub fn set_proposal_selector(&mut self, selector: Box<dyn ProposalSelector>) {
+        self.proposal_selector = selector;
+    }
+
+    /// Executor settings, applied both to blocks executed by the exec demon
+    /// and to synced ones.
+    pub fn set_executor_config(&mut self, config: ExecutorConfig) {
+        if let Some(demons) = self.exec_demons.as_mut() {
+            demons.executor_config = config.clone();
+        }
+        self.executor_config = config;
+    }
+
     pub fn take_exec_demon(&mut self) -> ExecDemons<S, DB, EF, Mapping> {
         assert!(self.exec_demons.is_some());
         self.exec_demons.take().unwrap()
@@ -873,6 +794,8 @@ pub struct ExecDemons<S, DB, EF, Mapping> {
     pin_ef: PhantomData<EF>,
     queue:  Receiver<ExecuteInfo>,
     status: StatusAgent,
+
+    executor_config: ExecutorConfig,
 }
 
 impl<S, DB, EF, Mapping> ExecDemons<S, DB, EF, Mapping>
@@ -896,6 +819,7 @@ where
             queue: rx,
             pin_ef: PhantomData,
             status: status_agent,
+            executor_config: ExecutorConfig::default(),
         }
     }
 
@@ -935,6 +859,7 @@ where
             Arc::clone(&self.trie_db),
             Arc::clone(&self.storage),
             Arc::clone(&self.service_mapping),
+            &self.executor_config,
         )?;
         let exec_params = ExecutorParams {
             state_root: state_root.clone(),
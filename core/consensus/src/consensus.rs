@@ -1,7 +1,9 @@
 use std::sync::Arc;
+use std::time::Duration;
 
 use async_trait::async_trait;
 use creep::Context;
+use futures::channel::oneshot;
 use futures::lock::Mutex;
 use overlord::types::{
     AggregatedVote, Node, OverlordMsg, SignedChoke, SignedProposal, SignedVote, Status,
@@ -10,14 +12,14 @@ use overlord::{DurationConfig, Overlord, OverlordHandler};
 
 use common_apm::muta_apm;
 
-use protocol::traits::{Consensus, ConsensusAdapter, NodeInfo};
-use protocol::types::Validator;
+use protocol::traits::{CommitStatus, Consensus, ConsensusAdapter, NodeInfo};
+use protocol::types::{Address, Block, Proof, Validator, ValidatorExtend};
 use protocol::ProtocolResult;
 
-use crate::engine::ConsensusEngine;
+use crate::engine::{ConsensusEngine, StopSignal};
 use crate::fixed_types::FixedPill;
 use crate::status::StatusAgent;
-use crate::util::OverlordCrypto;
+use crate::util::{verify_block_proof, OverlordCrypto};
 use crate::wal::{ConsensusWal, SignedTxsWAL};
 use crate::{ConsensusError, ConsensusType};
 
@@ -29,6 +31,10 @@ pub struct OverlordConsensus<Adapter: ConsensusAdapter + 'static> {
     >,
     /// An overlord consensus protocol handler.
     handler: OverlordHandler<FixedPill>,
+    /// Consensus engine, records the latest message type for diagnosis.
+    engine:  Arc<ConsensusEngine<Adapter>>,
+    /// Crypto of overlord, holds BLS public keys of validators.
+    crypto:  Arc<OverlordCrypto>,
 }
 
 #[async_trait]
@@ -37,6 +43,8 @@ impl<Adapter: ConsensusAdapter + 'static> Consensus for OverlordConsensus<Adapte
     async fn set_proposal(&self, ctx: Context, proposal: Vec<u8>) -> ProtocolResult<()> {
         let signed_proposal: SignedProposal<FixedPill> = rlp::decode(&proposal)
             .map_err(|_| ConsensusError::DecodeErr(ConsensusType::SignedProposal))?;
+        self.engine
+            .set_last_consensus_type(ConsensusType::SignedProposal);
 
         let msg = OverlordMsg::SignedProposal(signed_proposal);
         tracing_overlord_message(ctx.clone(), &msg);
@@ -62,6 +70,8 @@ impl<Adapter: ConsensusAdapter + 'static> Consensus for OverlordConsensus<Adapte
 
         let signed_vote: SignedVote =
             rlp::decode(&vote).map_err(|_| ConsensusError::DecodeErr(ConsensusType::SignedVote))?;
+        self.engine
+            .set_last_consensus_type(ConsensusType::SignedVote);
 
         let msg = OverlordMsg::SignedVote(signed_vote);
         tracing_overlord_message(ctx.clone(), &msg);
@@ -76,6 +86,8 @@ impl<Adapter: ConsensusAdapter + 'static> Consensus for OverlordConsensus<Adapte
     async fn set_qc(&self, ctx: Context, qc: Vec<u8>) -> ProtocolResult<()> {
         let aggregated_vote: AggregatedVote = rlp::decode(&qc)
             .map_err(|_| ConsensusError::DecodeErr(ConsensusType::AggregateVote))?;
+        self.engine
+            .set_last_consensus_type(ConsensusType::AggregateVote);
 
         let msg = OverlordMsg::AggregatedVote(aggregated_vote);
         tracing_overlord_message(ctx.clone(), &msg);
@@ -90,6 +102,8 @@ impl<Adapter: ConsensusAdapter + 'static> Consensus for OverlordConsensus<Adapte
     async fn set_choke(&self, ctx: Context, choke: Vec<u8>) -> ProtocolResult<()> {
         let signed_choke: SignedChoke = rlp::decode(&choke)
             .map_err(|_| ConsensusError::DecodeErr(ConsensusType::SignedChoke))?;
+        self.engine
+            .set_last_consensus_type(ConsensusType::SignedChoke);
 
         let msg = OverlordMsg::SignedChoke(signed_choke);
         tracing_overlord_message(ctx.clone(), &msg);
@@ -121,7 +135,12 @@ impl<Adapter: ConsensusAdapter + 'static> OverlordConsensus<Adapter> {
             consensus_wal,
         ));
 
-        let overlord = Overlord::new(node_info.self_pub_key, Arc::clone(&engine), crypto, engine);
+        let overlord = Overlord::new(
+            node_info.self_pub_key,
+            Arc::clone(&engine),
+            Arc::clone(&crypto),
+            Arc::clone(&engine),
+        );
         let overlord_handler = overlord.get_handler();
         let status = status_agent.to_inner();
 
@@ -143,8 +162,10 @@ impl<Adapter: ConsensusAdapter + 'static> OverlordConsensus<Adapter> {
         }
 
         Self {
-            inner:   Arc::new(overlord),
+            inner: Arc::new(overlord),
             handler: overlord_handler,
+            engine,
+            crypto,
         }
     }
 
@@ -152,6 +173,56 @@ impl<Adapter: ConsensusAdapter + 'static> OverlordConsensus<Adapter> {
         self.handler.clone()
     }
 
+    /// Adjust propose, prevote, precommit and brake durations, or reset them
+    /// to ratios in metadata with `None`. Takes effect at the next height.
+    pub fn update_durations(&self, cfg: Option<DurationConfig>) {
+        self.engine.update_durations(cfg);
+    }
+
+    /// Minimum interval between blocks when there is no transaction, see
+    /// `ConsensusEngine::set_min_block_interval`.
+    pub fn set_min_block_interval(&self, interval: Duration) {
+        self.engine.set_min_block_interval(interval);
+    }
+
+    /// Finish the current height and stop, see
+    /// `ConsensusEngine::request_stop`.
+    pub fn request_stop(&self) -> oneshot::Receiver<u64> {
+        self.engine.request_stop()
+    }
+
+    pub fn stop_signal(&self) -> StopSignal {
+        self.engine.stop_signal()
+    }
+
+    /// Height and time of the latest block this node committed.
+    pub fn last_commit_status(&self) -> CommitStatus {
+        self.engine.last_commit_status()
+    }
+
+    /// Validator set of the latest committed height, read only.
+    pub fn current_validators(&self) -> Vec<Node> {
+        self.engine.current_validators()
+    }
+
+    /// Vote participation rate of each validator over the recent heights, an
+    /// abstaining validator's rate drops as its votes miss commit proofs.
+    pub fn participation(&self) -> Vec<(Address, f64)> {
+        self.engine.participation()
+    }
+
+    /// Verify the proof of an arbitrary block against `validators`, the
+    /// validator set of its previous height, without touching storage or
+    /// consensus status.
+    pub fn verify_block_proof(
+        &self,
+        block: &Block,
+        proof: &Proof,
+        validators: &[ValidatorExtend],
+    ) -> Result<(), ConsensusError> {
+        verify_block_proof(&self.crypto, &block.header, proof, validators)
+    }
+
     pub async fn run(
         &self,
         init_height: u64,
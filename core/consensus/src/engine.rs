@@ -1,27 +1,28 @@
-use std::collections::{HashMap, HashSet};
-use std::convert::TryFrom;
+use std::collections::HashSet;
 use std::error::Error;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 
 use async_trait::async_trait;
+use futures::channel::oneshot;
 use futures::lock::Mutex;
 use futures_timer::Delay;
 use json::JsonValue;
 use log::{error, info, warn};
 use overlord::error::ConsensusError as OverlordError;
-use overlord::types::{Commit, Node, OverlordMsg, Status, ViewChangeReason};
+use overlord::types::{Commit, Node, OverlordMsg, Status, ViewChangeReason, VoteType};
 use overlord::{Consensus as Engine, DurationConfig, Wal};
 use parking_lot::RwLock;
 use rlp::Encodable;
 
 use common_apm::muta_apm;
-use common_crypto::BlsPublicKey;
 use common_logger::{json, log};
 use common_merkle::Merkle;
 
 use protocol::fixed_codec::FixedCodec;
-use protocol::traits::{ConsensusAdapter, Context, MessageTarget, NodeInfo, TrustFeedback};
+use protocol::traits::{
+    CommitStatus, ConsensusAdapter, Context, MessageTarget, MixedTxHashes, NodeInfo, TrustFeedback,
+};
 use protocol::types::{
     Address, Block, BlockHeader, Hash, MerkleRoot, Metadata, Pill, Proof, SignedTransaction,
     Validator,
@@ -33,14 +34,15 @@ use crate::message::{
     END_GOSSIP_AGGREGATED_VOTE, END_GOSSIP_SIGNED_CHOKE, END_GOSSIP_SIGNED_PROPOSAL,
     END_GOSSIP_SIGNED_VOTE,
 };
-use crate::status::StatusAgent;
+use crate::status::{CurrentConsensusStatus, StatusAgent};
 use crate::util::{check_list_roots, digest_signed_transactions, time_now, OverlordCrypto};
 use crate::wal::{ConsensusWal, SignedTxsWAL};
-use crate::ConsensusError;
+use crate::{ConsensusError, ConsensusType};
 
 const RETRY_COMMIT_INTERVAL: u64 = 1000; // 1s
 const RETRY_CHECK_ROOT_LIMIT: u8 = 15;
 const RETRY_CHECK_ROOT_INTERVAL: u64 = 100; // 100ms
+const EMPTY_MEMPOOL_POLL_INTERVAL: u64 = 50; // 50ms
 
 /// validator is for create new block, and authority is for build overlord
 /// status.
@@ -57,6 +59,67 @@ pub struct ConsensusEngine<Adapter> {
     last_commit_time:             RwLock<u64>,
     consensus_wal:                Arc<ConsensusWal>,
     last_check_block_fail_reason: RwLock<String>,
+    last_consensus_type:          RwLock<Option<ConsensusType>>,
+    duration_config:              RwLock<Option<DurationConfig>>,
+    stop_signal:                  StopSignal,
+    last_commit_status:           RwLock<CommitStatus>,
+    min_block_interval:           RwLock<Duration>,
+}
+
+/// Stop request shared by the engine and synchronization, whichever commits
+/// the height in progress first stops there.
+#[derive(Clone, Default)]
+pub struct StopSignal {
+    inner: Arc<RwLock<StopState>>,
+}
+
+#[derive(Default)]
+struct StopState {
+    requested:  bool,
+    stopped_at: Option<u64>,
+    waiters:    Vec<oneshot::Sender<u64>>,
+}
+
+impl StopSignal {
+    /// The returned receiver resolves to the last committed height.
+    pub fn request(&self) -> oneshot::Receiver<u64> {
+        let (tx, rx) = oneshot::channel();
+        let mut state = self.inner.write();
+
+        match state.stopped_at {
+            Some(height) => {
+                let _ = tx.send(height);
+            }
+            None => {
+                state.requested = true;
+                state.waiters.push(tx);
+            }
+        }
+        rx
+    }
+
+    pub fn stopped_at(&self) -> Option<u64> {
+        self.inner.read().stopped_at
+    }
+
+    /// Called once `height` is committed, return true if no new height should
+    /// begin afterwards.
+    pub fn stop_after(&self, height: u64) -> bool {
+        let mut state = self.inner.write();
+        if state.stopped_at.is_some() {
+            return true;
+        }
+        if !state.requested {
+            return false;
+        }
+
+        info!("[consensus]: stop after commit height {}", height);
+        state.stopped_at = Some(height);
+        for waiter in state.waiters.drain(..) {
+            let _ = waiter.send(height);
+        }
+        true
+    }
 }
 
 #[async_trait]
@@ -82,13 +145,7 @@ impl<Adapter: ConsensusAdapter + 'static> Engine<FixedPill> for ConsensusEngine<
         }
 
         let (ordered_tx_hashes, propose_hashes) = self
-            .adapter
-            .get_txs_from_mempool(
-                ctx.clone(),
-                next_height,
-                current_consensus_status.cycles_limit,
-                current_consensus_status.tx_num_limit,
-            )
+            .package_txs(ctx.clone(), next_height, &current_consensus_status)
             .await?
             .clap();
         let signed_txs = self
@@ -236,6 +293,10 @@ impl<Adapter: ConsensusAdapter + 'static> Engine<FixedPill> for ConsensusEngine<
         current_height: u64,
         commit: Commit<FixedPill>,
     ) -> Result<Status, Box<dyn Error + Send>> {
+        if let Some(height) = self.stop_signal.stopped_at() {
+            return Err(ProtocolError::from(ConsensusError::Stopped(height)).into());
+        }
+
         let lock = self.lock.try_lock();
         if lock.is_none() {
             return Err(ProtocolError::from(ConsensusError::LockInSync).into());
@@ -246,12 +307,7 @@ impl<Adapter: ConsensusAdapter + 'static> Engine<FixedPill> for ConsensusEngine<
             let status = Status {
                 height:         current_height + 1,
                 interval:       Some(current_consensus_status.consensus_interval),
-                timer_config:   Some(DurationConfig {
-                    propose_ratio:   current_consensus_status.propose_ratio,
-                    prevote_ratio:   current_consensus_status.prevote_ratio,
-                    precommit_ratio: current_consensus_status.precommit_ratio,
-                    brake_ratio:     current_consensus_status.brake_ratio,
-                }),
+                timer_config:   Some(self.duration_config(&current_consensus_status)),
                 authority_list: covert_to_overlord_authority(&current_consensus_status.validators),
             };
             return Ok(status);
@@ -347,16 +403,17 @@ impl<Adapter: ConsensusAdapter + 'static> Engine<FixedPill> for ConsensusEngine<
         let mut set = self.exemption_hash.write();
         set.clear();
 
+        // Without a status of the next height overlord never starts it.
+        if self.stop_signal.stop_after(current_height) {
+            self.metric_commit(current_height, txs_len);
+            return Err(ProtocolError::from(ConsensusError::Stopped(current_height)).into());
+        }
+
         let current_consensus_status = self.status_agent.to_inner();
         let status = Status {
             height:         current_height + 1,
             interval:       Some(current_consensus_status.consensus_interval),
-            timer_config:   Some(DurationConfig {
-                propose_ratio:   current_consensus_status.propose_ratio,
-                prevote_ratio:   current_consensus_status.prevote_ratio,
-                precommit_ratio: current_consensus_status.precommit_ratio,
-                brake_ratio:     current_consensus_status.brake_ratio,
-            }),
+            timer_config:   Some(self.duration_config(&current_consensus_status)),
             authority_list: covert_to_overlord_authority(&current_consensus_status.validators),
         };
 
@@ -493,12 +550,22 @@ impl<Adapter: ConsensusAdapter + 'static> Engine<FixedPill> for ConsensusEngine<
             _ => reason.to_string(),
         };
 
+        let (phase, stall_reason) = view_change_phase_and_stall_reason(&reason);
+        let last_consensus_type = match self.last_consensus_type.read().as_ref() {
+            Some(ty) => ty.to_string(),
+            None => "None".to_owned(),
+        };
+
+        common_apm::metrics::consensus::CONSENSUS_VIEW_CHANGE_COUNTER_VEC
+            .with_label_values(&[stall_reason])
+            .inc();
+
         log(
             log::Level::Warn,
             "consensus",
             "cons000",
             &cx,
-            json!({"height", height; "round", round; "reason", view_change_reason}),
+            json!({"height", height; "round", round; "phase", phase; "reason", view_change_reason; "last_consensus_type", last_consensus_type}),
         );
     }
 }
@@ -528,6 +595,12 @@ impl<Adapter: ConsensusAdapter + 'static> ConsensusEngine<Adapter> {
         lock: Arc<Mutex<()>>,
         consensus_wal: Arc<ConsensusWal>,
     ) -> Self {
+        // Count from start up until the first commit
+        let last_commit_status = CommitStatus {
+            height:       status_agent.to_inner().latest_committed_height,
+            committed_at: Instant::now(),
+        };
+
         Self {
             status_agent,
             node_info,
@@ -539,7 +612,88 @@ impl<Adapter: ConsensusAdapter + 'static> ConsensusEngine<Adapter> {
             last_commit_time: RwLock::new(time_now()),
             consensus_wal,
             last_check_block_fail_reason: RwLock::new(String::new()),
+            last_consensus_type: RwLock::new(None),
+            duration_config: RwLock::new(None),
+            stop_signal: StopSignal::default(),
+            last_commit_status: RwLock::new(last_commit_status),
+            min_block_interval: RwLock::new(Duration::from_millis(0)),
+        }
+    }
+
+    /// Delay proposing an empty block until `interval` has passed since the
+    /// last commit. Keep it below the propose timeout, otherwise the round
+    /// times out before the proposal arrives.
+    pub fn set_min_block_interval(&self, interval: Duration) {
+        *self.min_block_interval.write() = interval;
+    }
+
+    /// Height and time of the latest commit of this node, before any commit
+    /// it's the height on start up and the start up time.
+    pub fn last_commit_status(&self) -> CommitStatus {
+        *self.last_commit_status.read()
+    }
+
+    /// Validator set of the latest committed height, sorted the same way as
+    /// the authority list handed to overlord.
+    pub fn current_validators(&self) -> Vec<Node> {
+        covert_to_overlord_authority(&self.status_agent.to_inner().validators)
+    }
+
+    /// Vote participation rate of each validator over recent heights.
+    pub fn participation(&self) -> Vec<(Address, f64)> {
+        self.status_agent
+            .participation()
+            .into_iter()
+            .filter_map(
+                |(pub_key, rate)| match Address::from_pubkey_bytes(pub_key) {
+                    Ok(address) => Some((address, rate)),
+                    Err(e) => {
+                        warn!("[consensus]: participation of invalid pub key {:?}", e);
+                        None
+                    }
+                },
+            )
+            .collect()
+    }
+
+    /// Stop consensus once the height in progress is committed, no new height
+    /// begins afterwards. The returned receiver resolves to the last committed
+    /// height.
+    pub fn request_stop(&self) -> oneshot::Receiver<u64> {
+        self.stop_signal.request()
+    }
+
+    /// Share the stop signal with synchronization, so that syncing doesn't
+    /// begin a new height after the stop either.
+    pub fn stop_signal(&self) -> StopSignal {
+        self.stop_signal.clone()
+    }
+
+    /// Override durations of consensus phases, `None` goes back to ratios in
+    /// metadata. Overlord only takes durations from the status returned on
+    /// commit, so the change applies from the next height and never changes a
+    /// running round.
+    pub fn update_durations(&self, cfg: Option<DurationConfig>) {
+        *self.duration_config.write() = cfg;
+    }
+
+    fn duration_config(&self, status: &CurrentConsensusStatus) -> DurationConfig {
+        if let Some(cfg) = self.duration_config.read().as_ref() {
+            return cfg.clone();
         }
+
+        DurationConfig {
+            propose_ratio:   status.propose_ratio,
+            prevote_ratio:   status.prevote_ratio,
+            precommit_ratio: status.precommit_ratio,
+            brake_ratio:     status.brake_ratio,
+        }
+    }
+
+    /// Record the type of the latest consensus message handed to overlord,
+    /// it is reported on view change.
+    pub fn set_last_consensus_type(&self, ty: ConsensusType) {
+        *self.last_consensus_type.write() = Some(ty);
     }
 
     #[muta_apm::derive::tracing_span(kind = "consensus.engine")]
@@ -802,8 +956,44 @@ impl<Adapter: ConsensusAdapter + 'static> ConsensusEngine<Adapter> {
         Ok(())
     }
 
+    /// Package transactions for the next block. With an empty mempool, wait
+    /// out `min_block_interval` since the last commit, mempool is checked
+    /// while waiting so that new transactions are proposed immediately.
+    async fn package_txs(
+        &self,
+        ctx: Context,
+        next_height: u64,
+        status: &CurrentConsensusStatus,
+    ) -> ProtocolResult<MixedTxHashes> {
+        let min_interval = *self.min_block_interval.read();
+
+        loop {
+            let txs = self
+                .adapter
+                .get_txs_from_mempool(
+                    ctx.clone(),
+                    next_height,
+                    status.cycles_limit,
+                    status.tx_num_limit,
+                )
+                .await?;
+            if !txs.order_tx_hashes.is_empty() || !txs.propose_tx_hashes.is_empty() {
+                return Ok(txs);
+            }
+
+            let last_commit_time = *self.last_commit_time.read();
+            let elapsed = Duration::from_millis(time_now().saturating_sub(last_commit_time));
+            if elapsed >= min_interval {
+                return Ok(txs);
+            }
+
+            let poll_interval = Duration::from_millis(EMPTY_MEMPOOL_POLL_INTERVAL);
+            Delay::new((min_interval - elapsed).min(poll_interval)).await;
+        }
+    }
+
     fn update_overlord_crypto(&self, metadata: Metadata) -> ProtocolResult<()> {
-        self.crypto.update(generate_new_crypto_map(metadata)?);
+        self.crypto.update_validators(&metadata.verifier_list)?;
         Ok(())
     }
 
@@ -817,29 +1007,20 @@ impl<Adapter: ConsensusAdapter + 'static> ConsensusEngine<Adapter> {
         common_apm::metrics::consensus::ENGINE_CONSENSUS_COST_TIME.observe(elapsed / 1e3);
         let mut last_commit_time = self.last_commit_time.write();
         *last_commit_time = now;
+
+        *self.last_commit_status.write() = CommitStatus {
+            height:       current_height,
+            committed_at: Instant::now(),
+        };
     }
 
     #[cfg(test)]
-    pub fn get_current_status(&self) -> crate::status::CurrentConsensusStatus {
+    pub fn get_current_status(&self) -> CurrentConsensusStatus {
         self.status_agent.to_inner()
     }
 }
 
-pub fn generate_new_crypto_map(metadata: Metadata) -> ProtocolResult<HashMap<Bytes, BlsPublicKey>> {
-    let mut new_addr_pubkey_map = HashMap::new();
-    for validator in metadata.verifier_list.into_iter() {
-        let addr = validator.pub_key.decode();
-        let hex_pubkey = hex::decode(validator.bls_pub_key.as_string_trim0x()).map_err(|err| {
-            ConsensusError::Other(format!("hex decode metadata bls pubkey error {:?}", err))
-        })?;
-        let pubkey = BlsPublicKey::try_from(hex_pubkey.as_ref())
-            .map_err(|err| ConsensusError::Other(format!("try from bls pubkey error {:?}", err)))?;
-        new_addr_pubkey_map.insert(addr, pubkey);
-    }
-    Ok(new_addr_pubkey_map)
-}
-
-fn covert_to_overlord_authority(validators: &[Validator]) -> Vec<Node> {
+pub(crate) fn covert_to_overlord_authority(validators: &[Validator]) -> Vec<Node> {
     let mut authority = validators
         .iter()
         .map(|v| Node {
@@ -876,6 +1057,27 @@ fn validate_timestamp(
     true
 }
 
+/// Return the overlord phase where the round stalled and the reason label of
+/// view change metric.
+fn view_change_phase_and_stall_reason(reason: &ViewChangeReason) -> (&'static str, &'static str) {
+    match reason {
+        ViewChangeReason::NoProposalFromNetwork => ("propose", "no_proposal"),
+        ViewChangeReason::CheckBlockNotPass => ("propose", "check_block_failed"),
+        ViewChangeReason::NoPrevoteQCFromNetwork => ("prevote", "no_prevote_qc"),
+        ViewChangeReason::NoPrecommitQCFromNetwork => ("precommit", "no_precommit_qc"),
+        ViewChangeReason::LeaderReceivedVoteBelowThreshold(VoteType::Prevote) => {
+            ("prevote", "vote_below_threshold")
+        }
+        ViewChangeReason::LeaderReceivedVoteBelowThreshold(VoteType::Precommit) => {
+            ("precommit", "vote_below_threshold")
+        }
+        ViewChangeReason::UpdateFromHigherPrevoteQC(..)
+        | ViewChangeReason::UpdateFromHigherPrecommitQC(..)
+        | ViewChangeReason::UpdateFromHigherChokeQC(..) => ("brake", "higher_round_qc"),
+        ViewChangeReason::Others => ("unknown", "others"),
+    }
+}
+
 fn gauge_txs_len(pill: &Pill) {
     common_apm::metrics::consensus::ENGINE_ORDER_TX_GAUGE
         .set(pill.block.ordered_tx_hashes.len() as i64);
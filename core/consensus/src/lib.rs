@@ -27,8 +27,9 @@ use common_crypto::Error as CryptoError;
 use protocol::types::{Hash, MerkleRoot};
 use protocol::{ProtocolError, ProtocolErrorKind};
 
-pub use crate::adapter::OverlordConsensusAdapter;
+pub use crate::adapter::{DefaultProposalSelector, OverlordConsensusAdapter};
 pub use crate::consensus::OverlordConsensus;
+pub use crate::engine::StopSignal;
 pub use crate::synchronization::{OverlordSynchronization, RichBlock};
 pub use crate::wal::{ConsensusWal, SignedTxsWAL};
 pub use overlord::{types::Node, DurationConfig};
@@ -143,6 +144,9 @@ pub enum ConsensusError {
     #[display(fmt = "Wal transactions mismatch, height {}", _0)]
     WalTxsMismatch(u64),
 
+    #[display(fmt = "Wal transactions corrupted, height {}", _0)]
+    WalCorrupted(u64),
+
     #[display(
         fmt = "Commit an outdated block, block_height {}, last_committed_height {}",
         _0,
@@ -150,6 +154,9 @@ pub enum ConsensusError {
     )]
     OutdatedCommit(u64, u64),
 
+    #[display(fmt = "Consensus stopped after height {}", _0)]
+    Stopped(u64),
+
     /// Other error used for very few errors.
     #[display(fmt = "{:?}", _0)]
     Other(String),
@@ -1,28 +1,149 @@
+use std::collections::{BTreeMap, VecDeque};
 use std::sync::Arc;
 
 use derive_more::Display;
-use parking_lot::RwLock;
+use overlord::extract_voters;
+use parking_lot::{Mutex, RwLock};
 use serde::{Deserialize, Serialize};
 
 use common_merkle::Merkle;
+use common_pubsub::{Publish, Subscription};
 use protocol::fixed_codec::FixedCodec;
 use protocol::traits::{Context, ExecutorResp};
 use protocol::types::{Block, Hash, MerkleRoot, Metadata, Proof, Validator};
+use protocol::Bytes;
 
+use crate::engine::covert_to_overlord_authority;
 use crate::util::check_list_roots;
 
-#[derive(Clone, Debug)]
+/// Number of recent heights that vote participation rates are counted over.
+pub const PARTICIPATION_WINDOW: usize = 100;
+
+/// Emitted when the validator set changes after a status update.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ValidatorSetChange {
+    pub height:         u64,
+    pub old_validators: Vec<Validator>,
+    pub new_validators: Vec<Validator>,
+}
+
+impl ValidatorSetChange {
+    pub fn added(&self) -> Vec<Validator> {
+        self.new_validators
+            .iter()
+            .filter(|v| !self.old_validators.contains(v))
+            .cloned()
+            .collect()
+    }
+
+    pub fn removed(&self) -> Vec<Validator> {
+        self.old_validators
+            .iter()
+            .filter(|v| !self.new_validators.contains(v))
+            .cloned()
+            .collect()
+    }
+}
+
+/// Which validators signed the commit proof, for each of the recent heights.
+#[derive(Debug, Default)]
+pub(crate) struct VoteParticipation {
+    records: VecDeque<(u64, Vec<(Bytes, bool)>)>,
+}
+
+impl VoteParticipation {
+    /// Record the voters of `proof` among `validators`, the validator set of
+    /// the proof height. Heights not above the last recorded one are ignored.
+    pub(crate) fn record(&mut self, proof: &Proof, validators: &[Validator]) {
+        if let Some((height, _)) = self.records.back() {
+            if proof.height <= *height {
+                return;
+            }
+        }
+
+        let mut authority_list = covert_to_overlord_authority(validators);
+        let voters = match extract_voters(&mut authority_list, &proof.bitmap) {
+            Ok(voters) => voters,
+            Err(e) => {
+                log::warn!(
+                    "[consensus]: extract voters of height {} {:?}",
+                    proof.height,
+                    e
+                );
+                return;
+            }
+        };
+        let record = authority_list
+            .into_iter()
+            .map(|node| {
+                let voted = voters.contains(&node.address);
+                (node.address, voted)
+            })
+            .collect();
+
+        self.records.push_back((proof.height, record));
+        if self.records.len() > PARTICIPATION_WINDOW {
+            self.records.pop_front();
+        }
+    }
+
+    /// Participation rate of each validator by public key, over the recorded
+    /// heights it was a validator of.
+    pub(crate) fn rates(&self) -> Vec<(Bytes, f64)> {
+        let mut counts: BTreeMap<Bytes, (u64, u64)> = BTreeMap::new();
+        for (_, record) in self.records.iter() {
+            for (pub_key, voted) in record.iter() {
+                let count = counts.entry(pub_key.clone()).or_default();
+                count.1 += 1;
+                if *voted {
+                    count.0 += 1;
+                }
+            }
+        }
+
+        counts
+            .into_iter()
+            .map(|(pub_key, (voted, total))| (pub_key, voted as f64 / total as f64))
+            .collect()
+    }
+}
+
+#[derive(Clone)]
 pub struct StatusAgent {
-    status: Arc<RwLock<CurrentConsensusStatus>>,
+    status:           Arc<RwLock<CurrentConsensusStatus>>,
+    validator_change: Arc<Mutex<Publish<ValidatorSetChange>>>,
+    participation:    Arc<Mutex<VoteParticipation>>,
+}
+
+impl std::fmt::Debug for StatusAgent {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("StatusAgent")
+            .field("status", &self.status)
+            .finish()
+    }
 }
 
 impl StatusAgent {
     pub fn new(status: CurrentConsensusStatus) -> Self {
         Self {
-            status: Arc::new(RwLock::new(status)),
+            status:           Arc::new(RwLock::new(status)),
+            validator_change: Arc::new(Mutex::new(Publish::new())),
+            participation:    Arc::new(Mutex::new(VoteParticipation::default())),
         }
     }
 
+    /// Subscribe validator set changes, at most `buffer` events are kept for
+    /// a slow subscriber, later ones are missed.
+    pub fn subscribe_validator_change(&self, buffer: usize) -> Subscription<ValidatorSetChange> {
+        self.validator_change.lock().subscribe(buffer)
+    }
+
+    /// Vote participation rate of each validator by public key, over the
+    /// last `PARTICIPATION_WINDOW` committed heights.
+    pub fn participation(&self) -> Vec<(Bytes, f64)> {
+        self.participation.lock().rates()
+    }
+
     pub fn update_by_executed(&self, info: ExecutedInfo) {
         self.status.write().update_by_executed(info);
     }
@@ -34,14 +155,24 @@ impl StatusAgent {
         block_hash: Hash,
         current_proof: Proof,
     ) {
-        self.status
-            .write()
-            .update_by_committed(metadata, block, block_hash, current_proof)
+        let mut status = self.status.write();
+        let old_validators = status.validators.clone();
+        // The proof is signed by the validator set before this commit.
+        self.participation
+            .lock()
+            .record(&current_proof, &old_validators);
+        status.update_by_committed(metadata, block, block_hash, current_proof);
+        self.notify_validator_change(
+            status.latest_committed_height,
+            old_validators,
+            &status.validators,
+        );
     }
 
     // TODO(yejiayu): Is there a better way to write it?
     pub fn replace(&self, new_status: CurrentConsensusStatus) {
         let mut status = self.status.write();
+        let old_validators = status.validators.clone();
         status.cycles_price = new_status.cycles_price;
         status.cycles_limit = new_status.cycles_limit;
         status.latest_committed_height = new_status.latest_committed_height;
@@ -55,11 +186,36 @@ impl StatusAgent {
         status.current_proof = new_status.current_proof;
         status.validators = new_status.validators;
         status.consensus_interval = new_status.consensus_interval;
+        self.notify_validator_change(
+            status.latest_committed_height,
+            old_validators,
+            &status.validators,
+        );
     }
 
     pub fn to_inner(&self) -> CurrentConsensusStatus {
         self.status.read().clone()
     }
+
+    fn notify_validator_change(
+        &self,
+        height: u64,
+        old_validators: Vec<Validator>,
+        new_validators: &[Validator],
+    ) {
+        if old_validators == new_validators {
+            return;
+        }
+
+        let change = ValidatorSetChange {
+            height,
+            old_validators,
+            new_validators: new_validators.to_vec(),
+        };
+        if let Err(e) = self.validator_change.lock().try_send(change) {
+            log::warn!("[consensus]: notify validator set change {}", e);
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, Display, PartialEq, Eq)]
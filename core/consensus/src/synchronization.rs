@@ -14,7 +14,7 @@ use protocol::traits::{
 use protocol::types::{Block, Hash, Proof, Receipt, SignedTransaction};
 use protocol::ProtocolResult;
 
-use crate::engine::generate_new_crypto_map;
+use crate::engine::StopSignal;
 use crate::status::{ExecutedInfo, StatusAgent};
 use crate::util::{digest_signed_transactions, OverlordCrypto};
 use crate::ConsensusError;
@@ -37,6 +37,7 @@ pub struct OverlordSynchronization<Adapter: SynchronizationAdapter> {
     syncing: Mutex<()>,
 
     sync_txs_chunk_size: usize,
+    stop_signal:         StopSignal,
 }
 
 #[async_trait]
@@ -60,6 +61,14 @@ impl<Adapter: SynchronizationAdapter> Synchronization for OverlordSynchronizatio
             return Ok(());
         }
 
+        if let Some(height) = self.stop_signal.stopped_at() {
+            log::info!(
+                "[synchronization]: consensus stopped at {}, skip sync",
+                height
+            );
+            return Ok(());
+        }
+
         let current_height = self.status.to_inner().latest_committed_height;
 
         if remote_height <= current_height {
@@ -122,9 +131,16 @@ impl<Adapter: SynchronizationAdapter> OverlordSynchronization<Adapter> {
             syncing,
 
             sync_txs_chunk_size,
+            stop_signal: StopSignal::default(),
         }
     }
 
+    /// Stop syncing together with consensus, see
+    /// `OverlordConsensus::request_stop`.
+    pub fn set_stop_signal(&mut self, stop_signal: StopSignal) {
+        self.stop_signal = stop_signal;
+    }
+
     pub async fn polling_broadcast(&self) -> ProtocolResult<()> {
         loop {
             let current_height = self.status.to_inner().latest_committed_height;
@@ -290,6 +306,10 @@ impl<Adapter: SynchronizationAdapter> OverlordSynchronization<Adapter> {
             common_apm::metrics::consensus::ENGINE_SYNC_BLOCK_COUNTER.inc_by(1 as i64);
             common_apm::metrics::consensus::ENGINE_SYNC_BLOCK_HISTOGRAM
                 .observe(common_apm::metrics::duration_to_sec(inst.elapsed()));
+
+            if self.stop_signal.stopped_at().is_some() {
+                break;
+            }
         }
         Ok(())
     }
@@ -316,8 +336,7 @@ impl<Adapter: SynchronizationAdapter> OverlordSynchronization<Adapter> {
             block.header.proposer.clone(),
         )?;
 
-        self.crypto
-            .update(generate_new_crypto_map(metadata.clone())?);
+        self.crypto.update_validators(&metadata.verifier_list)?;
 
         self.adapter.set_args(
             ctx.clone(),
@@ -498,6 +517,15 @@ impl<Adapter: SynchronizationAdapter> OverlordSynchronization<Adapter> {
         let sync_status = sync_status_agent.to_inner();
 
         self.status.replace(sync_status.clone());
+
+        // Without a status of the next height overlord never starts it.
+        if self
+            .stop_signal
+            .stop_after(sync_status.latest_committed_height)
+        {
+            return Ok(());
+        }
+
         self.adapter.update_status(
             ctx,
             sync_status.latest_committed_height,
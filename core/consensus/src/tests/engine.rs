@@ -1,30 +1,42 @@
 use std::collections::HashMap;
 use std::convert::TryFrom;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex as StdMutex};
+use std::time::{Duration, Instant};
 
 use async_trait::async_trait;
 use futures::lock::Mutex;
-use overlord::types::{AggregatedSignature, Commit, Proof as OverlordProof};
-use overlord::Consensus;
+use overlord::types::{
+    AggregatedSignature, Commit, Node, Proof as OverlordProof, ViewChangeReason,
+};
+use overlord::{Consensus, DurationConfig};
+
+use cita_trie::MemoryDB;
 
 use common_crypto::BlsPrivateKey;
+use core_storage::adapter::memory::MemoryAdapter;
+use core_storage::ImplStorage;
 use protocol::fixed_codec::FixedCodec;
 use protocol::traits::{
-    CommonConsensusAdapter, ConsensusAdapter, Context, MessageTarget, MixedTxHashes, NodeInfo,
-    TrustFeedback,
+    CommonConsensusAdapter, ConsensusAdapter, Context, Executor, ExecutorConfig, ExecutorFactory,
+    Gossip, MemPool, MessageCodec, MessageTarget, MixedTxHashes, Network, NodeInfo, PeerTag,
+    PeerTrust, Priority, ProposalSelector, Rpc, SDKFactory, Service, ServiceMapping, ServiceSDK,
+    Storage, TrustFeedback,
 };
 use protocol::types::{
     Address, Block, BlockHeader, Hash, Hex, MerkleRoot, Metadata, Pill, Proof, Receipt,
-    SignedTransaction, Validator,
+    SignedTransaction, Validator, ValidatorExtend,
 };
 use protocol::{Bytes, ProtocolResult};
 
+use crate::adapter::OverlordConsensusAdapter;
 use crate::engine::ConsensusEngine;
 use crate::fixed_types::FixedPill;
 use crate::status::StatusAgent;
 use crate::util::OverlordCrypto;
 use crate::wal::{ConsensusWal, SignedTxsWAL};
+use crate::ConsensusType;
 
+use super::synchronization::mock_verifier_list;
 use super::*;
 
 static FULL_TXS_PATH: &str = "./free-space/engine/txs";
@@ -52,6 +64,262 @@ async fn test_repetitive_commit() {
     assert_eq!(status, engine.get_current_status());
 }
 
+#[tokio::test]
+async fn test_update_durations() {
+    let init_status = mock_current_status(1);
+    let engine = init_engine(init_status.clone());
+
+    let cfg = DurationConfig {
+        propose_ratio:   init_status.propose_ratio + 1,
+        prevote_ratio:   init_status.prevote_ratio + 2,
+        precommit_ratio: init_status.precommit_ratio + 3,
+        brake_ratio:     init_status.brake_ratio + 4,
+    };
+    engine.update_durations(Some(cfg.clone()));
+
+    let block = mock_block_from_status(&init_status);
+    let status = engine
+        .commit(Context::new(), 11, mock_commit(block.clone()))
+        .await
+        .unwrap();
+    assert_eq!(status.height, 12);
+    assert_eq!(status.timer_config, Some(cfg.clone()));
+
+    // Reset to ratios in metadata
+    let engine = init_engine(init_status);
+    engine.update_durations(Some(cfg));
+    engine.update_durations(None);
+    let status = engine
+        .commit(Context::new(), 11, mock_commit(block))
+        .await
+        .unwrap();
+    let current_status = engine.get_current_status();
+    assert_eq!(
+        status.timer_config,
+        Some(DurationConfig {
+            propose_ratio:   current_status.propose_ratio,
+            prevote_ratio:   current_status.prevote_ratio,
+            precommit_ratio: current_status.precommit_ratio,
+            brake_ratio:     current_status.brake_ratio,
+        })
+    );
+}
+
+#[tokio::test]
+async fn test_request_stop() {
+    let init_status = mock_current_status(1);
+    let engine = init_engine(init_status.clone());
+
+    let stopped = engine.request_stop();
+
+    let block = mock_block_from_status(&init_status);
+    let res = engine
+        .commit(Context::new(), 11, mock_commit(block.clone()))
+        .await;
+    assert!(res.is_err());
+    assert_eq!(stopped.await, Ok(11));
+    assert_eq!(engine.get_current_status().latest_committed_height, 11);
+
+    // No more height after stop
+    let res = engine.commit(Context::new(), 11, mock_commit(block)).await;
+    assert!(res.is_err());
+    assert_eq!(engine.request_stop().await, Ok(11));
+}
+
+#[tokio::test]
+async fn test_last_commit_status() {
+    let init_status = mock_current_status(1);
+    let engine = init_engine(init_status.clone());
+
+    let before = engine.last_commit_status();
+    assert_eq!(before.height, 10);
+
+    let block = mock_block_from_status(&init_status);
+    let res = engine.commit(Context::new(), 11, mock_commit(block)).await;
+    assert!(res.is_ok());
+
+    let after = engine.last_commit_status();
+    assert_eq!(after.height, 11);
+    assert!(after.committed_at >= before.committed_at);
+}
+
+#[tokio::test]
+async fn test_current_validators() {
+    let init_status = mock_current_status(1);
+    let adapter = Arc::new(MockConsensusAdapter::default());
+    let engine = init_engine_with_adapter(init_status.clone(), Arc::clone(&adapter));
+
+    let mut expected = init_status
+        .validators
+        .iter()
+        .map(|v| Node {
+            address:        v.pub_key.clone(),
+            propose_weight: v.propose_weight,
+            vote_weight:    v.vote_weight,
+        })
+        .collect::<Vec<_>>();
+    expected.sort();
+    assert_eq!(engine.current_validators(), expected);
+
+    // The committee of the next height comes from metadata.
+    let committee = mock_verifier_list();
+    *adapter.verifier_list.lock().unwrap() = committee.clone();
+    let block = mock_block_from_status(&init_status);
+    let res = engine.commit(Context::new(), 11, mock_commit(block)).await;
+    assert!(res.is_ok());
+
+    let mut expected = committee
+        .iter()
+        .map(|v| Node {
+            address:        v.pub_key.decode(),
+            propose_weight: v.propose_weight,
+            vote_weight:    v.vote_weight,
+        })
+        .collect::<Vec<_>>();
+    expected.sort();
+    let validators = engine.current_validators();
+    assert_eq!(validators.len(), committee.len());
+    assert_eq!(validators, expected);
+}
+
+#[test]
+fn test_report_view_change_on_timeout() {
+    let engine = init_engine(mock_current_status(1));
+    let stall_counter = || {
+        common_apm::metrics::consensus::CONSENSUS_VIEW_CHANGE_COUNTER_VEC
+            .with_label_values(&["no_prevote_qc"])
+            .get()
+    };
+
+    let before = stall_counter();
+    engine.set_last_consensus_type(ConsensusType::SignedProposal);
+    engine.report_view_change(
+        Context::new(),
+        11,
+        0,
+        ViewChangeReason::NoPrevoteQCFromNetwork,
+    );
+    engine.report_view_change(
+        Context::new(),
+        11,
+        1,
+        ViewChangeReason::NoPrevoteQCFromNetwork,
+    );
+
+    assert_eq!(stall_counter() - before, 2);
+}
+
+#[tokio::test]
+async fn test_min_block_interval() {
+    let interval = Duration::from_millis(300);
+
+    // Empty mempool, wait out the interval since last commit.
+    let engine = init_engine(mock_current_status(1));
+    engine.set_min_block_interval(interval);
+    let start = Instant::now();
+    let (pill, _) = engine.get_block(Context::new(), 11).await.unwrap();
+    assert!(pill.inner.block.ordered_tx_hashes.is_empty());
+    assert!(start.elapsed() > interval - Duration::from_millis(1));
+
+    // The interval has passed, propose at once.
+    let start = Instant::now();
+    engine.get_block(Context::new(), 11).await.unwrap();
+    assert!(start.elapsed() < interval);
+
+    // Pending transactions are never delayed.
+    let adapter = Arc::new(MockConsensusAdapter::default());
+    let tx_hash = mock_hash();
+    adapter.mempool_txs.lock().unwrap().push(tx_hash.clone());
+    let engine = init_engine_with_adapter(mock_current_status(1), Arc::clone(&adapter));
+    engine.set_min_block_interval(Duration::from_secs(10));
+    let start = Instant::now();
+    let (pill, _) = engine.get_block(Context::new(), 11).await.unwrap();
+    assert_eq!(pill.inner.block.ordered_tx_hashes, vec![tx_hash.clone()]);
+    assert!(start.elapsed() < interval);
+
+    // A transaction arriving while waiting is proposed without waiting out
+    // the rest of the interval.
+    adapter.mempool_txs.lock().unwrap().clear();
+    let engine = init_engine_with_adapter(mock_current_status(1), Arc::clone(&adapter));
+    engine.set_min_block_interval(Duration::from_secs(10));
+    let start = Instant::now();
+    tokio::spawn(async move {
+        tokio::time::delay_for(Duration::from_millis(100)).await;
+        adapter.mempool_txs.lock().unwrap().push(tx_hash);
+    });
+    let (pill, _) = engine.get_block(Context::new(), 11).await.unwrap();
+    assert_eq!(pill.inner.block.ordered_tx_hashes.len(), 1);
+    assert!(start.elapsed() < Duration::from_secs(1));
+}
+
+struct ReverseProposalSelector;
+
+impl ProposalSelector for ReverseProposalSelector {
+    fn select(&self, _ctx: Context, _height: u64, txs: MixedTxHashes) -> MixedTxHashes {
+        let (mut order_tx_hashes, propose_tx_hashes) = txs.clap();
+        order_tx_hashes.reverse();
+
+        MixedTxHashes {
+            order_tx_hashes,
+            propose_tx_hashes,
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_proposal_selector() {
+    let order_tx_hashes = (0..10).map(|_| mock_hash()).collect::<Vec<_>>();
+    let propose_tx_hashes = (0..5).map(|_| mock_hash()).collect::<Vec<_>>();
+    let txs = MixedTxHashes {
+        order_tx_hashes:   order_tx_hashes.clone(),
+        propose_tx_hashes: propose_tx_hashes.clone(),
+    };
+
+    // Default selector keeps the order packaged by mempool
+    let pill = propose_with_selector(None, txs.clone()).await;
+    assert_eq!(pill.block.ordered_tx_hashes, order_tx_hashes);
+    assert_eq!(pill.propose_hashes, propose_tx_hashes);
+
+    let pill = propose_with_selector(Some(Box::new(ReverseProposalSelector)), txs).await;
+    let mut reversed = order_tx_hashes;
+    reversed.reverse();
+    assert_eq!(pill.block.ordered_tx_hashes, reversed);
+    assert_eq!(pill.propose_hashes, propose_tx_hashes);
+}
+
+async fn propose_with_selector(
+    selector: Option<Box<dyn ProposalSelector>>,
+    txs: MixedTxHashes,
+) -> Pill {
+    let status_agent = StatusAgent::new(mock_current_status(1));
+    let mut adapter = OverlordConsensusAdapter::<MockExecutorFactory, _, _, _, _, _>::new(
+        Arc::new(MockNetwork),
+        Arc::new(MockMemPool { txs }),
+        Arc::new(ImplStorage::new(Arc::new(MemoryAdapter::new()))),
+        Arc::new(MemoryDB::new(false)),
+        Arc::new(MockServiceMapping),
+        status_agent.clone(),
+        Arc::new(init_crypto()),
+        1,
+    )
+    .unwrap();
+    if let Some(selector) = selector {
+        adapter.set_proposal_selector(selector);
+    }
+
+    let engine = ConsensusEngine::new(
+        status_agent,
+        mock_node_info(),
+        Arc::new(SignedTxsWAL::new(FULL_TXS_PATH)),
+        Arc::new(adapter),
+        Arc::new(init_crypto()),
+        Arc::new(Mutex::new(())),
+        Arc::new(ConsensusWal::new(FULL_CONSENSUS_PATH)),
+    );
+    let (pill, _) = engine.get_block(Context::new(), 11).await.unwrap();
+    pill.inner
+}
+
 fn mock_commit(block: Block) -> Commit<FixedPill> {
     let pill = Pill {
         block:          block.clone(),
@@ -73,11 +341,18 @@ fn mock_commit(block: Block) -> Commit<FixedPill> {
 }
 
 fn init_engine(init_status: CurrentConsensusStatus) -> ConsensusEngine<MockConsensusAdapter> {
+    init_engine_with_adapter(init_status, Arc::new(MockConsensusAdapter::default()))
+}
+
+fn init_engine_with_adapter(
+    init_status: CurrentConsensusStatus,
+    adapter: Arc<MockConsensusAdapter>,
+) -> ConsensusEngine<MockConsensusAdapter> {
     ConsensusEngine::new(
         StatusAgent::new(init_status),
         mock_node_info(),
         Arc::new(SignedTxsWAL::new(FULL_TXS_PATH)),
-        Arc::new(MockConsensusAdapter {}),
+        adapter,
         Arc::new(init_crypto()),
         Arc::new(Mutex::new(())),
         Arc::new(ConsensusWal::new(FULL_CONSENSUS_PATH)),
@@ -127,7 +402,11 @@ fn mock_metadata() -> Metadata {
     }
 }
 
-pub struct MockConsensusAdapter;
+#[derive(Default)]
+pub struct MockConsensusAdapter {
+    mempool_txs:   StdMutex<Vec<Hash>>,
+    verifier_list: StdMutex<Vec<ValidatorExtend>>,
+}
 
 #[async_trait]
 impl CommonConsensusAdapter for MockConsensusAdapter {
@@ -214,7 +493,9 @@ impl CommonConsensusAdapter for MockConsensusAdapter {
         _timestamp: u64,
         _proposer: Address,
     ) -> ProtocolResult<Metadata> {
-        Ok(mock_metadata())
+        let mut metadata = mock_metadata();
+        metadata.verifier_list = self.verifier_list.lock().unwrap().clone();
+        Ok(metadata)
     }
 
     fn report_bad(&self, _ctx: Context, _feedback: TrustFeedback) {}
@@ -263,7 +544,10 @@ impl ConsensusAdapter for MockConsensusAdapter {
         _cycles_limit: u64,
         _tx_num_limit: u64,
     ) -> ProtocolResult<MixedTxHashes> {
-        unimplemented!()
+        Ok(MixedTxHashes {
+            order_tx_hashes:   self.mempool_txs.lock().unwrap().clone(),
+            propose_tx_hashes: vec![],
+        })
     }
 
     async fn sync_txs(&self, _ctx: Context, _txs: Vec<Hash>) -> ProtocolResult<()> {
@@ -324,3 +608,172 @@ impl ConsensusAdapter for MockConsensusAdapter {
         Ok(())
     }
 }
+
+struct MockMemPool {
+    txs: MixedTxHashes,
+}
+
+#[async_trait]
+impl MemPool for MockMemPool {
+    async fn insert(&self, _ctx: Context, _tx: SignedTransaction) -> ProtocolResult<()> {
+        unimplemented!()
+    }
+
+    async fn package(
+        &self,
+        _ctx: Context,
+        _cycles_limit: u64,
+        _tx_num_limit: u64,
+    ) -> ProtocolResult<MixedTxHashes> {
+        Ok(self.txs.clone())
+    }
+
+    async fn flush(&self, _ctx: Context, _tx_hashes: &[Hash]) -> ProtocolResult<()> {
+        unimplemented!()
+    }
+
+    async fn remove(&self, _ctx: Context, _tx_hash: Hash) -> ProtocolResult<bool> {
+        unimplemented!()
+    }
+
+    async fn get_full_txs(
+        &self,
+        _ctx: Context,
+        _height: Option<u64>,
+        _tx_hashes: &[Hash],
+    ) -> ProtocolResult<Vec<SignedTransaction>> {
+        Ok(vec![])
+    }
+
+    async fn ensure_order_txs(
+        &self,
+        _ctx: Context,
+        _height: Option<u64>,
+        _order_tx_hashes: &[Hash],
+    ) -> ProtocolResult<()> {
+        unimplemented!()
+    }
+
+    async fn sync_propose_txs(
+        &self,
+        _ctx: Context,
+        _propose_tx_hashes: Vec<Hash>,
+    ) -> ProtocolResult<()> {
+        unimplemented!()
+    }
+
+    fn set_args(&self, _timeout_gap: u64, _cycles_limit: u64, _max_tx_size: u64) {}
+}
+
+struct MockNetwork;
+
+#[async_trait]
+impl Gossip for MockNetwork {
+    async fn broadcast<M>(
+        &self,
+        _cx: Context,
+        _end: &str,
+        _msg: M,
+        _p: Priority,
+    ) -> ProtocolResult<()>
+    where
+        M: MessageCodec,
+    {
+        unimplemented!()
+    }
+
+    async fn multicast<'a, M, P>(
+        &self,
+        _cx: Context,
+        _end: &str,
+        _peer_ids: P,
+        _msg: M,
+        _p: Priority,
+    ) -> ProtocolResult<()>
+    where
+        M: MessageCodec,
+        P: AsRef<[Bytes]> + Send + 'a,
+    {
+        unimplemented!()
+    }
+}
+
+#[async_trait]
+impl Rpc for MockNetwork {
+    async fn call<M, R>(
+        &self,
+        _ctx: Context,
+        _end: &str,
+        _msg: M,
+        _pri: Priority,
+    ) -> ProtocolResult<R>
+    where
+        M: MessageCodec,
+        R: MessageCodec,
+    {
+        unimplemented!()
+    }
+
+    async fn response<M>(
+        &self,
+        _cx: Context,
+        _end: &str,
+        _ret: ProtocolResult<M>,
+        _p: Priority,
+    ) -> ProtocolResult<()>
+    where
+        M: MessageCodec,
+    {
+        unimplemented!()
+    }
+}
+
+impl Network for MockNetwork {
+    fn tag(&self, _ctx: Context, _peer_id: Bytes, _tag: PeerTag) -> ProtocolResult<()> {
+        unimplemented!()
+    }
+
+    fn untag(&self, _ctx: Context, _peer_id: Bytes, _tag: &PeerTag) -> ProtocolResult<()> {
+        unimplemented!()
+    }
+
+    fn tag_consensus(&self, _ctx: Context, _peer_ids: Vec<Bytes>) -> ProtocolResult<()> {
+        unimplemented!()
+    }
+}
+
+impl PeerTrust for MockNetwork {
+    fn report(&self, _ctx: Context, _feedback: TrustFeedback) {}
+}
+
+struct MockExecutorFactory;
+
+impl<DB: cita_trie::DB, S: Storage, Mapping: ServiceMapping> ExecutorFactory<DB, S, Mapping>
+    for MockExecutorFactory
+{
+    fn from_root(
+        _root: MerkleRoot,
+        _db: Arc<DB>,
+        _storage: Arc<S>,
+        _mapping: Arc<Mapping>,
+        _config: &ExecutorConfig,
+    ) -> ProtocolResult<Box<dyn Executor>> {
+        unimplemented!()
+    }
+}
+
+struct MockServiceMapping;
+
+impl ServiceMapping for MockServiceMapping {
+    fn get_service<SDK: 'static + ServiceSDK, Factory: SDKFactory<SDK>>(
+        &self,
+        _name: &str,
+        _factory: &Factory,
+    ) -> ProtocolResult<Box<dyn Service>> {
+        unimplemented!()
+    }
+
+    fn list_service_name(&self) -> Vec<String> {
+        vec![]
+    }
+}
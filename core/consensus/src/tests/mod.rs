@@ -1,6 +1,7 @@
 mod engine;
 mod status;
 mod synchronization;
+mod util;
 
 use rand::random;
 
@@ -1,10 +1,15 @@
 use creep::Context;
+use futures::executor::block_on;
+use futures::StreamExt;
 use rand::random;
 
 use protocol::fixed_codec::FixedCodec;
-use protocol::types::{Hash, Hex, Metadata, ValidatorExtend};
+use protocol::types::{Hash, Hex, Metadata, Validator, ValidatorExtend};
+use protocol::Bytes;
 
-use crate::status::{CurrentConsensusStatus, ExecutedInfo};
+use crate::status::{
+    CurrentConsensusStatus, ExecutedInfo, StatusAgent, VoteParticipation, PARTICIPATION_WINDOW,
+};
 
 use super::*;
 
@@ -65,6 +70,99 @@ fn test_update_by_committed() {
     );
 }
 
+#[test]
+fn test_validator_set_change_event() {
+    let metadata = mock_metadata();
+    let new_validators = metadata
+        .verifier_list
+        .iter()
+        .map(|v| Validator {
+            pub_key:        v.pub_key.decode(),
+            propose_weight: v.propose_weight,
+            vote_weight:    v.vote_weight,
+        })
+        .collect::<Vec<_>>();
+    let removed = mock_validator();
+
+    let mut status = mock_current_status(2);
+    status.validators = new_validators[..3].to_vec();
+    status.validators.push(removed.clone());
+    let block = mock_block_from_status(&status);
+    let block_hash = Hash::digest(block.encode_fixed().unwrap());
+
+    let agent = StatusAgent::new(status.clone());
+    let mut rx = agent.subscribe_validator_change(1);
+    agent.update_by_committed(
+        metadata,
+        block.clone(),
+        block_hash,
+        block.header.proof.clone(),
+    );
+
+    let change = block_on(rx.next()).unwrap();
+    assert_eq!(change.height, block.header.height);
+    assert_eq!(change.old_validators, status.validators);
+    assert_eq!(change.new_validators, new_validators);
+    assert_eq!(change.added(), vec![new_validators[3].clone()]);
+    assert_eq!(change.removed(), vec![removed]);
+
+    // Same validator set, no event.
+    agent.replace(agent.to_inner());
+    drop(agent);
+    assert!(block_on(rx.next()).is_none());
+}
+
+#[test]
+fn test_vote_participation() {
+    let validators = (0..4u8)
+        .map(|i| Validator {
+            pub_key:        Bytes::from(vec![i]),
+            propose_weight: 1,
+            vote_weight:    1,
+        })
+        .collect::<Vec<_>>();
+    let mut participation = VoteParticipation::default();
+    let mut height = 0;
+    let mut commit = |participation: &mut VoteParticipation, bitmap: u8| {
+        height += 1;
+        let mut proof = mock_proof(height);
+        proof.bitmap = Bytes::from(vec![bitmap]);
+        participation.record(&proof, &validators);
+    };
+    let rate_of = |participation: &VoteParticipation, i: u8| {
+        participation
+            .rates()
+            .into_iter()
+            .find(|(pub_key, _)| pub_key == &Bytes::from(vec![i]))
+            .map(|(_, rate)| rate)
+            .unwrap()
+    };
+
+    // All vote, then the last validator abstains as many rounds.
+    for _ in 0..10 {
+        commit(&mut participation, 0b1111_0000);
+    }
+    assert_eq!(rate_of(&participation, 3), 1.0);
+    for _ in 0..10 {
+        commit(&mut participation, 0b1110_0000);
+    }
+    assert_eq!(rate_of(&participation, 3), 0.5);
+    assert_eq!(rate_of(&participation, 0), 1.0);
+
+    // A stale height changes nothing.
+    let mut proof = mock_proof(1);
+    proof.bitmap = Bytes::from(vec![0]);
+    participation.record(&proof, &validators);
+    assert_eq!(rate_of(&participation, 0), 1.0);
+
+    // Votes out of the window are forgotten.
+    for _ in 0..PARTICIPATION_WINDOW {
+        commit(&mut participation, 0b1110_0000);
+    }
+    assert_eq!(rate_of(&participation, 3), 0.0);
+    assert_eq!(participation.rates().len(), 4);
+}
+
 fn check_metadata(status: &CurrentConsensusStatus, metadata: &Metadata) {
     assert_eq!(status.consensus_interval, metadata.interval);
     assert_eq!(status.propose_ratio, metadata.propose_ratio);
@@ -26,6 +26,7 @@ use protocol::types::{
 };
 use protocol::ProtocolResult;
 
+use crate::engine::StopSignal;
 use crate::status::{CurrentConsensusStatus, StatusAgent};
 use crate::synchronization::{OverlordSynchronization, RichBlock};
 use crate::util::{convert_hex_to_bls_pubkeys, digest_signed_transactions, OverlordCrypto};
@@ -39,64 +40,8 @@ const PUB_KEY_STR: &str = "02ef0cb0d7bc6c18b4bea1f5908d9106522b35ab3c399369605d4
 #[test]
 fn sync_gap_test() {
     for gap in [1, 2, 3, 4].iter() {
-        let key_tool = get_mock_key_tool();
-
         let max_height = 10 * *gap;
-
-        let list_rich_block = mock_chained_rich_block(max_height, *gap, &key_tool);
-
-        let remote_blocks = gen_remote_block_hashmap(list_rich_block.0.clone());
-        let remote_proofs = gen_remote_proof_hashmap(list_rich_block.1.clone());
-        let genesis_block = remote_blocks.read().get(&0).unwrap().clone();
-
-        let local_blocks = Arc::new(RwLock::new(HashMap::new()));
-        local_blocks
-            .write()
-            .insert(genesis_block.header.height, genesis_block.clone());
-
-        let local_transactions = Arc::new(RwLock::new(HashMap::new()));
-        let remote_transactions = gen_remote_tx_hashmap(list_rich_block.0.clone());
-
-        let adapter = Arc::new(MockCommonConsensusAdapter::new(
-            0,
-            local_blocks,
-            remote_blocks,
-            remote_proofs,
-            local_transactions,
-            remote_transactions,
-            Arc::clone(&key_tool.overlord_crypto),
-        ));
-        let block_hash = Hash::digest(genesis_block.header.encode_fixed().unwrap());
-        let status = CurrentConsensusStatus {
-            cycles_price:                1,
-            cycles_limit:                300_000_000,
-            latest_committed_height:     genesis_block.header.height,
-            exec_height:                 genesis_block.header.exec_height,
-            current_hash:                block_hash,
-            list_confirm_root:           vec![],
-            latest_committed_state_root: genesis_block.header.state_root.clone(),
-            list_state_root:             vec![],
-            list_receipt_root:           vec![],
-            list_cycles_used:            vec![],
-            current_proof:               genesis_block.header.proof,
-            validators:                  genesis_block.header.validators,
-            consensus_interval:          3000,
-            propose_ratio:               15,
-            prevote_ratio:               10,
-            precommit_ratio:             10,
-            brake_ratio:                 3,
-            tx_num_limit:                20000,
-            max_tx_size:                 1_073_741_824,
-        };
-        let status_agent = StatusAgent::new(status);
-        let lock = Arc::new(Mutex::new(()));
-        let sync = OverlordSynchronization::<_>::new(
-            5000,
-            Arc::clone(&adapter),
-            status_agent.clone(),
-            Arc::new(mock_crypto()),
-            lock,
-        );
+        let (sync, status_agent, adapter) = mock_sync(max_height, *gap);
 
         // simulate to get a block
         block_on(sync.receive_remote_block(Context::new(), max_height / 2)).unwrap();
@@ -121,6 +66,105 @@ fn sync_gap_test() {
     }
 }
 
+#[test]
+fn sync_after_stop_test() {
+    // Consensus stopped at genesis, peers moving on don't start a new height
+    let (mut sync, status_agent, adapter) = mock_sync(10, 1);
+    let stop_signal = StopSignal::default();
+    sync.set_stop_signal(stop_signal.clone());
+
+    let stopped = stop_signal.request();
+    assert!(stop_signal.stop_after(0));
+    assert_eq!(block_on(stopped), Ok(0));
+
+    block_on(sync.receive_remote_block(Context::new(), 10)).unwrap();
+    assert_eq!(status_agent.to_inner().latest_committed_height, 0);
+    assert!(adapter.updated_heights.read().is_empty());
+
+    // Stop requested while syncing, stop once a height is synced
+    let (mut sync, status_agent, adapter) = mock_sync(10, 1);
+    let stop_signal = StopSignal::default();
+    sync.set_stop_signal(stop_signal.clone());
+
+    let stopped = stop_signal.request();
+    block_on(sync.receive_remote_block(Context::new(), 5)).unwrap();
+    assert_eq!(block_on(stopped), Ok(1));
+    assert_eq!(status_agent.to_inner().latest_committed_height, 1);
+    assert!(adapter.updated_heights.read().is_empty());
+
+    block_on(sync.receive_remote_block(Context::new(), 10)).unwrap();
+    assert_eq!(status_agent.to_inner().latest_committed_height, 1);
+    assert!(adapter.updated_heights.read().is_empty());
+}
+
+fn mock_sync(
+    max_height: u64,
+    gap: u64,
+) -> (
+    OverlordSynchronization<MockCommonConsensusAdapter>,
+    StatusAgent,
+    Arc<MockCommonConsensusAdapter>,
+) {
+    let key_tool = get_mock_key_tool();
+
+    let list_rich_block = mock_chained_rich_block(max_height, gap, &key_tool);
+
+    let remote_blocks = gen_remote_block_hashmap(list_rich_block.0.clone());
+    let remote_proofs = gen_remote_proof_hashmap(list_rich_block.1.clone());
+    let genesis_block = remote_blocks.read().get(&0).unwrap().clone();
+
+    let local_blocks = Arc::new(RwLock::new(HashMap::new()));
+    local_blocks
+        .write()
+        .insert(genesis_block.header.height, genesis_block.clone());
+
+    let local_transactions = Arc::new(RwLock::new(HashMap::new()));
+    let remote_transactions = gen_remote_tx_hashmap(list_rich_block.0.clone());
+
+    let adapter = Arc::new(MockCommonConsensusAdapter::new(
+        0,
+        local_blocks,
+        remote_blocks,
+        remote_proofs,
+        local_transactions,
+        remote_transactions,
+        Arc::clone(&key_tool.overlord_crypto),
+    ));
+    let block_hash = Hash::digest(genesis_block.header.encode_fixed().unwrap());
+    let status = CurrentConsensusStatus {
+        cycles_price:                1,
+        cycles_limit:                300_000_000,
+        latest_committed_height:     genesis_block.header.height,
+        exec_height:                 genesis_block.header.exec_height,
+        current_hash:                block_hash,
+        list_confirm_root:           vec![],
+        latest_committed_state_root: genesis_block.header.state_root.clone(),
+        list_state_root:             vec![],
+        list_receipt_root:           vec![],
+        list_cycles_used:            vec![],
+        current_proof:               genesis_block.header.proof,
+        validators:                  genesis_block.header.validators,
+        consensus_interval:          3000,
+        propose_ratio:               15,
+        prevote_ratio:               10,
+        precommit_ratio:             10,
+        brake_ratio:                 3,
+        tx_num_limit:                20000,
+        max_tx_size:                 1_073_741_824,
+    };
+    let status_agent = StatusAgent::new(status);
+    let lock = Arc::new(Mutex::new(()));
+    let sync = OverlordSynchronization::<_>::new(
+        5000,
+        Arc::clone(&adapter),
+        status_agent.clone(),
+        Arc::new(mock_crypto()),
+        lock,
+    );
+
+    (sync, status_agent, adapter)
+}
+
 pub type SafeHashMap<K, V> = Arc<RwLock<HashMap<K, V>>>;
 
 pub struct MockCommonConsensusAdapter {
@@ -131,6 +175,7 @@ pub struct MockCommonConsensusAdapter {
     local_transactions:  SafeHashMap<Hash, SignedTransaction>,
     remote_transactions: SafeHashMap<Hash, SignedTransaction>,
     crypto:              Arc<OverlordCrypto>,
+    updated_heights:     RwLock<Vec<u64>>,
 }
 
 impl MockCommonConsensusAdapter {
@@ -151,6 +196,7 @@ impl MockCommonConsensusAdapter {
             local_transactions,
             remote_transactions,
             crypto,
+            updated_heights: RwLock::new(Vec::new()),
         }
     }
 }
@@ -160,7 +206,7 @@ impl SynchronizationAdapter for MockCommonConsensusAdapter {
     fn update_status(
         &self,
         _: Context,
-        _: u64,
+        height: u64,
         _: u64,
         _: u64,
         _: u64,
@@ -168,6 +214,7 @@ impl SynchronizationAdapter for MockCommonConsensusAdapter {
         _: u64,
         _: Vec<Validator>,
     ) -> ProtocolResult<()> {
+        self.updated_heights.write().push(height);
         Ok(())
     }
 
@@ -1077,7 +1124,7 @@ fn get_mock_public_keys_and_common_ref() -> (HashMap<Bytes, BlsPublicKey>, BlsCo
     (bls_pub_keys, common_ref)
 }
 
-fn mock_verifier_list() -> Vec<ValidatorExtend> {
+pub(super) fn mock_verifier_list() -> Vec<ValidatorExtend> {
     vec![
         ValidatorExtend {
             bls_pub_key: Hex::from_string("0x04102947214862a503c73904deb5818298a186d68c7907bb609583192a7de6331493835e5b8281f4d9ee705537c0e765580e06f86ddce5867812fceb42eecefd209f0eddd0389d6b7b0100f00fb119ef9ab23826c6ea09aadcc76fa6cea6a32724".to_owned()).unwrap(),
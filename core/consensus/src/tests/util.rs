@@ -0,0 +1,208 @@
+use std::collections::HashMap;
+use std::convert::TryFrom;
+
+use overlord::types::{Vote, VoteType};
+use overlord::Crypto;
+
+use common_crypto::{
+    BlsPrivateKey, BlsPublicKey, BlsSignature, HashValue, PrivateKey, PublicKey, Signature,
+    ToBlsPublicKey,
+};
+use protocol::fixed_codec::FixedCodec;
+use protocol::types::{Address, Block, Hash, Hex, Proof, ValidatorExtend};
+use protocol::Bytes;
+
+use crate::util::{verify_block_proof, OverlordCrypto};
+use crate::{BlockProofField, ConsensusError};
+
+use super::*;
+
+const PRIVATE_KEYS: [&str; 4] = [
+    "000000000000000000000000000000001abd6ffdb44427d9e1fcb6f84e7fe7d98f2b5b205b30a94992ec24d94bb0c970",
+    "00000000000000000000000000000000320b11d7c1ae66fdad1b4a75221244ae2d84903d3548c581d7d30dc135aac817",
+    "000000000000000000000000000000006a41e900d0426e615ca9d9393e6792baf9bda4398d5d407e59f77cb6c6f393cc",
+    "00000000000000000000000000000000125d81e0eb0a9c3746d868bf3b4f07760fdd430daded41d92f53b4e484ef3415",
+];
+
+struct Committee {
+    crypto:       OverlordCrypto,
+    private_keys: Vec<BlsPrivateKey>,
+    validators:   Vec<ValidatorExtend>,
+}
+
+fn mock_committee() -> Committee {
+    let private_keys = PRIVATE_KEYS
+        .iter()
+        .map(|key| BlsPrivateKey::try_from(hex::decode(key).unwrap().as_ref()).unwrap())
+        .collect::<Vec<_>>();
+
+    let mut validators = Vec::new();
+    for (i, key) in private_keys.iter().enumerate() {
+        let pub_key = Bytes::from(vec![i as u8 + 1; 33]);
+        let bls_pub_key: BlsPublicKey = key.pub_key(&"muta".into());
+        validators.push(ValidatorExtend {
+            bls_pub_key:    Hex::from_string(format!("0x{}", hex::encode(bls_pub_key.to_bytes())))
+                .unwrap(),
+            pub_key:        Hex::from_string(format!("0x{}", hex::encode(&pub_key))).unwrap(),
+            address:        Address::from_hash(Hash::digest(pub_key)).unwrap(),
+            propose_weight: 1,
+            vote_weight:    1,
+        });
+    }
+
+    // Keys of the committee come from the validator set only, as if it's
+    // a validator set of an earlier height.
+    Committee {
+        crypto: OverlordCrypto::new(private_keys[0].clone(), HashMap::new(), "muta".into()),
+        private_keys,
+        validators,
+    }
+}
+
+// Validators are sorted by address, signers are given by their index.
+fn mock_signed_proof(committee: &Committee, block: &Block, signers: &[usize]) -> Proof {
+    let block_hash = Hash::digest(block.header.encode_fixed().unwrap());
+    let vote = Vote {
+        height:     block.header.height,
+        round:      0,
+        vote_type:  VoteType::Precommit,
+        block_hash: block_hash.as_bytes(),
+    };
+    let vote_hash = committee.crypto.hash(Bytes::from(rlp::encode(&vote)));
+    let hash = HashValue::try_from(vote_hash.as_ref()).unwrap();
+
+    let mut bitmap = 0u8;
+    let mut sigs_pubkeys = Vec::new();
+    for &i in signers.iter() {
+        bitmap |= 0x80 >> i;
+        let key = &committee.private_keys[i];
+        let pub_key: BlsPublicKey = key.pub_key(&"muta".into());
+        sigs_pubkeys.push((key.sign_message(&hash), pub_key));
+    }
+
+    Proof {
+        height: block.header.height,
+        round: 0,
+        block_hash,
+        signature: BlsSignature::combine(sigs_pubkeys).to_bytes(),
+        bitmap: Bytes::from(vec![bitmap]),
+    }
+}
+
+#[test]
+fn test_verify_block_proof() {
+    let committee = mock_committee();
+    let block = mock_block_from_status(&mock_current_status(1));
+
+    let proof = mock_signed_proof(&committee, &block, &[0, 1, 2]);
+    assert!(verify_block_proof(
+        &committee.crypto,
+        &block.header,
+        &proof,
+        &committee.validators
+    )
+    .is_ok());
+
+    let proof = mock_signed_proof(&committee, &block, &[0, 1]);
+    match verify_block_proof(
+        &committee.crypto,
+        &block.header,
+        &proof,
+        &committee.validators,
+    ) {
+        Err(ConsensusError::VerifyProof(_, BlockProofField::Weight)) => (),
+        _ => panic!("should not have enough weight"),
+    }
+
+    let mut other_block = block.clone();
+    other_block.header.timestamp = block.header.timestamp.wrapping_add(1);
+    let proof = mock_signed_proof(&committee, &other_block, &[0, 1, 2]);
+    match verify_block_proof(
+        &committee.crypto,
+        &block.header,
+        &proof,
+        &committee.validators,
+    ) {
+        Err(ConsensusError::VerifyProof(_, BlockProofField::HashMismatch)) => (),
+        _ => panic!("should be hash mismatch"),
+    }
+}
+
+#[test]
+fn test_verify_block_proof_tampered_signature() {
+    let committee = mock_committee();
+    let block = mock_block_from_status(&mock_current_status(1));
+
+    // Signed by validators 0, 1, 3 but claimed to be 0, 1, 2
+    let mut proof = mock_signed_proof(&committee, &block, &[0, 1, 2]);
+    proof.signature = mock_signed_proof(&committee, &block, &[0, 1, 3]).signature;
+
+    match verify_block_proof(
+        &committee.crypto,
+        &block.header,
+        &proof,
+        &committee.validators,
+    ) {
+        Err(ConsensusError::VerifyProof(_, BlockProofField::Signature)) => (),
+        _ => panic!("should be invalid signature"),
+    }
+}
+
+#[test]
+fn test_verify_block_proof_with_cached_keys() {
+    let committee = mock_committee();
+    let block = mock_block_from_status(&mock_current_status(1));
+    committee
+        .crypto
+        .update_validators(&committee.validators)
+        .unwrap();
+
+    // The same validator set in another order hits the cache as well.
+    let mut validators = committee.validators.clone();
+    validators.reverse();
+
+    let proof = mock_signed_proof(&committee, &block, &[0, 1, 2]);
+    assert!(verify_block_proof(&committee.crypto, &block.header, &proof, &validators).is_ok());
+
+    let mut proof = mock_signed_proof(&committee, &block, &[0, 1, 2]);
+    proof.signature = mock_signed_proof(&committee, &block, &[0, 1, 3]).signature;
+    match verify_block_proof(&committee.crypto, &block.header, &proof, &validators) {
+        Err(ConsensusError::VerifyProof(_, BlockProofField::Signature)) => (),
+        _ => panic!("should be invalid signature"),
+    }
+}
+
+#[test]
+fn test_verify_aggregated_signature_with_cached_keys() {
+    let committee = mock_committee();
+    let block = mock_block_from_status(&mock_current_status(1));
+    committee
+        .crypto
+        .update_validators(&committee.validators)
+        .unwrap();
+
+    let proof = mock_signed_proof(&committee, &block, &[1, 3]);
+    let vote = Vote {
+        height:     proof.height,
+        round:      proof.round,
+        vote_type:  VoteType::Precommit,
+        block_hash: proof.block_hash.as_bytes(),
+    };
+    let vote_hash = committee.crypto.hash(Bytes::from(rlp::encode(&vote)));
+    let voters = [1, 3]
+        .iter()
+        .map(|&i| committee.validators[i].pub_key.decode())
+        .collect::<Vec<_>>();
+
+    assert!(committee
+        .crypto
+        .verify_aggregated_signature(proof.signature.clone(), vote_hash.clone(), voters.clone())
+        .is_ok());
+
+    let mut unknown_voters = voters;
+    unknown_voters.push(Bytes::from(vec![0u8; 33]));
+    assert!(committee
+        .crypto
+        .verify_aggregated_signature(proof.signature, vote_hash, unknown_voters)
+        .is_err());
+}
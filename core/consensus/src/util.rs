@@ -5,17 +5,20 @@ use std::time::{SystemTime, UNIX_EPOCH};
 
 use bytes::buf::BufMut;
 use bytes::BytesMut;
-use overlord::Crypto;
+use overlord::types::{Node, Vote, VoteType};
+use overlord::{extract_voters, Crypto};
 use parking_lot::RwLock;
 
-use crate::ConsensusError;
+use crate::{BlockProofField, ConsensusError};
 use common_crypto::{
     BlsCommonReference, BlsPrivateKey, BlsPublicKey, BlsSignature, BlsSignatureVerify, HashValue,
-    PrivateKey, Signature,
+    PrivateKey, Signature, ValidatorKeySet,
 };
 use protocol::fixed_codec::FixedCodec;
 use protocol::traits::Context;
-use protocol::types::{Address, Hash, Hex, MerkleRoot, SignedTransaction};
+use protocol::types::{
+    Address, BlockHeader, Hash, Hex, MerkleRoot, Proof, SignedTransaction, ValidatorExtend,
+};
 use protocol::{Bytes, ProtocolError, ProtocolResult};
 
 pub fn time_now() -> u64 {
@@ -26,9 +29,52 @@ pub fn time_now() -> u64 {
 }
 
 pub struct OverlordCrypto {
-    private_key: BlsPrivateKey,
-    addr_pubkey: RwLock<HashMap<Bytes, BlsPublicKey>>,
-    common_ref:  BlsCommonReference,
+    private_key:    BlsPrivateKey,
+    addr_pubkey:    RwLock<HashMap<Bytes, BlsPublicKey>>,
+    validator_keys: RwLock<ValidatorKeys>,
+    common_ref:     BlsCommonReference,
+}
+
+// Aggregated public keys of voter subsets of the current validator set.
+struct ValidatorKeys {
+    // Sorted the same way as overlord sorts the authority list, so that a
+    // bitmap of voters selects keys of `key_set` directly.
+    addrs:       Vec<Bytes>,
+    // Tells whether a validator set passed in is the current one, `None`
+    // until the set is given by `update_validators`.
+    fingerprint: Option<Hash>,
+    key_set:     ValidatorKeySet,
+}
+
+impl ValidatorKeys {
+    fn new(addr_pubkey: &HashMap<Bytes, BlsPublicKey>) -> Self {
+        let mut keys = ValidatorKeys {
+            addrs:       Vec::new(),
+            fingerprint: None,
+            key_set:     ValidatorKeySet::default(),
+        };
+        keys.update(addr_pubkey, None);
+        keys
+    }
+
+    fn update(&mut self, addr_pubkey: &HashMap<Bytes, BlsPublicKey>, fingerprint: Option<Hash>) {
+        let mut addrs = addr_pubkey.keys().cloned().collect::<Vec<_>>();
+        addrs.sort();
+        let pub_keys = addrs.iter().map(|addr| addr_pubkey[addr].clone()).collect();
+
+        self.key_set.update(pub_keys);
+        self.addrs = addrs;
+        self.fingerprint = fingerprint;
+    }
+
+    fn bitmap(&self, voters: &[Bytes]) -> Option<Vec<u8>> {
+        let mut bitmap = vec![0u8; (self.addrs.len() + 7) / 8];
+        for voter in voters.iter() {
+            let index = self.addrs.binary_search(voter).ok()?;
+            bitmap[index / 8] |= 0x80 >> (index % 8);
+        }
+        Some(bitmap)
+    }
 }
 
 impl Crypto for OverlordCrypto {
@@ -105,16 +151,16 @@ impl Crypto for OverlordCrypto {
         hash: Bytes,
         voters: Vec<Bytes>,
     ) -> Result<(), Box<dyn Error + Send>> {
-        let map = self.addr_pubkey.read();
-        let mut pub_keys = Vec::new();
-        for addr in voters.iter() {
-            let pub_key = map.get(addr).ok_or_else(|| {
-                ProtocolError::from(ConsensusError::Other("lose public key".to_string()))
-            })?;
-            pub_keys.push(pub_key.clone());
-        }
-
-        self.inner_verify_aggregated_signature(hash, pub_keys, aggregated_signature)?;
+        let aggregate_key = {
+            let keys = self.validator_keys.read();
+            keys.bitmap(&voters)
+                .and_then(|bitmap| keys.key_set.aggregate(&bitmap))
+                .ok_or_else(|| {
+                    ProtocolError::from(ConsensusError::Other("lose public key".to_string()))
+                })?
+        };
+
+        self.verify_with_aggregate_key(hash, &aggregate_key, aggregated_signature)?;
         Ok(())
     }
 }
@@ -126,6 +172,7 @@ impl OverlordCrypto {
         common_ref: BlsCommonReference,
     ) -> Self {
         OverlordCrypto {
+            validator_keys: RwLock::new(ValidatorKeys::new(&pubkey_to_bls_pubkey)),
             addr_pubkey: RwLock::new(pubkey_to_bls_pubkey),
             private_key,
             common_ref,
@@ -135,7 +182,44 @@ impl OverlordCrypto {
     pub fn update(&self, new_addr_pubkey: HashMap<Bytes, BlsPublicKey>) {
         let mut map = self.addr_pubkey.write();
 
+        self.validator_keys.write().update(&new_addr_pubkey, None);
+        *map = new_addr_pubkey;
+    }
+
+    /// Replace the validator set. Cached aggregated keys are only dropped if
+    /// the set changes.
+    pub fn update_validators(&self, validators: &[ValidatorExtend]) -> ProtocolResult<()> {
+        let fingerprint = validators_fingerprint(validators);
+        if self.validator_keys.read().fingerprint.as_ref() == Some(&fingerprint) {
+            return Ok(());
+        }
+
+        let mut new_addr_pubkey = HashMap::new();
+        for validator in validators.iter() {
+            let pub_key = hex_to_bls_pubkey(validator.bls_pub_key.clone())?;
+            new_addr_pubkey.insert(validator.pub_key.decode(), pub_key);
+        }
+
+        let mut map = self.addr_pubkey.write();
+        self.validator_keys
+            .write()
+            .update(&new_addr_pubkey, Some(fingerprint));
         *map = new_addr_pubkey;
+        Ok(())
+    }
+
+    // Aggregated key of voters in `bitmap` from the cache, `None` if
+    // `validators` isn't the current validator set.
+    fn cached_aggregate_key(
+        &self,
+        validators: &[ValidatorExtend],
+        bitmap: &[u8],
+    ) -> Option<BlsPublicKey> {
+        let keys = self.validator_keys.read();
+        if keys.fingerprint.as_ref()? != &validators_fingerprint(validators) {
+            return None;
+        }
+        keys.key_set.aggregate(bitmap)
     }
 
     pub fn inner_verify_aggregated_signature(
@@ -145,6 +229,15 @@ impl OverlordCrypto {
         signature: Bytes,
     ) -> ProtocolResult<()> {
         let aggregate_key = BlsPublicKey::aggregate(pub_keys);
+        self.verify_with_aggregate_key(hash, &aggregate_key, signature)
+    }
+
+    fn verify_with_aggregate_key(
+        &self,
+        hash: Bytes,
+        aggregate_key: &BlsPublicKey,
+        signature: Bytes,
+    ) -> ProtocolResult<()> {
         let aggregated_signature = BlsSignature::try_from(signature.as_ref())
             .map_err(|e| ProtocolError::from(ConsensusError::CryptoErr(Box::new(e))))?;
         let hash = HashValue::try_from(hash.as_ref()).map_err(|_| {
@@ -154,7 +247,7 @@ impl OverlordCrypto {
         })?;
 
         aggregated_signature
-            .verify(&hash, &aggregate_key, &self.common_ref)
+            .verify(&hash, aggregate_key, &self.common_ref)
             .map_err(|e| ProtocolError::from(ConsensusError::CryptoErr(Box::new(e))))?;
         Ok(())
     }
@@ -197,12 +290,168 @@ pub fn digest_signed_transactions(signed_txs: &[SignedTransaction]) -> ProtocolR
     Ok(Hash::digest(list_bytes.freeze()))
 }
 
+/// Verify that `proof` commits `header` with precommit votes of more than 2/3
+/// vote weight of `validators`, the validator set of the previous height.
+/// BLS public keys of voters are taken from `validators`, so a block signed by
+/// an earlier validator set verifies as well. The genesis block has no proof
+/// and always passes.
+pub fn verify_block_proof(
+    crypto: &OverlordCrypto,
+    header: &BlockHeader,
+    proof: &Proof,
+    validators: &[ValidatorExtend],
+) -> Result<(), ConsensusError> {
+    let height = header.height;
+    if height == 0 {
+        return Ok(());
+    }
+
+    if height != proof.height {
+        return Err(ConsensusError::VerifyProof(
+            height,
+            BlockProofField::HeightMismatch(height, proof.height),
+        ));
+    }
+
+    let header_bytes = header
+        .encode_fixed()
+        .map_err(|e| ConsensusError::Other(e.to_string()))?;
+    if Hash::digest(header_bytes) != proof.block_hash {
+        return Err(ConsensusError::VerifyProof(
+            height,
+            BlockProofField::HashMismatch,
+        ));
+    }
+
+    let mut authority_list = validators
+        .iter()
+        .map(|v| Node {
+            address:        v.pub_key.decode(),
+            propose_weight: v.propose_weight,
+            vote_weight:    v.vote_weight,
+        })
+        .collect::<Vec<_>>();
+    let signed_voters = extract_voters(&mut authority_list, &proof.bitmap)
+        .map_err(|_| ConsensusError::VerifyProof(height, BlockProofField::BitMap))?;
+
+    let weight_map = authority_list
+        .iter()
+        .map(|node| (node.address.clone(), node.vote_weight))
+        .collect::<HashMap<_, _>>();
+    verify_proof_weight(height, &weight_map, &signed_voters)?;
+
+    let vote = Vote {
+        height:     proof.height,
+        round:      proof.round,
+        vote_type:  VoteType::Precommit,
+        block_hash: proof.block_hash.as_bytes(),
+    };
+    let vote_hash = crypto.hash(Bytes::from(rlp::encode(&vote)));
+    if let Some(aggregate_key) = crypto.cached_aggregate_key(validators, &proof.bitmap) {
+        return crypto
+            .verify_with_aggregate_key(vote_hash, &aggregate_key, proof.signature.clone())
+            .map_err(|e| {
+                log::error!("[consensus] verify_proof_signature error: {}", e);
+                ConsensusError::VerifyProof(height, BlockProofField::Signature)
+            });
+    }
+
+    let vote_keys = validators
+        .iter()
+        .filter(|v| signed_voters.contains(&v.pub_key.decode()))
+        .map(|v| v.bls_pub_key.clone())
+        .collect();
+    verify_proof_signature(
+        crypto,
+        height,
+        vote_hash,
+        proof.signature.clone(),
+        vote_keys,
+    )
+}
+
+/// Check that `signed_voters` hold more than 2/3 of the weight in
+/// `weight_map`.
+pub fn verify_proof_weight(
+    height: u64,
+    weight_map: &HashMap<Bytes, u32>,
+    signed_voters: &[Bytes],
+) -> Result<(), ConsensusError> {
+    let total_validator_weight: u64 = weight_map.values().map(|w| u64::from(*w)).sum();
+
+    let mut accumulator = 0u64;
+    for signed_voter_address in signed_voters {
+        match weight_map.get(signed_voter_address) {
+            Some(weight) => accumulator += u64::from(*weight),
+            None => {
+                log::error!(
+                    "[consensus] verify_proof_weight, weight not found, signed_voter_address: {:?}",
+                    signed_voter_address
+                );
+                return Err(ConsensusError::VerifyProof(
+                    height,
+                    BlockProofField::Validator,
+                ));
+            }
+        }
+    }
+
+    if 3 * accumulator <= 2 * total_validator_weight {
+        log::error!(
+            "[consensus] verify_proof_weight, accumulator: {}, total: {}",
+            accumulator,
+            total_validator_weight
+        );
+        return Err(ConsensusError::VerifyProof(height, BlockProofField::Weight));
+    }
+    Ok(())
+}
+
+/// Check the aggregated signature against the aggregation of `vote_keys`.
+pub fn verify_proof_signature(
+    crypto: &OverlordCrypto,
+    height: u64,
+    vote_hash: Bytes,
+    aggregated_signature_bytes: Bytes,
+    vote_keys: Vec<Hex>,
+) -> Result<(), ConsensusError> {
+    let mut pub_keys = Vec::with_capacity(vote_keys.len());
+    for hex in vote_keys.into_iter() {
+        pub_keys.push(hex_to_bls_pubkey(hex)?);
+    }
+
+    crypto
+        .inner_verify_aggregated_signature(vote_hash, pub_keys, aggregated_signature_bytes)
+        .map_err(|e| {
+            log::error!("[consensus] verify_proof_signature error: {}", e);
+            ConsensusError::VerifyProof(height, BlockProofField::Signature)
+        })
+}
+
+// Identify a validator set regardless of the order of validators.
+fn validators_fingerprint(validators: &[ValidatorExtend]) -> Hash {
+    let mut keys = validators
+        .iter()
+        .map(|v| (v.pub_key.decode(), v.bls_pub_key.decode()))
+        .collect::<Vec<_>>();
+    keys.sort();
+
+    let mut bytes = BytesMut::new();
+    for (pub_key, bls_pub_key) in keys.into_iter() {
+        bytes.put(pub_key);
+        bytes.put(bls_pub_key);
+    }
+    Hash::digest(bytes.freeze())
+}
+
 pub fn convert_hex_to_bls_pubkeys(hex: Hex) -> ProtocolResult<BlsPublicKey> {
+    Ok(hex_to_bls_pubkey(hex)?)
+}
+
+fn hex_to_bls_pubkey(hex: Hex) -> Result<BlsPublicKey, ConsensusError> {
     let hex_pubkey = hex::decode(hex.as_string_trim0x())
         .map_err(|e| ConsensusError::Other(format!("from hex error {:?}", e)))?;
-    let ret = BlsPublicKey::try_from(hex_pubkey.as_ref())
-        .map_err(|e| ConsensusError::CryptoErr(Box::new(e)))?;
-    Ok(ret)
+    BlsPublicKey::try_from(hex_pubkey.as_ref()).map_err(|e| ConsensusError::CryptoErr(Box::new(e)))
 }
 
 #[cfg(test)]
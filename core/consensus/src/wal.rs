@@ -8,6 +8,7 @@ use protocol::types::{Bytes, Hash, SignedTransaction};
 use protocol::ProtocolResult;
 
 use crate::fixed_types::FixedSignedTxs;
+use crate::util::digest_signed_transactions;
 use crate::ConsensusError;
 use bytes::{BufMut, BytesMut};
 use creep::Context;
@@ -62,8 +63,14 @@ impl SignedTxsWAL {
         };
 
         let data = FixedSignedTxs::new(txs).encode_sync()?;
+        let check_sum = Hash::digest(data.clone());
+
+        let mut content = BytesMut::new();
+        content.put(check_sum.as_bytes());
+        content.put(data);
+
         wal_file
-            .write_all(data.as_ref())
+            .write_all(content.as_ref())
             .map_err(ConsensusError::WALErr)?;
         Ok(())
     }
@@ -98,27 +105,37 @@ impl SignedTxsWAL {
         file_path.push(ordered_signed_transactions_hash.as_hex());
         file_path.set_extension("txt");
 
-        self.recover_stxs(file_path)
+        self.recover_stxs(height, file_path)
     }
 
-    pub fn load_by_height(&self, height: u64) -> Vec<SignedTransaction> {
+    /// Load all saved transactions of the given height. Return an empty vec
+    /// if nothing saved. A corrupted file is removed and skipped rather than
+    /// failing the whole height, the transactions can be received from other
+    /// peers again.
+    pub fn load_by_height(&self, height: u64) -> ProtocolResult<Vec<SignedTransaction>> {
         let mut dir = self.path.clone();
         dir.push(height.to_string());
         let dir = if let Ok(res) = fs::read_dir(dir) {
             res
         } else {
-            return Vec::new();
+            return Ok(Vec::new());
         };
 
         let mut ret = Vec::new();
         for entry in dir {
-            if let Ok(file_dir) = entry {
-                if let Ok(mut stxs) = self.recover_stxs(file_dir.path()) {
-                    ret.append(&mut stxs);
-                }
+            let file_path = entry.map_err(ConsensusError::WALErr)?.path();
+            match self.recover_stxs(height, file_path.clone()) {
+                Ok(mut stxs) => ret.append(&mut stxs),
+                Err(e) => match e.downcast_ref::<ConsensusError>() {
+                    Some(ConsensusError::WalCorrupted(_)) => {
+                        log::warn!("[consensus]: wal file {:?} corrupted, remove it", file_path);
+                        fs::remove_file(file_path).map_err(ConsensusError::WALErr)?;
+                    }
+                    _ => return Err(e),
+                },
             }
         }
-        ret
+        Ok(ret)
     }
 
     pub fn remove(&self, committed_height: u64) -> ProtocolResult<()> {
@@ -143,14 +160,41 @@ impl SignedTxsWAL {
         Ok(())
     }
 
-    fn recover_stxs(&self, file_path: PathBuf) -> ProtocolResult<Vec<SignedTransaction>> {
+    fn recover_stxs(
+        &self,
+        height: u64,
+        file_path: PathBuf,
+    ) -> ProtocolResult<Vec<SignedTransaction>> {
         let mut read_buf = Vec::new();
         let mut file = fs::File::open(&file_path).map_err(ConsensusError::WALErr)?;
         let _ = file
             .read_to_end(&mut read_buf)
             .map_err(ConsensusError::WALErr)?;
-        let txs = FixedSignedTxs::decode_sync(Bytes::from(read_buf))?;
-        Ok(txs.inner)
+
+        let data = Bytes::from(read_buf);
+        let hash_len = Hash::default().as_bytes().len();
+        if data.len() >= hash_len {
+            let content = data.slice(hash_len..);
+            if data.slice(..hash_len) == Hash::digest(content.clone()).as_bytes() {
+                let txs = FixedSignedTxs::decode_sync(content)?;
+                return Ok(txs.inner);
+            }
+        }
+
+        // Files written before the checksum was added hold the encoded
+        // transactions only, they are named by the digest of the transactions.
+        match FixedSignedTxs::decode_sync(data) {
+            Ok(txs) => {
+                let digest = digest_signed_transactions(&txs.inner)?;
+                let file_stem = file_path.file_stem().and_then(|stem| stem.to_str());
+                if file_stem == Some(digest.as_hex().as_str()) {
+                    Ok(txs.inner)
+                } else {
+                    Err(ConsensusError::WalCorrupted(height).into())
+                }
+            }
+            Err(_) => Err(ConsensusError::WalCorrupted(height).into()),
+        }
     }
 }
 
@@ -411,7 +455,7 @@ mod tests {
         let hash_03 = Hash::digest(Bytes::from(rlp::encode_list(&txs_03)));
         wal.save(3u64, hash_03, txs_03.clone()).unwrap();
 
-        let res = wal.load_by_height(3);
+        let res = wal.load_by_height(3).unwrap();
         assert_eq!(res.len(), 200);
 
         for tx in res.iter() {
@@ -429,6 +473,67 @@ mod tests {
         wal.remove(3u64).unwrap();
     }
 
+    #[test]
+    fn test_txs_wal_corrupted() {
+        let path = "./free-space/wal/corrupted_txs";
+        let _ = fs::remove_dir_all(PathBuf::from_str(path).unwrap());
+
+        let wal = SignedTxsWAL::new(path.to_string());
+        let txs = mock_wal_txs(10);
+        let hash = Hash::digest(Bytes::from(rlp::encode_list(&txs)));
+        wal.save(1u64, hash.clone(), txs.clone()).unwrap();
+        assert_eq!(wal.load_by_height(1).unwrap(), txs);
+
+        // Flip the last byte of the saved file
+        let mut file_path = PathBuf::from_str(path).unwrap();
+        file_path.push("1");
+        file_path.push(hash.as_hex());
+        file_path.set_extension("txt");
+        let mut data = fs::read(&file_path).unwrap();
+        let last = data.len() - 1;
+        data[last] ^= 0xff;
+        fs::write(&file_path, data).unwrap();
+
+        match wal.load(1u64, hash) {
+            Err(e) => assert!(e.to_string().contains("WalCorrupted")),
+            Ok(_) => panic!("should fail to load a corrupted wal"),
+        }
+
+        // A corrupted file is removed and skipped, the others of the same
+        // height are still loaded
+        let valid_txs = mock_wal_txs(10);
+        let valid_hash = Hash::digest(Bytes::from(rlp::encode_list(&valid_txs)));
+        wal.save(1u64, valid_hash, valid_txs.clone()).unwrap();
+        assert_eq!(wal.load_by_height(1).unwrap(), valid_txs);
+        assert!(!file_path.exists());
+
+        fs::remove_dir_all(PathBuf::from_str(path).unwrap()).unwrap();
+    }
+
+    #[test]
+    fn test_txs_wal_legacy() {
+        let path = "./free-space/wal/legacy_txs";
+        let _ = fs::remove_dir_all(PathBuf::from_str(path).unwrap());
+
+        let wal = SignedTxsWAL::new(path.to_string());
+        let txs = mock_wal_txs(10);
+        let hash = digest_signed_transactions(&txs).unwrap();
+
+        // Legacy file holds the encoded transactions without checksum
+        let mut file_path = PathBuf::from_str(path).unwrap();
+        file_path.push("1");
+        fs::create_dir(&file_path).unwrap();
+        file_path.push(hash.as_hex());
+        file_path.set_extension("txt");
+        let data = FixedSignedTxs::new(txs.clone()).encode_sync().unwrap();
+        fs::write(&file_path, data).unwrap();
+
+        assert_eq!(wal.load(1u64, hash).unwrap(), txs);
+        assert_eq!(wal.load_by_height(1).unwrap(), txs);
+
+        fs::remove_dir_all(PathBuf::from_str(path).unwrap()).unwrap();
+    }
+
     #[test]
     fn test_consensus_wal() {
         // write one, read one
@@ -3,6 +3,8 @@ use super::TxContext;
 pub mod message;
 
 use std::{
+    cmp,
+    collections::HashSet,
     error::Error,
     marker::PhantomData,
     sync::atomic::{AtomicU64, Ordering},
@@ -21,14 +23,15 @@ use futures::{
     stream::StreamExt,
 };
 use futures_timer::Delay;
-use log::{debug, error};
+use log::{debug, error, warn};
 
-use common_crypto::Crypto;
+use common_channel::TryRecvBatch;
+use common_crypto::{constant_time_eq, Crypto};
 use protocol::{
     fixed_codec::FixedCodec,
     traits::{
-        Context, ExecutorFactory, ExecutorParams, Gossip, MemPoolAdapter, PeerTrust, Priority, Rpc,
-        ServiceMapping, ServiceResponse, Storage, TrustFeedback,
+        Context, ExecutorConfig, ExecutorFactory, ExecutorParams, Gossip, MemPoolAdapter,
+        PeerTrust, Priority, Rpc, ServiceMapping, ServiceResponse, Storage, TrustFeedback,
     },
     types::{Address, Hash, SignedTransaction, TransactionRequest},
     ProtocolError, ProtocolErrorKind, ProtocolResult,
@@ -41,14 +44,101 @@ use crate::MemPoolError;
 
 pub const DEFAULT_BROADCAST_TXS_SIZE: usize = 200;
 pub const DEFAULT_BROADCAST_TXS_INTERVAL: u64 = 200; // milliseconds
+pub const DEFAULT_BROADCAST_CHANNEL_SIZE: usize = 20_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BroadcastMode {
+    /// Broadcast once `broadcast_txs_size` txs are cached or the interval is
+    /// reached.
+    Fixed,
+    /// Double the batch size up to `max_txs_size` if the cache is filled up
+    /// within an interval, otherwise halve the interval down to
+    /// `min_interval`, which is at least 1 millisecond. Both go back toward
+    /// the configured values when the load changes.
+    Adaptive {
+        max_txs_size: usize,
+        min_interval: u64,
+    },
+}
+
+struct BroadcastBatch {
+    base_txs_size: usize,
+    max_txs_size:  usize,
+    base_interval: u64,
+    min_interval:  u64,
+
+    txs_size:   usize,
+    interval:   Arc<AtomicU64>,
+    full_times: usize,
+}
+
+impl BroadcastBatch {
+    pub fn new(mode: BroadcastMode, txs_size: usize, interval: u64) -> Self {
+        let (max_txs_size, min_interval) = match mode {
+            BroadcastMode::Fixed => (txs_size, interval),
+            BroadcastMode::Adaptive {
+                max_txs_size,
+                min_interval,
+            } => (
+                cmp::max(max_txs_size, txs_size),
+                // A zero interval never doubles back and spins the timer
+                cmp::max(cmp::min(min_interval, interval), 1),
+            ),
+        };
+
+        BroadcastBatch {
+            base_txs_size: txs_size,
+            max_txs_size,
+            base_interval: interval,
+            min_interval,
+
+            txs_size,
+            interval: Arc::new(AtomicU64::new(interval)),
+            full_times: 0,
+        }
+    }
+
+    pub fn interval(&self) -> Arc<AtomicU64> {
+        Arc::clone(&self.interval)
+    }
+
+    pub fn txs_size(&self) -> usize {
+        self.txs_size
+    }
+
+    pub fn full(&mut self) {
+        self.full_times += 1;
+    }
+
+    pub fn interval_reached(&mut self) {
+        let interval = self.interval.load(Ordering::SeqCst);
+
+        // In fixed mode, max and base are the same, so nothing changes.
+        let (txs_size, interval) = if self.full_times > 0 {
+            (
+                cmp::min(self.txs_size * 2, self.max_txs_size),
+                cmp::min(interval * 2, self.base_interval),
+            )
+        } else {
+            (
+                cmp::max(self.txs_size / 2, self.base_txs_size),
+                cmp::max(interval / 2, self.min_interval),
+            )
+        };
+
+        self.txs_size = txs_size;
+        self.interval.store(interval, Ordering::SeqCst);
+        self.full_times = 0;
+    }
+}
 
 struct IntervalTxsBroadcaster;
 
 impl IntervalTxsBroadcaster {
     pub async fn broadcast<G>(
-        stx_rx: UnboundedReceiver<SignedTransaction>,
+        stx_rx: Receiver<SignedTransaction>,
         interval_reached: Receiver<()>,
-        tx_size: usize,
+        mut batch: BroadcastBatch,
         gossip: G,
         err_tx: UnboundedSender<ProtocolError>,
     ) where
@@ -57,15 +147,19 @@ impl IntervalTxsBroadcaster {
         let mut stx_rx = stx_rx.fuse();
         let mut interval_rx = interval_reached.fuse();
 
-        let mut txs_cache = Vec::with_capacity(tx_size);
+        let mut txs_cache = Vec::with_capacity(batch.txs_size());
 
         loop {
             select! {
                 opt_stx = stx_rx.next() => {
                     if let Some(stx) = opt_stx {
                         txs_cache.push(stx);
+                        // Take the rest of a burst without going through select
+                        let room = batch.txs_size().saturating_sub(txs_cache.len());
+                        txs_cache.extend(stx_rx.get_mut().try_recv_batch(room));
 
-                        if txs_cache.len() == tx_size {
+                        if txs_cache.len() >= batch.txs_size() {
+                            batch.full();
                             Self::do_broadcast(&mut txs_cache, &gossip, err_tx.clone()).await
                         }
                     } else {
@@ -74,6 +168,7 @@ impl IntervalTxsBroadcaster {
                 },
                 signal = interval_rx.next() => {
                     if signal.is_some() {
+                        batch.interval_reached();
                         Self::do_broadcast(&mut txs_cache, &gossip, err_tx.clone()).await
                     }
                 },
@@ -82,11 +177,9 @@ impl IntervalTxsBroadcaster {
         }
     }
 
-    pub async fn timer(mut signal_tx: Sender<()>, interval: u64) {
-        let interval = Duration::from_millis(interval);
-
+    pub async fn timer(mut signal_tx: Sender<()>, interval: Arc<AtomicU64>) {
         loop {
-            Delay::new(interval).await;
+            Delay::new(Duration::from_millis(interval.load(Ordering::SeqCst))).await;
 
             if let Err(err) = signal_tx.try_send(()) {
                 // This means previous interval signal hasn't processed
@@ -145,7 +238,9 @@ pub struct DefaultMemPoolAdapter<EF, C, N, S, DB, Mapping> {
     cycles_limit: AtomicU64,
     max_tx_size:  AtomicU64,
 
-    stx_tx: UnboundedSender<SignedTransaction>,
+    executor_config: ExecutorConfig,
+
+    stx_tx: Mutex<Sender<SignedTransaction>>,
     err_rx: Mutex<UnboundedReceiver<ProtocolError>>,
 
     pin_c:  PhantomData<C>,
@@ -161,6 +256,12 @@ where
     DB: cita_trie::DB + 'static,
     Mapping: ServiceMapping + 'static,
 {
+    /// The broadcast channel is bounded by `broadcast_channel_size`. An
+    /// unbounded channel never drops a transaction, but it grows without limit
+    /// when gossip is slow. When the bounded channel is full, new transactions
+    /// are still inserted into pool, only their broadcast is dropped and
+    /// counted by `muta_mempool_broadcast_dropped_total`.
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         network: N,
         storage: Arc<S>,
@@ -168,20 +269,20 @@ where
         service_mapping: Arc<Mapping>,
         broadcast_txs_size: usize,
         broadcast_txs_interval: u64,
+        broadcast_channel_size: usize,
+        broadcast_mode: BroadcastMode,
     ) -> Self {
-        let (stx_tx, stx_rx) = unbounded();
+        let (stx_tx, stx_rx) = channel(broadcast_channel_size);
         let (err_tx, err_rx) = unbounded();
         let (signal_tx, interval_reached) = channel(1);
+        let batch = BroadcastBatch::new(broadcast_mode, broadcast_txs_size, broadcast_txs_interval);
 
-        tokio::spawn(IntervalTxsBroadcaster::timer(
-            signal_tx,
-            broadcast_txs_interval,
-        ));
+        tokio::spawn(IntervalTxsBroadcaster::timer(signal_tx, batch.interval()));
 
         tokio::spawn(IntervalTxsBroadcaster::broadcast(
             stx_rx,
             interval_reached,
-            broadcast_txs_size,
+            batch,
             network.clone(),
             err_tx,
         ));
@@ -196,13 +297,21 @@ where
             cycles_limit: AtomicU64::new(0),
             max_tx_size: AtomicU64::new(0),
 
-            stx_tx,
+            executor_config: ExecutorConfig::default(),
+
+            stx_tx: Mutex::new(stx_tx),
             err_rx: Mutex::new(err_rx),
 
             pin_c: PhantomData,
             pin_ef: PhantomData,
         }
     }
+
+    /// Executor settings of the node, new transactions offering a lower cycles
+    /// price than its floor or sent by blacklisted senders are rejected.
+    pub fn set_executor_config(&mut self, config: ExecutorConfig) {
+        self.executor_config = config;
+    }
 }
 
 #[async_trait]
@@ -239,9 +348,7 @@ where
     }
 
     async fn broadcast_tx(&self, _ctx: Context, stx: SignedTransaction) -> ProtocolResult<()> {
-        self.stx_tx
-            .unbounded_send(stx)
-            .map_err(AdapterError::from)?;
+        try_send_broadcast(&mut *self.stx_tx.lock().await, stx)?;
 
         if let Some(mut err_rx) = self.err_rx.try_lock() {
             match err_rx.try_next() {
@@ -266,6 +373,7 @@ where
         let storage_clone = Arc::clone(&self.storage);
         let service_mapping_clone = Arc::clone(&self.service_mapping);
         let tx_hash = tx.tx_hash.clone();
+        let executor_config = self.executor_config.clone();
 
         let blocking_res: ProtocolResult<ServiceResponse<String>> =
             tokio::task::spawn_blocking(move || {
@@ -273,7 +381,7 @@ where
                 let fixed_bytes = tx.raw.encode_fixed()?;
                 let tx_hash = Hash::digest(fixed_bytes);
 
-                if tx_hash != tx.tx_hash {
+                if !constant_time_eq(tx_hash.as_slice(), tx.tx_hash.as_slice()) {
                     if ctx_clone.is_network_origin_txs() {
                         network.report(
                             ctx_clone,
@@ -298,6 +406,7 @@ where
                     Arc::clone(&trie_db_clone),
                     Arc::clone(&storage_clone),
                     Arc::clone(&service_mapping_clone),
+                    &executor_config,
                 )?;
                 let params = ExecutorParams {
                     state_root:   header.state_root,
@@ -340,14 +449,17 @@ where
         Ok(())
     }
 
-    async fn check_transaction(&self, ctx: Context, stx: &SignedTransaction) -> ProtocolResult<()> {
-        let fixed_bytes = stx.raw.encode_fixed()?;
-        let size = fixed_bytes.len() as u64;
+    async fn check_transaction(
+        &self,
+        ctx: Context,
+        stx: &SignedTransaction,
+        height: Option<u64>,
+    ) -> ProtocolResult<()> {
         let tx_hash = stx.tx_hash.clone();
 
         // check tx size
         let max_tx_size = self.max_tx_size.load(Ordering::SeqCst);
-        if size > max_tx_size {
+        if let Err(e) = check_tx_size(stx, max_tx_size) {
             if ctx.is_network_origin_txs() {
                 self.network.report(
                     ctx.clone(),
@@ -357,12 +469,7 @@ where
                     )),
                 );
             }
-            return Err(MemPoolError::ExceedSizeLimit {
-                tx_hash,
-                max_tx_size,
-                size,
-            }
-            .into());
+            return Err(e.into());
         }
 
         // check cycle limit
@@ -386,42 +493,28 @@ where
             .into());
         }
 
+        // Check cycles price and sender of new transactions only, transactions
+        // of a block being verified or synced are up to consensus.
+        if height.is_none() {
+            check_cycles_price(stx, self.executor_config.min_cycles_price)?;
+            check_sender(stx, &self.executor_config.sender_blacklist)?;
+        }
+
         // Verify chain id
         let latest_header = self.storage.get_latest_block_header(ctx.clone()).await?;
-        if latest_header.chain_id != stx.raw.chain_id {
+        if let Err(e) = check_chain_id(stx, &latest_header.chain_id) {
             if ctx.is_network_origin_txs() {
                 self.network.report(
                     ctx.clone(),
                     TrustFeedback::Worse(format!("Mempool wrong chain of tx {:?}", stx.tx_hash)),
                 );
             }
-            let wrong_chain_id = MemPoolError::WrongChain {
-                tx_hash: stx.tx_hash.clone(),
-            };
-
-            return Err(wrong_chain_id.into());
+            return Err(e.into());
         }
 
         // Verify timeout
-        let latest_height = latest_header.height;
         let timeout_gap = self.timeout_gap.load(Ordering::SeqCst);
-
-        if stx.raw.timeout > latest_height + timeout_gap {
-            let invalid_timeout = MemPoolError::InvalidTimeout {
-                tx_hash: stx.tx_hash.clone(),
-            };
-
-            return Err(invalid_timeout.into());
-        }
-
-        if stx.raw.timeout < latest_height {
-            let timeout = MemPoolError::Timeout {
-                tx_hash: stx.tx_hash.clone(),
-                timeout: stx.raw.timeout,
-            };
-
-            return Err(timeout.into());
-        }
+        check_timeout(stx, height, latest_header.height, timeout_gap)?;
 
         Ok(())
     }
@@ -459,6 +552,23 @@ where
         }
     }
 
+    async fn get_block_transactions(
+        &self,
+        ctx: Context,
+        height: u64,
+    ) -> ProtocolResult<Vec<SignedTransaction>> {
+        let block = match self.storage.get_block(ctx.clone(), height).await? {
+            Some(block) => block,
+            None => return Ok(Vec::new()),
+        };
+
+        let txs = self
+            .storage
+            .get_transactions(ctx, height, &block.ordered_tx_hashes)
+            .await?;
+        Ok(txs.into_iter().flatten().collect())
+    }
+
     fn report_good(&self, ctx: Context) {
         if ctx.is_network_origin_txs() {
             self.network.report(ctx, TrustFeedback::Good);
@@ -472,6 +582,25 @@ where
     }
 }
 
+fn try_send_broadcast(
+    stx_tx: &mut Sender<SignedTransaction>,
+    stx: SignedTransaction,
+) -> Result<(), AdapterError> {
+    match stx_tx.try_send(stx) {
+        Ok(()) => Ok(()),
+        Err(err) if err.is_full() => {
+            // The transaction is still in pool, only broadcast is skipped.
+            warn!(
+                "mempool: broadcast channel full, drop tx {:?}",
+                err.into_inner().tx_hash
+            );
+            common_apm::metrics::mempool::MEMPOOL_BROADCAST_DROPPED_COUNTER.inc();
+            Ok(())
+        }
+        Err(err) => Err(AdapterError::from(err)),
+    }
+}
+
 #[derive(Debug, Display)]
 pub enum AdapterError {
     #[display(fmt = "adapter: interval broadcaster drop")]
@@ -495,14 +624,96 @@ impl From<AdapterError> for ProtocolError {
     }
 }
 
+/// Size is the length of fixed codec encoding of raw transaction, computed
+/// without encoding it.
+fn check_tx_size(stx: &SignedTransaction, max_tx_size: u64) -> Result<(), MemPoolError> {
+    let size = stx.raw.encoded_len() as u64;
+    if size > max_tx_size {
+        return Err(MemPoolError::ExceedSizeLimit {
+            tx_hash: stx.tx_hash.clone(),
+            max_tx_size,
+            size,
+        });
+    }
+    Ok(())
+}
+
+fn check_cycles_price(stx: &SignedTransaction, min_cycles_price: u64) -> Result<(), MemPoolError> {
+    if stx.raw.cycles_price < min_cycles_price {
+        return Err(MemPoolError::CyclesPriceTooLow {
+            tx_hash: stx.tx_hash.clone(),
+            cycles_price: stx.raw.cycles_price,
+            min_cycles_price,
+        });
+    }
+    Ok(())
+}
+
+fn check_sender(stx: &SignedTransaction, blacklist: &HashSet<Address>) -> Result<(), MemPoolError> {
+    if blacklist.contains(&stx.raw.sender) {
+        return Err(MemPoolError::SenderBlacklisted {
+            tx_hash: stx.tx_hash.clone(),
+            sender:  stx.raw.sender.clone(),
+        });
+    }
+    Ok(())
+}
+
+/// A transaction signed for another chain may be a replay attempt, count it
+/// so that operators can notice.
+fn check_chain_id(stx: &SignedTransaction, chain_id: &Hash) -> Result<(), MemPoolError> {
+    if &stx.raw.chain_id != chain_id {
+        common_apm::metrics::mempool::MEMPOOL_WRONG_CHAIN_TX_COUNTER.inc();
+        return Err(MemPoolError::WrongChain {
+            tx_hash: stx.tx_hash.clone(),
+        });
+    }
+    Ok(())
+}
+
+/// Transactions of block at `height` were packaged when `height - 1` was the
+/// latest one, so a transaction pulled during sync isn't rejected just because
+/// it has expired at the current tip.
+fn check_timeout(
+    stx: &SignedTransaction,
+    height: Option<u64>,
+    latest_height: u64,
+    timeout_gap: u64,
+) -> Result<(), MemPoolError> {
+    let latest_height = match height {
+        Some(height) => height.saturating_sub(1),
+        None => latest_height,
+    };
+
+    if stx.raw.timeout > latest_height + timeout_gap {
+        return Err(MemPoolError::InvalidTimeout {
+            tx_hash: stx.tx_hash.clone(),
+        });
+    }
+
+    if stx.raw.timeout < latest_height {
+        return Err(MemPoolError::Timeout {
+            tx_hash: stx.tx_hash.clone(),
+            timeout: stx.raw.timeout,
+        });
+    }
+
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
-    use super::IntervalTxsBroadcaster;
+    use super::{
+        check_chain_id, check_cycles_price, check_sender, check_timeout, check_tx_size,
+        try_send_broadcast, BroadcastBatch, BroadcastMode, IntervalTxsBroadcaster,
+    };
 
-    use crate::{adapter::message::MsgNewTxs, tests::default_mock_txs};
+    use crate::{adapter::message::MsgNewTxs, tests::default_mock_txs, MemPoolError};
 
     use protocol::{
+        fixed_codec::FixedCodec,
         traits::{Context, Gossip, MessageCodec, Priority},
+        types::{Address, Hash},
         Bytes, ProtocolResult,
     };
 
@@ -514,7 +725,9 @@ mod tests {
     use parking_lot::Mutex;
 
     use std::{
+        collections::HashSet,
         ops::Sub,
+        sync::atomic::{AtomicU64, Ordering},
         sync::Arc,
         time::{Duration, Instant},
     };
@@ -585,7 +798,10 @@ mod tests {
         let interval = Duration::from_millis(200);
         let now = Instant::now();
 
-        tokio::spawn(IntervalTxsBroadcaster::timer(tx, 200));
+        tokio::spawn(IntervalTxsBroadcaster::timer(
+            tx,
+            Arc::new(AtomicU64::new(200)),
+        ));
         rx.next().await.expect("await interval signal fail");
 
         assert!(now.elapsed().sub(interval).as_millis() < 100u128);
@@ -593,23 +809,23 @@ mod tests {
 
     #[tokio::test]
     async fn test_interval_broadcast_reach_cache_size() {
-        let (stx_tx, stx_rx) = unbounded();
+        let (mut stx_tx, stx_rx) = channel(100);
         let (err_tx, _err_rx) = unbounded();
         let (_signal_tx, interval_reached) = channel(1);
-        let tx_size = 10;
+        let batch = BroadcastBatch::new(BroadcastMode::Fixed, 10, 200);
         let (broadcast_signal_tx, mut broadcast_signal_rx) = unbounded();
         let gossip = MockGossip::new(broadcast_signal_tx);
 
         tokio::spawn(IntervalTxsBroadcaster::broadcast(
             stx_rx,
             interval_reached,
-            tx_size,
+            batch,
             gossip.clone(),
             err_tx,
         ));
 
         for stx in default_mock_txs(11).into_iter() {
-            stx_tx.unbounded_send(stx).expect("send stx fail");
+            stx_tx.try_send(stx).expect("send stx fail");
         }
 
         broadcast_signal_rx.next().await;
@@ -622,24 +838,24 @@ mod tests {
 
     #[tokio::test]
     async fn test_interval_broadcast_reach_interval() {
-        let (stx_tx, stx_rx) = unbounded();
+        let (mut stx_tx, stx_rx) = channel(100);
         let (err_tx, _err_rx) = unbounded();
         let (signal_tx, interval_reached) = channel(1);
-        let tx_size = 10;
+        let batch = BroadcastBatch::new(BroadcastMode::Fixed, 10, 200);
         let (broadcast_signal_tx, mut broadcast_signal_rx) = unbounded();
         let gossip = MockGossip::new(broadcast_signal_tx);
 
-        tokio::spawn(IntervalTxsBroadcaster::timer(signal_tx, 200));
+        tokio::spawn(IntervalTxsBroadcaster::timer(signal_tx, batch.interval()));
         tokio::spawn(IntervalTxsBroadcaster::broadcast(
             stx_rx,
             interval_reached,
-            tx_size,
+            batch,
             gossip.clone(),
             err_tx,
         ));
 
         for stx in default_mock_txs(9).into_iter() {
-            stx_tx.unbounded_send(stx).expect("send stx fail");
+            stx_tx.try_send(stx).expect("send stx fail");
         }
 
         broadcast_signal_rx.next().await;
@@ -652,24 +868,24 @@ mod tests {
 
     #[tokio::test]
     async fn test_interval_broadcast() {
-        let (stx_tx, stx_rx) = unbounded();
+        let (mut stx_tx, stx_rx) = channel(100);
         let (err_tx, _err_rx) = unbounded();
         let (signal_tx, interval_reached) = channel(1);
-        let tx_size = 10;
+        let batch = BroadcastBatch::new(BroadcastMode::Fixed, 10, 200);
         let (broadcast_signal_tx, mut broadcast_signal_rx) = unbounded();
         let gossip = MockGossip::new(broadcast_signal_tx);
 
-        tokio::spawn(IntervalTxsBroadcaster::timer(signal_tx, 200));
+        tokio::spawn(IntervalTxsBroadcaster::timer(signal_tx, batch.interval()));
         tokio::spawn(IntervalTxsBroadcaster::broadcast(
             stx_rx,
             interval_reached,
-            tx_size,
+            batch,
             gossip.clone(),
             err_tx,
         ));
 
         for stx in default_mock_txs(19).into_iter() {
-            stx_tx.unbounded_send(stx).expect("send stx fail");
+            stx_tx.try_send(stx).expect("send stx fail");
         }
 
         // Should got two broadcast
@@ -693,4 +909,223 @@ mod tests {
             "first message should only have 10 stx"
         );
     }
+
+    async fn count_broadcast_batches(mode: BroadcastMode) -> Vec<usize> {
+        let (mut stx_tx, stx_rx) = channel(100);
+        let (err_tx, _err_rx) = unbounded();
+        let (mut signal_tx, interval_reached) = channel(1);
+        let batch = BroadcastBatch::new(mode, 10, 200);
+        let (broadcast_signal_tx, _broadcast_signal_rx) = unbounded();
+        let gossip = MockGossip::new(broadcast_signal_tx);
+
+        tokio::spawn(IntervalTxsBroadcaster::broadcast(
+            stx_rx,
+            interval_reached,
+            batch,
+            gossip.clone(),
+            err_tx,
+        ));
+
+        // Insert 100 txs in every interval, let broadcaster consume them
+        // before next interval signal.
+        for _ in 0..10 {
+            for stx in default_mock_txs(100).into_iter() {
+                stx_tx.try_send(stx).expect("send stx fail");
+            }
+            tokio::task::yield_now().await;
+
+            signal_tx.try_send(()).expect("send interval signal fail");
+            tokio::task::yield_now().await;
+        }
+
+        let msgs = gossip.msgs.lock().drain(..).collect::<Vec<_>>();
+        msgs.into_iter()
+            .map(|msg| {
+                MsgNewTxs::decode(msg)
+                    .expect("decode MsgNewTxs fail")
+                    .batch_stxs
+                    .len()
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_adaptive_broadcast_high_rate() {
+        let fixed_batches = count_broadcast_batches(BroadcastMode::Fixed).await;
+        assert_eq!(fixed_batches.len(), 100);
+        assert!(fixed_batches.iter().all(|len| *len == 10));
+
+        let adaptive_batches = count_broadcast_batches(BroadcastMode::Adaptive {
+            max_txs_size: 80,
+            min_interval: 50,
+        })
+        .await;
+        assert_eq!(adaptive_batches.iter().sum::<usize>(), 1000);
+        assert!(adaptive_batches.len() < fixed_batches.len());
+        assert_eq!(adaptive_batches.iter().max(), Some(&80));
+    }
+
+    #[test]
+    fn test_adaptive_broadcast_low_rate() {
+        let mut batch = BroadcastBatch::new(
+            BroadcastMode::Adaptive {
+                max_txs_size: 80,
+                min_interval: 50,
+            },
+            10,
+            200,
+        );
+        let interval = batch.interval();
+
+        batch.full();
+        batch.interval_reached();
+        assert_eq!(batch.txs_size(), 20);
+        assert_eq!(interval.load(Ordering::SeqCst), 200);
+
+        batch.interval_reached();
+        assert_eq!(batch.txs_size(), 10);
+        assert_eq!(interval.load(Ordering::SeqCst), 100);
+
+        batch.interval_reached();
+        batch.interval_reached();
+        assert_eq!(batch.txs_size(), 10);
+        assert_eq!(interval.load(Ordering::SeqCst), 50);
+    }
+
+    #[test]
+    fn test_adaptive_broadcast_zero_min_interval() {
+        let mut batch = BroadcastBatch::new(
+            BroadcastMode::Adaptive {
+                max_txs_size: 80,
+                min_interval: 0,
+            },
+            10,
+            200,
+        );
+        let interval = batch.interval();
+
+        for _ in 0..10 {
+            batch.interval_reached();
+        }
+        assert_eq!(interval.load(Ordering::SeqCst), 1);
+
+        batch.full();
+        batch.interval_reached();
+        assert_eq!(interval.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn test_broadcast_channel_full() {
+        let (mut stx_tx, _stx_rx) = channel(1);
+        let dropped = common_apm::metrics::mempool::MEMPOOL_BROADCAST_DROPPED_COUNTER.get();
+
+        // Nobody consumes the receiver, only buffer plus one sender slot fit.
+        for stx in default_mock_txs(10).into_iter() {
+            assert!(try_send_broadcast(&mut stx_tx, stx).is_ok());
+        }
+
+        let dropped_now = common_apm::metrics::mempool::MEMPOOL_BROADCAST_DROPPED_COUNTER.get();
+        assert_eq!(dropped_now - dropped, 8, "should drop 8 stx");
+    }
+
+    #[test]
+    fn test_check_tx_size() {
+        let stx = default_mock_txs(1).pop().unwrap();
+        let size = stx.raw.encode_fixed().unwrap().len() as u64;
+
+        assert!(check_tx_size(&stx, size).is_ok());
+        match check_tx_size(&stx, size - 1) {
+            Err(MemPoolError::ExceedSizeLimit {
+                size: actual,
+                max_tx_size,
+                ..
+            }) => {
+                assert_eq!(actual, size);
+                assert_eq!(max_tx_size, size - 1);
+            }
+            _ => panic!("should exceed size limit"),
+        }
+    }
+
+    #[test]
+    fn test_check_cycles_price() {
+        let mut stx = default_mock_txs(1).pop().unwrap();
+        stx.raw.cycles_price = 10;
+
+        assert!(check_cycles_price(&stx, 0).is_ok());
+        assert!(check_cycles_price(&stx, 10).is_ok());
+        match check_cycles_price(&stx, 11) {
+            Err(MemPoolError::CyclesPriceTooLow {
+                cycles_price,
+                min_cycles_price,
+                ..
+            }) => {
+                assert_eq!(cycles_price, 10);
+                assert_eq!(min_cycles_price, 11);
+            }
+            _ => panic!("should be below the cycles price floor"),
+        }
+    }
+
+    #[test]
+    fn test_check_sender() {
+        let stx = default_mock_txs(1).pop().unwrap();
+        let other = Address::from_hash(Hash::digest(Bytes::from_static(b"other"))).unwrap();
+
+        let blacklist = vec![other].into_iter().collect::<HashSet<_>>();
+        assert!(check_sender(&stx, &blacklist).is_ok());
+
+        let blacklist = vec![stx.raw.sender.clone()]
+            .into_iter()
+            .collect::<HashSet<_>>();
+        match check_sender(&stx, &blacklist) {
+            Err(MemPoolError::SenderBlacklisted { sender, .. }) => {
+                assert_eq!(sender, stx.raw.sender)
+            }
+            _ => panic!("should be blacklisted"),
+        }
+    }
+
+    #[test]
+    fn test_check_chain_id() {
+        let stx = default_mock_txs(1).pop().unwrap();
+        let rejected = common_apm::metrics::mempool::MEMPOOL_WRONG_CHAIN_TX_COUNTER.get();
+
+        assert!(check_chain_id(&stx, &stx.raw.chain_id).is_ok());
+        match check_chain_id(&stx, &Hash::digest(Bytes::from_static(b"other chain"))) {
+            Err(MemPoolError::WrongChain { tx_hash }) => assert_eq!(tx_hash, stx.tx_hash),
+            _ => panic!("should be wrong chain"),
+        }
+
+        let rejected_now = common_apm::metrics::mempool::MEMPOOL_WRONG_CHAIN_TX_COUNTER.get();
+        assert_eq!(rejected_now - rejected, 1);
+    }
+
+    #[test]
+    fn test_check_timeout() {
+        let mut stx = default_mock_txs(1).pop().unwrap();
+        stx.raw.timeout = 10;
+        let latest_height = 20;
+
+        match check_timeout(&stx, None, latest_height, 5) {
+            Err(MemPoolError::Timeout { timeout, .. }) => assert_eq!(timeout, 10),
+            _ => panic!("should be timeout at the latest height"),
+        }
+        // valid for a past block
+        assert!(check_timeout(&stx, Some(11), latest_height, 5).is_ok());
+        assert!(check_timeout(&stx, Some(12), latest_height, 5).is_err());
+        match check_timeout(&stx, Some(4), latest_height, 5) {
+            Err(MemPoolError::InvalidTimeout { .. }) => (),
+            _ => panic!("should be too far ahead"),
+        }
+    }
+
+    #[test]
+    fn test_broadcast_channel_disconnected() {
+        let (mut stx_tx, stx_rx) = channel(1);
+        drop(stx_rx);
+
+        let stx = default_mock_txs(1).pop().unwrap();
+        assert!(try_send_broadcast(&mut stx_tx, stx).is_err());
+    }
 }
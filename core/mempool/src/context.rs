@@ -2,11 +2,37 @@ use protocol::traits::Context;
 
 const TXS_ORIGINAL_KEY: &str = "txs_original";
 const NETWORK_TXS: usize = 1;
+const INTERNAL_TXS: usize = 2;
+
+/// Where a transaction comes from, read from the context of insertion.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TxOrigin {
+    /// Sent by a client through api, the default one.
+    Api,
+    /// Broadcast or synced from other nodes.
+    Network,
+    /// Inserted by the node itself, for example, recovered from wal.
+    Internal,
+}
+
+impl TxOrigin {
+    pub(crate) fn as_str(self) -> &'static str {
+        match self {
+            TxOrigin::Api => "api",
+            TxOrigin::Network => "network",
+            TxOrigin::Internal => "internal",
+        }
+    }
+}
 
 pub(crate) trait TxContext {
     fn mark_network_origin_new_txs(&self) -> Self;
 
+    fn mark_internal_origin_txs(&self) -> Self;
+
     fn is_network_origin_txs(&self) -> bool;
+
+    fn tx_origin(&self) -> TxOrigin;
 }
 
 impl TxContext for Context {
@@ -14,7 +40,19 @@ impl TxContext for Context {
         self.with_value::<usize>(TXS_ORIGINAL_KEY, NETWORK_TXS)
     }
 
+    fn mark_internal_origin_txs(&self) -> Self {
+        self.with_value::<usize>(TXS_ORIGINAL_KEY, INTERNAL_TXS)
+    }
+
     fn is_network_origin_txs(&self) -> bool {
-        self.get::<usize>(TXS_ORIGINAL_KEY) == Some(&NETWORK_TXS)
+        self.tx_origin() == TxOrigin::Network
+    }
+
+    fn tx_origin(&self) -> TxOrigin {
+        match self.get::<usize>(TXS_ORIGINAL_KEY) {
+            Some(&NETWORK_TXS) => TxOrigin::Network,
+            Some(&INTERNAL_TXS) => TxOrigin::Internal,
+            _ => TxOrigin::Api,
+        }
     }
 }
@@ -7,48 +7,90 @@ mod map;
 #[cfg(test)]
 mod tests;
 mod tx_cache;
+mod wal;
 
 pub use adapter::message::{
     MsgNewTxs, MsgPullTxs, MsgPushTxs, NewTxsHandler, PullTxsHandler, END_GOSSIP_NEW_TXS,
     RPC_PULL_TXS, RPC_RESP_PULL_TXS, RPC_RESP_PULL_TXS_SYNC,
 };
-pub use adapter::DefaultMemPoolAdapter;
-pub use adapter::{DEFAULT_BROADCAST_TXS_INTERVAL, DEFAULT_BROADCAST_TXS_SIZE};
+pub use adapter::{BroadcastMode, DefaultMemPoolAdapter};
+pub use adapter::{
+    DEFAULT_BROADCAST_CHANNEL_SIZE, DEFAULT_BROADCAST_TXS_INTERVAL, DEFAULT_BROADCAST_TXS_SIZE,
+};
+pub use wal::MemPoolWAL;
 
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::error::Error;
-use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::Arc;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
 use async_trait::async_trait;
 use derive_more::Display;
+use futures::channel::mpsc::{channel, Receiver, Sender};
 use futures::future::try_join_all;
+use parking_lot::Mutex;
 use tokio::sync::RwLock;
 
 use protocol::traits::{Context, MemPool, MemPoolAdapter, MixedTxHashes};
-use protocol::types::{Hash, SignedTransaction};
+use protocol::types::{Address, Hash, SignedTransaction};
 use protocol::{ProtocolError, ProtocolErrorKind, ProtocolResult};
 
-use crate::context::TxContext;
+use crate::context::{TxContext, TxOrigin};
 use crate::map::Map;
 use crate::tx_cache::TxCache;
 
+/// Capacity events buffered for one subscriber, newer events are dropped
+/// until a lagging subscriber catches up.
+const CAPACITY_EVENTS_BUFFER: usize = 16;
+
 /// Memory pool for caching transactions.
 pub struct HashMemPool<Adapter: MemPoolAdapter> {
     /// Pool size limit.
-    pool_size:      usize,
+    pool_size:              usize,
     /// A system param limits the life time of an off-chain transaction.
-    timeout_gap:    AtomicU64,
+    timeout_gap:            AtomicU64,
+    /// How far ahead of latest height a new transaction's timeout may be,
+    /// tightens `timeout_gap` for insertion only, not for packaging.
+    future_block_tolerance: Option<u64>,
     /// A structure for caching new transactions and responsible transactions of
     /// propose-sync.
-    tx_cache:       TxCache,
+    tx_cache:               TxCache,
     /// A structure for caching fresh transactions in order transaction hashes.
-    callback_cache: Arc<Map<SignedTransaction>>,
+    callback_cache:         Arc<Map<SignedTransaction>>,
+    /// Transactions of recent blocks loaded by `warm_up`, unlike callback
+    /// cache they are kept across flushes until `timeout_gap` blocks after
+    /// `warmed_height`.
+    warmed_cache:           Map<SignedTransaction>,
+    /// Latest height when warmed up.
+    warmed_height:          AtomicU64,
     /// Supply necessary functions from outer modules.
-    adapter:        Arc<Adapter>,
+    adapter:                Arc<Adapter>,
     /// exclusive flush_memory and insert_tx to avoid repeat txs insertion.
-    flush_lock:     RwLock<()>,
+    flush_lock:             RwLock<()>,
+    /// Hashes of transactions being inserted, guards the window between
+    /// existence check and insertion against concurrent inserts of same tx.
+    in_flight:              Mutex<HashSet<Hash>>,
+    /// Optional write-ahead log keeping pending transactions across restarts.
+    wal:                    Option<MemPoolWAL>,
+    /// Called with hashes of transactions included in a block on flush.
+    on_included:            Option<Box<dyn Fn(&[Hash]) + Send + Sync>>,
+    /// Whether the pool reached `pool_size` when last checked, only its
+    /// transitions are sent to capacity subscribers.
+    is_full:                AtomicBool,
+    capacity_subscribers:   Mutex<Vec<Sender<CapacityEvent>>>,
+    /// How long a flushed transaction is still rejected as duplicated.
+    dedup_window:           Option<Duration>,
+    /// Hashes of flushed transactions and when they were flushed.
+    recently_flushed:       Mutex<HashMap<Hash, Instant>>,
+}
+
+/// Sent when the pool transitions across `pool_size`, so that producers can
+/// pause submitting on `Full` and resume on `HasRoom`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CapacityEvent {
+    Full,
+    HasRoom,
 }
 
 impl<Adapter: 'static> HashMemPool<Adapter>
@@ -57,20 +99,32 @@ where
 {
     pub async fn new(
         pool_size: usize,
+        future_block_tolerance: Option<u64>,
         adapter: Adapter,
         initial_txs: Vec<SignedTransaction>,
     ) -> Self {
         let mempool = HashMemPool {
             pool_size,
             timeout_gap: AtomicU64::new(0),
+            future_block_tolerance,
             tx_cache: TxCache::new(pool_size * 2),
             callback_cache: Arc::new(Map::new(pool_size)),
+            warmed_cache: Map::new(pool_size),
+            warmed_height: AtomicU64::new(0),
             adapter: Arc::new(adapter),
             flush_lock: RwLock::new(()),
+            in_flight: Mutex::new(HashSet::new()),
+            wal: None,
+            on_included: None,
+            is_full: AtomicBool::new(false),
+            capacity_subscribers: Mutex::new(Vec::new()),
+            dedup_window: None,
+            recently_flushed: Mutex::new(HashMap::new()),
         };
 
         for tx in initial_txs.into_iter() {
-            if let Err(e) = mempool.initial_insert(Context::new(), tx).await {
+            let ctx = Context::new().mark_internal_origin_txs();
+            if let Err(e) = mempool.initial_insert(ctx, tx).await {
                 log::warn!("[mempool]: initial insert tx failed {:?}", e);
             }
         }
@@ -78,6 +132,112 @@ where
         mempool
     }
 
+    pub fn with_wal(mut self, wal: MemPoolWAL) -> Self {
+        self.wal = Some(wal);
+        self
+    }
+
+    /// Notify `callback` of transactions included in a block, it's called
+    /// on flush with the committed transaction hashes.
+    pub fn on_included(mut self, callback: Box<dyn Fn(&[Hash]) + Send + Sync>) -> Self {
+        self.on_included = Some(callback);
+        self
+    }
+
+    /// Keep rejecting flushed transactions as duplicated for `window`, so that
+    /// a re-submitted one isn't accepted and broadcast again right after it
+    /// leaves the pool.
+    pub fn with_dedup_window(mut self, window: Duration) -> Self {
+        self.dedup_window = Some(window);
+        self
+    }
+
+    /// Replay transactions saved in wal, each one is verified again as a new
+    /// transaction. Committed, expired or invalid transactions are pruned from
+    /// wal. Return the number of recovered transactions.
+    pub async fn recover(&self) -> ProtocolResult<usize> {
+        let wal = match &self.wal {
+            Some(wal) => wal,
+            None => return Ok(0),
+        };
+
+        let mut recovered = 0;
+        for tx in wal.load_all().await?.into_iter() {
+            let tx_hash = tx.tx_hash.clone();
+            // Already in pool, for example, recovered from consensus wal.
+            if self.tx_cache.check_exist(&tx_hash).await.is_err() {
+                continue;
+            }
+            // Keep the rest in wal for the next restart.
+            if self
+                .tx_cache
+                .check_reach_limit(self.pool_size)
+                .await
+                .is_err()
+            {
+                break;
+            }
+
+            let ctx = Context::new().mark_internal_origin_txs();
+            match self.insert_tx(ctx, tx, TxType::NewTx).await {
+                Ok(()) => recovered += 1,
+                Err(e) => {
+                    log::info!("[mempool]: drop tx {:?} from wal {:?}", tx_hash, e);
+                    wal.remove(&[tx_hash]).await?;
+                }
+            }
+        }
+
+        log::info!("[mempool]: recover {} txs from wal", recovered);
+        Ok(recovered)
+    }
+
+    /// Preload transactions of committed blocks from `from_height` to the
+    /// latest one, so that peers fetching recent full blocks are served right
+    /// after a restart instead of waiting for them to be pulled again. Newer
+    /// blocks are loaded first, at most `pool_size` transactions in total.
+    /// They are kept until `timeout_gap` blocks after the latest one. Return
+    /// the number of loaded transactions.
+    ///
+    /// Uncommitted transactions aren't in storage, those of the next proposal
+    /// are recovered from consensus wal by `new`, and pending ones by
+    /// `recover`.
+    pub async fn warm_up(&self, ctx: Context, from_height: u64) -> ProtocolResult<usize> {
+        let latest_height = self.adapter.get_latest_height(ctx.clone()).await?;
+
+        let mut loaded = 0;
+        'blocks: for height in (from_height..=latest_height).rev() {
+            let txs = self
+                .adapter
+                .get_block_transactions(ctx.clone(), height)
+                .await?;
+            for tx in txs.into_iter() {
+                if loaded >= self.pool_size {
+                    break 'blocks;
+                }
+                self.warmed_cache.insert(tx.tx_hash.clone(), tx).await;
+                loaded += 1;
+            }
+        }
+
+        self.warmed_height.store(latest_height, Ordering::Relaxed);
+
+        log::info!(
+            "[mempool]: warm up {} txs since height {}",
+            loaded,
+            from_height
+        );
+        Ok(loaded)
+    }
+
+    /// Subscribe to pool capacity transitions. Only changes are sent, the
+    /// current state isn't.
+    pub fn capacity_events(&self) -> Receiver<CapacityEvent> {
+        let (tx, rx) = channel(CAPACITY_EVENTS_BUFFER);
+        self.capacity_subscribers.lock().push(tx);
+        rx
+    }
+
     pub fn get_tx_cache(&self) -> &TxCache {
         &self.tx_cache
     }
@@ -86,16 +246,28 @@ where
         &self.callback_cache
     }
 
+    pub fn get_warmed_cache(&self) -> &Map<SignedTransaction> {
+        &self.warmed_cache
+    }
+
     pub fn get_adapter(&self) -> &Adapter {
         &self.adapter
     }
 
+    /// Pending transactions sent by `sender`, in no particular order. Wallets
+    /// use them to compute next nonce or to pick one to cancel.
+    pub async fn pending_by_sender(&self, sender: &Address) -> Vec<SignedTransaction> {
+        self.tx_cache.get_by_sender(sender).await
+    }
+
     async fn show_unknown_txs(&self, tx_hashes: &[Hash]) -> Vec<Hash> {
         let tx_hashes = self.tx_cache.show_unknown(tx_hashes).await;
         let mut unknown_hashes = vec![];
 
         for tx_hash in tx_hashes.into_iter() {
-            if !self.callback_cache.contains_key(&tx_hash).await {
+            if !self.callback_cache.contains_key(&tx_hash).await
+                && !self.warmed_cache.contains_key(&tx_hash).await
+            {
                 unknown_hashes.push(tx_hash)
             }
         }
@@ -110,7 +282,7 @@ where
         self.adapter
             .check_storage_exist(ctx.clone(), &tx.tx_hash)
             .await?;
-        self.tx_cache.insert_propose_tx(tx).await
+        self.tx_cache.insert_propose_tx(tx, ctx.tx_origin()).await
     }
 
     async fn insert_tx(
@@ -120,22 +292,45 @@ where
         tx_type: TxType,
     ) -> ProtocolResult<()> {
         let _lock = self.flush_lock.read().await;
+        let _in_flight = InFlightGuard::new(&self.in_flight, &tx.tx_hash)?;
 
         let tx = Box::new(tx);
         let tx_hash = &tx.tx_hash;
         self.tx_cache.check_reach_limit(self.pool_size).await?;
         self.tx_cache.check_exist(tx_hash).await?;
+        self.check_recently_flushed(tx_hash)?;
+        if let TxType::NewTx = tx_type {
+            self.check_future_block_tolerance(ctx.clone(), &tx).await?;
+        }
         self.adapter
             .check_authorization(ctx.clone(), tx.clone())
             .await?;
-        self.adapter.check_transaction(ctx.clone(), &tx).await?;
+        self.adapter
+            .check_transaction(ctx.clone(), &tx, None)
+            .await?;
         self.adapter
             .check_storage_exist(ctx.clone(), tx_hash)
             .await?;
 
+        // Propose transactions are always pulled from other nodes
+        let origin = match tx_type {
+            TxType::NewTx => ctx.tx_origin(),
+            TxType::ProposeTx => TxOrigin::Network,
+        };
         match tx_type {
-            TxType::NewTx => self.tx_cache.insert_new_tx(*tx.clone()).await?,
-            TxType::ProposeTx => self.tx_cache.insert_propose_tx(*tx.clone()).await?,
+            TxType::NewTx => self.tx_cache.insert_new_tx(*tx.clone(), origin).await?,
+            TxType::ProposeTx => self.tx_cache.insert_propose_tx(*tx.clone(), origin).await?,
+        }
+        common_apm::metrics::mempool::MEMPOOL_INSERT_TX_ORIGIN_COUNTER_VEC
+            .with_label_values(&[origin.as_str()])
+            .inc();
+        self.notify_capacity().await;
+
+        // A failed wal write only costs durability, the tx is still in pool.
+        if let Some(wal) = &self.wal {
+            if let Err(e) = wal.save(&tx).await {
+                log::error!("[mempool]: save tx {:?} to wal failed {:?}", tx_hash, e);
+            }
         }
 
         if !ctx.is_network_origin_txs() {
@@ -147,7 +342,87 @@ where
         Ok(())
     }
 
-    async fn verify_tx_in_parallel(&self, ctx: Context, tx_ptrs: Vec<usize>) -> ProtocolResult<()> {
+    async fn notify_capacity(&self) {
+        let is_full = self.tx_cache.len().await >= self.pool_size;
+        if self.is_full.swap(is_full, Ordering::SeqCst) == is_full {
+            return;
+        }
+
+        let event = if is_full {
+            CapacityEvent::Full
+        } else {
+            CapacityEvent::HasRoom
+        };
+        // Dropped receivers are unsubscribed, a full buffer only drops this event.
+        let mut subscribers = self.capacity_subscribers.lock();
+        *subscribers = std::mem::take(&mut *subscribers)
+            .into_iter()
+            .filter_map(|mut tx| match tx.try_send(event) {
+                Err(e) if e.is_disconnected() => None,
+                _ => Some(tx),
+            })
+            .collect();
+    }
+
+    fn check_recently_flushed(&self, tx_hash: &Hash) -> ProtocolResult<()> {
+        let window = match self.dedup_window {
+            Some(window) => window,
+            None => return Ok(()),
+        };
+
+        let recently_flushed = self.recently_flushed.lock();
+        match recently_flushed.get(tx_hash) {
+            Some(flushed_at) if flushed_at.elapsed() < window => Err(MemPoolError::Dup {
+                tx_hash: tx_hash.clone(),
+            }
+            .into()),
+            _ => Ok(()),
+        }
+    }
+
+    fn record_flushed(&self, tx_hashes: &[Hash]) {
+        let window = match self.dedup_window {
+            Some(window) => window,
+            None => return,
+        };
+
+        let now = Instant::now();
+        let mut recently_flushed = self.recently_flushed.lock();
+        recently_flushed.retain(|_, flushed_at| now.duration_since(*flushed_at) < window);
+        for tx_hash in tx_hashes.iter() {
+            recently_flushed.insert(tx_hash.clone(), now);
+        }
+    }
+
+    async fn check_future_block_tolerance(
+        &self,
+        ctx: Context,
+        tx: &SignedTransaction,
+    ) -> ProtocolResult<()> {
+        let tolerance = match self.future_block_tolerance {
+            Some(tolerance) => tolerance,
+            None => return Ok(()),
+        };
+
+        let latest_height = self.adapter.get_latest_height(ctx).await?;
+        if tx.raw.timeout > latest_height.saturating_add(tolerance) {
+            return Err(MemPoolError::InvalidTimeout {
+                tx_hash: tx.tx_hash.clone(),
+            }
+            .into());
+        }
+
+        Ok(())
+    }
+
+    /// Verify transactions of block at `height`, see
+    /// `MemPoolAdapter::check_transaction`.
+    async fn verify_tx_in_parallel(
+        &self,
+        ctx: Context,
+        height: Option<u64>,
+        tx_ptrs: Vec<usize>,
+    ) -> ProtocolResult<()> {
         let now = Instant::now();
         let len = tx_ptrs.len();
 
@@ -162,7 +437,9 @@ where
                     let signed_tx = *(boxed_stx.clone());
 
                     adapter.check_authorization(ctx.clone(), boxed_stx).await?;
-                    adapter.check_transaction(ctx.clone(), &signed_tx).await?;
+                    adapter
+                        .check_transaction(ctx.clone(), &signed_tx, height)
+                        .await?;
                     adapter
                         .check_storage_exist(ctx.clone(), &signed_tx.tx_hash)
                         .await
@@ -251,10 +528,41 @@ where
             )
             .await;
         self.callback_cache.clear().await;
+        let timeout_gap = self.timeout_gap.load(Ordering::Relaxed);
+        if current_height > self.warmed_height.load(Ordering::Relaxed) + timeout_gap {
+            self.warmed_cache.clear().await;
+        }
+        self.record_flushed(tx_hashes);
+        self.notify_capacity().await;
+        // Transactions are committed already, failing here would fail the commit.
+        // Files left behind are pruned on recover since they are in storage.
+        if let Some(wal) = &self.wal {
+            if let Err(e) = wal.remove(tx_hashes).await {
+                log::error!("[mempool]: remove flushed txs from wal failed {:?}", e);
+            }
+        }
+        if let Some(callback) = &self.on_included {
+            callback(tx_hashes);
+        }
 
         Ok(())
     }
 
+    async fn remove(&self, _ctx: Context, tx_hash: Hash) -> ProtocolResult<bool> {
+        let _lock = self.flush_lock.read().await;
+
+        let in_tx_cache = self.tx_cache.remove(&tx_hash).await;
+        self.notify_capacity().await;
+        let in_callback_cache = self.callback_cache.remove(&tx_hash).await.is_some();
+        if let Some(wal) = &self.wal {
+            if let Err(e) = wal.remove(std::slice::from_ref(&tx_hash)).await {
+                log::error!("[mempool]: remove tx {:?} from wal failed {:?}", tx_hash, e);
+            }
+        }
+
+        Ok(in_tx_cache || in_callback_cache)
+    }
+
     async fn get_full_txs(
         &self,
         ctx: Context,
@@ -270,6 +578,8 @@ where
                 full_txs.push(tx);
             } else if let Some(tx) = self.callback_cache.get(tx_hash).await {
                 full_txs.push(tx);
+            } else if let Some(tx) = self.warmed_cache.get(tx_hash).await {
+                full_txs.push(tx);
             } else {
                 missing_hashes.push(tx_hash.clone());
             }
@@ -334,7 +644,8 @@ where
                 })
                 .unzip();
 
-            self.verify_tx_in_parallel(ctx.clone(), tx_ptrs).await?;
+            self.verify_tx_in_parallel(ctx.clone(), height, tx_ptrs)
+                .await?;
 
             for signed_tx in txs.into_iter() {
                 self.callback_cache
@@ -390,6 +701,36 @@ fn check_dup_order_hashes(order_tx_hashes: &[Hash]) -> ProtocolResult<()> {
     Ok(())
 }
 
+/// Marks a transaction hash as being inserted, and unmarks it on drop, so
+/// that error paths release the hash as well.
+struct InFlightGuard<'a> {
+    in_flight: &'a Mutex<HashSet<Hash>>,
+    tx_hash:   Hash,
+}
+
+impl<'a> InFlightGuard<'a> {
+    fn new(in_flight: &'a Mutex<HashSet<Hash>>, tx_hash: &Hash) -> ProtocolResult<Self> {
+        let mut set = in_flight.lock();
+        if !set.insert(tx_hash.clone()) {
+            return Err(MemPoolError::Dup {
+                tx_hash: tx_hash.clone(),
+            }
+            .into());
+        }
+
+        Ok(InFlightGuard {
+            in_flight,
+            tx_hash: tx_hash.clone(),
+        })
+    }
+}
+
+impl<'a> Drop for InFlightGuard<'a> {
+    fn drop(&mut self) {
+        self.in_flight.lock().remove(&self.tx_hash);
+    }
+}
+
 pub enum TxType {
     NewTx,
     ProposeTx,
@@ -421,6 +762,21 @@ pub enum MemPoolError {
         cycles_limit_tx:     u64,
     },
 
+    #[display(
+        fmt = "Tx: {:?} cycles price {} is lower than {}",
+        tx_hash,
+        cycles_price,
+        min_cycles_price
+    )]
+    CyclesPriceTooLow {
+        tx_hash:          Hash,
+        cycles_price:     u64,
+        min_cycles_price: u64,
+    },
+
+    #[display(fmt = "Tx: {:?} sender {:?} is blacklisted", tx_hash, sender)]
+    SenderBlacklisted { tx_hash: Hash, sender: Address },
+
     #[display(fmt = "Tx: {:?} inserts failed", tx_hash)]
     Insert { tx_hash: Hash },
 
@@ -439,6 +795,12 @@ pub enum MemPoolError {
     )]
     EnsureDup { hash: Hash },
 
+    #[display(
+        fmt = "Package duplicated tx_hash {:?}, tx cache is inconsistent",
+        hash
+    )]
+    PackageDup { hash: Hash },
+
     #[display(fmt = "Fetch full txs, require: {}, response: {}", require, response)]
     MisMatch { require: usize, response: usize },
 
@@ -468,6 +830,9 @@ pub enum MemPoolError {
 
     #[display(fmt = "Encode transaction to JSON failed")]
     EncodeJson,
+
+    #[display(fmt = "Mempool wal error {:?}", _0)]
+    WALErr(std::io::Error),
 }
 
 impl Error for MemPoolError {}
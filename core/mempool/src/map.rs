@@ -44,7 +44,7 @@ where
         bucket.get(hash).await
     }
 
-    pub async fn remove(&self, hash: &Hash) {
+    pub async fn remove(&self, hash: &Hash) -> Option<V> {
         let bucket = self.get_bucket(hash);
         bucket.remove(hash).await
     }
@@ -129,9 +129,9 @@ where
         self.store.read().await.get(hash).map(Clone::clone)
     }
 
-    async fn remove(&self, hash: &Hash) {
+    async fn remove(&self, hash: &Hash) -> Option<V> {
         let mut store = self.store.write().await;
-        store.remove(hash);
+        store.remove(hash)
     }
 
     async fn remove_batch(&self, hashes: Vec<Hash>) {
@@ -1,5 +1,7 @@
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
+use futures::future::Either;
 use test::Bencher;
 
 use protocol::types::Hash;
@@ -56,6 +58,36 @@ async fn test_insert() {
     insert!(invalid(80, 10, 80));
 }
 
+#[tokio::test]
+async fn test_concurrent_insert_same_tx() {
+    let mempool = Arc::new(default_mempool().await);
+    let tx = default_mock_txs(1).remove(0);
+
+    // Park the first insert inside `check_transaction`, after it passed the
+    // existence check but before the tx reaches the cache.
+    let gate = mempool.get_adapter().check_gate.lock().await;
+    let first = {
+        let mempool = Arc::clone(&mempool);
+        let tx = tx.clone();
+        tokio::spawn(async move { mempool.insert(Context::new(), tx).await })
+    };
+    while mempool.get_adapter().checking.load(Ordering::SeqCst) == 0 {
+        tokio::task::yield_now().await;
+    }
+
+    let second = Box::pin(mempool.insert(Context::new(), tx));
+    let timeout = futures_timer::Delay::new(Duration::from_secs(5));
+    match futures::future::select(second, timeout).await {
+        Either::Left((res, _)) => assert!(res.is_err()),
+        Either::Right(_) => panic!("second insert entered the check window"),
+    }
+    assert_eq!(mempool.get_adapter().checking.load(Ordering::SeqCst), 1);
+
+    drop(gate);
+    assert!(first.await.expect("insert task").is_ok());
+    assert_eq!(mempool.get_tx_cache().len().await, 1);
+}
+
 macro_rules! package {
     (normal($tx_num_limit: expr, $insert: expr, $expect_order: expr, $expect_propose: expr)) => {
         package!(inner(
@@ -159,6 +191,63 @@ async fn test_flush() {
     assert_eq!(mempool.get_tx_cache().queue_len(), 432);
 }
 
+#[tokio::test]
+async fn test_remove() {
+    let mempool = Arc::new(default_mempool().await);
+
+    let txs = default_mock_txs(10);
+    concurrent_insert(txs.clone(), Arc::clone(&mempool)).await;
+    assert_eq!(mempool.get_tx_cache().len().await, 10);
+
+    let tx_hash = txs[0].tx_hash.clone();
+    let removed = mempool.remove(Context::new(), tx_hash.clone()).await;
+    assert_eq!(removed.unwrap(), true);
+    assert_eq!(mempool.get_tx_cache().len().await, 9);
+
+    let full_txs = mempool
+        .get_full_txs(Context::new(), None, &[tx_hash.clone()])
+        .await;
+    assert!(full_txs.is_err());
+
+    // remove absent tx
+    let removed = mempool.remove(Context::new(), tx_hash).await;
+    assert_eq!(removed.unwrap(), false);
+
+    // removed tx should not be packaged
+    let mixed_tx_hashes = exec_package(Arc::clone(&mempool), CYCLE_LIMIT, TX_NUM_LIMIT).await;
+    assert_eq!(mixed_tx_hashes.order_tx_hashes.len(), 9);
+}
+
+#[tokio::test]
+async fn test_pending_by_sender() {
+    let mempool = Arc::new(default_mempool().await);
+
+    let txs_a = default_mock_txs(3);
+    let txs_b = default_mock_txs(5);
+    let sender_a = txs_a[0].raw.sender.clone();
+    let sender_b = txs_b[0].raw.sender.clone();
+    concurrent_insert(txs_a.clone(), Arc::clone(&mempool)).await;
+    concurrent_insert(txs_b.clone(), Arc::clone(&mempool)).await;
+
+    let sorted_hashes = |txs: &[SignedTransaction]| {
+        let mut hashes = txs.iter().map(|tx| tx.tx_hash.clone()).collect::<Vec<_>>();
+        hashes.sort_by(|a, b| a.as_bytes().cmp(&b.as_bytes()));
+        hashes
+    };
+
+    let pending_a = mempool.pending_by_sender(&sender_a).await;
+    assert_eq!(sorted_hashes(&pending_a), sorted_hashes(&txs_a));
+    let pending_b = mempool.pending_by_sender(&sender_b).await;
+    assert_eq!(sorted_hashes(&pending_b), sorted_hashes(&txs_b));
+
+    exec_flush(vec![txs_a[0].tx_hash.clone()], Arc::clone(&mempool)).await;
+    let pending_a = mempool.pending_by_sender(&sender_a).await;
+    assert_eq!(sorted_hashes(&pending_a), sorted_hashes(&txs_a[1..]));
+
+    let unknown = default_mock_txs(1)[0].raw.sender.clone();
+    assert!(mempool.pending_by_sender(&unknown).await.is_empty());
+}
+
 macro_rules! ensure_order_txs {
     ($in_pool: expr, $out_pool: expr) => {
         let mempool = &Arc::new(default_mempool().await);
@@ -486,3 +575,241 @@ fn bench_check_sig_serial_1000(b: &mut Bencher) {
         }
     })
 }
+
+#[tokio::test]
+async fn test_recover_from_wal() {
+    let wal_path = "./free-space/mempool/wal";
+    let _ = std::fs::remove_dir_all(wal_path);
+
+    let mempool = Arc::new(default_mempool().await.with_wal(MemPoolWAL::new(wal_path)));
+    let txs = default_mock_txs(10);
+    concurrent_insert(txs.clone(), Arc::clone(&mempool)).await;
+    assert_eq!(mempool.get_tx_cache().len().await, 10);
+
+    // flush committed txs, they should be pruned from wal.
+    let (committed_txs, pending_txs) = txs.split_at(4);
+    let committed_hashes: Vec<Hash> = committed_txs.iter().map(|tx| tx.tx_hash.clone()).collect();
+    exec_flush(committed_hashes, Arc::clone(&mempool)).await;
+    drop(mempool);
+
+    // restart with the same wal.
+    let mempool = default_mempool().await.with_wal(MemPoolWAL::new(wal_path));
+    assert_eq!(mempool.recover().await.unwrap(), 6);
+    assert_eq!(mempool.get_tx_cache().len().await, 6);
+    for tx in pending_txs.iter() {
+        assert!(mempool.get_tx_cache().get(&tx.tx_hash).await.is_some());
+    }
+
+    std::fs::remove_dir_all(wal_path).unwrap();
+}
+
+#[tokio::test]
+async fn test_future_block_tolerance() {
+    let tolerance = 10;
+    let mempool = HashMemPool::new(
+        POOL_SIZE,
+        Some(tolerance),
+        HashMemPoolAdapter::new(),
+        vec![],
+    )
+    .await;
+    mempool.set_args(TIMEOUT_GAP, CYCLE_LIMIT, MAX_TX_SIZE);
+
+    // exactly at the tolerance
+    let tx = mock_txs(1, 0, CURRENT_HEIGHT + tolerance).remove(0);
+    assert!(mempool.insert(Context::new(), tx).await.is_ok());
+
+    // one block over the tolerance
+    let tx = mock_txs(1, 0, CURRENT_HEIGHT + tolerance + 1).remove(0);
+    assert!(mempool.insert(Context::new(), tx).await.is_err());
+    assert_eq!(mempool.get_tx_cache().len().await, 1);
+}
+
+#[tokio::test]
+async fn test_on_included() {
+    let included = Arc::new(Mutex::new(Vec::new()));
+    let included_clone = Arc::clone(&included);
+    let mempool = default_mempool()
+        .await
+        .on_included(Box::new(move |hashes: &[Hash]| {
+            included_clone.lock().unwrap().extend_from_slice(hashes)
+        }));
+    let mempool = Arc::new(mempool);
+
+    let txs = default_mock_txs(10);
+    concurrent_insert(txs.clone(), Arc::clone(&mempool)).await;
+    assert!(included.lock().unwrap().is_empty());
+
+    let flush_hashes: Vec<Hash> = txs[..4].iter().map(|tx| tx.tx_hash.clone()).collect();
+    exec_flush(flush_hashes.clone(), Arc::clone(&mempool)).await;
+    assert_eq!(*included.lock().unwrap(), flush_hashes);
+}
+
+#[tokio::test]
+async fn test_dedup_window() {
+    let window = Duration::from_millis(200);
+    let mempool = Arc::new(default_mempool().await.with_dedup_window(window));
+
+    let txs = default_mock_txs(2);
+    concurrent_insert(txs.clone(), Arc::clone(&mempool)).await;
+    exec_flush(vec![txs[0].tx_hash.clone()], Arc::clone(&mempool)).await;
+    assert_eq!(mempool.get_tx_cache().len().await, 1);
+
+    // Re-submitted within the window.
+    let err = mempool
+        .insert(Context::new(), txs[0].clone())
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("Dup"));
+    assert_eq!(mempool.get_tx_cache().len().await, 1);
+
+    // Accepted again once the window passed.
+    futures_timer::Delay::new(window).await;
+    assert!(mempool.insert(Context::new(), txs[0].clone()).await.is_ok());
+    assert_eq!(mempool.get_tx_cache().len().await, 2);
+}
+
+#[tokio::test]
+async fn test_origin_counter() {
+    use common_apm::metrics::mempool::{
+        MEMPOOL_INSERT_TX_ORIGIN_COUNTER_VEC, MEMPOOL_PACKAGE_TX_ORIGIN_COUNTER_VEC,
+    };
+
+    use crate::context::TxContext;
+
+    let insert_counter = |origin| {
+        MEMPOOL_INSERT_TX_ORIGIN_COUNTER_VEC
+            .with_label_values(&[origin])
+            .get()
+    };
+    let package_counter = |origin| {
+        MEMPOOL_PACKAGE_TX_ORIGIN_COUNTER_VEC
+            .with_label_values(&[origin])
+            .get()
+    };
+    let (network_inserted, network_packaged) =
+        (insert_counter("network"), package_counter("network"));
+    let api_inserted = insert_counter("api");
+
+    let mempool = default_mempool().await;
+    let network_txs = default_mock_txs(3);
+    for tx in network_txs.into_iter() {
+        let ctx = Context::new().mark_network_origin_new_txs();
+        mempool.insert(ctx, tx).await.unwrap();
+    }
+    for tx in default_mock_txs(2).into_iter() {
+        mempool.insert(Context::new(), tx).await.unwrap();
+    }
+
+    assert_eq!(insert_counter("network") - network_inserted, 3);
+    assert!(insert_counter("api") - api_inserted >= 2);
+
+    mempool
+        .package(Context::new(), CYCLE_LIMIT, TX_NUM_LIMIT)
+        .await
+        .unwrap();
+    assert_eq!(package_counter("network") - network_packaged, 3);
+}
+
+#[tokio::test]
+async fn test_capacity_events() {
+    let mempool = Arc::new(new_mempool(4, TIMEOUT_GAP, CYCLE_LIMIT, MAX_TX_SIZE).await);
+    let mut events = mempool.capacity_events();
+
+    let txs = default_mock_txs(4);
+    concurrent_insert(txs[..3].to_vec(), Arc::clone(&mempool)).await;
+    assert!(events.try_next().is_err());
+
+    exec_insert(txs[3].clone(), Arc::clone(&mempool)).await;
+    assert_eq!(events.try_next().unwrap(), Some(CapacityEvent::Full));
+    // Debounced, a rejected insert on a full pool sends nothing
+    exec_insert(default_mock_txs(1).remove(0), Arc::clone(&mempool)).await;
+    assert!(events.try_next().is_err());
+
+    let flush_hashes: Vec<Hash> = txs[..2].iter().map(|tx| tx.tx_hash.clone()).collect();
+    exec_flush(flush_hashes, Arc::clone(&mempool)).await;
+    assert_eq!(events.try_next().unwrap(), Some(CapacityEvent::HasRoom));
+    assert!(events.try_next().is_err());
+
+    // A dropped receiver is unsubscribed on the next transition
+    drop(events);
+    concurrent_insert(default_mock_txs(2), Arc::clone(&mempool)).await;
+    assert!(mempool.capacity_subscribers.lock().is_empty());
+}
+
+#[tokio::test]
+async fn test_capacity_events_bounded() {
+    let mempool = Arc::new(new_mempool(1, TIMEOUT_GAP, CYCLE_LIMIT, MAX_TX_SIZE).await);
+    let mut events = mempool.capacity_events();
+
+    // A lagging subscriber buffers a bounded number of events
+    for tx in default_mock_txs(CAPACITY_EVENTS_BUFFER * 2).into_iter() {
+        exec_insert(tx.clone(), Arc::clone(&mempool)).await;
+        exec_flush(vec![tx.tx_hash], Arc::clone(&mempool)).await;
+    }
+
+    let mut received = 0;
+    while let Ok(Some(_)) = events.try_next() {
+        received += 1;
+    }
+    assert!(received > 0 && received <= CAPACITY_EVENTS_BUFFER + 1);
+    assert_eq!(mempool.capacity_subscribers.lock().len(), 1);
+}
+
+#[tokio::test]
+async fn test_warm_up() {
+    let mempool = default_mempool().await;
+    let txs = default_mock_txs(6);
+    let committed_txs = &mempool.get_adapter().committed_txs;
+    committed_txs.insert(CURRENT_HEIGHT, txs[..2].to_vec());
+    committed_txs.insert(CURRENT_HEIGHT - 1, txs[2..4].to_vec());
+    committed_txs.insert(CURRENT_HEIGHT - 5, txs[4..].to_vec());
+
+    let loaded = mempool
+        .warm_up(Context::new(), CURRENT_HEIGHT - 1)
+        .await
+        .unwrap();
+    assert_eq!(loaded, 4);
+
+    let warmed_hashes: Vec<Hash> = txs[..4].iter().map(|tx| tx.tx_hash.clone()).collect();
+    let full_txs = mempool
+        .get_full_txs(Context::new(), None, &warmed_hashes)
+        .await
+        .unwrap();
+    assert_eq!(full_txs, txs[..4].to_vec());
+
+    // older than `from_height`
+    let old_hashes = vec![txs[4].tx_hash.clone()];
+    assert!(mempool
+        .get_full_txs(Context::new(), None, &old_hashes)
+        .await
+        .is_err());
+
+    // kept across flushes until timeout gap passes
+    mempool.set_args(TIMEOUT_GAP, CYCLE_LIMIT, MAX_TX_SIZE);
+    let latest_height = &mempool.get_adapter().latest_height;
+    latest_height.store(CURRENT_HEIGHT + TIMEOUT_GAP, Ordering::SeqCst);
+    mempool.flush(Context::new(), &[]).await.unwrap();
+    let full_txs = mempool
+        .get_full_txs(Context::new(), None, &warmed_hashes)
+        .await
+        .unwrap();
+    assert_eq!(full_txs, txs[..4].to_vec());
+
+    latest_height.store(CURRENT_HEIGHT + TIMEOUT_GAP + 1, Ordering::SeqCst);
+    mempool.flush(Context::new(), &[]).await.unwrap();
+    assert_eq!(mempool.get_warmed_cache().len().await, 0);
+
+    // bounded by pool size
+    let small_mempool = new_mempool(3, TIMEOUT_GAP, CYCLE_LIMIT, MAX_TX_SIZE).await;
+    let committed_txs = &small_mempool.get_adapter().committed_txs;
+    committed_txs.insert(CURRENT_HEIGHT, txs.clone());
+    assert_eq!(
+        small_mempool
+            .warm_up(Context::new(), CURRENT_HEIGHT)
+            .await
+            .unwrap(),
+        3
+    );
+    assert_eq!(small_mempool.get_warmed_cache().len().await, 3);
+}
@@ -3,11 +3,13 @@ extern crate test;
 mod mempool;
 
 use std::convert::{From, TryFrom};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
 use std::sync::Arc;
 
 use async_trait::async_trait;
 use chashmap::CHashMap;
 use futures::executor;
+use futures::lock::Mutex as AsyncMutex;
 use rand::random;
 use rand::rngs::OsRng;
 
@@ -20,7 +22,10 @@ use protocol::traits::{Context, MemPool, MemPoolAdapter, MixedTxHashes};
 use protocol::types::{Address, Hash, RawTransaction, SignedTransaction, TransactionRequest};
 use protocol::{Bytes, ProtocolResult};
 
-use crate::{check_dup_order_hashes, HashMemPool, MemPoolError};
+use crate::{
+    check_dup_order_hashes, CapacityEvent, HashMemPool, MemPoolError, MemPoolWAL,
+    CAPACITY_EVENTS_BUFFER,
+};
 
 const CYCLE_LIMIT: u64 = 1_000_000;
 const TX_NUM_LIMIT: u64 = 10_000;
@@ -32,13 +37,23 @@ const TIMEOUT_GAP: u64 = 100;
 const TX_CYCLE: u64 = 1;
 
 pub struct HashMemPoolAdapter {
-    network_txs: CHashMap<Hash, SignedTransaction>,
+    network_txs:   CHashMap<Hash, SignedTransaction>,
+    committed_txs: CHashMap<u64, Vec<SignedTransaction>>,
+    latest_height: AtomicU64,
+    // Holding the gate parks `check_transaction`, keeping inserts in the
+    // window between the existence check and the cache insertion.
+    check_gate:    AsyncMutex<()>,
+    checking:      AtomicUsize,
 }
 
 impl HashMemPoolAdapter {
     fn new() -> HashMemPoolAdapter {
         HashMemPoolAdapter {
-            network_txs: CHashMap::new(),
+            network_txs:   CHashMap::new(),
+            committed_txs: CHashMap::new(),
+            latest_height: AtomicU64::new(CURRENT_HEIGHT),
+            check_gate:    AsyncMutex::new(()),
+            checking:      AtomicUsize::new(0),
         }
     }
 }
@@ -78,7 +93,10 @@ impl MemPoolAdapter for HashMemPoolAdapter {
         &self,
         _ctx: Context,
         _tx: &SignedTransaction,
+        _height: Option<u64>,
     ) -> ProtocolResult<()> {
+        self.checking.fetch_add(1, Ordering::SeqCst);
+        let _gate = self.check_gate.lock().await;
         Ok(())
     }
 
@@ -87,7 +105,7 @@ impl MemPoolAdapter for HashMemPoolAdapter {
     }
 
     async fn get_latest_height(&self, _ctx: Context) -> ProtocolResult<u64> {
-        Ok(CURRENT_HEIGHT)
+        Ok(self.latest_height.load(Ordering::SeqCst))
     }
 
     async fn get_transactions_from_storage(
@@ -99,6 +117,18 @@ impl MemPoolAdapter for HashMemPoolAdapter {
         Ok(vec![])
     }
 
+    async fn get_block_transactions(
+        &self,
+        _ctx: Context,
+        height: u64,
+    ) -> ProtocolResult<Vec<SignedTransaction>> {
+        Ok(self
+            .committed_txs
+            .get(&height)
+            .map(|txs| txs.clone())
+            .unwrap_or_default())
+    }
+
     fn report_good(&self, _ctx: Context) {}
 
     fn set_args(&self, _timeout_gap: u64, _cycles_limit: u64, _max_tx_size: u64) {}
@@ -134,7 +164,7 @@ async fn new_mempool(
     max_tx_size: u64,
 ) -> HashMemPool<HashMemPoolAdapter> {
     let adapter = HashMemPoolAdapter::new();
-    let mempool = HashMemPool::new(pool_size, adapter, vec![]).await;
+    let mempool = HashMemPool::new(pool_size, None, adapter, vec![]).await;
     mempool.set_args(timeout_gap, cycles_limit, max_tx_size);
     mempool
 }
@@ -1,12 +1,16 @@
-use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use crossbeam_queue::ArrayQueue;
+use tokio::sync::RwLock;
 
 use protocol::traits::MixedTxHashes;
-use protocol::types::{Hash, SignedTransaction};
+use protocol::types::{Address, Hash, SignedTransaction};
 use protocol::ProtocolResult;
 
+use crate::context::TxOrigin;
 use crate::map::Map;
 use crate::MemPoolError;
 
@@ -20,33 +24,41 @@ use crate::MemPoolError;
 /// transaction hashes for consensus.
 pub struct TxWrapper {
     /// Content.
-    tx:       SignedTransaction,
+    tx:          SignedTransaction,
     /// While map removes a `shared_tx` during flush, it will mark `removed`
     /// true. Afterwards, queue removes the transaction which marks
     /// `removed` true during package.
-    removed:  AtomicBool,
+    removed:     AtomicBool,
     /// The response transactions in propose-syncing will insert into `TxCache`
     /// marking `proposed` true.
     /// While collecting propose_tx_hashes during package,
     /// it will skips transactions which marks 'proposed` true.
-    proposed: AtomicBool,
+    proposed:    AtomicBool,
+    /// When the transaction is inserted, used to measure pending time.
+    inserted_at: Instant,
+    /// Where the transaction comes from, for metrics.
+    origin:      TxOrigin,
 }
 
 impl TxWrapper {
     #[allow(dead_code)]
-    pub(crate) fn new(tx: SignedTransaction) -> Self {
+    pub(crate) fn new(tx: SignedTransaction, origin: TxOrigin) -> Self {
         TxWrapper {
             tx,
             removed: AtomicBool::new(false),
             proposed: AtomicBool::new(false),
+            inserted_at: Instant::now(),
+            origin,
         }
     }
 
-    pub(crate) fn propose(tx: SignedTransaction) -> Self {
+    pub(crate) fn propose(tx: SignedTransaction, origin: TxOrigin) -> Self {
         TxWrapper {
             tx,
             removed: AtomicBool::new(false),
             proposed: AtomicBool::new(true),
+            inserted_at: Instant::now(),
+            origin,
         }
     }
 
@@ -119,6 +131,8 @@ pub struct TxCache {
     queue_1:          Arc<ArrayQueue<SharedTx>>,
     /// A map for randomly search and removal.
     map:              Map<SharedTx>,
+    /// Secondary index from sender to hashes of its transactions in `map`.
+    senders:          RwLock<HashMap<Address, HashSet<Hash>>>,
     /// This is used to pick a queue for insertion,
     /// If true selects `queue_0`, else `queue_1`.
     is_zero:          AtomicBool,
@@ -127,6 +141,10 @@ pub struct TxCache {
     /// still insert into the old queue. We use this state to make sure
     /// switch insertions *happen-before* old queue re-pop.
     concurrent_count: AtomicUsize,
+    /// Number of committed transactions and their total pending time in
+    /// microseconds.
+    committed_count:  AtomicU64,
+    pending_micros:   AtomicU64,
 }
 
 impl TxCache {
@@ -135,8 +153,11 @@ impl TxCache {
             queue_0:          Arc::new(ArrayQueue::new(pool_size * 2)),
             queue_1:          Arc::new(ArrayQueue::new(pool_size * 2)),
             map:              Map::new(pool_size * 2),
+            senders:          RwLock::new(HashMap::new()),
             is_zero:          AtomicBool::new(true),
             concurrent_count: AtomicUsize::new(0),
+            committed_count:  AtomicU64::new(0),
+            pending_micros:   AtomicU64::new(0),
         }
     }
 
@@ -144,16 +165,24 @@ impl TxCache {
         self.map.len().await
     }
 
-    pub async fn insert_new_tx(&self, signed_tx: SignedTransaction) -> ProtocolResult<()> {
+    pub async fn insert_new_tx(
+        &self,
+        signed_tx: SignedTransaction,
+        origin: TxOrigin,
+    ) -> ProtocolResult<()> {
         let tx_hash = signed_tx.tx_hash.clone();
-        let tx_wrapper = TxWrapper::new(signed_tx);
+        let tx_wrapper = TxWrapper::new(signed_tx, origin);
         let shared_tx = Arc::new(tx_wrapper);
         self.insert(tx_hash, shared_tx).await
     }
 
-    pub async fn insert_propose_tx(&self, signed_tx: SignedTransaction) -> ProtocolResult<()> {
+    pub async fn insert_propose_tx(
+        &self,
+        signed_tx: SignedTransaction,
+        origin: TxOrigin,
+    ) -> ProtocolResult<()> {
         let tx_hash = signed_tx.tx_hash.clone();
-        let tx_wrapper = TxWrapper::propose(signed_tx);
+        let tx_wrapper = TxWrapper::propose(signed_tx, origin);
         let shared_tx = Arc::new(tx_wrapper);
         self.insert(tx_hash, shared_tx).await
     }
@@ -171,17 +200,44 @@ impl TxCache {
     }
 
     pub async fn flush(&self, tx_hashes: &[Hash], current_height: u64, timeout: u64) {
+        let mut flushed_txs = Vec::with_capacity(tx_hashes.len());
         for tx_hash in tx_hashes {
             let opt = self.map.get(tx_hash).await;
             if let Some(shared_tx) = opt {
                 shared_tx.set_removed();
+                flushed_txs.push(shared_tx);
             }
         }
         // Dividing set removed and remove into two loops is to avoid lock competition.
         self.map.remove_batch(tx_hashes).await;
+        self.unindex_senders(&flushed_txs).await;
+        self.observe_committed(&flushed_txs);
         self.flush_incumbent_queue(current_height, timeout).await;
     }
 
+    /// Average time committed transactions stayed in cache, zero if none was
+    /// committed yet.
+    pub fn average_pending_time(&self) -> Duration {
+        let count = self.committed_count.load(Ordering::SeqCst);
+        if count == 0 {
+            return Duration::from_micros(0);
+        }
+
+        Duration::from_micros(self.pending_micros.load(Ordering::SeqCst) / count)
+    }
+
+    /// Remove a single transaction, return whether it was in cache. The queue
+    /// element is only marked `removed` and will be dropped during package.
+    pub async fn remove(&self, tx_hash: &Hash) -> bool {
+        if let Some(shared_tx) = self.map.remove(tx_hash).await {
+            shared_tx.set_removed();
+            self.unindex_senders(&[shared_tx]).await;
+            true
+        } else {
+            false
+        }
+    }
+
     pub async fn package(
         &self,
         _cycles_limit: u64,
@@ -193,10 +249,12 @@ impl TxCache {
 
         let mut order_tx_hashes = Vec::new();
         let mut propose_tx_hashes = Vec::new();
-        let mut timeout_tx_hashes = Vec::new();
+        let mut timeout_txs = Vec::new();
 
         let mut tx_count: u64 = 0;
         let mut stage = Stage::OrderTxs;
+        let mut queued_hashes = HashSet::new();
+        let mut dup_hash = None;
 
         loop {
             if let Ok(shared_tx) = queue_role.incumbent.pop() {
@@ -206,7 +264,15 @@ impl TxCache {
                     continue;
                 }
                 if shared_tx.is_timeout(current_height, timeout) {
-                    timeout_tx_hashes.push(tx_hash.clone());
+                    timeout_txs.push(Arc::clone(&shared_tx));
+                    continue;
+                }
+                // A transaction queued twice means the cache is inconsistent. Drop the
+                // duplicate from queue and finish the role switch, so that the next
+                // package works, but never propose this one.
+                if !queued_hashes.insert(tx_hash.clone()) {
+                    log::error!("[core_mempool]: package duplicated tx {:?}", tx_hash);
+                    dup_hash.get_or_insert_with(|| tx_hash.clone());
                     continue;
                 }
                 // After previous filter, tx are valid and should cache in temp_queue.
@@ -219,7 +285,7 @@ impl TxCache {
                         "[core_mempool]: candidate queue is full while package, delete {:?}",
                         &shared_tx.tx.tx_hash
                     );
-                    self.map.remove(&shared_tx.tx.tx_hash).await;
+                    self.evict(&shared_tx).await;
                 }
 
                 if stage == Stage::Finished
@@ -234,7 +300,12 @@ impl TxCache {
                 }
 
                 match stage {
-                    Stage::OrderTxs => order_tx_hashes.push(tx_hash.clone()),
+                    Stage::OrderTxs => {
+                        common_apm::metrics::mempool::MEMPOOL_PACKAGE_TX_ORIGIN_COUNTER_VEC
+                            .with_label_values(&[shared_tx.origin.as_str()])
+                            .inc();
+                        order_tx_hashes.push(tx_hash.clone())
+                    }
                     Stage::ProposeTxs => propose_tx_hashes.push(tx_hash.clone()),
                     Stage::Finished => {}
                 }
@@ -247,7 +318,11 @@ impl TxCache {
             }
         }
         // Remove timeout tx in map
-        self.map.remove_batch(&timeout_tx_hashes).await;
+        self.remove_timeout_txs(timeout_txs).await;
+
+        if let Some(hash) = dup_hash {
+            return Err(MemPoolError::PackageDup { hash }.into());
+        }
 
         Ok(MixedTxHashes {
             order_tx_hashes,
@@ -283,6 +358,24 @@ impl TxCache {
             .map(|shared_tx| shared_tx.tx.clone())
     }
 
+    /// Transactions of `sender` still in cache, in no particular order.
+    pub async fn get_by_sender(&self, sender: &Address) -> Vec<SignedTransaction> {
+        let tx_hashes = match self.senders.read().await.get(sender) {
+            Some(tx_hashes) => tx_hashes.iter().cloned().collect::<Vec<_>>(),
+            None => return Vec::new(),
+        };
+
+        let mut txs = Vec::with_capacity(tx_hashes.len());
+        for tx_hash in tx_hashes.iter() {
+            if let Some(shared_tx) = self.map.get(tx_hash).await {
+                if !shared_tx.is_removed() {
+                    txs.push(shared_tx.tx.clone());
+                }
+            }
+        }
+        txs
+    }
+
     pub fn queue_len(&self) -> usize {
         if self.is_zero.load(Ordering::Relaxed) {
             self.queue_0.len()
@@ -316,10 +409,64 @@ impl TxCache {
             self.map.remove(&tx_hash).await;
             Err(MemPoolError::Insert { tx_hash }.into())
         } else {
+            self.senders
+                .write()
+                .await
+                .entry(shared_tx.tx.raw.sender.clone())
+                .or_insert_with(HashSet::new)
+                .insert(tx_hash);
             Ok(())
         }
     }
 
+    fn observe_committed(&self, shared_txs: &[SharedTx]) {
+        for shared_tx in shared_txs.iter() {
+            let elapsed = shared_tx.inserted_at.elapsed();
+            common_apm::metrics::mempool::MEMPOOL_TX_PENDING_SECONDS
+                .observe(common_apm::metrics::duration_to_sec(elapsed));
+
+            self.committed_count.fetch_add(1, Ordering::SeqCst);
+            self.pending_micros
+                .fetch_add(elapsed.as_micros() as u64, Ordering::SeqCst);
+        }
+    }
+
+    fn observe_evicted(&self, shared_txs: &[SharedTx]) {
+        for shared_tx in shared_txs.iter() {
+            common_apm::metrics::mempool::MEMPOOL_TX_EVICTED_PENDING_SECONDS.observe(
+                common_apm::metrics::duration_to_sec(shared_tx.inserted_at.elapsed()),
+            );
+        }
+    }
+
+    // Remove a transaction dropped from a full queue.
+    async fn evict(&self, shared_tx: &SharedTx) {
+        self.map.remove(&shared_tx.tx.tx_hash).await;
+        self.unindex_senders(&[Arc::clone(shared_tx)]).await;
+        self.observe_evicted(&[Arc::clone(shared_tx)]);
+    }
+
+    async fn unindex_senders(&self, shared_txs: &[SharedTx]) {
+        if shared_txs.is_empty() {
+            return;
+        }
+
+        let mut senders = self.senders.write().await;
+        for shared_tx in shared_txs.iter() {
+            let sender = &shared_tx.tx.raw.sender;
+            let is_empty = match senders.get_mut(sender) {
+                Some(tx_hashes) => {
+                    tx_hashes.remove(&shared_tx.tx.tx_hash);
+                    tx_hashes.is_empty()
+                }
+                None => false,
+            };
+            if is_empty {
+                senders.remove(sender);
+            }
+        }
+    }
+
     // Process transactions insert into previous incumbent queue during role switch.
     async fn process_omission_txs(&self, queue_role: QueueRole) {
         'outer: loop {
@@ -336,7 +483,7 @@ impl TxCache {
                             "[core_mempool]: incumbent queue is full while process_omission_txs, delete {:?}",
                             &shared_tx.tx.tx_hash
                         );
-                        self.map.remove(&shared_tx.tx.tx_hash).await;
+                        self.evict(&shared_tx).await;
                     }
                 }
                 break 'outer;
@@ -346,17 +493,15 @@ impl TxCache {
 
     async fn flush_incumbent_queue(&self, current_height: u64, timeout: u64) {
         let queue_role = self.get_queue_role();
-        let mut timeout_tx_hashes = Vec::new();
+        let mut timeout_txs = Vec::new();
 
         loop {
             if let Ok(shared_tx) = queue_role.incumbent.pop() {
-                let tx_hash = &shared_tx.tx.tx_hash;
-
                 if shared_tx.is_removed() {
                     continue;
                 }
                 if shared_tx.is_timeout(current_height, timeout) {
-                    timeout_tx_hashes.push(tx_hash.clone());
+                    timeout_txs.push(Arc::clone(&shared_tx));
                     continue;
                 }
                 // After previous filter, tx are valid and should cache in temp_queue.
@@ -369,7 +514,7 @@ impl TxCache {
                         "[core_mempool]: candidate queue is full while flush_incumbent_queue, delete {:?}",
                         &shared_tx.tx.tx_hash
                     );
-                    self.map.remove(&shared_tx.tx.tx_hash).await;
+                    self.evict(&shared_tx).await;
                 }
             } else {
                 // Switch queue_roles
@@ -380,7 +525,18 @@ impl TxCache {
             }
         }
         // Remove timeout tx in map
+        self.remove_timeout_txs(timeout_txs).await;
+    }
+
+    async fn remove_timeout_txs(&self, timeout_txs: Vec<SharedTx>) {
+        let timeout_tx_hashes = timeout_txs
+            .iter()
+            .map(|shared_tx| shared_tx.tx.tx_hash.clone())
+            .collect::<Vec<_>>();
+
         self.map.remove_batch(&timeout_tx_hashes).await;
+        self.unindex_senders(&timeout_txs).await;
+        self.observe_evicted(&timeout_txs);
     }
 
     fn switch_queue_role(&self) -> QueueRole {
@@ -406,6 +562,7 @@ mod tests {
     extern crate test;
 
     use std::sync::Arc;
+    use std::time::Duration;
 
     use rand::random;
     use test::Bencher;
@@ -414,8 +571,10 @@ mod tests {
         Address, Bytes, Hash, RawTransaction, SignedTransaction, TransactionRequest,
     };
 
+    use crate::context::TxOrigin;
     use crate::map::Map;
     use crate::tx_cache::{TxCache, TxWrapper};
+    use crate::MemPoolError;
 
     const POOL_SIZE: usize = 1000;
     const BYTES_LEN: usize = 10;
@@ -477,7 +636,7 @@ mod tests {
             .into_iter()
             .map(|tx| {
                 let tx_cache = Arc::clone(&tx_cache);
-                tokio::spawn(async move { tx_cache.insert_new_tx(tx.clone()).await })
+                tokio::spawn(async move { tx_cache.insert_new_tx(tx.clone(), TxOrigin::Api).await })
             })
             .collect::<Vec<_>>();
 
@@ -525,18 +684,71 @@ mod tests {
         let tx = txs.get(0).unwrap();
         let map = Map::new(POOL_SIZE);
 
-        let tx_wrapper_0 = TxWrapper::new(tx.clone());
+        let tx_wrapper_0 = TxWrapper::new(tx.clone(), TxOrigin::Api);
         tx_wrapper_0.set_removed();
         map.insert(tx.tx_hash.clone(), Arc::new(tx_wrapper_0)).await;
         let shared_tx_0 = map.get(&tx.tx_hash).await.unwrap();
         assert!(shared_tx_0.is_removed());
 
-        let tx_wrapper_1 = TxWrapper::new(tx.clone());
+        let tx_wrapper_1 = TxWrapper::new(tx.clone(), TxOrigin::Api);
         map.insert(tx.tx_hash.clone(), Arc::new(tx_wrapper_1)).await;
         let shared_tx_1 = map.get(&tx.tx_hash).await.unwrap();
         assert!(shared_tx_1.is_removed());
     }
 
+    #[tokio::test]
+    async fn test_package_dup_guard() {
+        let txs = gen_signed_txs(3);
+        let tx_cache = TxCache::new(POOL_SIZE);
+        for tx in txs.iter() {
+            tx_cache
+                .insert_new_tx(tx.clone(), TxOrigin::Api)
+                .await
+                .unwrap();
+        }
+
+        // Inconsistent cache, the same transaction is queued twice
+        let shared_tx = tx_cache.map.get(&txs[1].tx_hash).await.unwrap();
+        tx_cache.get_queue_role().incumbent.push(shared_tx).unwrap();
+
+        let res = tx_cache
+            .package(CYCLE_LIMIT, TX_NUM_LIMIT, CURRENT_H, TIMEOUT)
+            .await;
+        let err = res.unwrap_err();
+        match err.downcast_ref::<MemPoolError>() {
+            Some(MemPoolError::PackageDup { hash }) => assert_eq!(hash, &txs[1].tx_hash),
+            _ => panic!("should be package dup error, got {:?}", err),
+        }
+
+        // The duplicate was dropped from queue, the transaction is still cached
+        assert_eq!(tx_cache.queue_len(), 3);
+        assert_eq!(tx_cache.len().await, 3);
+
+        let mixed_tx_hashes = tx_cache
+            .package(CYCLE_LIMIT, TX_NUM_LIMIT, CURRENT_H, TIMEOUT)
+            .await
+            .unwrap();
+        let tx_hashes = txs.iter().map(|tx| tx.tx_hash.clone()).collect::<Vec<_>>();
+        assert_eq!(mixed_tx_hashes.order_tx_hashes, tx_hashes);
+    }
+
+    #[tokio::test]
+    async fn test_average_pending_time() {
+        let txs = gen_signed_txs(2);
+        let tx_hashes = txs.iter().map(|tx| tx.tx_hash.clone()).collect::<Vec<_>>();
+        let tx_cache = TxCache::new(POOL_SIZE);
+        assert_eq!(tx_cache.average_pending_time(), Duration::from_micros(0));
+
+        for tx in txs.into_iter() {
+            tx_cache.insert_new_tx(tx, TxOrigin::Api).await.unwrap();
+        }
+        tokio::time::delay_for(Duration::from_millis(20)).await;
+
+        tx_cache.flush(&tx_hashes, CURRENT_H, TIMEOUT).await;
+        assert!(tx_cache.average_pending_time() >= Duration::from_millis(20));
+        assert!(common_apm::metrics::mempool::MEMPOOL_TX_PENDING_SECONDS.get_sample_count() >= 2);
+    }
+
     #[bench]
     fn bench_gen_txs(b: &mut Bencher) {
         b.iter(|| {
@@ -0,0 +1,146 @@
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+
+use bytes::{BufMut, BytesMut};
+
+use protocol::codec::ProtocolCodecSync;
+use protocol::types::{Bytes, Hash, SignedTransaction};
+use protocol::ProtocolResult;
+
+use crate::MemPoolError;
+
+/// Write-ahead log of pending transactions, so that they survive a restart.
+/// Each transaction is saved to its own file named by tx hash, which makes
+/// pruning committed transactions a plain file removal. File operations run
+/// on the blocking thread pool, files are synced before a save returns.
+#[derive(Debug, Clone)]
+pub struct MemPoolWAL {
+    path: PathBuf,
+}
+
+impl MemPoolWAL {
+    pub fn new<P: AsRef<Path>>(path: P) -> Self {
+        if !path.as_ref().exists() {
+            fs::create_dir_all(&path).expect("Failed to create mempool wal directory");
+        }
+
+        MemPoolWAL {
+            path: path.as_ref().to_path_buf(),
+        }
+    }
+
+    pub async fn save(&self, stx: &SignedTransaction) -> ProtocolResult<()> {
+        let data = stx.encode_sync()?;
+        let wal = self.clone();
+        let file_path = self.file_path(&stx.tx_hash);
+
+        run_blocking(move || wal.save_file(&file_path, data)).await
+    }
+
+    pub async fn remove(&self, tx_hashes: &[Hash]) -> ProtocolResult<()> {
+        let file_paths = tx_hashes
+            .iter()
+            .map(|tx_hash| self.file_path(tx_hash))
+            .collect::<Vec<_>>();
+
+        run_blocking(move || {
+            for file_path in file_paths.into_iter() {
+                if file_path.exists() {
+                    fs::remove_file(file_path).map_err(MemPoolError::WALErr)?;
+                }
+            }
+            Ok(())
+        })
+        .await
+    }
+
+    /// Load all saved transactions. A corrupted file is removed and skipped
+    /// rather than failing the whole load, the transaction can be received
+    /// from other peers again.
+    pub async fn load_all(&self) -> ProtocolResult<Vec<SignedTransaction>> {
+        let path = self.path.clone();
+
+        run_blocking(move || {
+            let mut stxs = Vec::new();
+            for entry in fs::read_dir(&path).map_err(MemPoolError::WALErr)? {
+                let file_path = entry.map_err(MemPoolError::WALErr)?.path();
+                if !file_path.is_file() {
+                    continue;
+                }
+
+                match Self::recover_stx(&file_path) {
+                    Some(stx) => stxs.push(stx),
+                    None => {
+                        log::warn!("[mempool]: wal file {:?} corrupted, remove it", file_path);
+                        fs::remove_file(file_path).map_err(MemPoolError::WALErr)?;
+                    }
+                }
+            }
+            Ok(stxs)
+        })
+        .await
+    }
+
+    fn save_file(&self, file_path: &Path, data: Bytes) -> ProtocolResult<()> {
+        if file_path.exists() {
+            return Ok(());
+        }
+
+        let check_sum = Hash::digest(data.clone());
+        let mut content = BytesMut::new();
+        content.put(check_sum.as_bytes());
+        content.put(data);
+
+        let mut wal_file = fs::File::create(file_path).map_err(MemPoolError::WALErr)?;
+        wal_file
+            .write_all(content.as_ref())
+            .map_err(MemPoolError::WALErr)?;
+        wal_file.sync_all().map_err(MemPoolError::WALErr)?;
+
+        // Sync directory too, otherwise the new file entry may be lost on crash
+        fs::File::open(&self.path)
+            .and_then(|dir| dir.sync_all())
+            .map_err(MemPoolError::WALErr)?;
+        Ok(())
+    }
+
+    fn file_path(&self, tx_hash: &Hash) -> PathBuf {
+        let mut file_path = self.path.clone();
+        file_path.push(tx_hash.as_hex());
+        file_path
+    }
+
+    fn recover_stx(file_path: &Path) -> Option<SignedTransaction> {
+        let mut read_buf = Vec::new();
+        let mut file = fs::File::open(file_path).ok()?;
+        file.read_to_end(&mut read_buf).ok()?;
+
+        let mut data = Bytes::from(read_buf);
+        let hash_len = Hash::default().as_bytes().len();
+        if data.len() < hash_len {
+            return None;
+        }
+
+        let content = data.split_off(hash_len);
+        if data != Hash::digest(content.clone()).as_bytes() {
+            return None;
+        }
+
+        let stx = SignedTransaction::decode_sync(content).ok()?;
+        if stx.tx_hash.as_hex() != file_path.file_name()?.to_str()? {
+            return None;
+        }
+        Some(stx)
+    }
+}
+
+async fn run_blocking<T, F>(f: F) -> ProtocolResult<T>
+where
+    T: Send + 'static,
+    F: FnOnce() -> ProtocolResult<T> + Send + 'static,
+{
+    tokio::task::spawn_blocking(f)
+        .await
+        .map_err(|e| MemPoolError::WALErr(io::Error::new(io::ErrorKind::Other, e)))?
+}
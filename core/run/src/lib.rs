@@ -5,9 +5,10 @@ use derive_more::{Display, From};
 
 use protocol::{ProtocolError, ProtocolErrorKind};
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::convert::TryFrom;
 use std::panic;
+use std::str::FromStr;
 use std::sync::Arc;
 use std::thread;
 use std::time::Duration;
@@ -26,7 +27,7 @@ use common_crypto::{
     ToPublicKey, UncompressedPublicKey,
 };
 use core_api::adapter::DefaultAPIAdapter;
-use core_api::config::{GraphQLConfig, GraphQLTLS};
+use core_api::config::{ChainInfo, GraphQLConfig, GraphQLTLS};
 use core_consensus::fixed_types::{FixedBlock, FixedProof, FixedSignedTxs};
 use core_consensus::message::{
     ChokeMessageHandler, ProposalMessageHandler, PullBlockRpcHandler, PullProofRpcHandler,
@@ -43,15 +44,16 @@ use core_consensus::{
     OverlordSynchronization, RichBlock, SignedTxsWAL,
 };
 use core_mempool::{
-    DefaultMemPoolAdapter, HashMemPool, MsgPushTxs, NewTxsHandler, PullTxsHandler,
-    END_GOSSIP_NEW_TXS, RPC_PULL_TXS, RPC_RESP_PULL_TXS, RPC_RESP_PULL_TXS_SYNC,
+    BroadcastMode, DefaultMemPoolAdapter, HashMemPool, MemPoolWAL, MsgPushTxs, NewTxsHandler,
+    PullTxsHandler, END_GOSSIP_NEW_TXS, RPC_PULL_TXS, RPC_RESP_PULL_TXS, RPC_RESP_PULL_TXS_SYNC,
 };
 use core_network::{NetworkConfig, NetworkService, PeerId, PeerIdExt};
 use core_storage::{adapter::rocks::RocksAdapter, ImplStorage, StorageError};
 use framework::binding::state::RocksTrieDB;
 use framework::executor::{ServiceExecutor, ServiceExecutorFactory};
 use protocol::traits::{
-    APIAdapter, CommonStorage, Context, MemPool, Network, NodeInfo, ServiceMapping, Storage,
+    APIAdapter, CommonStorage, Context, ExecutorConfig, MemPool, Network, NodeInfo, ServiceMapping,
+    Storage,
 };
 use protocol::types::{Address, Block, BlockHeader, Genesis, Hash, Metadata, Proof, Validator};
 use protocol::{fixed_codec::FixedCodec, ProtocolResult};
@@ -65,6 +67,7 @@ where
     config:          Config,
     genesis:         Genesis,
     service_mapping: Arc<Mapping>,
+    version:         String,
 }
 
 impl<Mapping: 'static + ServiceMapping> Muta<Mapping> {
@@ -73,9 +76,16 @@ impl<Mapping: 'static + ServiceMapping> Muta<Mapping> {
             config,
             genesis,
             service_mapping,
+            version: env!("CARGO_PKG_VERSION").to_owned(),
         }
     }
 
+    /// Version of the chain binary, reported by api `chainInfo`.
+    pub fn with_version(mut self, version: &str) -> Self {
+        self.version = version.to_owned();
+        self
+    }
+
     pub fn run(self) -> ProtocolResult<()> {
         if let Some(apm_config) = &self.config.apm {
             muta_apm::global_tracer_register(
@@ -116,7 +126,10 @@ impl<Mapping: 'static + ServiceMapping> Muta<Mapping> {
             path_block,
             self.config.rocksdb.max_open_files,
         )?);
-        let storage = Arc::new(ImplStorage::new(rocks_adapter));
+        let storage = Arc::new(
+            ImplStorage::new_with_block_cache(rocks_adapter, self.config.rocksdb.block_cache_size)
+                .with_block_checksum(self.config.rocksdb.block_checksum),
+        );
 
         match storage.get_latest_block(Context::new()).await {
             Ok(genesis_block) => {
@@ -213,7 +226,13 @@ impl<Mapping: 'static + ServiceMapping> Muta<Mapping> {
             path_block.clone(),
             config.rocksdb.max_open_files,
         )?);
-        let storage = Arc::new(ImplStorage::new(Arc::clone(&rocks_adapter)));
+        let storage = Arc::new(
+            ImplStorage::new_with_block_cache(
+                Arc::clone(&rocks_adapter),
+                config.rocksdb.block_cache_size,
+            )
+            .with_block_checksum(config.rocksdb.block_checksum),
+        );
 
         // Init network
         let network_config = NetworkConfig::new()
@@ -279,15 +298,46 @@ impl<Mapping: 'static + ServiceMapping> Muta<Mapping> {
 
         // Recover signed transactions of current height
         let current_block = storage.get_latest_block(Context::new()).await?;
-        let current_stxs = txs_wal.load_by_height(current_block.header.height + 1);
+        // A corrupted wal shouldn't stop the node, these transactions can be pulled
+        // from other peers again.
+        let current_stxs = txs_wal
+            .load_by_height(current_block.header.height + 1)
+            .unwrap_or_else(|e| {
+                log::error!("Recover tx from wal failed {:?}", e);
+                Vec::new()
+            });
         log::info!(
             "Recover {} tx of height {} from wal",
             current_stxs.len(),
             current_block.header.height + 1
         );
 
+        // Executor settings, shared by consensus, mempool admission and queries
+        let mut sender_blacklist = HashSet::new();
+        for address in config.executor.sender_blacklist.iter() {
+            let address = Address::from_str(address)
+                .map_err(|e| MainError::Other(format!("sender blacklist: {}", e)))?;
+            sender_blacklist.insert(address);
+        }
+        let executor_config = ExecutorConfig {
+            min_cycles_price:          config.executor.min_cycles_price,
+            min_cycles_price_height:   config.executor.min_cycles_price_height,
+            sender_blacklist:          Arc::new(sender_blacklist),
+            storage_cycles_per_byte:   config.executor.storage_cycles_per_byte,
+            storage_cycles_height:     config.executor.storage_cycles_height,
+            parallel_exec:             config.executor.parallel_exec,
+            out_of_cycles_code_height: config.executor.out_of_cycles_code_height,
+        };
+
         // Init mempool
-        let mempool_adapter =
+        let broadcast_mode = match &config.mempool.adaptive_broadcast {
+            Some(adaptive) => BroadcastMode::Adaptive {
+                max_txs_size: adaptive.max_txs_size,
+                min_interval: adaptive.min_interval,
+            },
+            None => BroadcastMode::Fixed,
+        };
+        let mut mempool_adapter =
             DefaultMemPoolAdapter::<ServiceExecutorFactory, Secp256k1, _, _, _, _>::new(
                 network_service.handle(),
                 Arc::clone(&storage),
@@ -295,15 +345,25 @@ impl<Mapping: 'static + ServiceMapping> Muta<Mapping> {
                 Arc::clone(&service_mapping),
                 config.mempool.broadcast_txs_size,
                 config.mempool.broadcast_txs_interval,
+                config.mempool.broadcast_channel_size,
+                broadcast_mode,
             );
-        let mempool = Arc::new(
-            HashMemPool::new(
-                config.mempool.pool_size as usize,
-                mempool_adapter,
-                current_stxs,
-            )
-            .await,
-        );
+        mempool_adapter.set_executor_config(executor_config.clone());
+        let mut mempool = HashMemPool::new(
+            config.mempool.pool_size as usize,
+            config.mempool.future_block_tolerance,
+            mempool_adapter,
+            current_stxs,
+        )
+        .await;
+        if config.mempool.enable_wal {
+            let mempool_wal = MemPoolWAL::new(config.data_path_for_mempool_wal());
+            mempool = mempool.with_wal(mempool_wal);
+        }
+        if config.mempool.dedup_window != 0 {
+            mempool = mempool.with_dedup_window(Duration::from_millis(config.mempool.dedup_window));
+        }
+        let mempool = Arc::new(mempool);
 
         let monitor_mempool = Arc::clone(&mempool);
         tokio::spawn(async move {
@@ -324,12 +384,13 @@ impl<Mapping: 'static + ServiceMapping> Muta<Mapping> {
         let my_address = Address::from_pubkey_bytes(my_pubkey.to_uncompressed_bytes())?;
 
         // Get metadata
-        let api_adapter = DefaultAPIAdapter::<ServiceExecutorFactory, _, _, _, _>::new(
+        let mut api_adapter = DefaultAPIAdapter::<ServiceExecutorFactory, _, _, _, _>::new(
             Arc::clone(&mempool),
             Arc::clone(&storage),
             Arc::clone(&trie_db),
             Arc::clone(&service_mapping),
         );
+        api_adapter.set_executor_config(executor_config.clone());
 
         let exec_resp = api_adapter
             .query_service(
@@ -362,6 +423,19 @@ impl<Mapping: 'static + ServiceMapping> Muta<Mapping> {
             metadata.max_tx_size,
         );
 
+        // Recover pending transactions from mempool wal, after args are set so
+        // that they are verified against current metadata.
+        if let Err(e) = mempool.recover().await {
+            log::error!("Recover tx from mempool wal failed {:?}", e);
+        }
+        // Serve transactions of the latest block to peers still syncing it.
+        if let Err(e) = mempool
+            .warm_up(Context::new(), current_block.header.height)
+            .await
+        {
+            log::error!("Warm up mempool failed {:?}", e);
+        }
+
         // register broadcast new transaction
         network_service.register_endpoint_handler(
             END_GOSSIP_NEW_TXS,
@@ -462,6 +536,7 @@ impl<Mapping: 'static + ServiceMapping> Muta<Mapping> {
                 Arc::clone(&crypto),
                 config.consensus.overlord_gap,
             )?;
+        consensus_adapter.set_executor_config(executor_config);
 
         let exec_demon = consensus_adapter.take_exec_demon();
         let consensus_adapter = Arc::new(consensus_adapter);
@@ -479,14 +554,18 @@ impl<Mapping: 'static + ServiceMapping> Muta<Mapping> {
         ));
 
         consensus_adapter.set_overlord_handler(overlord_consensus.get_overlord_handler());
+        overlord_consensus
+            .set_min_block_interval(Duration::from_millis(config.consensus.min_block_interval));
 
-        let synchronization = Arc::new(OverlordSynchronization::<_>::new(
+        let mut synchronization = OverlordSynchronization::<_>::new(
             config.consensus.sync_txs_chunk_size,
             consensus_adapter,
             status_agent.clone(),
             crypto,
             lock,
-        ));
+        );
+        synchronization.set_stop_signal(overlord_consensus.stop_signal());
+        let synchronization = Arc::new(synchronization);
 
         let peer_ids = metadata
             .verifier_list
@@ -591,6 +670,29 @@ impl<Mapping: 'static + ServiceMapping> Muta<Mapping> {
             brake_ratio:     metadata.brake_ratio,
         };
 
+        let commit_status: core_api::CommitStatusFn = {
+            let overlord_consensus = Arc::clone(&overlord_consensus);
+            Arc::new(move || overlord_consensus.last_commit_status())
+        };
+        let validators: core_api::ValidatorsFn = {
+            let overlord_consensus = Arc::clone(&overlord_consensus);
+            Arc::new(move || {
+                overlord_consensus
+                    .current_validators()
+                    .into_iter()
+                    .map(|node| Validator {
+                        pub_key:        node.address,
+                        propose_weight: node.propose_weight,
+                        vote_weight:    node.vote_weight,
+                    })
+                    .collect()
+            })
+        };
+        let participation: core_api::ParticipationFn = {
+            let overlord_consensus = Arc::clone(&overlord_consensus);
+            Arc::new(move || overlord_consensus.participation())
+        };
+
         tokio::spawn(async move {
             if let Err(e) = overlord_consensus
                 .run(
@@ -622,6 +724,24 @@ impl<Mapping: 'static + ServiceMapping> Muta<Mapping> {
         if config.graphql.max_payload_size != 0 {
             graphql_config.max_payload_size = config.graphql.max_payload_size;
         }
+        if config.graphql.max_batch_query_size != 0 {
+            graphql_config.max_batch_query_size = config.graphql.max_batch_query_size;
+        }
+        if config.graphql.max_batch_request_size != 0 {
+            graphql_config.max_batch_request_size = config.graphql.max_batch_request_size;
+        }
+        if config.graphql.max_query_cost != 0 {
+            graphql_config.max_query_cost = config.graphql.max_query_cost;
+        }
+        if config.graphql.max_query_depth != 0 {
+            graphql_config.max_query_depth = config.graphql.max_query_depth;
+        }
+        if config.graphql.max_aliases != 0 {
+            graphql_config.max_aliases = config.graphql.max_aliases;
+        }
+        if config.graphql.ready_commit_timeout != 0 {
+            graphql_config.ready_commit_timeout = config.graphql.ready_commit_timeout;
+        }
         if let Some(tls) = config.graphql.tls {
             graphql_config.tls = Some(GraphQLTLS {
                 private_key_file_path:       tls.private_key_file_path,
@@ -629,13 +749,29 @@ impl<Mapping: 'static + ServiceMapping> Muta<Mapping> {
             })
         }
         graphql_config.enable_dump_profile = config.graphql.enable_dump_profile.unwrap_or(false);
+        graphql_config.maintenance_mode = config.graphql.maintenance_mode;
+        graphql_config.admin_token = config.graphql.admin_token.clone().filter(|t| !t.is_empty());
+        if let Some(genesis_header) = storage.get_block_header(Context::new(), 0).await? {
+            graphql_config.chain_info = Some(ChainInfo {
+                chain_id:     metadata.chain_id.clone(),
+                version:      self.version.clone(),
+                genesis_hash: Hash::digest(genesis_header.encode_fixed()?),
+            });
+        }
 
         tokio::task::spawn_local(async move {
             let local = tokio::task::LocalSet::new();
             let actix_rt = actix_rt::System::run_in_tokio("muta-graphql", &local);
             tokio::task::spawn_local(actix_rt);
 
-            core_api::start_graphql(graphql_config, api_adapter).await;
+            core_api::start_graphql(
+                graphql_config,
+                api_adapter,
+                Some(commit_status),
+                Some(validators),
+                Some(participation),
+            )
+            .await;
         });
 
         let ctrl_c_handler = tokio::task::spawn_local(async {
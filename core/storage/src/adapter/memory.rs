@@ -1,4 +1,4 @@
-use std::collections::{hash_map, HashMap};
+use std::collections::{btree_map, BTreeMap, HashMap};
 use std::error::Error;
 use std::marker::PhantomData;
 use std::sync::Arc;
@@ -9,12 +9,14 @@ use parking_lot::RwLock;
 
 use protocol::codec::ProtocolCodecSync;
 use protocol::traits::{
-    IntoIteratorByRef, StorageAdapter, StorageBatchModify, StorageIterator, StorageSchema,
+    IntoIteratorByRef, StorageAdapter, StorageBatch, StorageBatchModify, StorageIterator,
+    StorageSchema,
 };
 use protocol::Bytes;
 use protocol::{ProtocolError, ProtocolErrorKind, ProtocolResult};
 
-type Category = HashMap<Vec<u8>, Vec<u8>>;
+// Ordered by key like rocksdb, so prefix iteration behaves the same.
+type Category = BTreeMap<Vec<u8>, Vec<u8>>;
 
 #[derive(Debug)]
 pub struct MemoryAdapter {
@@ -38,7 +40,7 @@ impl Default for MemoryAdapter {
 }
 
 pub struct MemoryIterator<'a, S: StorageSchema> {
-    inner: hash_map::Iter<'a, Vec<u8>, Vec<u8>>,
+    inner: btree_map::Range<'a, Vec<u8>, Vec<u8>>,
     pin_s: PhantomData<S>,
 }
 
@@ -61,8 +63,9 @@ impl<'a, S: StorageSchema> Iterator for MemoryIterator<'a, S> {
 }
 
 pub struct MemoryIntoIterator<'a, S: StorageSchema> {
-    inner: parking_lot::RwLockReadGuard<'a, HashMap<String, Category>>,
-    pin_s: PhantomData<S>,
+    inner:  parking_lot::RwLockReadGuard<'a, HashMap<String, Category>>,
+    prefix: Vec<u8>,
+    pin_s:  PhantomData<S>,
 }
 
 impl<'a, 'b: 'a, S: StorageSchema> IntoIterator for &'b MemoryIntoIterator<'a, S> {
@@ -75,7 +78,7 @@ impl<'a, 'b: 'a, S: StorageSchema> IntoIterator for &'b MemoryIntoIterator<'a, S
                 .inner
                 .get(&S::category().to_string())
                 .expect("impossible, already ensure we have category in prepare_iter")
-                .iter(),
+                .range(self.prefix.clone()..),
             pin_s: PhantomData::<S>,
         })
     }
@@ -100,7 +103,7 @@ impl StorageAdapter for MemoryAdapter {
         let mut db = self.db.write();
         let db = db
             .entry(S::category().to_string())
-            .or_insert_with(HashMap::new);
+            .or_insert_with(Category::new);
 
         db.insert(key, val);
 
@@ -116,7 +119,7 @@ impl StorageAdapter for MemoryAdapter {
         let mut db = self.db.write();
         let db = db
             .entry(S::category().to_string())
-            .or_insert_with(HashMap::new);
+            .or_insert_with(Category::new);
 
         let opt_bytes = db.get(&key.to_vec()).cloned();
 
@@ -135,7 +138,7 @@ impl StorageAdapter for MemoryAdapter {
         let mut db = self.db.write();
         let db = db
             .entry(S::category().to_string())
-            .or_insert_with(HashMap::new);
+            .or_insert_with(Category::new);
 
         db.remove(&key);
 
@@ -151,7 +154,7 @@ impl StorageAdapter for MemoryAdapter {
         let mut db = self.db.write();
         let db = db
             .entry(S::category().to_string())
-            .or_insert_with(HashMap::new);
+            .or_insert_with(Category::new);
 
         Ok(db.get(&key).is_some())
     }
@@ -181,7 +184,7 @@ impl StorageAdapter for MemoryAdapter {
         let mut db = self.db.write();
         let db = db
             .entry(S::category().to_string())
-            .or_insert_with(HashMap::new);
+            .or_insert_with(Category::new);
 
         for (key, value) in pairs.into_iter() {
             match value {
@@ -193,20 +196,36 @@ impl StorageAdapter for MemoryAdapter {
         Ok(())
     }
 
+    async fn write_batch(&self, batch: StorageBatch) -> ProtocolResult<()> {
+        let mut db = self.db.write();
+
+        for (category, key, value) in batch.into_modifies() {
+            let db = db.entry(category.to_string()).or_insert_with(Category::new);
+
+            match value {
+                Some(value) => db.insert(key.to_vec(), value.to_vec()),
+                None => db.remove(&key.to_vec()),
+            };
+        }
+
+        Ok(())
+    }
+
     fn prepare_iter<'a, 'b: 'a, S: StorageSchema + 'static, P: AsRef<[u8]> + 'a>(
         &'b self,
-        _prefix: &P,
+        prefix: &P,
     ) -> ProtocolResult<Box<dyn IntoIteratorByRef<S> + 'a>> {
         {
             self.db
                 .write()
                 .entry(S::category().to_string())
-                .or_insert_with(HashMap::new);
+                .or_insert_with(Category::new);
         }
 
         Ok(Box::new(MemoryIntoIterator {
-            inner: self.db.read(),
-            pin_s: PhantomData::<S>,
+            inner:  self.db.read(),
+            prefix: prefix.as_ref().to_vec(),
+            pin_s:  PhantomData::<S>,
         }))
     }
 }
@@ -9,6 +9,7 @@ pub mod adapter;
 use std::collections::{HashMap, HashSet};
 use std::convert::From;
 use std::error::Error;
+use std::marker::PhantomData;
 use std::str::FromStr;
 use std::sync::Arc;
 use std::time::Instant;
@@ -17,19 +18,24 @@ use arc_swap::ArcSwap;
 use async_trait::async_trait;
 use derive_more::{Display, From};
 use lazy_static::lazy_static;
+use lru::LruCache;
+use parking_lot::Mutex;
 
-use common_apm::metrics::storage::on_storage_get_cf;
+use common_apm::metrics::storage::{on_latest_block_cache, on_storage_get_cf};
 use common_apm::muta_apm;
 use protocol::codec::ProtocolCodecSync;
 use protocol::traits::{
-    CommonStorage, Context, MaintenanceStorage, Storage, StorageAdapter, StorageBatchModify,
-    StorageCategory, StorageSchema,
+    CommonStorage, Context, MaintenanceStorage, SelfCheckReport, Storage, StorageAdapter,
+    StorageBatch, StorageBatchModify, StorageCategory, StorageSchema,
 };
 use protocol::types::{Block, BlockHeader, Hash, Proof, Receipt, SignedTransaction};
 use protocol::Bytes;
 use protocol::{ProtocolError, ProtocolErrorKind, ProtocolResult};
 
 const BATCH_VALUE_DECODE_NUMBER: usize = 1000;
+const MIGRATE_BATCH_SIZE: usize = 1000;
+/// Block cache size of a node unless configured otherwise.
+pub const DEFAULT_BLOCK_CACHE_SIZE: usize = 32;
 
 lazy_static! {
     pub static ref LATEST_BLOCK_KEY: Hash = Hash::digest(Bytes::from("latest_hash"));
@@ -103,15 +109,255 @@ macro_rules! impl_storage_schema_for {
 pub struct ImplStorage<Adapter> {
     adapter: Arc<Adapter>,
 
-    latest_block: ArcSwap<Option<Block>>,
+    latest_block:   ArcSwap<Option<Block>>,
+    block_cache:    Mutex<BlockCache>,
+    block_checksum: bool,
 }
 
 impl<Adapter: StorageAdapter> ImplStorage<Adapter> {
+    /// Storage without block cache, every block read goes to the adapter.
     pub fn new(adapter: Arc<Adapter>) -> Self {
+        Self::new_with_block_cache(adapter, 0)
+    }
+
+    /// Keep the most recently read `cache_size` blocks in memory besides the
+    /// latest one, zero disables the cache. Nodes take the size from
+    /// `rocksdb.block_cache_size` in config.
+    pub fn new_with_block_cache(adapter: Arc<Adapter>, cache_size: usize) -> Self {
         Self {
             adapter,
             latest_block: ArcSwap::from(Arc::new(None)),
+            block_cache: Mutex::new(BlockCache::new(cache_size)),
+            block_checksum: false,
+        }
+    }
+
+    /// Store a checksum beside each block written and verify it on read, a
+    /// mismatch fails the read with `StorageError::Corrupted`. Blocks written
+    /// without a checksum are read unverified.
+    ///
+    /// Checksums are left alone while disabled, so don't enable it again
+    /// after blocks have been overwritten with it disabled, they would fail
+    /// against the checksums of the blocks they replaced.
+    pub fn with_block_checksum(mut self, enabled: bool) -> Self {
+        self.block_checksum = enabled;
+        self
+    }
+
+    async fn verify_block_checksum(&self, height: u64, block: &Block) -> ProtocolResult<()> {
+        if !self.block_checksum {
+            return Ok(());
+        }
+
+        let opt_checksum = get!(self, block_checksum_key(height), BlockChecksumSchema)?;
+        match opt_checksum {
+            Some(checksum) if checksum != block_checksum(block)? => {
+                Err(StorageError::Corrupted(height).into())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Rewrite every row of `Old`'s category from `Old` value format to `New`
+    /// by applying `f`, returns the number of migrated rows.
+    ///
+    /// Rows are migrated in key order by batches, the last migrated key is
+    /// saved after each batch, so an interrupted migration resumes from there.
+    /// A row which fails to decode as `Old` but decodes as `New` is taken as
+    /// already migrated, this covers a batch written without its progress.
+    pub async fn migrate<Old, New, F>(&self, _ctx: Context, f: F) -> ProtocolResult<u64>
+    where
+        Old: StorageSchema + 'static,
+        New: StorageSchema<Key = <Old as StorageSchema>::Key> + 'static,
+        F: Fn(<Old as StorageSchema>::Value) -> <New as StorageSchema>::Value,
+    {
+        if Old::category() != New::category() {
+            return Err(StorageError::MigrateCategory {
+                old: Old::category(),
+                new: New::category(),
+            }
+            .into());
+        }
+
+        let progress_key = migrate_progress_key(Old::category());
+        let mut start = self
+            .adapter
+            .get::<MigrateProgressSchema>(progress_key.clone())
+            .await?
+            .unwrap_or_default();
+        let mut migrated = 0;
+
+        loop {
+            let rows = {
+                let prepare_iter = self
+                    .adapter
+                    .prepare_iter::<MigrateBytesSchema<Old>, _>(&start)?;
+                prepare_iter
+                    .ref_to_iter()
+                    .take(MIGRATE_BATCH_SIZE)
+                    .collect::<ProtocolResult<Vec<_>>>()?
+            };
+
+            let mut keys = Vec::with_capacity(rows.len());
+            let mut vals = Vec::with_capacity(rows.len());
+            let mut last_key = None;
+
+            for (key, val_bytes) in rows.into_iter() {
+                let key_bytes = key.encode_sync()?;
+                if key_bytes == start {
+                    continue;
+                }
+                last_key = Some(key_bytes);
+
+                match <Old as StorageSchema>::Value::decode_sync(val_bytes.clone()) {
+                    Ok(val) => {
+                        keys.push(key);
+                        vals.push(StorageBatchModify::Insert(f(val)));
+                    }
+                    Err(e) => {
+                        if <New as StorageSchema>::Value::decode_sync(val_bytes).is_err() {
+                            return Err(e);
+                        }
+                    }
+                }
+            }
+
+            let last_key = match last_key {
+                Some(key) => key,
+                None => break,
+            };
+
+            migrated += keys.len() as u64;
+            self.adapter.batch_modify::<New>(keys, vals).await?;
+            self.adapter
+                .insert::<MigrateProgressSchema>(progress_key.clone(), last_key.clone())
+                .await?;
+            start = last_key;
         }
+
+        self.adapter
+            .remove::<MigrateProgressSchema>(progress_key)
+            .await?;
+        Ok(migrated)
+    }
+
+    /// Iterate rows of `S` whose encoded key starts with `prefix` by pages.
+    /// Returns at most `limit` rows after the `start_after` key, and the key
+    /// to pass as `start_after` for the next page, `None` if no more rows.
+    ///
+    /// Rows are in encoded key order.
+    pub fn scan_paged<S>(
+        &self,
+        prefix: &[u8],
+        start_after: Option<<S as StorageSchema>::Key>,
+        limit: usize,
+    ) -> ProtocolResult<Page<S>>
+    where
+        S: StorageSchema + 'static,
+        <S as StorageSchema>::Key: Clone,
+    {
+        let start_after = start_after.map(|key| key.encode_sync()).transpose()?;
+        let start = match &start_after {
+            Some(key) => key.to_vec(),
+            None => prefix.to_vec(),
+        };
+
+        let mut rows = Vec::with_capacity(limit);
+        let mut has_more = false;
+        {
+            let prepare_iter = self.adapter.prepare_iter::<S, _>(&start)?;
+            for item in prepare_iter.ref_to_iter() {
+                let (key, val) = item?;
+                let key_bytes = key.encode_sync()?;
+                if !key_bytes.starts_with(prefix) {
+                    break;
+                }
+                // Iterator seeks to `start` inclusive, only `start_after` itself is
+                // skipped, a row whose key equals `prefix` belongs to the page.
+                if Some(&key_bytes) == start_after.as_ref() {
+                    continue;
+                }
+
+                if rows.len() == limit {
+                    has_more = true;
+                    break;
+                }
+                rows.push((key, val));
+            }
+        }
+
+        let next = if has_more {
+            rows.last().map(|(key, _)| key.clone())
+        } else {
+            None
+        };
+        Ok((rows, next))
+    }
+}
+
+/// Rows of a page and the cursor of the next page.
+pub type Page<S> = (
+    Vec<(<S as StorageSchema>::Key, <S as StorageSchema>::Value)>,
+    Option<<S as StorageSchema>::Key>,
+);
+
+fn migrate_progress_key(category: StorageCategory) -> Hash {
+    Hash::digest(Bytes::from(format!("migrate_progress_{}", category)))
+}
+
+fn block_checksum_key(height: u64) -> Hash {
+    Hash::digest(Bytes::from(format!("block_checksum_{}", height)))
+}
+
+fn block_checksum(block: &Block) -> ProtocolResult<Hash> {
+    Ok(Hash::digest(block.encode_sync()?))
+}
+
+// Block writes bump the generation, a read only caches its block if no write
+// happened since the read started. Otherwise a block read before a write could
+// be cached after the write invalidated it, and stay stale. A single counter
+// for all heights only costs an uncached read now and then.
+#[derive(Debug)]
+struct BlockCache {
+    blocks:     LruCache<u64, Block>,
+    generation: u64,
+}
+
+impl BlockCache {
+    fn new(cache_size: usize) -> Self {
+        BlockCache {
+            blocks:     LruCache::new(cache_size),
+            generation: 0,
+        }
+    }
+
+    fn put(&mut self, generation: u64, height: u64, block: Block) {
+        if self.blocks.cap() > 0 && generation == self.generation {
+            self.blocks.put(height, block);
+        }
+    }
+
+    fn invalidate(&mut self, height: u64) {
+        self.generation += 1;
+        self.blocks.pop(&height);
+    }
+
+    fn clear(&mut self) {
+        self.generation += 1;
+        self.blocks.clear();
+    }
+}
+
+// Reads values of `S`'s category as raw bytes, so that migrated rows can be
+// told apart instead of failing the whole iteration.
+struct MigrateBytesSchema<S>(PhantomData<S>);
+
+impl<S: StorageSchema> StorageSchema for MigrateBytesSchema<S> {
+    type Key = <S as StorageSchema>::Key;
+    type Value = Bytes;
+
+    fn category() -> StorageCategory {
+        S::category()
     }
 }
 
@@ -256,9 +502,151 @@ impl_storage_schema_for!(ReceiptBytesSchema, CommonHashKey, Bytes, Receipt);
 impl_storage_schema_for!(HashHeightSchema, Hash, u64, HashHeight);
 impl_storage_schema_for!(LatestBlockSchema, Hash, Block, Block);
 impl_storage_schema_for!(LatestProofSchema, Hash, Proof, Block);
+impl_storage_schema_for!(BlockChecksumSchema, Hash, Hash, Block);
+impl_storage_schema_for!(MigrateProgressSchema, Hash, Bytes, Wal);
+
+impl_storage_schema_for!(RawBlockSchema, Bytes, Bytes, Block);
+impl_storage_schema_for!(RawBlockHeaderSchema, Bytes, Bytes, BlockHeader);
+impl_storage_schema_for!(RawReceiptSchema, Bytes, Bytes, Receipt);
+impl_storage_schema_for!(RawTransactionSchema, Bytes, Bytes, SignedTransaction);
+impl_storage_schema_for!(RawWalSchema, Bytes, Bytes, Wal);
+impl_storage_schema_for!(RawHashHeightSchema, Bytes, Bytes, HashHeight);
+
+// Run `$body` with `$schema` as the raw bytes schema of `$category`.
+macro_rules! with_raw_schema {
+    ($category: expr, $schema: ident, $body: expr) => {
+        match $category {
+            StorageCategory::Block => {
+                type $schema = RawBlockSchema;
+                $body
+            }
+            StorageCategory::BlockHeader => {
+                type $schema = RawBlockHeaderSchema;
+                $body
+            }
+            StorageCategory::Receipt => {
+                type $schema = RawReceiptSchema;
+                $body
+            }
+            StorageCategory::SignedTransaction => {
+                type $schema = RawTransactionSchema;
+                $body
+            }
+            StorageCategory::Wal => {
+                type $schema = RawWalSchema;
+                $body
+            }
+            StorageCategory::HashHeight => {
+                type $schema = RawHashHeightSchema;
+                $body
+            }
+        }
+    };
+}
 
 #[async_trait]
-impl<Adapter: StorageAdapter> MaintenanceStorage for ImplStorage<Adapter> {}
+impl<Adapter: StorageAdapter> MaintenanceStorage for ImplStorage<Adapter> {
+    #[muta_apm::derive::tracing_span(kind = "storage")]
+    async fn self_check(&self, ctx: Context, sample: usize) -> ProtocolResult<SelfCheckReport> {
+        let mappings = {
+            let prepare_iter = self.adapter.prepare_iter::<HashHeightSchema, _>(&[])?;
+            prepare_iter
+                .ref_to_iter()
+                .take(sample)
+                .collect::<ProtocolResult<Vec<_>>>()?
+        };
+
+        let mut report = SelfCheckReport::default();
+        for (tx_hash, height) in mappings.into_iter() {
+            let key = CommonHashKey::new(height, tx_hash.clone());
+            if !self.adapter.contains::<TransactionSchema>(key).await? {
+                report.dangling.push((tx_hash, height));
+            }
+            report.checked += 1;
+        }
+
+        Ok(report)
+    }
+
+    #[muta_apm::derive::tracing_span(kind = "storage")]
+    async fn sync(&self, ctx: Context) -> ProtocolResult<()> {
+        self.adapter.flush().await
+    }
+
+    // !be careful, only call this function in maintenance mode!
+    // Everything of the block is removed in one batch, so that a crash never
+    // leaves a partly removed block.
+    #[muta_apm::derive::tracing_span(kind = "storage")]
+    async fn remove_block_full(&self, ctx: Context, height: u64) -> ProtocolResult<()> {
+        let block = match self.get_block(ctx, height).await? {
+            Some(block) => block,
+            None => return Ok(()),
+        };
+        let tx_hashes = block.ordered_tx_hashes;
+        let heights = self
+            .adapter
+            .get_batch::<HashHeightSchema>(tx_hashes.clone())
+            .await?;
+
+        let mut batch = StorageBatch::default();
+        for (hash, opt_height) in tx_hashes.into_iter().zip(heights.into_iter()) {
+            let key = CommonHashKey::new(height, hash.clone());
+            batch.remove::<TransactionSchema>(key.clone())?;
+            batch.remove::<ReceiptSchema>(key)?;
+
+            // Keep mappings which point to another height
+            if opt_height == Some(height) {
+                batch.remove::<HashHeightSchema>(hash)?;
+            }
+        }
+        batch.remove::<BlockHeaderSchema>(BlockKey::new(height))?;
+        batch.remove::<BlockChecksumSchema>(block_checksum_key(height))?;
+        batch.remove::<BlockSchema>(BlockKey::new(height))?;
+        self.adapter.write_batch(batch).await?;
+
+        self.block_cache.lock().invalidate(height);
+        Ok(())
+    }
+
+    #[muta_apm::derive::tracing_span(kind = "storage")]
+    async fn compact_range(
+        &self,
+        ctx: Context,
+        category: StorageCategory,
+        from: Option<Bytes>,
+        to: Option<Bytes>,
+    ) -> ProtocolResult<()> {
+        self.adapter.compact_range(category, from, to).await
+    }
+
+    #[muta_apm::derive::tracing_span(kind = "storage")]
+    async fn get_raw(
+        &self,
+        ctx: Context,
+        category: StorageCategory,
+        key: Bytes,
+    ) -> ProtocolResult<Option<Bytes>> {
+        with_raw_schema!(category, S, self.adapter.get::<S>(key).await)
+    }
+
+    // !be careful, only call this function in maintenance mode!
+    #[muta_apm::derive::tracing_span(kind = "storage")]
+    async fn put_raw(
+        &self,
+        ctx: Context,
+        category: StorageCategory,
+        key: Bytes,
+        value: Bytes,
+    ) -> ProtocolResult<()> {
+        with_raw_schema!(category, S, self.adapter.insert::<S>(key, value).await)?;
+
+        // Blocks are cached by height, which can't be told from a raw key.
+        if category == StorageCategory::Block {
+            self.block_cache.lock().clear();
+        }
+        Ok(())
+    }
+}
 
 #[async_trait]
 impl<Adapter: StorageAdapter> Storage for ImplStorage<Adapter> {
@@ -318,39 +706,10 @@ impl<Adapter: StorageAdapter> Storage for ImplStorage<Adapter> {
             }
         }
 
-        let mut found = {
-            if found.len() <= BATCH_VALUE_DECODE_NUMBER {
-                found
-                    .drain(..)
-                    .map(|(k, v): (Hash, Bytes)| SignedTransaction::decode_sync(v).map(|v| (k, v)))
-                    .collect::<ProtocolResult<Vec<_>>>()?
-                    .into_iter()
-                    .collect::<HashMap<_, _>>()
-            } else {
-                let futs = found
-                    .chunks(BATCH_VALUE_DECODE_NUMBER)
-                    .map(|vals| {
-                        let vals = vals.to_owned();
-
-                        // FIXME: cancel decode
-                        tokio::spawn(async move {
-                            vals.into_iter()
-                                .map(|(k, v)| <_>::decode_sync(v).map(|v| (k, v)))
-                                .collect::<ProtocolResult<Vec<_>>>()
-                        })
-                    })
-                    .collect::<Vec<_>>();
-
-                futures::future::try_join_all(futs)
-                    .await
-                    .map_err(|_| StorageError::BatchDecode)?
-                    .into_iter()
-                    .collect::<ProtocolResult<Vec<Vec<_>>>>()?
-                    .into_iter()
-                    .flatten()
-                    .collect::<HashMap<_, _>>()
-            }
-        };
+        let mut found = batch_decode(found)
+            .await?
+            .into_iter()
+            .collect::<HashMap<_, _>>();
 
         Ok(hashes.iter().map(|h| found.remove(&h)).collect::<Vec<_>>())
     }
@@ -371,6 +730,90 @@ impl<Adapter: StorageAdapter> Storage for ImplStorage<Adapter> {
         }
     }
 
+    #[muta_apm::derive::tracing_span(kind = "storage")]
+    async fn get_transactions_by_hashes(
+        &self,
+        ctx: Context,
+        hashes: &[Hash],
+    ) -> ProtocolResult<Vec<Option<SignedTransaction>>> {
+        let heights = self
+            .adapter
+            .get_batch::<HashHeightSchema>(hashes.to_vec())
+            .await?;
+        let keys = hashes
+            .iter()
+            .zip(heights.iter())
+            .filter_map(|(hash, opt_height)| {
+                opt_height.map(|height| CommonHashKey::new(height, hash.clone()))
+            })
+            .collect::<Vec<_>>();
+        let mut stxs = self
+            .adapter
+            .get_batch::<TransactionSchema>(keys)
+            .await?
+            .into_iter();
+
+        Ok(heights
+            .into_iter()
+            .map(|opt_height| opt_height.and_then(|_| stxs.next().flatten()))
+            .collect())
+    }
+
+    async fn get_transaction_at(
+        &self,
+        _ctx: Context,
+        block_height: u64,
+        hash: &Hash,
+    ) -> ProtocolResult<Option<SignedTransaction>> {
+        get!(
+            self,
+            CommonHashKey::new(block_height, hash.clone()),
+            TransactionSchema
+        )
+    }
+
+    async fn get_transaction_location(
+        &self,
+        ctx: Context,
+        hash: &Hash,
+    ) -> ProtocolResult<Option<(u64, usize)>> {
+        let block_height = match get!(self, hash.clone(), HashHeightSchema)? {
+            Some(block_height) => block_height,
+            None => return Ok(None),
+        };
+
+        let opt_index = self.get_block(ctx, block_height).await?.and_then(|block| {
+            block
+                .ordered_tx_hashes
+                .iter()
+                .position(|tx_hash| tx_hash == hash)
+        });
+        Ok(opt_index.map(|index| (block_height, index)))
+    }
+
+    async fn contains_transaction(&self, _ctx: Context, hash: &Hash) -> ProtocolResult<bool> {
+        if let Some(block_height) = get!(self, hash.clone(), HashHeightSchema)? {
+            self.adapter
+                .contains::<TransactionSchema>(CommonHashKey::new(block_height, hash.clone()))
+                .await
+        } else {
+            Ok(false)
+        }
+    }
+
+    async fn filter_existing(&self, _ctx: Context, hashes: Vec<Hash>) -> ProtocolResult<Vec<Hash>> {
+        let heights = self
+            .adapter
+            .get_batch::<HashHeightSchema>(hashes.clone())
+            .await?;
+
+        Ok(hashes
+            .into_iter()
+            .zip(heights.into_iter())
+            .filter_map(|(hash, height)| height.map(|_| hash))
+            .collect())
+    }
+
     #[muta_apm::derive::tracing_span(kind = "storage")]
     async fn insert_receipts(
         &self,
@@ -423,39 +866,10 @@ impl<Adapter: StorageAdapter> Storage for ImplStorage<Adapter> {
             }
         }
 
-        let mut found = {
-            if found.len() <= BATCH_VALUE_DECODE_NUMBER {
-                found
-                    .drain(..)
-                    .map(|(k, v): (Hash, Bytes)| Receipt::decode_sync(v).map(|v| (k, v)))
-                    .collect::<ProtocolResult<Vec<_>>>()?
-                    .into_iter()
-                    .collect::<HashMap<_, _>>()
-            } else {
-                let futs = found
-                    .chunks(BATCH_VALUE_DECODE_NUMBER)
-                    .map(|vals| {
-                        let vals = vals.to_owned();
-
-                        // FIXME: cancel decode
-                        tokio::spawn(async move {
-                            vals.into_iter()
-                                .map(|(k, v)| <_>::decode_sync(v).map(|v| (k, v)))
-                                .collect::<ProtocolResult<Vec<_>>>()
-                        })
-                    })
-                    .collect::<Vec<_>>();
-
-                futures::future::try_join_all(futs)
-                    .await
-                    .map_err(|_| StorageError::BatchDecode)?
-                    .into_iter()
-                    .collect::<ProtocolResult<Vec<Vec<_>>>>()?
-                    .into_iter()
-                    .flatten()
-                    .collect::<HashMap<_, _>>()
-            }
-        };
+        let mut found = batch_decode(found)
+            .await?
+            .into_iter()
+            .collect::<HashMap<_, _>>();
 
         Ok(hashes
             .into_iter()
@@ -463,6 +877,39 @@ impl<Adapter: StorageAdapter> Storage for ImplStorage<Adapter> {
             .collect::<Vec<_>>())
     }
 
+    #[muta_apm::derive::tracing_span(kind = "storage")]
+    async fn get_block_receipts(
+        &self,
+        ctx: Context,
+        block_height: u64,
+    ) -> ProtocolResult<Vec<Receipt>> {
+        let key_prefix = CommonPrefix::new(block_height);
+        let mut found = Vec::new();
+
+        {
+            let inst = Instant::now();
+            let prepare_iter = self
+                .adapter
+                .prepare_iter::<ReceiptBytesSchema, _>(&key_prefix)?;
+            let mut iter = prepare_iter.ref_to_iter();
+
+            while let Some(item) = iter.next() {
+                let (key, receipt_bytes) = item?;
+                if key.height() != block_height {
+                    break;
+                }
+                found.push((key.hash, receipt_bytes));
+            }
+            on_storage_get_cf(StorageCategory::Receipt, inst.elapsed(), found.len() as i64);
+        }
+
+        Ok(batch_decode::<Receipt>(found)
+            .await?
+            .into_iter()
+            .map(|(_, receipt)| receipt)
+            .collect())
+    }
+
     async fn get_receipt_by_hash(
         &self,
         _ctx: Context,
@@ -499,7 +946,48 @@ impl<Adapter: StorageAdapter> CommonStorage for ImplStorage<Adapter> {
     }
 
     async fn get_block(&self, _ctx: Context, height: u64) -> ProtocolResult<Option<Block>> {
-        self.adapter.get::<BlockSchema>(BlockKey::new(height)).await
+        let generation = {
+            let mut cache = self.block_cache.lock();
+            if let Some(block) = cache.blocks.get(&height) {
+                return Ok(Some(block.clone()));
+            }
+            cache.generation
+        };
+
+        let opt_block = self
+            .adapter
+            .get::<BlockSchema>(BlockKey::new(height))
+            .await?;
+        if let Some(block) = opt_block.as_ref() {
+            self.verify_block_checksum(height, block).await?;
+            self.block_cache
+                .lock()
+                .put(generation, height, block.clone());
+        }
+        Ok(opt_block)
+    }
+
+    async fn contains_block(&self, _ctx: Context, height: u64) -> ProtocolResult<bool> {
+        self.adapter
+            .contains::<BlockSchema>(BlockKey::new(height))
+            .await
+    }
+
+    async fn get_blocks(&self, _ctx: Context, from: u64, limit: u64) -> ProtocolResult<Vec<Block>> {
+        let keys = (from..from.saturating_add(limit))
+            .map(BlockKey::new)
+            .collect::<Vec<_>>();
+        let opt_blocks = self.adapter.get_batch::<BlockSchema>(keys).await?;
+
+        let blocks = opt_blocks
+            .into_iter()
+            .take_while(Option::is_some)
+            .flatten()
+            .collect::<Vec<_>>();
+        for (height, block) in (from..).zip(blocks.iter()) {
+            self.verify_block_checksum(height, block).await?;
+        }
+        Ok(blocks)
     }
 
     async fn get_block_header(
@@ -519,28 +1007,47 @@ impl<Adapter: StorageAdapter> CommonStorage for ImplStorage<Adapter> {
     }
 
     // !!!be careful, the prev_hash may mismatch and latest block may diverse!!!
+    // The checksum is written in the same batch, so that it never goes without
+    // its block.
     async fn set_block(&self, _ctx: Context, block: Block) -> ProtocolResult<()> {
-        self.adapter
-            .insert::<BlockSchema>(BlockKey::new(block.header.height), block.clone())
-            .await?;
-        self.adapter
-            .insert::<BlockHeaderSchema>(BlockKey::new(block.header.height), block.header.clone())
-            .await?;
+        let height = block.header.height;
+
+        let mut batch = StorageBatch::default();
+        if self.block_checksum {
+            batch.insert::<BlockChecksumSchema>(
+                block_checksum_key(height),
+                block_checksum(&block)?,
+            )?;
+        }
+        batch.insert::<BlockHeaderSchema>(BlockKey::new(height), block.header.clone())?;
+        batch.insert::<BlockSchema>(BlockKey::new(height), block)?;
+        self.adapter.write_batch(batch).await?;
+
+        self.block_cache.lock().invalidate(height);
         Ok(())
     }
 
     // !be careful, only call this function in maintenance mode!
     async fn remove_block(&self, _ctx: Context, height: u64) -> ProtocolResult<()> {
-        self.adapter
-            .remove::<BlockSchema>(BlockKey::new(height))
-            .await
+        let mut batch = StorageBatch::default();
+        batch.remove::<BlockChecksumSchema>(block_checksum_key(height))?;
+        batch.remove::<BlockSchema>(BlockKey::new(height))?;
+        self.adapter.write_batch(batch).await?;
+
+        self.block_cache.lock().invalidate(height);
+        Ok(())
     }
 
     async fn get_latest_block(&self, _ctx: Context) -> ProtocolResult<Block> {
-        if let Some(block) = self.latest_block.load().as_ref().clone() {
+        let opt_block = self.latest_block.load().as_ref().clone();
+        on_latest_block_cache(opt_block.is_some());
+
+        if let Some(block) = opt_block {
             Ok(block)
         } else {
             let block = ensure_get!(self, LATEST_BLOCK_KEY.clone(), LatestBlockSchema);
+            self.verify_block_checksum(block.header.height, &block)
+                .await?;
             Ok(block)
         }
     }
@@ -551,6 +1058,7 @@ impl<Adapter: StorageAdapter> CommonStorage for ImplStorage<Adapter> {
             let opt_block = guard.as_ref();
             opt_block.as_ref().map(|b| b.header.clone())
         };
+        on_latest_block_cache(opt_header.is_some());
 
         if let Some(header) = opt_header {
             Ok(header)
@@ -571,6 +1079,40 @@ impl<Adapter: StorageAdapter> CommonStorage for ImplStorage<Adapter> {
     }
 }
 
+async fn batch_decode<T: ProtocolCodecSync + Send + 'static>(
+    mut found: Vec<(Hash, Bytes)>,
+) -> ProtocolResult<Vec<(Hash, T)>> {
+    if found.len() <= BATCH_VALUE_DECODE_NUMBER {
+        found
+            .drain(..)
+            .map(|(k, v)| T::decode_sync(v).map(|v| (k, v)))
+            .collect::<ProtocolResult<Vec<_>>>()
+    } else {
+        let futs = found
+            .chunks(BATCH_VALUE_DECODE_NUMBER)
+            .map(|vals| {
+                let vals = vals.to_owned();
+
+                // FIXME: cancel decode
+                tokio::spawn(async move {
+                    vals.into_iter()
+                        .map(|(k, v)| T::decode_sync(v).map(|v| (k, v)))
+                        .collect::<ProtocolResult<Vec<_>>>()
+                })
+            })
+            .collect::<Vec<_>>();
+
+        Ok(futures::future::try_join_all(futs)
+            .await
+            .map_err(|_| StorageError::BatchDecode)?
+            .into_iter()
+            .collect::<ProtocolResult<Vec<Vec<_>>>>()?
+            .into_iter()
+            .flatten()
+            .collect())
+    }
+}
+
 #[derive(Debug, Display, From)]
 pub enum StorageError {
     #[display(fmt = "get none")]
@@ -578,6 +1120,15 @@ pub enum StorageError {
 
     #[display(fmt = "decode batch value")]
     BatchDecode,
+
+    #[display(fmt = "can't migrate from category {} to {}", old, new)]
+    MigrateCategory {
+        old: StorageCategory,
+        new: StorageCategory,
+    },
+
+    #[display(fmt = "block {} is corrupted, checksum mismatch", _0)]
+    Corrupted(u64),
 }
 
 impl Error for StorageError {}
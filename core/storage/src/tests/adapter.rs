@@ -1,10 +1,12 @@
-use protocol::traits::{StorageAdapter, StorageBatchModify};
+use std::collections::HashMap;
+
+use protocol::traits::{StorageAdapter, StorageBatch, StorageBatchModify, StorageCategory};
 use protocol::types::Hash;
 
 use crate::adapter::memory::MemoryAdapter;
-use crate::adapter::rocks::RocksAdapter;
+use crate::adapter::rocks::{ColumnFamilyOptions, RocksAdapter};
 use crate::tests::{get_random_bytes, mock_signed_tx};
-use crate::{CommonHashKey, TransactionSchema};
+use crate::{CommonHashKey, HashHeightSchema, TransactionSchema};
 
 #[tokio::test]
 async fn test_adapter_insert() {
@@ -22,6 +24,24 @@ async fn test_adapter_batch_modify() {
     .await
 }
 
+#[tokio::test]
+async fn test_adapter_get_batch() {
+    adapter_get_batch_test(MemoryAdapter::new()).await;
+    adapter_get_batch_test(
+        RocksAdapter::new("rocksdb/test_adapter_get_batch".to_string(), 64).unwrap(),
+    )
+    .await
+}
+
+#[tokio::test]
+async fn test_adapter_write_batch() {
+    adapter_write_batch_test(MemoryAdapter::new()).await;
+    adapter_write_batch_test(
+        RocksAdapter::new("rocksdb/test_adapter_write_batch".to_string(), 64).unwrap(),
+    )
+    .await
+}
+
 #[tokio::test]
 async fn test_adapter_remove() {
     adapter_remove_test(MemoryAdapter::new()).await;
@@ -29,6 +49,26 @@ async fn test_adapter_remove() {
         .await
 }
 
+#[tokio::test]
+async fn test_adapter_with_cf_options() {
+    let mut cf_options = HashMap::new();
+    cf_options.insert(StorageCategory::SignedTransaction, ColumnFamilyOptions {
+        block_size:        Some(16 * 1024),
+        bloom_filter_bits: Some(10),
+        write_buffer_size: Some(128 * 1024 * 1024),
+    });
+    cf_options.insert(StorageCategory::Block, ColumnFamilyOptions {
+        bloom_filter_bits: Some(10),
+        ..Default::default()
+    });
+
+    adapter_insert_test(
+        RocksAdapter::new_with_cf_options("rocksdb/test_adapter_cf_options", 64, cf_options)
+            .unwrap(),
+    )
+    .await
+}
+
 async fn adapter_insert_test(db: impl StorageAdapter) {
     let tx_hash = Hash::digest(get_random_bytes(10));
     let tx_key = CommonHashKey::new(1, tx_hash.clone());
@@ -68,6 +108,60 @@ async fn adapter_batch_modify_test(db: impl StorageAdapter) {
     }
 }
 
+async fn adapter_get_batch_test(db: impl StorageAdapter) {
+    let mut keys = Vec::new();
+    let mut expected = Vec::new();
+
+    for i in 0..10 {
+        let tx_hash = Hash::digest(get_random_bytes(10));
+        let tx_key = CommonHashKey::new(1, tx_hash.clone());
+        keys.push(tx_key.clone());
+
+        if i % 2 == 0 {
+            db.insert::<TransactionSchema>(tx_key, mock_signed_tx(tx_hash.clone()))
+                .await
+                .unwrap();
+            expected.push(Some(tx_hash));
+        } else {
+            expected.push(None);
+        }
+    }
+
+    let opt_stxs = db.get_batch::<TransactionSchema>(keys).await.unwrap();
+    let found = opt_stxs
+        .into_iter()
+        .map(|opt_stx| opt_stx.map(|stx| stx.tx_hash))
+        .collect::<Vec<_>>();
+    assert_eq!(found, expected);
+}
+
+async fn adapter_write_batch_test(db: impl StorageAdapter) {
+    let tx_hash = Hash::digest(get_random_bytes(10));
+    let tx_key = CommonHashKey::new(1, tx_hash.clone());
+    let stale_hash = Hash::digest(get_random_bytes(10));
+    db.insert::<HashHeightSchema>(stale_hash.clone(), 1)
+        .await
+        .unwrap();
+
+    let mut batch = StorageBatch::default();
+    batch
+        .insert::<TransactionSchema>(tx_key.clone(), mock_signed_tx(tx_hash.clone()))
+        .unwrap();
+    batch
+        .insert::<HashHeightSchema>(tx_hash.clone(), 1)
+        .unwrap();
+    batch
+        .remove::<HashHeightSchema>(stale_hash.clone())
+        .unwrap();
+    assert_eq!(batch.len(), 3);
+    db.write_batch(batch).await.unwrap();
+
+    let stx = db.get::<TransactionSchema>(tx_key).await.unwrap();
+    assert_eq!(stx.map(|stx| stx.tx_hash), Some(tx_hash.clone()));
+    assert_eq!(db.get::<HashHeightSchema>(tx_hash).await.unwrap(), Some(1));
+    assert!(!db.contains::<HashHeightSchema>(stale_hash).await.unwrap());
+}
+
 async fn adapter_remove_test(db: impl StorageAdapter) {
     let tx_hash = Hash::digest(get_random_bytes(10));
     let tx_key = CommonHashKey::new(1, tx_hash.clone());
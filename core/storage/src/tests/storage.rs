@@ -1,17 +1,128 @@
 extern crate test;
 
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
 
+use async_trait::async_trait;
+use futures::lock::Mutex;
 use test::Bencher;
 
-use protocol::traits::{CommonStorage, Context, Storage};
+use protocol::codec::ProtocolCodecSync;
+use protocol::traits::{
+    CommonStorage, Context, IntoIteratorByRef, MaintenanceStorage, Storage, StorageAdapter,
+    StorageBatch, StorageBatchModify, StorageCategory, StorageSchema,
+};
 use protocol::types::Hash;
+use protocol::{Bytes, ProtocolResult};
 use tokio::runtime::Runtime;
 
 use crate::adapter::memory::MemoryAdapter;
+use crate::adapter::rocks::RocksAdapter;
 use crate::tests::{get_random_bytes, mock_block, mock_proof, mock_receipt, mock_signed_tx};
-use crate::ImplStorage;
 use crate::BATCH_VALUE_DECODE_NUMBER;
+use crate::{
+    migrate_progress_key, BlockKey, CommonHashKey, HashHeightSchema, ImplStorage,
+    MigrateProgressSchema, RawHashHeightSchema, StorageError, TransactionSchema, LATEST_BLOCK_KEY,
+};
+
+struct ToyHashSchema;
+
+impl StorageSchema for ToyHashSchema {
+    type Key = u64;
+    type Value = Hash;
+
+    fn category() -> StorageCategory {
+        StorageCategory::HashHeight
+    }
+}
+
+struct ToyNumberSchema;
+
+impl StorageSchema for ToyNumberSchema {
+    type Key = u64;
+    type Value = u64;
+
+    fn category() -> StorageCategory {
+        StorageCategory::HashHeight
+    }
+}
+
+/// Memory adapter counting reads, to tell whether storage hits the adapter.
+struct CountingAdapter {
+    inner:      MemoryAdapter,
+    gets:       AtomicUsize,
+    // Held to pause writes of block category
+    block_gate: Mutex<()>,
+    // Held to pause reads of block category after the value is read
+    read_gate:  Mutex<()>,
+}
+
+impl CountingAdapter {
+    fn new() -> Self {
+        CountingAdapter {
+            inner:      MemoryAdapter::new(),
+            gets:       AtomicUsize::new(0),
+            block_gate: Mutex::new(()),
+            read_gate:  Mutex::new(()),
+        }
+    }
+}
+
+#[async_trait]
+impl StorageAdapter for CountingAdapter {
+    async fn insert<S: StorageSchema>(
+        &self,
+        key: <S as StorageSchema>::Key,
+        val: <S as StorageSchema>::Value,
+    ) -> ProtocolResult<()> {
+        if S::category() == StorageCategory::Block {
+            let _gate = self.block_gate.lock().await;
+        }
+        self.inner.insert::<S>(key, val).await
+    }
+
+    async fn get<S: StorageSchema>(
+        &self,
+        key: <S as StorageSchema>::Key,
+    ) -> ProtocolResult<Option<<S as StorageSchema>::Value>> {
+        self.gets.fetch_add(1, Ordering::SeqCst);
+        let val = self.inner.get::<S>(key).await;
+        if S::category() == StorageCategory::Block {
+            let _gate = self.read_gate.lock().await;
+        }
+        val
+    }
+
+    async fn remove<S: StorageSchema>(&self, key: <S as StorageSchema>::Key) -> ProtocolResult<()> {
+        self.inner.remove::<S>(key).await
+    }
+
+    async fn contains<S: StorageSchema>(
+        &self,
+        key: <S as StorageSchema>::Key,
+    ) -> ProtocolResult<bool> {
+        self.inner.contains::<S>(key).await
+    }
+
+    async fn batch_modify<S: StorageSchema>(
+        &self,
+        keys: Vec<<S as StorageSchema>::Key>,
+        vals: Vec<StorageBatchModify<S>>,
+    ) -> ProtocolResult<()> {
+        self.inner.batch_modify::<S>(keys, vals).await
+    }
+
+    async fn write_batch(&self, batch: StorageBatch) -> ProtocolResult<()> {
+        self.inner.write_batch(batch).await
+    }
+
+    fn prepare_iter<'a, 'b: 'a, S: StorageSchema + 'static, P: AsRef<[u8]> + 'a>(
+        &'b self,
+        prefix: &'a P,
+    ) -> ProtocolResult<Box<dyn IntoIteratorByRef<S> + 'a>> {
+        self.inner.prepare_iter::<S, P>(prefix)
+    }
+}
 
 #[tokio::test]
 async fn test_storage_block_insert() {
@@ -29,6 +140,319 @@ async fn test_storage_block_insert() {
     assert_eq!(Some(height), block.map(|b| b.header.height));
 }
 
+#[tokio::test]
+async fn test_storage_block_checksum() {
+    let adapter = Arc::new(MemoryAdapter::new());
+    let storage =
+        ImplStorage::new_with_block_cache(Arc::clone(&adapter), 0).with_block_checksum(true);
+
+    let height = 100;
+    let block = mock_block(height, Hash::digest(get_random_bytes(10)));
+    storage
+        .insert_block(Context::new(), block.clone())
+        .await
+        .unwrap();
+    assert_eq!(
+        storage.get_block(Context::new(), height).await.unwrap(),
+        Some(block.clone())
+    );
+
+    // Corrupt the stored bytes so that they still decode to a block
+    let mut corrupted = block.clone();
+    corrupted.header.timestamp += 1;
+    storage
+        .put_raw(
+            Context::new(),
+            StorageCategory::Block,
+            BlockKey::new(height).encode_sync().unwrap(),
+            corrupted.encode_sync().unwrap(),
+        )
+        .await
+        .unwrap();
+    let err = storage.get_block(Context::new(), height).await.unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<StorageError>(),
+        Some(StorageError::Corrupted(100))
+    ));
+    assert!(storage.get_blocks(Context::new(), height, 1).await.is_err());
+
+    // Latest block is verified once it's read from storage
+    storage
+        .put_raw(
+            Context::new(),
+            StorageCategory::Block,
+            LATEST_BLOCK_KEY.encode_sync().unwrap(),
+            corrupted.encode_sync().unwrap(),
+        )
+        .await
+        .unwrap();
+    let restarted =
+        ImplStorage::new_with_block_cache(Arc::clone(&adapter), 0).with_block_checksum(true);
+    let err = restarted
+        .get_latest_block(Context::new())
+        .await
+        .unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<StorageError>(),
+        Some(StorageError::Corrupted(100))
+    ));
+
+    // Blocks written without checksum are read unverified
+    let legacy = ImplStorage::new(Arc::clone(&adapter));
+    let block = mock_block(height + 1, Hash::digest(get_random_bytes(10)));
+    legacy
+        .set_block(Context::new(), block.clone())
+        .await
+        .unwrap();
+    assert_eq!(
+        storage.get_block(Context::new(), height + 1).await.unwrap(),
+        Some(block)
+    );
+}
+
+#[tokio::test]
+async fn test_storage_block_cache() {
+    let adapter = Arc::new(CountingAdapter::new());
+    let storage = ImplStorage::new_with_block_cache(Arc::clone(&adapter), 2);
+
+    let block = mock_block(100, Hash::digest(get_random_bytes(10)));
+    storage.set_block(Context::new(), block).await.unwrap();
+
+    storage
+        .get_block(Context::new(), 100)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(adapter.gets.load(Ordering::SeqCst), 1);
+    storage
+        .get_block(Context::new(), 100)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(adapter.gets.load(Ordering::SeqCst), 1);
+
+    // Overwritten block is read from adapter again
+    let block = mock_block(100, Hash::digest(get_random_bytes(10)));
+    storage
+        .set_block(Context::new(), block.clone())
+        .await
+        .unwrap();
+    let cached = storage.get_block(Context::new(), 100).await.unwrap();
+    assert_eq!(cached, Some(block));
+    assert_eq!(adapter.gets.load(Ordering::SeqCst), 2);
+
+    storage.remove_block(Context::new(), 100).await.unwrap();
+    assert!(storage
+        .get_block(Context::new(), 100)
+        .await
+        .unwrap()
+        .is_none());
+}
+
+#[tokio::test]
+async fn test_storage_block_cache_concurrent_overwrite() {
+    let adapter = Arc::new(CountingAdapter::new());
+    let storage = Arc::new(ImplStorage::new_with_block_cache(Arc::clone(&adapter), 2));
+
+    let old_block = mock_block(100, Hash::digest(get_random_bytes(10)));
+    storage
+        .set_block(Context::new(), old_block.clone())
+        .await
+        .unwrap();
+
+    // Read the block while its overwrite is waiting for adapter
+    let gate = adapter.block_gate.lock().await;
+    let new_block = mock_block(100, Hash::digest(get_random_bytes(10)));
+    let overwrite = tokio::spawn({
+        let storage = Arc::clone(&storage);
+        let new_block = new_block.clone();
+        async move { storage.set_block(Context::new(), new_block).await }
+    });
+    tokio::task::yield_now().await;
+    let block = storage.get_block(Context::new(), 100).await.unwrap();
+    assert_eq!(block, Some(old_block));
+
+    drop(gate);
+    overwrite.await.unwrap().unwrap();
+    let block = storage.get_block(Context::new(), 100).await.unwrap();
+    assert_eq!(block, Some(new_block));
+}
+
+#[tokio::test]
+async fn test_storage_block_cache_read_before_overwrite() {
+    let adapter = Arc::new(CountingAdapter::new());
+    let storage = Arc::new(ImplStorage::new_with_block_cache(Arc::clone(&adapter), 2));
+
+    let old_block = mock_block(100, Hash::digest(get_random_bytes(10)));
+    storage
+        .set_block(Context::new(), old_block.clone())
+        .await
+        .unwrap();
+
+    // Read the old block, then overwrite it before the read caches it
+    let gate = adapter.read_gate.lock().await;
+    let gets = adapter.gets.load(Ordering::SeqCst);
+    let read = tokio::spawn({
+        let storage = Arc::clone(&storage);
+        async move { storage.get_block(Context::new(), 100).await }
+    });
+    while adapter.gets.load(Ordering::SeqCst) == gets {
+        tokio::task::yield_now().await;
+    }
+
+    let new_block = mock_block(100, Hash::digest(get_random_bytes(10)));
+    storage
+        .set_block(Context::new(), new_block.clone())
+        .await
+        .unwrap();
+
+    drop(gate);
+    assert_eq!(read.await.unwrap().unwrap(), Some(old_block));
+    let block = storage.get_block(Context::new(), 100).await.unwrap();
+    assert_eq!(block, Some(new_block));
+}
+
+#[tokio::test]
+async fn test_storage_latest_block_cache_metrics() {
+    use common_apm::metrics::storage::STORAGE_LATEST_BLOCK_CACHE_COUNTER_VEC;
+
+    let counter = |result| {
+        STORAGE_LATEST_BLOCK_CACHE_COUNTER_VEC
+            .with_label_values(&[result])
+            .get()
+    };
+
+    let adapter = Arc::new(MemoryAdapter::new());
+    let block = mock_block(100, Hash::digest(get_random_bytes(10)));
+    ImplStorage::new(Arc::clone(&adapter))
+        .set_latest_block(Context::new(), block.clone())
+        .await
+        .unwrap();
+
+    // Cold start, the latest block is read from adapter
+    let storage = ImplStorage::new(adapter);
+    let misses = counter("miss");
+    storage.get_latest_block(Context::new()).await.unwrap();
+    assert!(counter("miss") > misses);
+
+    storage
+        .set_latest_block(Context::new(), block)
+        .await
+        .unwrap();
+    let hits = counter("hit");
+    storage.get_latest_block(Context::new()).await.unwrap();
+    storage
+        .get_latest_block_header(Context::new())
+        .await
+        .unwrap();
+    assert!(counter("hit") >= hits + 2);
+}
+
+#[tokio::test]
+async fn test_storage_scan_paged() {
+    let adapter = Arc::new(MemoryAdapter::new());
+    let storage = ImplStorage::new(Arc::clone(&adapter));
+
+    let hashes = (0..25)
+        .map(|_| Hash::digest(get_random_bytes(10)))
+        .collect::<Vec<_>>();
+    for (height, hash) in hashes.iter().enumerate() {
+        adapter
+            .insert::<HashHeightSchema>(hash.clone(), height as u64)
+            .await
+            .unwrap();
+    }
+
+    let mut scanned = Vec::new();
+    let mut cursor = None;
+    loop {
+        let (rows, next) = storage
+            .scan_paged::<HashHeightSchema>(&[], cursor, 10)
+            .unwrap();
+        assert!(rows.len() <= 10);
+        scanned.extend(rows.into_iter().map(|(hash, _)| hash));
+
+        match next {
+            Some(key) => cursor = Some(key),
+            None => break,
+        }
+    }
+
+    assert_eq!(scanned.len(), hashes.len());
+    let scanned = scanned
+        .into_iter()
+        .collect::<std::collections::HashSet<_>>();
+    assert_eq!(scanned, hashes.into_iter().collect());
+}
+
+#[tokio::test]
+async fn test_storage_scan_paged_key_equals_prefix() {
+    let adapter = Arc::new(MemoryAdapter::new());
+    let storage = ImplStorage::new(Arc::clone(&adapter));
+
+    for key in ["ab", "abc", "abd", "b"].iter() {
+        adapter
+            .insert::<RawHashHeightSchema>(Bytes::from(*key), Bytes::from(*key))
+            .await
+            .unwrap();
+    }
+
+    let (rows, next) = storage
+        .scan_paged::<RawHashHeightSchema>(b"ab", None, 2)
+        .unwrap();
+    let keys = rows.into_iter().map(|(key, _)| key).collect::<Vec<_>>();
+    assert_eq!(keys, vec![Bytes::from("ab"), Bytes::from("abc")]);
+    assert_eq!(next, Some(Bytes::from("abc")));
+
+    let (rows, next) = storage
+        .scan_paged::<RawHashHeightSchema>(b"ab", next, 2)
+        .unwrap();
+    let keys = rows.into_iter().map(|(key, _)| key).collect::<Vec<_>>();
+    assert_eq!(keys, vec![Bytes::from("abd")]);
+    assert_eq!(next, None);
+}
+
+#[tokio::test]
+async fn test_storage_contains_block() {
+    let storage = ImplStorage::new(Arc::new(MemoryAdapter::new()));
+
+    let height = 100;
+    let block = mock_block(height, Hash::digest(get_random_bytes(10)));
+
+    storage.insert_block(Context::new(), block).await.unwrap();
+
+    assert!(storage
+        .contains_block(Context::new(), height)
+        .await
+        .unwrap());
+    assert!(!storage
+        .contains_block(Context::new(), height + 1)
+        .await
+        .unwrap());
+}
+
+#[tokio::test]
+async fn test_storage_get_blocks() {
+    let storage = ImplStorage::new(Arc::new(MemoryAdapter::new()));
+
+    for height in 1..=20 {
+        let block = mock_block(height, Hash::digest(get_random_bytes(10)));
+        storage.insert_block(Context::new(), block).await.unwrap();
+    }
+
+    let blocks = storage.get_blocks(Context::new(), 5, 10).await.unwrap();
+    let heights = blocks.iter().map(|b| b.header.height).collect::<Vec<_>>();
+    assert_eq!(heights, (5..15).collect::<Vec<_>>());
+
+    // Stop at the latest block
+    let blocks = storage.get_blocks(Context::new(), 15, 10).await.unwrap();
+    let heights = blocks.iter().map(|b| b.header.height).collect::<Vec<_>>();
+    assert_eq!(heights, (15..=20).collect::<Vec<_>>());
+
+    let blocks = storage.get_blocks(Context::new(), 21, 10).await.unwrap();
+    assert!(blocks.is_empty());
+}
+
 #[tokio::test]
 async fn test_storage_receipts_insert() {
     let storage = ImplStorage::new(Arc::new(MemoryAdapter::new()));
@@ -61,6 +485,43 @@ async fn test_storage_receipts_insert() {
     }
 }
 
+#[tokio::test]
+async fn test_storage_get_block_receipts() {
+    let storage = ImplStorage::new(Arc::new(MemoryAdapter::new()));
+    let height = 2077;
+
+    let receipts = (0..10)
+        .map(|_| mock_receipt(Hash::digest(get_random_bytes(10))))
+        .collect::<Vec<_>>();
+    let next_receipts = (0..3)
+        .map(|_| mock_receipt(Hash::digest(get_random_bytes(10))))
+        .collect::<Vec<_>>();
+
+    storage
+        .insert_receipts(Context::new(), height, receipts.clone())
+        .await
+        .unwrap();
+    storage
+        .insert_receipts(Context::new(), height + 1, next_receipts)
+        .await
+        .unwrap();
+
+    let block_receipts = storage
+        .get_block_receipts(Context::new(), height)
+        .await
+        .unwrap();
+    assert_eq!(block_receipts.len(), receipts.len());
+    for receipt in receipts.iter() {
+        assert!(block_receipts.contains(receipt));
+    }
+
+    let empty = storage
+        .get_block_receipts(Context::new(), height - 1)
+        .await
+        .unwrap();
+    assert!(empty.is_empty());
+}
+
 #[tokio::test]
 async fn test_storage_receipts_get_batch_decode() {
     let storage = ImplStorage::new(Arc::new(MemoryAdapter::new()));
@@ -127,6 +588,448 @@ async fn test_storage_transactions_insert() {
     }
 }
 
+#[tokio::test]
+async fn test_storage_sync() {
+    let adapter = RocksAdapter::new("rocksdb/test_storage_sync".to_string(), 64).unwrap();
+    let storage = ImplStorage::new(Arc::new(adapter));
+    let height = 2020;
+
+    let txs = (0..10)
+        .map(|_| mock_signed_tx(Hash::digest(get_random_bytes(10))))
+        .collect::<Vec<_>>();
+    storage
+        .insert_transactions(Context::new(), height, txs)
+        .await
+        .unwrap();
+
+    storage.sync(Context::new()).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_storage_compact_range() {
+    let adapter = RocksAdapter::new("rocksdb/test_storage_compact_range".to_string(), 64).unwrap();
+    let storage = ImplStorage::new(Arc::new(adapter));
+
+    for height in 1..=20 {
+        let mut block = mock_block(height, Hash::digest(get_random_bytes(10)));
+        let txs = (0..10)
+            .map(|_| mock_signed_tx(Hash::digest(get_random_bytes(10))))
+            .collect::<Vec<_>>();
+        block.ordered_tx_hashes = txs.iter().map(|tx| tx.tx_hash.clone()).collect();
+
+        storage.set_block(Context::new(), block).await.unwrap();
+        storage
+            .insert_transactions(Context::new(), height, txs)
+            .await
+            .unwrap();
+    }
+    storage.sync(Context::new()).await.unwrap();
+
+    for height in 6..=20 {
+        storage
+            .remove_block_full(Context::new(), height)
+            .await
+            .unwrap();
+    }
+
+    for category in [StorageCategory::Block, StorageCategory::SignedTransaction].iter() {
+        storage
+            .compact_range(Context::new(), *category, None, None)
+            .await
+            .unwrap();
+    }
+    let from = BlockKey::new(3).as_ref().to_vec();
+    storage
+        .compact_range(
+            Context::new(),
+            StorageCategory::Block,
+            Some(Bytes::from(from)),
+            None,
+        )
+        .await
+        .unwrap();
+
+    assert!(storage.contains_block(Context::new(), 5).await.unwrap());
+    assert!(!storage.contains_block(Context::new(), 6).await.unwrap());
+    assert!(!storage.contains_block(Context::new(), 20).await.unwrap());
+}
+
+#[tokio::test]
+async fn test_storage_get_transaction_at() {
+    let storage = ImplStorage::new(Arc::new(MemoryAdapter::new()));
+    let height = 2020;
+
+    let tx_hash = Hash::digest(get_random_bytes(10));
+    let transaction = mock_signed_tx(tx_hash.clone());
+
+    storage
+        .insert_transactions(Context::new(), height, vec![transaction.clone()])
+        .await
+        .unwrap();
+
+    let stx = storage
+        .get_transaction_at(Context::new(), height, &tx_hash)
+        .await
+        .unwrap();
+    assert_eq!(stx, Some(transaction));
+
+    let stx = storage
+        .get_transaction_at(Context::new(), height + 1, &tx_hash)
+        .await
+        .unwrap();
+    assert_eq!(stx, None);
+}
+
+#[tokio::test]
+async fn test_storage_get_transaction_location() {
+    let storage = ImplStorage::new(Arc::new(MemoryAdapter::new()));
+    let height = 2020;
+
+    let tx_hashes = (0..3)
+        .map(|_| Hash::digest(get_random_bytes(10)))
+        .collect::<Vec<_>>();
+    let transactions = tx_hashes.iter().cloned().map(mock_signed_tx).collect();
+    let mut block = mock_block(height, Hash::digest(get_random_bytes(10)));
+    block.ordered_tx_hashes = tx_hashes.clone();
+
+    storage
+        .insert_transactions(Context::new(), height, transactions)
+        .await
+        .unwrap();
+    storage.insert_block(Context::new(), block).await.unwrap();
+
+    let location = storage
+        .get_transaction_location(Context::new(), &tx_hashes[2])
+        .await
+        .unwrap();
+    assert_eq!(location, Some((height, 2)));
+
+    let location = storage
+        .get_transaction_location(Context::new(), &Hash::digest(get_random_bytes(10)))
+        .await
+        .unwrap();
+    assert_eq!(location, None);
+}
+
+#[tokio::test]
+async fn test_storage_contains_transaction() {
+    let storage = ImplStorage::new(Arc::new(MemoryAdapter::new()));
+    let height = 2020;
+
+    let tx_hash = Hash::digest(get_random_bytes(10));
+    let transaction = mock_signed_tx(tx_hash.clone());
+
+    storage
+        .insert_transactions(Context::new(), height, vec![transaction])
+        .await
+        .unwrap();
+
+    assert!(storage
+        .contains_transaction(Context::new(), &tx_hash)
+        .await
+        .unwrap());
+
+    let absent_hash = Hash::digest(get_random_bytes(10));
+    assert!(!storage
+        .contains_transaction(Context::new(), &absent_hash)
+        .await
+        .unwrap());
+}
+
+#[tokio::test]
+async fn test_storage_filter_existing() {
+    filter_existing_test(ImplStorage::new(Arc::new(MemoryAdapter::new()))).await;
+    filter_existing_test(ImplStorage::new(Arc::new(
+        RocksAdapter::new("rocksdb/test_storage_filter_existing".to_string(), 64).unwrap(),
+    )))
+    .await;
+}
+
+async fn filter_existing_test<Adapter: StorageAdapter>(storage: ImplStorage<Adapter>) {
+    let height = 2020;
+
+    let existing = (0..5)
+        .map(|_| Hash::digest(get_random_bytes(10)))
+        .collect::<Vec<_>>();
+    let stxs = existing.iter().cloned().map(mock_signed_tx).collect();
+    storage
+        .insert_transactions(Context::new(), height, stxs)
+        .await
+        .unwrap();
+
+    let mut hashes = Vec::new();
+    for hash in existing.iter() {
+        hashes.push(hash.clone());
+        hashes.push(Hash::digest(get_random_bytes(10)));
+    }
+
+    let found = storage
+        .filter_existing(Context::new(), hashes)
+        .await
+        .unwrap();
+    assert_eq!(found, existing);
+
+    assert!(storage
+        .filter_existing(Context::new(), vec![])
+        .await
+        .unwrap()
+        .is_empty());
+}
+
+#[tokio::test]
+async fn test_storage_self_check() {
+    let adapter = Arc::new(MemoryAdapter::new());
+    let storage = ImplStorage::new(Arc::clone(&adapter));
+    let height = 2020;
+
+    let txs = (0..10)
+        .map(|_| mock_signed_tx(Hash::digest(get_random_bytes(10))))
+        .collect::<Vec<_>>();
+    let corrupted_hash = txs[3].tx_hash.clone();
+
+    storage
+        .insert_transactions(Context::new(), height, txs)
+        .await
+        .unwrap();
+
+    let report = storage.self_check(Context::new(), 100).await.unwrap();
+    assert_eq!(report.checked, 10);
+    assert!(report.dangling.is_empty());
+
+    // Point one mapping to a height without the transaction
+    adapter
+        .insert::<HashHeightSchema>(corrupted_hash.clone(), height + 1)
+        .await
+        .unwrap();
+
+    let report = storage.self_check(Context::new(), 100).await.unwrap();
+    assert_eq!(report.checked, 10);
+    assert_eq!(report.dangling, vec![(corrupted_hash, height + 1)]);
+
+    let report = storage.self_check(Context::new(), 5).await.unwrap();
+    assert_eq!(report.checked, 5);
+}
+
+#[tokio::test]
+async fn test_storage_remove_block_full() {
+    let adapter = Arc::new(MemoryAdapter::new());
+    let storage = ImplStorage::new(Arc::clone(&adapter));
+    let height = 100;
+
+    let tx_hashes = (0..10)
+        .map(|_| Hash::digest(get_random_bytes(10)))
+        .collect::<Vec<_>>();
+    let txs = tx_hashes
+        .iter()
+        .map(|hash| mock_signed_tx(hash.clone()))
+        .collect::<Vec<_>>();
+    let receipts = tx_hashes
+        .iter()
+        .map(|hash| mock_receipt(hash.clone()))
+        .collect::<Vec<_>>();
+    let mut block = mock_block(height, Hash::digest(get_random_bytes(10)));
+    block.ordered_tx_hashes = tx_hashes.clone();
+
+    storage.set_block(Context::new(), block).await.unwrap();
+    storage
+        .insert_transactions(Context::new(), height, txs)
+        .await
+        .unwrap();
+    storage
+        .insert_receipts(Context::new(), height, receipts)
+        .await
+        .unwrap();
+
+    // Another block at lower height is untouched
+    let other_hash = Hash::digest(get_random_bytes(10));
+    storage
+        .insert_transactions(Context::new(), height - 1, vec![mock_signed_tx(
+            other_hash.clone(),
+        )])
+        .await
+        .unwrap();
+
+    storage
+        .remove_block_full(Context::new(), height)
+        .await
+        .unwrap();
+
+    assert!(!storage
+        .contains_block(Context::new(), height)
+        .await
+        .unwrap());
+    assert!(storage
+        .get_block_header(Context::new(), height)
+        .await
+        .unwrap()
+        .is_none());
+    for hash in tx_hashes.iter() {
+        assert!(!adapter
+            .contains::<TransactionSchema>(CommonHashKey::new(height, hash.clone()))
+            .await
+            .unwrap());
+        assert!(adapter
+            .get::<HashHeightSchema>(hash.clone())
+            .await
+            .unwrap()
+            .is_none());
+    }
+    let receipts = storage
+        .get_block_receipts(Context::new(), height)
+        .await
+        .unwrap();
+    assert!(receipts.is_empty());
+
+    let report = storage.self_check(Context::new(), 100).await.unwrap();
+    assert_eq!(report.checked, 1);
+    assert!(report.dangling.is_empty());
+    assert!(storage
+        .contains_transaction(Context::new(), &other_hash)
+        .await
+        .unwrap());
+
+    // Nothing to remove
+    storage
+        .remove_block_full(Context::new(), height)
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn test_storage_raw_get_put() {
+    let storage = ImplStorage::new_with_block_cache(Arc::new(MemoryAdapter::new()), 2);
+    let key = Bytes::from_static(b"raw_key");
+    let value = get_random_bytes(32);
+
+    let raw = |category, key| storage.get_raw(Context::new(), category, key);
+    assert_eq!(raw(StorageCategory::Wal, key.clone()).await.unwrap(), None);
+    storage
+        .put_raw(
+            Context::new(),
+            StorageCategory::Wal,
+            key.clone(),
+            value.clone(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        raw(StorageCategory::Wal, key.clone()).await.unwrap(),
+        Some(value)
+    );
+    assert_eq!(raw(StorageCategory::Receipt, key).await.unwrap(), None);
+
+    // Raw bytes of a block are its encoding
+    let height = 100;
+    let block = mock_block(height, Hash::digest(get_random_bytes(10)));
+    storage
+        .insert_block(Context::new(), block.clone())
+        .await
+        .unwrap();
+    let key = BlockKey::new(height).encode_sync().unwrap();
+    assert_eq!(
+        raw(StorageCategory::Block, key.clone()).await.unwrap(),
+        Some(block.encode_sync().unwrap())
+    );
+
+    // Raw write replaces the cached block as well
+    assert_eq!(
+        storage.get_block(Context::new(), height).await.unwrap(),
+        Some(block)
+    );
+    let other = mock_block(height, Hash::digest(get_random_bytes(10)));
+    storage
+        .put_raw(
+            Context::new(),
+            StorageCategory::Block,
+            key,
+            other.encode_sync().unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        storage.get_block(Context::new(), height).await.unwrap(),
+        Some(other)
+    );
+}
+
+#[tokio::test]
+async fn test_storage_migrate() {
+    let adapter = Arc::new(MemoryAdapter::new());
+    let storage = ImplStorage::new(Arc::clone(&adapter));
+    let hash_of = |n: u64| Hash::digest(Bytes::from(n.to_be_bytes().to_vec()));
+
+    for n in 1..=5u64 {
+        adapter
+            .insert::<ToyHashSchema>(n, hash_of(n))
+            .await
+            .unwrap();
+    }
+
+    // Pretend a previous migration stopped after row 2
+    for n in 1..=2u64 {
+        adapter.insert::<ToyNumberSchema>(n, n * 10).await.unwrap();
+    }
+    let progress_key = migrate_progress_key(StorageCategory::HashHeight);
+    adapter
+        .insert::<MigrateProgressSchema>(
+            progress_key.clone(),
+            Bytes::from(2u64.to_le_bytes().to_vec()),
+        )
+        .await
+        .unwrap();
+    // Row 3 was written but its progress was lost
+    adapter.insert::<ToyNumberSchema>(3, 30).await.unwrap();
+
+    let migrated = storage
+        .migrate::<ToyHashSchema, ToyNumberSchema, _>(Context::new(), |hash| {
+            (1..=5u64).find(|n| hash_of(*n) == hash).unwrap() * 10
+        })
+        .await
+        .unwrap();
+    assert_eq!(migrated, 2);
+
+    for n in 1..=5u64 {
+        let val = adapter.get::<ToyNumberSchema>(n).await.unwrap();
+        assert_eq!(val, Some(n * 10));
+    }
+    assert!(adapter
+        .get::<MigrateProgressSchema>(progress_key)
+        .await
+        .unwrap()
+        .is_none());
+}
+
+#[tokio::test]
+async fn test_storage_get_transactions_by_hashes() {
+    let storage = ImplStorage::new(Arc::new(MemoryAdapter::new()));
+
+    let mut transactions = Vec::new();
+    for height in 1..4 {
+        let stx = mock_signed_tx(Hash::digest(get_random_bytes(10)));
+        storage
+            .insert_transactions(Context::new(), height, vec![stx.clone()])
+            .await
+            .unwrap();
+        transactions.push(stx);
+    }
+
+    let missing = Hash::digest(get_random_bytes(10));
+    let hashes = vec![
+        transactions[2].tx_hash.clone(),
+        missing,
+        transactions[0].tx_hash.clone(),
+    ];
+    let found = storage
+        .get_transactions_by_hashes(Context::new(), &hashes)
+        .await
+        .unwrap();
+    assert_eq!(found, vec![
+        Some(transactions[2].clone()),
+        None,
+        Some(transactions[0].clone())
+    ]);
+}
+
 #[tokio::test]
 async fn test_storage_transactions_get_batch_decode() {
     let storage = ImplStorage::new(Arc::new(MemoryAdapter::new()));
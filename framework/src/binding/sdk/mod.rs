@@ -10,10 +10,10 @@ use derive_more::Display;
 
 use protocol::fixed_codec::FixedCodec;
 use protocol::traits::{
-    ChainQuerier, SDKFactory, ServiceSDK, ServiceState, StoreArray, StoreBool, StoreMap,
+    ChainQuerier, SDKFactory, Savepoint, ServiceSDK, ServiceState, StoreArray, StoreBool, StoreMap,
     StoreString, StoreUint64,
 };
-use protocol::types::{Address, Block, Hash, Receipt, SignedTransaction};
+use protocol::types::{Address, Block, Hash, Receipt, ServiceContext, SignedTransaction};
 use protocol::{ProtocolError, ProtocolErrorKind, ProtocolResult};
 
 use crate::binding::state::GeneralServiceState;
@@ -44,8 +44,9 @@ impl<C: ChainQuerier, DB: 'static + TrieDB>
             service: name.to_owned(),
         })?;
 
-        Ok(DefaultServiceSDK::new(
+        Ok(DefaultServiceSDK::with_states(
             Rc::clone(state),
+            self.states.values().map(Rc::clone).collect(),
             Rc::clone(&self.chain_querier),
         ))
     }
@@ -53,13 +54,27 @@ impl<C: ChainQuerier, DB: 'static + TrieDB>
 
 pub struct DefaultServiceSDK<S: ServiceState, C: ChainQuerier> {
     state:         Rc<RefCell<S>>,
+    // States covered by savepoints, including `state`
+    states:        Vec<Rc<RefCell<S>>>,
     chain_querier: Rc<C>,
 }
 
 impl<S: ServiceState, C: ChainQuerier> DefaultServiceSDK<S, C> {
     pub fn new(state: Rc<RefCell<S>>, chain_querier: Rc<C>) -> Self {
+        let states = vec![Rc::clone(&state)];
+        Self::with_states(state, states, chain_querier)
+    }
+
+    /// Savepoints taken by this sdk cover all `states`, so that writes made
+    /// by inner calls into other services can be rolled back too.
+    pub fn with_states(
+        state: Rc<RefCell<S>>,
+        states: Vec<Rc<RefCell<S>>>,
+        chain_querier: Rc<C>,
+    ) -> Self {
         Self {
             state,
+            states,
             chain_querier,
         }
     }
@@ -171,6 +186,34 @@ impl<S: 'static + ServiceState, C: ChainQuerier> ServiceSDK for DefaultServiceSD
             .get_receipt_by_hash(tx_hash)
             .unwrap_or_else(|e| panic!("service sdk get receipt by hash failed: {}", e))
     }
+
+    // Take a savepoint of all service states and events emitted in `ctx`
+    fn stash_savepoint(&mut self, ctx: &ServiceContext) -> Savepoint {
+        let states = self
+            .states
+            .iter()
+            .map(|state| state.borrow_mut().stash_savepoint())
+            .collect::<ProtocolResult<Vec<_>>>()
+            .unwrap_or_else(|e| panic!("service sdk stash savepoint failed: {}", e));
+
+        Savepoint {
+            states,
+            event_index: ctx.events_len(),
+        }
+    }
+
+    // Roll back writes made to service states and events emitted in `ctx`
+    // after the savepoint
+    fn revert_to_savepoint(&mut self, ctx: &ServiceContext, savepoint: Savepoint) {
+        for (state, id) in self.states.iter().zip(savepoint.states) {
+            state
+                .borrow_mut()
+                .revert_to_savepoint(id)
+                .unwrap_or_else(|e| panic!("service sdk revert to savepoint failed: {}", e));
+        }
+
+        ctx.truncate_events(savepoint.event_index);
+    }
 }
 
 #[derive(Debug, Display)]
@@ -4,15 +4,17 @@ pub mod trie_db;
 pub use trie::{MPTTrie, MPTTrieError};
 pub use trie_db::{RocksTrieDB, RocksTrieDBError};
 
-use std::collections::HashMap;
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
 
 use bytes::Bytes;
 use cita_trie::DB as TrieDB;
+use derive_more::Display;
 
 use protocol::fixed_codec::FixedCodec;
 use protocol::traits::ServiceState;
 use protocol::types::{Address, Hash, MerkleRoot};
-use protocol::ProtocolResult;
+use protocol::{ProtocolError, ProtocolErrorKind, ProtocolResult};
 
 pub struct GeneralServiceState<DB: TrieDB> {
     trie: MPTTrie<DB>,
@@ -21,6 +23,29 @@ pub struct GeneralServiceState<DB: TrieDB> {
     // serializations.
     cache_map: HashMap<Bytes, Bytes>,
     stash_map: HashMap<Bytes, Bytes>,
+
+    // Bytes of key and value inserted into cache, reset on stash or revert.
+    cache_written_bytes: u64,
+
+    // Previous cache values of keys written while any savepoint is alive,
+    // used to roll back to a savepoint.
+    savepoints: Vec<Savepoint>,
+    journal:    Vec<(Bytes, Option<Bytes>)>,
+
+    // Keys read and stashed since `track_access` or the last
+    // `take_access_keys`, used to detect conflicts between transactions
+    // executed in parallel. Every read of the state, that is `get`, `contains`
+    // and `get_account_value`, goes through `get_bytes_value`, which records
+    // the key. Store types in `binding::store`, iterators included, only read
+    // through `get`. A read bypassing `get_bytes_value` would be missed by
+    // conflict detection, so any new read path must record its key too.
+    read_keys:    Option<RefCell<HashSet<Bytes>>>,
+    written_keys: Option<HashSet<Bytes>>,
+}
+
+struct Savepoint {
+    journal_len:         usize,
+    cache_written_bytes: u64,
 }
 
 impl<DB: TrieDB> GeneralServiceState<DB> {
@@ -30,10 +55,75 @@ impl<DB: TrieDB> GeneralServiceState<DB> {
 
             cache_map: HashMap::new(),
             stash_map: HashMap::new(),
+
+            cache_written_bytes: 0,
+
+            savepoints: Vec::new(),
+            journal: Vec::new(),
+
+            read_keys: None,
+            written_keys: None,
         }
     }
 
+    /// Start recording keys read from and stashed into this state.
+    pub fn track_access(&mut self) {
+        self.read_keys = Some(RefCell::new(HashSet::new()));
+        self.written_keys = Some(HashSet::new());
+    }
+
+    /// Take keys read and stashed since `track_access` or the previous call.
+    pub fn take_access_keys(&mut self) -> (HashSet<Bytes>, HashSet<Bytes>) {
+        let reads = self
+            .read_keys
+            .as_ref()
+            .map(|keys| keys.replace(HashSet::new()))
+            .unwrap_or_default();
+        let writes = self
+            .written_keys
+            .as_mut()
+            .map(std::mem::take)
+            .unwrap_or_default();
+
+        (reads, writes)
+    }
+
+    /// Take stashed values of `keys`, which are not committed.
+    pub fn take_stashed(&mut self, keys: &HashSet<Bytes>) -> HashMap<Bytes, Bytes> {
+        keys.iter()
+            .filter_map(|key| self.stash_map.remove_entry(key))
+            .collect()
+    }
+
+    /// Copy of stashed writes, which are not committed.
+    pub fn stash_snapshot(&self) -> HashMap<Bytes, Bytes> {
+        self.stash_map.clone()
+    }
+
+    /// Stash writes taken from another state on the same root.
+    pub fn merge_stash(&mut self, writes: HashMap<Bytes, Bytes>) {
+        self.stash_map.extend(writes);
+    }
+
+    /// Root of the trie as of the last commit, stashed writes are not in it.
+    pub fn committed_root(&self) -> MerkleRoot {
+        self.trie.root()
+    }
+
+    fn clear_savepoints(&mut self) {
+        self.savepoints.clear();
+        self.journal.clear();
+    }
+
+    pub fn cache_written_bytes(&self) -> u64 {
+        self.cache_written_bytes
+    }
+
     fn get_bytes_value(&self, key: Bytes) -> ProtocolResult<Option<Bytes>> {
+        if let Some(read_keys) = self.read_keys.as_ref() {
+            read_keys.borrow_mut().insert(key.clone());
+        }
+
         if let Some(value_bytes) = self.cache_map.get(&key) {
             if value_bytes.is_empty() {
                 return Ok(None);
@@ -85,8 +175,14 @@ impl<DB: TrieDB> ServiceState for GeneralServiceState<DB> {
         key: Key,
         value: Value,
     ) -> ProtocolResult<()> {
-        self.cache_map
-            .insert(key.encode_fixed()?, value.encode_fixed()?);
+        let key = key.encode_fixed()?;
+        let value = value.encode_fixed()?;
+
+        self.cache_written_bytes += (key.len() + value.len()) as u64;
+        let prev = self.cache_map.insert(key.clone(), value);
+        if !self.savepoints.is_empty() {
+            self.journal.push((key, prev));
+        }
         Ok(())
     }
 
@@ -112,14 +208,21 @@ impl<DB: TrieDB> ServiceState for GeneralServiceState<DB> {
     // Roll back all data in the cache
     fn revert_cache(&mut self) -> ProtocolResult<()> {
         self.cache_map.clear();
+        self.cache_written_bytes = 0;
+        self.clear_savepoints();
         Ok(())
     }
 
     // Move data from cache to stash
     fn stash(&mut self) -> ProtocolResult<()> {
+        if let Some(written_keys) = self.written_keys.as_mut() {
+            written_keys.extend(self.cache_map.keys().cloned());
+        }
         for (k, v) in self.cache_map.drain() {
             self.stash_map.insert(k, v);
         }
+        self.cache_written_bytes = 0;
+        self.clear_savepoints();
 
         Ok(())
     }
@@ -133,6 +236,58 @@ impl<DB: TrieDB> ServiceState for GeneralServiceState<DB> {
         let root = self.trie.commit()?;
         Ok(root)
     }
+
+    fn stash_savepoint(&mut self) -> ProtocolResult<usize> {
+        self.savepoints.push(Savepoint {
+            journal_len:         self.journal.len(),
+            cache_written_bytes: self.cache_written_bytes,
+        });
+
+        Ok(self.savepoints.len() - 1)
+    }
+
+    fn revert_to_savepoint(&mut self, savepoint: usize) -> ProtocolResult<()> {
+        if savepoint >= self.savepoints.len() {
+            return Err(ServiceStateError::SavepointNotFound {
+                savepoint,
+                depth: self.savepoints.len(),
+            }
+            .into());
+        }
+
+        let Savepoint {
+            journal_len,
+            cache_written_bytes,
+        } = self.savepoints[savepoint];
+        for (key, prev) in self.journal.drain(journal_len..).rev() {
+            match prev {
+                Some(value) => self.cache_map.insert(key, value),
+                None => self.cache_map.remove(&key),
+            };
+        }
+
+        self.savepoints.truncate(savepoint);
+        self.cache_written_bytes = cache_written_bytes;
+        if self.savepoints.is_empty() {
+            self.journal.clear();
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Display)]
+pub enum ServiceStateError {
+    #[display(fmt = "savepoint {} not found, depth {}", savepoint, depth)]
+    SavepointNotFound { savepoint: usize, depth: usize },
+}
+
+impl std::error::Error for ServiceStateError {}
+
+impl From<ServiceStateError> for ProtocolError {
+    fn from(err: ServiceStateError) -> ProtocolError {
+        ProtocolError::new(ProtocolErrorKind::Binding, Box::new(err))
+    }
 }
 
 fn get_address_key<Key: FixedCodec>(address: &Address, key: &Key) -> ProtocolResult<Hash> {
@@ -169,4 +324,70 @@ mod tests {
         assert_eq!(state.get::<Bytes, Bytes>(&key).unwrap().is_some(), false);
         assert_eq!(state.contains(&key).unwrap(), false);
     }
+
+    #[test]
+    fn test_revert_to_savepoint() {
+        let mut state = GeneralServiceState::new(MPTTrie::new(Arc::new(MemoryDB::new(false))));
+
+        let outer = Bytes::from("outer");
+        let inner = Bytes::from("inner");
+
+        state.insert(outer.clone(), Bytes::from("1")).unwrap();
+        let savepoint = state.stash_savepoint().unwrap();
+        state.insert(outer.clone(), Bytes::from("2")).unwrap();
+        state.insert(inner.clone(), Bytes::from("3")).unwrap();
+
+        state.revert_to_savepoint(savepoint).unwrap();
+        assert_eq!(
+            state.get::<Bytes, Bytes>(&outer).unwrap(),
+            Some(Bytes::from("1"))
+        );
+        assert_eq!(state.contains(&inner).unwrap(), false);
+        assert_eq!(state.cache_written_bytes(), 6);
+
+        // savepoint was released
+        assert!(state.revert_to_savepoint(savepoint).is_err());
+    }
+
+    #[test]
+    fn test_track_access() {
+        let mut state = GeneralServiceState::new(MPTTrie::new(Arc::new(MemoryDB::new(false))));
+        let address = Address::from_hash(Hash::digest(Bytes::from("alice"))).unwrap();
+        state.track_access();
+
+        state.get::<Bytes, Bytes>(&Bytes::from("get")).unwrap();
+        state.contains(&Bytes::from("contains")).unwrap();
+        state
+            .get_account_value::<Bytes, Bytes>(&address, &Bytes::from("account"))
+            .unwrap();
+
+        state
+            .insert(Bytes::from("reverted"), Bytes::from("1"))
+            .unwrap();
+        state.revert_cache().unwrap();
+        state
+            .insert(Bytes::from("stashed"), Bytes::from("2"))
+            .unwrap();
+        state.stash().unwrap();
+
+        let (reads, writes) = state.take_access_keys();
+        let account_key = get_address_key(&address, &Bytes::from("account"))
+            .unwrap()
+            .encode_fixed()
+            .unwrap();
+        let expect_reads = vec![Bytes::from("get"), Bytes::from("contains"), account_key]
+            .into_iter()
+            .collect::<HashSet<_>>();
+        assert_eq!(reads, expect_reads);
+        assert_eq!(
+            writes,
+            vec![Bytes::from("stashed")]
+                .into_iter()
+                .collect::<HashSet<_>>()
+        );
+
+        let stashed = state.take_stashed(&writes);
+        assert_eq!(stashed.len(), 1);
+        assert_eq!(state.take_access_keys(), (HashSet::new(), HashSet::new()));
+    }
 }
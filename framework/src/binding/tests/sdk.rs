@@ -115,6 +115,19 @@ impl CommonStorage for MockStorage {
         Ok(Some(mock_block(1)))
     }
 
+    async fn contains_block(&self, _ctx: Context, _height: u64) -> ProtocolResult<bool> {
+        Ok(true)
+    }
+
+    async fn get_blocks(
+        &self,
+        _ctx: Context,
+        _from: u64,
+        _limit: u64,
+    ) -> ProtocolResult<Vec<Block>> {
+        Ok(vec![mock_block(1)])
+    }
+
     async fn get_block_header(
         &self,
         _ctx: Context,
@@ -171,6 +184,39 @@ impl Storage for MockStorage {
         Ok(Some(mock_signed_tx()))
     }
 
+    async fn get_transactions_by_hashes(
+        &self,
+        _: Context,
+        _: &[Hash],
+    ) -> ProtocolResult<Vec<Option<SignedTransaction>>> {
+        unimplemented!()
+    }
+
+    async fn get_transaction_at(
+        &self,
+        _ctx: Context,
+        _: u64,
+        _: &Hash,
+    ) -> ProtocolResult<Option<SignedTransaction>> {
+        Ok(None)
+    }
+
+    async fn get_transaction_location(
+        &self,
+        _ctx: Context,
+        _: &Hash,
+    ) -> ProtocolResult<Option<(u64, usize)>> {
+        Ok(None)
+    }
+
+    async fn contains_transaction(&self, _ctx: Context, _: &Hash) -> ProtocolResult<bool> {
+        Ok(true)
+    }
+
+    async fn filter_existing(&self, _ctx: Context, hashes: Vec<Hash>) -> ProtocolResult<Vec<Hash>> {
+        Ok(hashes)
+    }
+
     async fn get_transactions(
         &self,
         _ctx: Context,
@@ -193,6 +239,10 @@ impl Storage for MockStorage {
         Err(StoreError::GetNone.into())
     }
 
+    async fn get_block_receipts(&self, _ctx: Context, _: u64) -> ProtocolResult<Vec<Receipt>> {
+        unimplemented!()
+    }
+
     async fn get_latest_proof(&self, _ctx: Context) -> ProtocolResult<Proof> {
         Err(StoreError::GetNone.into())
     }
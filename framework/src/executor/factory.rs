@@ -1,6 +1,6 @@
 use std::sync::Arc;
 
-use protocol::traits::{Executor, ExecutorFactory, ServiceMapping, Storage};
+use protocol::traits::{Executor, ExecutorConfig, ExecutorFactory, ServiceMapping, Storage};
 use protocol::types::MerkleRoot;
 use protocol::ProtocolResult;
 
@@ -16,8 +16,10 @@ impl<DB: 'static + cita_trie::DB, S: 'static + Storage, Mapping: 'static + Servi
         db: Arc<DB>,
         storage: Arc<S>,
         mapping: Arc<Mapping>,
+        config: &ExecutorConfig,
     ) -> ProtocolResult<Box<dyn Executor>> {
-        let executor = ServiceExecutor::with_root(root, db, storage, mapping)?;
+        let mut executor = ServiceExecutor::with_root(root, db, storage, mapping)?;
+        executor.set_config(config.clone());
         Ok(Box::new(executor))
     }
 }
@@ -1,5 +1,6 @@
 mod error;
 mod factory;
+mod parallel;
 #[cfg(test)]
 mod tests;
 
@@ -7,20 +8,21 @@ pub use factory::ServiceExecutorFactory;
 
 use std::{
     cell::RefCell,
-    collections::HashMap,
-    marker::PhantomData,
+    collections::{HashMap, HashSet},
     ops::{Deref, DerefMut},
     panic::{self, AssertUnwindSafe},
     rc::Rc,
     sync::Arc,
 };
 
+use bytes::Bytes;
 use cita_trie::DB as TrieDB;
+use rayon::prelude::*;
 
 use common_apm::muta_apm;
 use protocol::traits::{
-    Context, Executor, ExecutorParams, ExecutorResp, Service, ServiceMapping, ServiceResponse,
-    ServiceState, Storage,
+    Context, Executor, ExecutorConfig, ExecutorParams, ExecutorResp, Service, ServiceMapping,
+    ServiceResponse, ServiceState, Storage,
 };
 use protocol::types::{
     Address, Event, Hash, MerkleRoot, Receipt, ReceiptResponse, ServiceContext,
@@ -31,8 +33,59 @@ use protocol::{ProtocolError, ProtocolResult};
 use crate::binding::sdk::{DefaultChainQuerier, DefaultSDKFactory};
 use crate::binding::state::{GeneralServiceState, MPTTrie};
 use crate::executor::error::ExecutorError;
+use crate::executor::parallel::{
+    group_by_sender, has_conflict, ConflictDetector, GroupResult, StateAccess,
+};
 
 const SERVICE_NOT_FOUND_CODE: u64 = 62077;
+/// Error code of a receipt whose transaction ran out of cycles, no matter
+/// which code the service itself returned.
+pub const OUT_OF_CYCLES_CODE: u64 = 3;
+
+/// Error code of a receipt whose transaction offers a cycles price below the
+/// floor, it is rejected before running.
+pub const CYCLES_PRICE_TOO_LOW_CODE: u64 = 4;
+
+/// Error code of a receipt whose transaction is skipped because a tx hook
+/// failed before it ran.
+const SKIP_TX_RUN_CODE: u64 = 65535;
+
+/// Failures the executor itself writes into receipts. Receipts are hashed into
+/// blocks, so each one maps to a fixed code and message rather than how some
+/// error happens to render on a platform.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReceiptError {
+    SkipTxRun,
+    ServiceNotFound,
+    OutOfCycles,
+    OutOfCyclesForStorage,
+    CyclesPriceTooLow,
+}
+
+impl ReceiptError {
+    pub fn code(self) -> u64 {
+        match self {
+            ReceiptError::SkipTxRun => SKIP_TX_RUN_CODE,
+            ReceiptError::ServiceNotFound => SERVICE_NOT_FOUND_CODE,
+            ReceiptError::OutOfCycles | ReceiptError::OutOfCyclesForStorage => OUT_OF_CYCLES_CODE,
+            ReceiptError::CyclesPriceTooLow => CYCLES_PRICE_TOO_LOW_CODE,
+        }
+    }
+
+    pub fn message(self) -> &'static str {
+        match self {
+            ReceiptError::SkipTxRun => "skip_tx_run",
+            ReceiptError::ServiceNotFound => "can not found service",
+            ReceiptError::OutOfCycles => "out of cycles",
+            ReceiptError::OutOfCyclesForStorage => "out of cycles for storage write",
+            ReceiptError::CyclesPriceTooLow => "cycles price too low",
+        }
+    }
+
+    fn into_response(self) -> ServiceResponse<String> {
+        ServiceResponse::from_error(self.code(), self.message().to_owned())
+    }
+}
 
 trait TxHooks {
     fn before(
@@ -107,6 +160,13 @@ impl<DB: TrieDB> ServiceStateMap<DB> {
 
         Ok(())
     }
+
+    fn cache_written_bytes(&self) -> u64 {
+        self.0
+            .values()
+            .map(|state| state.borrow().cache_written_bytes())
+            .sum()
+    }
 }
 
 struct CommitHooks<DB: TrieDB> {
@@ -181,13 +241,29 @@ impl<DB: TrieDB> TxHooks for CommitHooks<DB> {
     }
 }
 
+/// What group executors of one block share.
+struct GroupContext<'a, S, DB, Mapping> {
+    ctx:      Context,
+    root:     MerkleRoot,
+    stashes:  HashMap<String, HashMap<Bytes, Bytes>>,
+    trie_db:  Arc<DB>,
+    storage:  Arc<S>,
+    mapping:  Arc<Mapping>,
+    config:   &'a ExecutorConfig,
+    params:   &'a ExecutorParams,
+    detector: ConflictDetector,
+}
+
 pub struct ServiceExecutor<S: Storage, DB: TrieDB, Mapping: ServiceMapping> {
     service_mapping: Arc<Mapping>,
     states:          Rc<ServiceStateMap<DB>>,
     root_state:      GeneralServiceState<DB>,
     services:        HashMap<String, Rc<RefCell<Box<dyn Service>>>>,
+    trie_db:         Arc<DB>,
+    storage:         Arc<S>,
 
-    phantom: PhantomData<S>,
+    config:             ExecutorConfig,
+    parallel_fallbacks: usize,
 }
 
 impl<S: 'static + Storage, DB: 'static + TrieDB, Mapping: 'static + ServiceMapping>
@@ -273,10 +349,50 @@ impl<S: 'static + Storage, DB: 'static + TrieDB, Mapping: 'static + ServiceMappi
             states,
             root_state,
             services,
-            phantom: PhantomData,
+            trie_db,
+            storage,
+            config: ExecutorConfig::default(),
+            parallel_fallbacks: 0,
         })
     }
 
+    /// Settings applied to transactions executed from now on, see
+    /// `ExecutorConfig`.
+    pub fn set_config(&mut self, config: ExecutorConfig) {
+        self.config = config;
+    }
+
+    /// Number of `exec` calls which enabled parallel execution but had to run
+    /// transactions sequentially.
+    pub fn parallel_fallbacks(&self) -> usize {
+        self.parallel_fallbacks
+    }
+
+    /// Call a read method of any service at a past `state_root`, by a
+    /// separate executor built from that root, so neither state is touched.
+    pub fn read_at(
+        &self,
+        state_root: MerkleRoot,
+        caller: &Address,
+        request: &TransactionRequest,
+    ) -> ProtocolResult<ServiceResponse<String>> {
+        let executor = Self::with_root(
+            state_root.clone(),
+            Arc::clone(&self.trie_db),
+            Arc::clone(&self.storage),
+            Arc::clone(&self.service_mapping),
+        )?;
+
+        let params = ExecutorParams {
+            state_root,
+            height: 0,
+            timestamp: 0,
+            cycles_limit: std::u64::MAX,
+            proposer: caller.clone(),
+        };
+        executor.read(&params, caller, 1, request)
+    }
+
     #[muta_apm::derive::tracing_span(kind = "executor.commit")]
     fn commit(&mut self, ctx: Context) -> ProtocolResult<MerkleRoot> {
         for (name, state) in self.states.iter() {
@@ -401,7 +517,7 @@ impl<S: 'static + Storage, DB: 'static + TrieDB, Mapping: 'static + ServiceMappi
         let ret = if resp.iter().any(|r| r.is_error()) {
             self.revert_cache()?;
             event.borrow_mut().truncate(event_index);
-            ServiceResponse::from_error(65535, "skip_tx_run".to_owned())
+            ReceiptError::SkipTxRun.into_response()
         } else {
             match panic::catch_unwind(AssertUnwindSafe(|| {
                 self.call(service_context.clone(), exec_type)
@@ -424,6 +540,46 @@ impl<S: 'static + Storage, DB: 'static + TrieDB, Mapping: 'static + ServiceMappi
             service_context.cancel("tx_exec_return_code_not_zero".to_owned());
         }
 
+        // Services report a failed `sub_cycles` with their own error codes, so
+        // classify it here to let clients tell cycle exhaustion from logic errors.
+        // It changes receipts, so blocks before the configured height keep the
+        // service codes.
+        let classify = match self.config.out_of_cycles_code_height {
+            Some(height) => service_context.get_current_height() >= height,
+            None => false,
+        };
+        let ret = if classify
+            && ret.is_error()
+            && ret.code != OUT_OF_CYCLES_CODE
+            && service_context.is_out_of_cycles()
+        {
+            ReceiptError::OutOfCycles.into_response()
+        } else {
+            ret
+        };
+
+        // Charge for bytes written by this call. Writes of a reverted call have been
+        // cleared from cache, so they cost nothing. It changes receipts, so blocks
+        // before the configured height write for free.
+        let cycles_per_byte = match self.config.storage_cycles_height {
+            Some(height) if service_context.get_current_height() >= height => {
+                self.config.storage_cycles_per_byte
+            }
+            _ => 0,
+        };
+        let storage_cycles = self
+            .states
+            .cache_written_bytes()
+            .saturating_mul(cycles_per_byte);
+        let ret = if !ret.is_error() && !service_context.sub_cycles(storage_cycles) {
+            event.borrow_mut().truncate(event_index);
+            self.states.revert_cache()?;
+            service_context.cancel("out_of_cycles_for_storage_write".to_owned());
+            ReceiptError::OutOfCyclesForStorage.into_response()
+        } else {
+            ret
+        };
+
         let resp = tx_hooks.after(context, service_context)?;
 
         if resp.iter().any(|r| r.is_error()) {
@@ -436,15 +592,203 @@ impl<S: 'static + Storage, DB: 'static + TrieDB, Mapping: 'static + ServiceMappi
         Ok(ret)
     }
 
+    fn exec_tx(
+        &mut self,
+        ctx: Context,
+        params: &ExecutorParams,
+        stx: &SignedTransaction,
+    ) -> ProtocolResult<Receipt> {
+        // It changes receipts, so blocks before the configured height run
+        // transactions of any price.
+        let check_price = match self.config.min_cycles_price_height {
+            Some(height) => params.height >= height,
+            None => false,
+        };
+        if check_price && stx.raw.cycles_price < self.config.min_cycles_price {
+            return Ok(rejected_receipt(
+                params,
+                stx,
+                ReceiptError::CyclesPriceTooLow,
+            ));
+        }
+
+        let event = Rc::new(RefCell::new(vec![]));
+        let service_context = self.get_context(
+            Some(stx.tx_hash.clone()),
+            Some(stx.raw.nonce.clone()),
+            &stx.raw.sender,
+            stx.raw.cycles_price,
+            stx.raw.cycles_limit,
+            params,
+            &stx.raw.request,
+            Rc::clone(&event),
+        )?;
+
+        let exec_resp = self.catch_call(
+            ctx,
+            service_context.clone(),
+            ExecType::Write,
+            Rc::clone(&event),
+        )?;
+        Ok(Receipt {
+            state_root:  MerkleRoot::from_empty(),
+            height:      service_context.get_current_height(),
+            tx_hash:     stx.tx_hash.clone(),
+            cycles_used: service_context.get_cycles_used(),
+            events:      service_context.get_events(),
+            response:    ReceiptResponse {
+                service_name: service_context.get_service_name().to_owned(),
+                method:       service_context.get_service_method().to_owned(),
+                response:     exec_resp,
+            },
+        })
+    }
+
+    /// Execute each sender group on its own executor from the current state,
+    /// then merge their writes. Returns None if groups conflict or any of them
+    /// fails, the caller should execute sequentially then.
+    fn exec_parallel(
+        &mut self,
+        ctx: Context,
+        params: &ExecutorParams,
+        txs: &[SignedTransaction],
+    ) -> ProtocolResult<Option<Vec<Receipt>>> {
+        let groups = group_by_sender(txs);
+        if groups.len() < 2 {
+            return Ok(None);
+        }
+
+        // Group executors start from the committed root plus the stashed writes
+        // of block hooks, nothing is written to trie db until the block commits.
+        let group_ctx = GroupContext {
+            ctx,
+            root: self.root_state.committed_root(),
+            stashes: self
+                .states
+                .iter()
+                .map(|(name, state)| (name.to_owned(), state.borrow().stash_snapshot()))
+                .collect(),
+            trie_db: Arc::clone(&self.trie_db),
+            storage: Arc::clone(&self.storage),
+            mapping: Arc::clone(&self.service_mapping),
+            config: &self.config,
+            params,
+            detector: ConflictDetector::default(),
+        };
+
+        let results = groups
+            .into_par_iter()
+            .enumerate()
+            .map(|(id, group)| Self::exec_group(&group_ctx, id, group))
+            .collect::<Vec<_>>();
+
+        let mut groups = Vec::with_capacity(results.len());
+        for result in results.into_iter() {
+            match result {
+                Ok(Some(group)) => groups.push(group),
+                Ok(None) => {
+                    log::info!("[executor]: transactions conflict, fall back to sequential exec");
+                    return Ok(None);
+                }
+                Err(e) => {
+                    log::warn!("[executor]: parallel exec failed, fall back: {}", e);
+                    return Ok(None);
+                }
+            }
+        }
+
+        if has_conflict(&groups.iter().map(|g| &g.access).collect::<Vec<_>>()) {
+            log::info!("[executor]: transactions conflict, fall back to sequential exec");
+            return Ok(None);
+        }
+
+        let mut receipts = Vec::with_capacity(txs.len());
+        for group in groups.into_iter() {
+            for (service, writes) in group.access.writes.into_iter() {
+                if let Some(state) = self.states.get(&service) {
+                    state.borrow_mut().merge_stash(writes);
+                }
+            }
+            receipts.extend(group.receipts);
+        }
+        receipts.sort_by_key(|(idx, _)| *idx);
+
+        Ok(Some(receipts.into_iter().map(|(_, r)| r).collect()))
+    }
+
+    /// Execute a group of transactions, returns None once the group or any
+    /// other one runs into a conflict.
+    fn exec_group(
+        group_ctx: &GroupContext<S, DB, Mapping>,
+        group_id: usize,
+        txs: Vec<(usize, &SignedTransaction)>,
+    ) -> ProtocolResult<Option<GroupResult>> {
+        let mut executor = Self::with_root(
+            group_ctx.root.clone(),
+            Arc::clone(&group_ctx.trie_db),
+            Arc::clone(&group_ctx.storage),
+            Arc::clone(&group_ctx.mapping),
+        )?;
+        executor.set_config(group_ctx.config.clone());
+        for (service, state) in executor.states.iter() {
+            let mut state = state.borrow_mut();
+            if let Some(stash) = group_ctx.stashes.get(service) {
+                state.merge_stash(stash.clone());
+            }
+            state.track_access();
+        }
+
+        let mut reads: HashMap<String, HashSet<Bytes>> = HashMap::new();
+        let mut written: HashMap<String, HashSet<Bytes>> = HashMap::new();
+        let mut receipts = Vec::with_capacity(txs.len());
+        for (idx, stx) in txs.into_iter() {
+            if group_ctx.detector.is_conflicted() {
+                return Ok(None);
+            }
+
+            let receipt = executor.exec_tx(group_ctx.ctx.clone(), group_ctx.params, stx)?;
+            receipts.push((idx, receipt));
+
+            for (service, state) in executor.states.iter() {
+                let (new_reads, new_writes) = state.borrow_mut().take_access_keys();
+                group_ctx
+                    .detector
+                    .record(group_id, service, &new_reads, &new_writes);
+                reads
+                    .entry(service.to_owned())
+                    .or_default()
+                    .extend(new_reads);
+                written
+                    .entry(service.to_owned())
+                    .or_default()
+                    .extend(new_writes);
+            }
+        }
+
+        if group_ctx.detector.is_conflicted() {
+            return Ok(None);
+        }
+
+        let mut access = StateAccess::default();
+        for (service, state) in executor.states.iter() {
+            let keys = written.remove(service).unwrap_or_default();
+            let writes = state.borrow_mut().take_stashed(&keys);
+            access.writes.insert(service.to_owned(), writes);
+            access.reads.insert(
+                service.to_owned(),
+                reads.remove(service).unwrap_or_default(),
+            );
+        }
+
+        Ok(Some(GroupResult { receipts, access }))
+    }
+
     fn call(&self, context: ServiceContext, exec_type: ExecType) -> ServiceResponse<String> {
         let service_name = context.get_service_name();
         let service = self.get_service(service_name);
 
         if service.is_err() {
-            return ServiceResponse::from_error(
-                SERVICE_NOT_FOUND_CODE,
-                "can not found service".to_owned(),
-            );
+            return ReceiptError::ServiceNotFound.into_response();
         }
 
         let service = service.unwrap();
@@ -467,50 +811,34 @@ impl<S: 'static + Storage, DB: 'static + TrieDB, Mapping: 'static + ServiceMappi
     ) -> ProtocolResult<ExecutorResp> {
         self.hook(ctx.clone(), HookType::Before, params)?;
 
-        let mut receipts = txs
-            .iter()
-            .map(|stx| {
-                let event = Rc::new(RefCell::new(vec![]));
-                let service_context = self.get_context(
-                    Some(stx.tx_hash.clone()),
-                    Some(stx.raw.nonce.clone()),
-                    &stx.raw.sender,
-                    stx.raw.cycles_price,
-                    stx.raw.cycles_limit,
-                    params,
-                    &stx.raw.request,
-                    Rc::clone(&event),
-                )?;
-
-                let exec_resp = self.catch_call(
-                    ctx.clone(),
-                    service_context.clone(),
-                    ExecType::Write,
-                    Rc::clone(&event),
-                )?;
-                Ok(Receipt {
-                    state_root:  MerkleRoot::from_empty(),
-                    height:      service_context.get_current_height(),
-                    tx_hash:     stx.tx_hash.clone(),
-                    cycles_used: service_context.get_cycles_used(),
-                    events:      service_context.get_events(),
-                    response:    ReceiptResponse {
-                        service_name: service_context.get_service_name().to_owned(),
-                        method:       service_context.get_service_method().to_owned(),
-                        response:     exec_resp,
-                    },
-                })
-            })
-            .collect::<Result<Vec<Receipt>, ProtocolError>>()?;
+        let parallel_receipts = if self.config.parallel_exec {
+            self.exec_parallel(ctx.clone(), params, txs)?
+        } else {
+            None
+        };
+
+        if self.config.parallel_exec && parallel_receipts.is_none() {
+            self.parallel_fallbacks += 1;
+        }
+
+        let mut receipts = match parallel_receipts {
+            Some(receipts) => receipts,
+            None => txs
+                .iter()
+                .map(|stx| self.exec_tx(ctx.clone(), params, stx))
+                .collect::<Result<Vec<Receipt>, ProtocolError>>()?,
+        };
 
         self.hook(ctx.clone(), HookType::After, params)?;
 
-        let state_root = self.commit(ctx)?;
+        let state_root = self.commit(ctx.clone())?;
         let mut all_cycles_used = 0;
 
-        for receipt in receipts.iter_mut() {
+        for (stx, receipt) in txs.iter().zip(receipts.iter_mut()) {
             receipt.state_root = state_root.clone();
             all_cycles_used += receipt.cycles_used;
+
+            audit_write(&ctx, stx, receipt);
         }
 
         Ok(ExecutorResp {
@@ -541,3 +869,37 @@ impl<S: 'static + Storage, DB: 'static + TrieDB, Mapping: 'static + ServiceMappi
             .map_err(|e| ProtocolError::from(ExecutorError::QueryService(format!("{:?}", e))))
     }
 }
+
+/// Record a committed write service call in the audit log, failed ones
+/// included.
+fn audit_write(ctx: &Context, stx: &SignedTransaction, receipt: &Receipt) {
+    common_logger::audit("write_service", ctx, common_logger::object! {
+        "height" => receipt.height,
+        "tx_hash" => stx.tx_hash.as_hex(),
+        "caller" => stx.raw.sender.to_string(),
+        "service" => stx.raw.request.service_name.as_str(),
+        "method" => stx.raw.request.method.as_str(),
+        "code" => receipt.response.response.code
+    });
+}
+
+/// Receipt of a transaction rejected before running, it uses no cycles and
+/// touches no state.
+fn rejected_receipt(
+    params: &ExecutorParams,
+    stx: &SignedTransaction,
+    error: ReceiptError,
+) -> Receipt {
+    Receipt {
+        state_root:  MerkleRoot::from_empty(),
+        height:      params.height,
+        tx_hash:     stx.tx_hash.clone(),
+        cycles_used: 0,
+        events:      vec![],
+        response:    ReceiptResponse {
+            service_name: stx.raw.request.service_name.clone(),
+            method:       stx.raw.request.method.clone(),
+            response:     error.into_response(),
+        },
+    }
+}
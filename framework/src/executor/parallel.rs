@@ -0,0 +1,147 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use bytes::Bytes;
+use parking_lot::Mutex;
+
+use protocol::types::{Address, Receipt, SignedTransaction};
+
+/// State keys read and written by a group of transactions, per service.
+/// Written values are the final ones after the whole group.
+#[derive(Default)]
+pub struct StateAccess {
+    pub reads:  HashMap<String, HashSet<Bytes>>,
+    pub writes: HashMap<String, HashMap<Bytes, Bytes>>,
+}
+
+impl StateAccess {
+    /// Whether executing two groups in any order may give different results.
+    /// Blind writes of the same value commute, so they don't conflict.
+    pub fn conflict_with(&self, other: &StateAccess) -> bool {
+        Self::read_written(self, other)
+            || Self::read_written(other, self)
+            || self.writes.iter().any(|(service, writes)| {
+                other.writes.get(service).map_or(false, |other_writes| {
+                    writes
+                        .iter()
+                        .any(|(key, value)| other_writes.get(key).map_or(false, |v| v != value))
+                })
+            })
+    }
+
+    fn read_written(reader: &StateAccess, writer: &StateAccess) -> bool {
+        reader.reads.iter().any(|(service, reads)| {
+            writer.writes.get(service).map_or(false, |writes| {
+                reads.iter().any(|key| writes.contains_key(key))
+            })
+        })
+    }
+}
+
+/// Keys read and written by groups executing in parallel so far. Groups
+/// record their keys after each transaction and stop once a key read by one
+/// group is written by another, instead of running to the end only to fall
+/// back, which is what a shared fee or tx hook write would cause otherwise.
+/// Writes of the same key by several groups are left to `has_conflict`,
+/// since blind writes of the same value don't conflict.
+#[derive(Default)]
+pub struct ConflictDetector {
+    keys:       Mutex<HashMap<(String, Bytes), KeyAccess>>,
+    conflicted: AtomicBool,
+}
+
+#[derive(Default)]
+struct KeyAccess {
+    readers: Groups,
+    writers: Groups,
+}
+
+/// Groups which accessed a key, only the first one is kept.
+#[derive(Default)]
+struct Groups {
+    first: Option<usize>,
+    many:  bool,
+}
+
+impl Groups {
+    fn add(&mut self, group: usize) {
+        match self.first {
+            Some(first) => self.many |= first != group,
+            None => self.first = Some(group),
+        }
+    }
+
+    fn has_other_than(&self, group: usize) -> bool {
+        self.many || self.first.map_or(false, |first| first != group)
+    }
+}
+
+impl ConflictDetector {
+    /// Record keys of `service` read and written by `group` since its last
+    /// record.
+    pub fn record(
+        &self,
+        group: usize,
+        service: &str,
+        reads: &HashSet<Bytes>,
+        writes: &HashSet<Bytes>,
+    ) {
+        if reads.is_empty() && writes.is_empty() {
+            return;
+        }
+
+        let mut keys = self.keys.lock();
+        let mut conflicted = false;
+        for key in reads.iter() {
+            let access = keys.entry((service.to_owned(), key.clone())).or_default();
+            conflicted |= access.writers.has_other_than(group);
+            access.readers.add(group);
+        }
+        for key in writes.iter() {
+            let access = keys.entry((service.to_owned(), key.clone())).or_default();
+            conflicted |= access.readers.has_other_than(group);
+            access.writers.add(group);
+        }
+
+        if conflicted {
+            self.conflicted.store(true, Ordering::SeqCst);
+        }
+    }
+
+    pub fn is_conflicted(&self) -> bool {
+        self.conflicted.load(Ordering::SeqCst)
+    }
+}
+
+pub struct GroupResult {
+    pub receipts: Vec<(usize, Receipt)>,
+    pub access:   StateAccess,
+}
+
+/// Group transactions by sender, keeping their order in block. Transactions
+/// of one sender always depend on each other, so they run in the same group.
+pub fn group_by_sender(txs: &[SignedTransaction]) -> Vec<Vec<(usize, &SignedTransaction)>> {
+    let mut groups: Vec<Vec<(usize, &SignedTransaction)>> = Vec::new();
+    let mut group_index: HashMap<&Address, usize> = HashMap::new();
+
+    for (idx, stx) in txs.iter().enumerate() {
+        match group_index.get(&stx.raw.sender) {
+            Some(&i) => groups[i].push((idx, stx)),
+            None => {
+                group_index.insert(&stx.raw.sender, groups.len());
+                groups.push(vec![(idx, stx)]);
+            }
+        }
+    }
+
+    groups
+}
+
+/// Whether any two groups conflict with each other.
+pub fn has_conflict(accesses: &[&StateAccess]) -> bool {
+    accesses.iter().enumerate().any(|(i, access)| {
+        accesses[i + 1..]
+            .iter()
+            .any(|other| access.conflict_with(other))
+    })
+}
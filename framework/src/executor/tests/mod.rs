@@ -4,6 +4,7 @@ extern crate test;
 mod framework;
 mod test_service;
 
+use std::collections::HashSet;
 use std::str::FromStr;
 use std::sync::Arc;
 
@@ -16,17 +17,21 @@ use asset::types::{Asset, GetBalanceResponse};
 use asset::AssetService;
 use metadata::MetadataService;
 use protocol::traits::{
-    CommonStorage, Context, Executor, ExecutorParams, SDKFactory, Service, ServiceMapping,
-    ServiceSDK, Storage,
+    CommonStorage, Context, Executor, ExecutorConfig, ExecutorParams, SDKFactory, Service,
+    ServiceMapping, ServiceSDK, Storage,
 };
 use protocol::types::{
-    Address, Block, BlockHeader, Genesis, Hash, Proof, RawTransaction, Receipt, SignedTransaction,
-    TransactionRequest,
+    Address, Block, BlockHeader, Genesis, Hash, PageRequest, Proof, RawTransaction, Receipt,
+    ServicePage, SignedTransaction, TransactionRequest,
 };
 use protocol::ProtocolResult;
 
-use crate::executor::{ServiceExecutor, SERVICE_NOT_FOUND_CODE};
-use test_service::TestService;
+use crate::executor::parallel::{has_conflict, ConflictDetector, StateAccess};
+use crate::executor::{
+    ReceiptError, ServiceExecutor, CYCLES_PRICE_TOO_LOW_CODE, OUT_OF_CYCLES_CODE,
+    SERVICE_NOT_FOUND_CODE,
+};
+use test_service::{InnerService, TestService};
 
 macro_rules! read {
     ($executor:expr, $params:expr, $caller:expr, $payload:expr) => {{
@@ -130,6 +135,188 @@ fn test_exec() {
     assert_eq!(asset.supply, 320_000_011);
 }
 
+#[test]
+fn test_read_at() {
+    let toml_str = include_str!("./genesis_services.toml");
+    let genesis: Genesis = toml::from_str(toml_str).unwrap();
+
+    let db = Arc::new(MemoryDB::new(false));
+
+    let root = ServiceExecutor::create_genesis(
+        genesis.services,
+        Arc::clone(&db),
+        Arc::new(MockStorage {}),
+        Arc::new(MockServiceMapping {}),
+    )
+    .unwrap();
+
+    let mut executor = ServiceExecutor::with_root(
+        root.clone(),
+        Arc::clone(&db),
+        Arc::new(MockStorage {}),
+        Arc::new(MockServiceMapping {}),
+    )
+    .unwrap();
+
+    let params = ExecutorParams {
+        state_root:   root.clone(),
+        height:       1,
+        timestamp:    0,
+        cycles_limit: std::u64::MAX,
+        proposer:     Address::from_hash(Hash::from_empty()).unwrap(),
+    };
+
+    let asset_id =
+        Hash::from_hex("0xf56924db538e77bb5951eb5ff0d02b88983c49c45eea30e8ae3e7234b311436c")
+            .unwrap();
+    let mut stx = mock_signed_tx();
+    let sender = stx.raw.sender.clone();
+    stx.raw.request = TransactionRequest {
+        service_name: "asset".to_owned(),
+        method:       "transfer".to_owned(),
+        payload:      format!(
+            r#"{{ "asset_id": "{}", "to": "{}", "value": 11 }}"#,
+            asset_id.as_hex(),
+            Address::from_hash(Hash::from_empty()).unwrap()
+        ),
+    };
+    let executor_resp = executor.exec(Context::new(), &params, &[stx]).unwrap();
+    assert_eq!(executor_resp.receipts[0].response.response.code, 0);
+
+    let request = TransactionRequest {
+        service_name: "asset".to_owned(),
+        method:       "get_balance".to_owned(),
+        payload:      format!(
+            r#"{{ "asset_id": "{}", "user": "{}" }}"#,
+            asset_id.as_hex(),
+            sender
+        ),
+    };
+    let balance_at = |state_root| {
+        let resp = executor.read_at(state_root, &sender, &request).unwrap();
+        assert!(!resp.is_error());
+        serde_json::from_str::<GetBalanceResponse>(&resp.succeed_data)
+            .unwrap()
+            .balance
+    };
+    assert_eq!(balance_at(root), 320_000_011);
+    assert_eq!(balance_at(executor_resp.state_root), 320_000_000);
+}
+
+#[test]
+fn test_reject_below_min_cycles_price() {
+    let toml_str = include_str!("./genesis_services.toml");
+    let genesis: Genesis = toml::from_str(toml_str).unwrap();
+
+    let db = Arc::new(MemoryDB::new(false));
+
+    let root = ServiceExecutor::create_genesis(
+        genesis.services,
+        Arc::clone(&db),
+        Arc::new(MockStorage {}),
+        Arc::new(MockServiceMapping {}),
+    )
+    .unwrap();
+
+    let params = ExecutorParams {
+        state_root:   root.clone(),
+        height:       1,
+        timestamp:    0,
+        cycles_limit: std::u64::MAX,
+        proposer:     Address::from_hash(Hash::from_empty()).unwrap(),
+    };
+
+    let exec = |min_cycles_price_height: Option<u64>, txs: &[SignedTransaction]| {
+        let mut executor = ServiceExecutor::with_root(
+            root.clone(),
+            Arc::clone(&db),
+            Arc::new(MockStorage {}),
+            Arc::new(MockServiceMapping {}),
+        )
+        .unwrap();
+        executor.set_config(ExecutorConfig {
+            min_cycles_price: 2,
+            min_cycles_price_height,
+            ..Default::default()
+        });
+        executor.exec(Context::new(), &params, txs).unwrap()
+    };
+
+    let stx = mock_signed_tx();
+    assert_eq!(stx.raw.cycles_price, 1);
+
+    let executor_resp = exec(Some(1), &[stx.clone()]);
+    let receipt = &executor_resp.receipts[0];
+    assert_eq!(receipt.response.response.code, CYCLES_PRICE_TOO_LOW_CODE);
+    assert_eq!(receipt.cycles_used, 0);
+    assert!(receipt.events.is_empty());
+
+    // Same state as a block without the transaction
+    assert_eq!(executor_resp.state_root, exec(Some(1), &[]).state_root);
+
+    // Blocks before the configured height run it
+    for height in [None, Some(2)].iter() {
+        let executor_resp = exec(*height, &[stx.clone()]);
+        assert_eq!(executor_resp.receipts[0].response.response.code, 0);
+    }
+}
+
+#[test]
+fn test_read_paged_list() {
+    let toml_str = include_str!("./genesis_services.toml");
+    let genesis: Genesis = toml::from_str(toml_str).unwrap();
+
+    let db = Arc::new(MemoryDB::new(false));
+
+    let root = ServiceExecutor::create_genesis(
+        genesis.services,
+        Arc::clone(&db),
+        Arc::new(MockStorage {}),
+        Arc::new(MockServiceMapping {}),
+    )
+    .unwrap();
+
+    let executor = ServiceExecutor::with_root(
+        root.clone(),
+        Arc::clone(&db),
+        Arc::new(MockStorage {}),
+        Arc::new(MockServiceMapping {}),
+    )
+    .unwrap();
+
+    let params = ExecutorParams {
+        state_root:   root,
+        height:       1,
+        timestamp:    0,
+        cycles_limit: std::u64::MAX,
+        proposer:     Address::from_hash(Hash::from_empty()).unwrap(),
+    };
+    let caller = Address::from_str("muta14e0lmgck835vm2dfm0w3ckv6svmez8fdgdl705").unwrap();
+
+    let read_page = |offset, limit| {
+        let payload = PageRequest { offset, limit }
+            .merge_into_payload("")
+            .unwrap();
+        let request = TransactionRequest {
+            service_name: "test".to_owned(),
+            method: "test_list".to_owned(),
+            payload,
+        };
+        let resp = executor.read(&params, &caller, 1, &request).unwrap();
+        assert_eq!(resp.code, 0);
+        serde_json::from_str::<ServicePage<u64>>(&resp.succeed_data).unwrap()
+    };
+
+    let page = read_page(10, 10);
+    assert_eq!(page.items, (10..20).collect::<Vec<_>>());
+    assert_eq!(page.total, 25);
+    assert_eq!(page.next_cursor, Some(20));
+
+    let page = read_page(20, 10);
+    assert_eq!(page.items, (20..25).collect::<Vec<_>>());
+    assert_eq!(page.next_cursor, None);
+}
+
 #[test]
 fn test_emit_event() {
     let toml_str = include_str!("./genesis_services.toml");
@@ -274,6 +461,141 @@ fn test_service_not_found_panic() {
     assert_eq!(receipt.response.response.code, SERVICE_NOT_FOUND_CODE);
 }
 
+#[test]
+fn test_out_of_cycles_classification() {
+    let toml_str = include_str!("./genesis_services.toml");
+    let genesis: Genesis = toml::from_str(toml_str).unwrap();
+
+    let consume_tx = |method: &str, cycles: u64| {
+        let mut stx = mock_signed_tx();
+        stx.raw.cycles_limit = 50_000;
+        stx.raw.request.service_name = "test".to_owned();
+        stx.raw.request.method = method.to_owned();
+        stx.raw.request.payload = cycles.to_string();
+        stx
+    };
+    let txs = vec![
+        consume_tx("test_consume_cycles", 1_000),
+        consume_tx("test_consume_cycles", 1_000_000),
+        consume_tx("test_consume_cycles_then_fail", 1_000_000),
+    ];
+
+    let exec = |out_of_cycles_code_height: Option<u64>| {
+        let db = Arc::new(MemoryDB::new(false));
+        let root = ServiceExecutor::create_genesis(
+            genesis.services.clone(),
+            Arc::clone(&db),
+            Arc::new(MockStorage {}),
+            Arc::new(MockServiceMapping {}),
+        )
+        .unwrap();
+
+        let mut executor = ServiceExecutor::with_root(
+            root.clone(),
+            Arc::clone(&db),
+            Arc::new(MockStorage {}),
+            Arc::new(MockServiceMapping {}),
+        )
+        .unwrap();
+        executor.set_config(ExecutorConfig {
+            out_of_cycles_code_height,
+            ..Default::default()
+        });
+
+        let params = ExecutorParams {
+            state_root:   root,
+            height:       1,
+            timestamp:    0,
+            cycles_limit: std::u64::MAX,
+            proposer:     Address::from_hash(Hash::from_empty()).unwrap(),
+        };
+        let resp = executor.exec(Context::new(), &params, &txs).unwrap();
+        resp.receipts
+            .into_iter()
+            .map(|r| r.response.response)
+            .collect::<Vec<_>>()
+    };
+
+    // Blocks before the configured height keep codes of services
+    for height in [None, Some(2)].iter() {
+        let responses = exec(*height);
+        assert_eq!(responses[0].code, 0);
+        assert_eq!(responses[1].code, 113);
+        assert_eq!(responses[1].error_message, "consume cycles failed");
+        assert_eq!(responses[2].code, 114);
+    }
+
+    let responses = exec(Some(1));
+    assert_eq!(responses[0].code, 0);
+    assert_eq!(responses[1].code, OUT_OF_CYCLES_CODE);
+    assert_eq!(
+        responses[1].error_message,
+        ReceiptError::OutOfCycles.message()
+    );
+    // Running out of cycles in a caught call isn't the cause of a later error
+    assert_eq!(responses[2].code, 114);
+}
+
+#[test]
+fn test_receipt_error_deterministic() {
+    let toml_str = include_str!("./genesis_services.toml");
+    let genesis: Genesis = toml::from_str(toml_str).unwrap();
+
+    let mut out_of_cycles = mock_signed_tx();
+    out_of_cycles.raw.cycles_limit = 50_000;
+    out_of_cycles.raw.request.service_name = "test".to_owned();
+    out_of_cycles.raw.request.method = "test_consume_cycles".to_owned();
+    out_of_cycles.raw.request.payload = 1_000_000.to_string();
+    let mut not_found = mock_signed_tx();
+    not_found.raw.request.service_name = "FlyMeToTheMars".to_owned();
+    let txs = vec![out_of_cycles, not_found];
+
+    let run = || {
+        let db = Arc::new(MemoryDB::new(false));
+        let root = ServiceExecutor::create_genesis(
+            genesis.services.clone(),
+            Arc::clone(&db),
+            Arc::new(MockStorage {}),
+            Arc::new(MockServiceMapping {}),
+        )
+        .unwrap();
+        let mut executor = ServiceExecutor::with_root(
+            root.clone(),
+            Arc::clone(&db),
+            Arc::new(MockStorage {}),
+            Arc::new(MockServiceMapping {}),
+        )
+        .unwrap();
+
+        let params = ExecutorParams {
+            state_root:   root,
+            height:       1,
+            timestamp:    0,
+            cycles_limit: std::u64::MAX,
+            proposer:     Address::from_hash(Hash::from_empty()).unwrap(),
+        };
+        executor.set_config(ExecutorConfig {
+            out_of_cycles_code_height: Some(0),
+            ..Default::default()
+        });
+        executor.exec(Context::new(), &params, &txs).unwrap()
+    };
+
+    let first = run();
+    let second = run();
+    assert_eq!(first.receipts, second.receipts);
+    assert_eq!(first.state_root, second.state_root);
+
+    for (receipt, error) in first
+        .receipts
+        .iter()
+        .zip(&[ReceiptError::OutOfCycles, ReceiptError::ServiceNotFound])
+    {
+        assert_eq!(receipt.response.response.code, error.code());
+        assert_eq!(receipt.response.response.error_message, error.message());
+    }
+}
+
 #[test]
 fn test_tx_hook() {
     let toml_str = include_str!("./genesis_services.toml");
@@ -506,6 +828,286 @@ fn test_tx_hook_after_panic() {
     assert_eq!(after.succeed_data, r#""""#);
 }
 
+#[test]
+fn test_storage_write_cycles() {
+    let toml_str = include_str!("./genesis_services.toml");
+    let genesis: Genesis = toml::from_str(toml_str).unwrap();
+
+    let db = Arc::new(MemoryDB::new(false));
+
+    let root = ServiceExecutor::create_genesis(
+        genesis.services,
+        Arc::clone(&db),
+        Arc::new(MockStorage {}),
+        Arc::new(MockServiceMapping {}),
+    )
+    .unwrap();
+
+    let mut executor = ServiceExecutor::with_root(
+        root.clone(),
+        Arc::clone(&db),
+        Arc::new(MockStorage {}),
+        Arc::new(MockServiceMapping {}),
+    )
+    .unwrap();
+    executor.set_config(ExecutorConfig {
+        storage_cycles_per_byte: 10,
+        storage_cycles_height: Some(1),
+        ..Default::default()
+    });
+
+    let params = ExecutorParams {
+        state_root:   root,
+        height:       1,
+        timestamp:    0,
+        cycles_limit: std::u64::MAX,
+        proposer:     Address::from_hash(Hash::from_empty()).unwrap(),
+    };
+
+    let write_tx = |value: &str| {
+        let mut stx = mock_signed_tx();
+        stx.raw.request.service_name = "test".to_owned();
+        stx.raw.request.method = "test_write".to_owned();
+        stx.raw.request.payload = format!(
+            r#"{{ "key": "storage", "value": "{}", "extra": "" }}"#,
+            value
+        );
+        stx
+    };
+
+    let small_value = "a".repeat(10);
+    let large_value = "a".repeat(1000);
+    let txs = vec![write_tx(&small_value), write_tx(&large_value)];
+    let executor_resp = executor.exec(Context::new(), &params, &txs).unwrap();
+    let small = &executor_resp.receipts[0];
+    let large = &executor_resp.receipts[1];
+
+    assert_eq!(small.response.response.code, 0);
+    assert_eq!(large.response.response.code, 0);
+    assert!(large.cycles_used - small.cycles_used >= 990 * 10);
+
+    // Out of cycles, the write should be reverted
+    let mut stx = write_tx(&"b".repeat(1000));
+    stx.raw.cycles_limit = small.cycles_used;
+    let executor_resp = executor.exec(Context::new(), &params, &[stx]).unwrap();
+    let receipt = &executor_resp.receipts[0];
+    assert_eq!(receipt.response.response.code, OUT_OF_CYCLES_CODE);
+
+    let caller = Address::from_str("muta14e0lmgck835vm2dfm0w3ckv6svmez8fdgdl705").unwrap();
+    let value = read!(executor, &params, &caller, r#""storage""#);
+    assert_eq!(value.succeed_data, format!(r#""{}""#, large_value));
+
+    // Blocks before the configured height write for free
+    executor.set_config(ExecutorConfig {
+        storage_cycles_per_byte: 10,
+        storage_cycles_height: Some(2),
+        ..Default::default()
+    });
+    let executor_resp = executor.exec(Context::new(), &params, &txs).unwrap();
+    let small = &executor_resp.receipts[0];
+    let large = &executor_resp.receipts[1];
+    assert!(large.cycles_used - small.cycles_used < 990 * 10);
+}
+
+#[test]
+fn test_revert_inner_call_to_savepoint() {
+    let toml_str = include_str!("./genesis_services.toml");
+    let genesis: Genesis = toml::from_str(toml_str).unwrap();
+
+    let db = Arc::new(MemoryDB::new(false));
+
+    let root = ServiceExecutor::create_genesis(
+        genesis.services,
+        Arc::clone(&db),
+        Arc::new(MockStorage {}),
+        Arc::new(MockServiceMapping {}),
+    )
+    .unwrap();
+
+    let mut executor = ServiceExecutor::with_root(
+        root.clone(),
+        Arc::clone(&db),
+        Arc::new(MockStorage {}),
+        Arc::new(MockServiceMapping {}),
+    )
+    .unwrap();
+
+    let params = ExecutorParams {
+        state_root:   root,
+        height:       1,
+        timestamp:    0,
+        cycles_limit: std::u64::MAX,
+        proposer:     Address::from_hash(Hash::from_empty()).unwrap(),
+    };
+
+    let mut stx = mock_signed_tx();
+    stx.raw.request.service_name = "test".to_owned();
+    stx.raw.request.method = "test_revert_inner_call".to_owned();
+    stx.raw.request.payload =
+        r#"{ "key": "outer", "value": "committed", "extra": "reverted" }"#.to_owned();
+
+    let executor_resp = executor.exec(Context::new(), &params, &[stx]).unwrap();
+    let receipt = &executor_resp.receipts[0];
+    assert_eq!(receipt.response.response.code, 0);
+
+    // Outer write and event survive, the failed call into inner service is
+    // rolled back
+    let events = receipt
+        .events
+        .iter()
+        .map(|e| e.service.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(events, vec!["test"]);
+
+    let caller = Address::from_str("muta14e0lmgck835vm2dfm0w3ckv6svmez8fdgdl705").unwrap();
+    let outer = read!(executor, &params, &caller, r#""outer""#);
+    assert_eq!(outer.succeed_data, r#""committed""#);
+
+    let request = TransactionRequest {
+        service_name: "inner".to_owned(),
+        method:       "test_read".to_owned(),
+        payload:      r#""reverted""#.to_owned(),
+    };
+    let inner = executor.read(&params, &caller, 1, &request).unwrap();
+    assert_eq!(inner.succeed_data, r#""""#);
+}
+
+#[test]
+fn test_parallel_exec() {
+    let toml_str = include_str!("./genesis_services.toml");
+    let genesis: Genesis = toml::from_str(toml_str).unwrap();
+
+    let db = Arc::new(MemoryDB::new(false));
+
+    let root = ServiceExecutor::create_genesis(
+        genesis.services,
+        Arc::clone(&db),
+        Arc::new(MockStorage {}),
+        Arc::new(MockServiceMapping {}),
+    )
+    .unwrap();
+
+    let params = ExecutorParams {
+        state_root:   root.clone(),
+        height:       1,
+        timestamp:    0,
+        cycles_limit: std::u64::MAX,
+        proposer:     Address::from_hash(Hash::from_empty()).unwrap(),
+    };
+
+    let write_tx = |sender: &str, key: &str, value: &str| {
+        let mut stx = mock_signed_tx();
+        stx.raw.sender = Address::from_hash(Hash::digest(Bytes::from(sender.to_owned()))).unwrap();
+        stx.raw.request.service_name = "test".to_owned();
+        stx.raw.request.method = "test_write".to_owned();
+        stx.raw.request.payload = format!(
+            r#"{{ "key": "{}", "value": "{}", "extra": "" }}"#,
+            key, value
+        );
+        stx
+    };
+
+    let exec = |parallel: bool, txs: &[SignedTransaction]| {
+        let mut executor = ServiceExecutor::with_root(
+            root.clone(),
+            Arc::clone(&db),
+            Arc::new(MockStorage {}),
+            Arc::new(MockServiceMapping {}),
+        )
+        .unwrap();
+        executor.set_config(ExecutorConfig {
+            parallel_exec: parallel,
+            ..Default::default()
+        });
+        let resp = executor.exec(Context::new(), &params, txs).unwrap();
+        (resp, executor.parallel_fallbacks())
+    };
+
+    // Disjoint writes
+    let txs = vec![
+        write_tx("alice", "alice", "1"),
+        write_tx("bob", "bob", "2"),
+        write_tx("alice", "alice", "3"),
+        write_tx("carol", "carol", "4"),
+    ];
+    let (sequential, _) = exec(false, &txs);
+    let (parallel, fallbacks) = exec(true, &txs);
+    assert_eq!(fallbacks, 0);
+    assert_eq!(parallel.state_root, sequential.state_root);
+    assert_eq!(parallel.receipts, sequential.receipts);
+    assert_eq!(parallel.all_cycles_used, sequential.all_cycles_used);
+
+    // Conflict writes, fall back to sequential
+    let txs = vec![
+        write_tx("alice", "shared", "1"),
+        write_tx("bob", "shared", "2"),
+    ];
+    let (sequential, _) = exec(false, &txs);
+    let (parallel, fallbacks) = exec(true, &txs);
+    assert_eq!(fallbacks, 1);
+    assert_eq!(parallel.state_root, sequential.state_root);
+    assert_eq!(parallel.receipts, sequential.receipts);
+}
+
+#[test]
+fn test_parallel_exec_conflict() {
+    let access = |reads: &[&str], writes: &[(&str, &str)]| {
+        let mut access = StateAccess::default();
+        access.reads.insert(
+            "test".to_owned(),
+            reads.iter().map(|k| Bytes::from(k.to_string())).collect(),
+        );
+        access.writes.insert(
+            "test".to_owned(),
+            writes
+                .iter()
+                .map(|(k, v)| (Bytes::from(k.to_string()), Bytes::from(v.to_string())))
+                .collect(),
+        );
+        access
+    };
+
+    let a = access(&["a"], &[("a", "1"), ("hook", "x")]);
+    let b = access(&["b"], &[("b", "2"), ("hook", "x")]);
+    assert!(!has_conflict(&[&a, &b]));
+
+    // Read a key written by another group
+    let c = access(&["a"], &[("c", "3")]);
+    assert!(has_conflict(&[&a, &b, &c]));
+
+    // Write different value to same key
+    let d = access(&[], &[("hook", "y")]);
+    assert!(has_conflict(&[&a, &d]));
+}
+
+#[test]
+fn test_conflict_detector() {
+    let keys = |keys: &[&str]| {
+        keys.iter()
+            .map(|k| Bytes::from(k.to_string()))
+            .collect::<HashSet<_>>()
+    };
+
+    let detector = ConflictDetector::default();
+    detector.record(0, "test", &keys(&["a", "shared"]), &keys(&["a"]));
+    detector.record(1, "test", &keys(&["b", "shared"]), &keys(&["b"]));
+    // Writes of the same key are left to has_conflict
+    detector.record(2, "test", &keys(&[]), &keys(&["hook"]));
+    detector.record(3, "test", &keys(&[]), &keys(&["hook"]));
+    // Same key in another service
+    detector.record(1, "other", &keys(&[]), &keys(&["a"]));
+    assert!(!detector.is_conflicted());
+
+    // Write a key read by other groups, like a fee hook
+    detector.record(0, "test", &keys(&[]), &keys(&["shared"]));
+    assert!(detector.is_conflicted());
+
+    let detector = ConflictDetector::default();
+    detector.record(0, "test", &keys(&[]), &keys(&["a"]));
+    detector.record(1, "test", &keys(&["a"]), &keys(&[]));
+    assert!(detector.is_conflicted());
+}
+
 #[bench]
 fn bench_execute(b: &mut Bencher) {
     let toml_str = include_str!("./genesis_services.toml");
@@ -582,7 +1184,11 @@ impl ServiceMapping for MockServiceMapping {
         let service = match name {
             "asset" => Box::new(AssetService::new(sdk)) as Box<dyn Service>,
             "metadata" => Box::new(MetadataService::new(sdk)) as Box<dyn Service>,
-            "test" => Box::new(TestService::new(sdk)) as Box<dyn Service>,
+            "test" => {
+                let inner = InnerService::new(factory.get_sdk("inner")?);
+                Box::new(TestService::new(sdk, inner)) as Box<dyn Service>
+            }
+            "inner" => Box::new(InnerService::new(sdk)) as Box<dyn Service>,
             _ => panic!("not found service"),
         };
 
@@ -590,7 +1196,12 @@ impl ServiceMapping for MockServiceMapping {
     }
 
     fn list_service_name(&self) -> Vec<String> {
-        vec!["asset".to_owned(), "metadata".to_owned(), "test".to_owned()]
+        vec![
+            "asset".to_owned(),
+            "metadata".to_owned(),
+            "test".to_owned(),
+            "inner".to_owned(),
+        ]
     }
 }
 
@@ -606,6 +1217,19 @@ impl CommonStorage for MockStorage {
         unimplemented!()
     }
 
+    async fn contains_block(&self, _ctx: Context, _height: u64) -> ProtocolResult<bool> {
+        unimplemented!()
+    }
+
+    async fn get_blocks(
+        &self,
+        _ctx: Context,
+        _from: u64,
+        _limit: u64,
+    ) -> ProtocolResult<Vec<Block>> {
+        unimplemented!()
+    }
+
     async fn get_block_header(
         &self,
         _ctx: Context,
@@ -662,6 +1286,39 @@ impl Storage for MockStorage {
         unimplemented!()
     }
 
+    async fn get_transactions_by_hashes(
+        &self,
+        _: Context,
+        _: &[Hash],
+    ) -> ProtocolResult<Vec<Option<SignedTransaction>>> {
+        unimplemented!()
+    }
+
+    async fn get_transaction_at(
+        &self,
+        _ctx: Context,
+        _: u64,
+        _: &Hash,
+    ) -> ProtocolResult<Option<SignedTransaction>> {
+        unimplemented!()
+    }
+
+    async fn get_transaction_location(
+        &self,
+        _ctx: Context,
+        _: &Hash,
+    ) -> ProtocolResult<Option<(u64, usize)>> {
+        unimplemented!()
+    }
+
+    async fn contains_transaction(&self, _ctx: Context, _: &Hash) -> ProtocolResult<bool> {
+        unimplemented!()
+    }
+
+    async fn filter_existing(&self, _ctx: Context, _: Vec<Hash>) -> ProtocolResult<Vec<Hash>> {
+        unimplemented!()
+    }
+
     async fn get_transactions(
         &self,
         _ctx: Context,
@@ -684,6 +1341,10 @@ impl Storage for MockStorage {
         unimplemented!()
     }
 
+    async fn get_block_receipts(&self, _ctx: Context, _: u64) -> ProtocolResult<Vec<Receipt>> {
+        unimplemented!()
+    }
+
     async fn get_latest_proof(&self, _ctx: Context) -> ProtocolResult<Proof> {
         unimplemented!()
     }
@@ -2,10 +2,11 @@ use serde::{Deserialize, Serialize};
 
 use binding_macro::{cycles, service, tx_hook_after, tx_hook_before};
 use protocol::traits::{ExecutorParams, ServiceResponse, ServiceSDK};
-use protocol::types::ServiceContext;
+use protocol::types::{PageRequest, ServiceContext, ServicePage};
 
 pub struct TestService<SDK> {
-    sdk: SDK,
+    sdk:   SDK,
+    inner: InnerService<SDK>,
 }
 
 #[derive(Deserialize, Serialize, Clone, Debug)]
@@ -20,8 +21,8 @@ pub struct TestWriteResponse {}
 
 #[service]
 impl<SDK: ServiceSDK> TestService<SDK> {
-    pub fn new(sdk: SDK) -> Self {
-        Self { sdk }
+    pub fn new(sdk: SDK, inner: InnerService<SDK>) -> Self {
+        Self { sdk, inner }
     }
 
     #[cycles(10_000)]
@@ -31,6 +32,17 @@ impl<SDK: ServiceSDK> TestService<SDK> {
         ServiceResponse::from_succeed(value)
     }
 
+    #[cycles(10_000)]
+    #[read]
+    fn test_list(
+        &self,
+        ctx: ServiceContext,
+        payload: PageRequest,
+    ) -> ServiceResponse<ServicePage<u64>> {
+        let all: Vec<u64> = (0..25).collect();
+        ServiceResponse::from_succeed(ServicePage::from_slice(&all, payload))
+    }
+
     #[cycles(21_000)]
     #[write]
     fn test_write(
@@ -74,6 +86,52 @@ impl<SDK: ServiceSDK> TestService<SDK> {
         ServiceResponse::<TestWriteResponse>::from_succeed(TestWriteResponse {})
     }
 
+    #[cycles(21_000)]
+    #[write]
+    fn test_revert_inner_call(
+        &mut self,
+        ctx: ServiceContext,
+        payload: TestWritePayload,
+    ) -> ServiceResponse<TestWriteResponse> {
+        self.sdk
+            .set_value(payload.key.clone(), payload.value.clone());
+        ctx.emit_event("test".to_owned(), "outer".to_owned(), payload.key.clone());
+
+        let savepoint = self.sdk.stash_savepoint(&ctx);
+        if self
+            .inner
+            .test_write_and_fail(ctx.clone(), payload)
+            .is_error()
+        {
+            self.sdk.revert_to_savepoint(&ctx, savepoint);
+        }
+
+        ServiceResponse::<TestWriteResponse>::from_succeed(TestWriteResponse {})
+    }
+
+    #[cycles(21_000)]
+    #[write]
+    fn test_consume_cycles(&mut self, ctx: ServiceContext, cycles: u64) -> ServiceResponse<()> {
+        if !ctx.sub_cycles(cycles) {
+            return ServiceResponse::from_error(113, "consume cycles failed".to_owned());
+        }
+        ServiceResponse::from_succeed(())
+    }
+
+    #[cycles(21_000)]
+    #[write]
+    fn test_consume_cycles_then_fail(
+        &mut self,
+        ctx: ServiceContext,
+        cycles: u64,
+    ) -> ServiceResponse<()> {
+        // Give up on the expensive work, then fail for another reason
+        if !ctx.sub_cycles(cycles) {
+            ctx.sub_cycles(1);
+        }
+        ServiceResponse::from_error(114, "failed after out of cycles".to_owned())
+    }
+
     #[cycles(21_000)]
     #[write]
     fn test_panic(&mut self, ctx: ServiceContext, _payload: String) -> ServiceResponse<()> {
@@ -148,3 +206,34 @@ impl<SDK: ServiceSDK> TestService<SDK> {
         ServiceResponse::from_succeed(())
     }
 }
+
+pub struct InnerService<SDK> {
+    sdk: SDK,
+}
+
+#[service]
+impl<SDK: ServiceSDK> InnerService<SDK> {
+    pub fn new(sdk: SDK) -> Self {
+        Self { sdk }
+    }
+
+    #[cycles(10_000)]
+    #[read]
+    fn test_read(&self, ctx: ServiceContext, payload: String) -> ServiceResponse<String> {
+        let value: String = self.sdk.get_value(&payload).unwrap_or_default();
+        ServiceResponse::from_succeed(value)
+    }
+
+    #[cycles(21_000)]
+    #[write]
+    fn test_write_and_fail(
+        &mut self,
+        ctx: ServiceContext,
+        payload: TestWritePayload,
+    ) -> ServiceResponse<TestWriteResponse> {
+        self.sdk
+            .set_value(payload.extra.clone(), "inner".to_owned());
+        ctx.emit_event("inner".to_owned(), "inner".to_owned(), payload.extra);
+        ServiceResponse::from_error(112, "inner call failed".to_owned())
+    }
+}
@@ -13,7 +13,7 @@ pub mod transaction;
 use std::error::Error;
 
 use async_trait::async_trait;
-use bytes::Bytes;
+use bytes::{BufMut, Bytes, BytesMut};
 use derive_more::{Display, From};
 
 use crate::{ProtocolError, ProtocolErrorKind, ProtocolResult};
@@ -36,6 +36,29 @@ pub trait ProtocolCodecSync: Sized + Send {
     fn encode_sync(&self) -> ProtocolResult<Bytes>;
 
     fn decode_sync(bytes: Bytes) -> ProtocolResult<Self>;
+
+    /// Encode with a leading version byte, so that a later release changing
+    /// the meaning of the message can tell old data apart. Not compatible
+    /// with plain `encode_sync`.
+    fn encode_versioned(&self, version: u8) -> ProtocolResult<Bytes> {
+        let encoded = self.encode_sync()?;
+
+        let mut bytes = BytesMut::with_capacity(encoded.len() + 1);
+        bytes.put_u8(version);
+        bytes.put(encoded);
+        Ok(bytes.freeze())
+    }
+
+    /// Decode bytes from `encode_versioned`, callers branch on the returned
+    /// version.
+    fn decode_versioned(mut bytes: Bytes) -> ProtocolResult<(u8, Self)> {
+        if bytes.is_empty() {
+            return Err(CodecError::MissingVersion.into());
+        }
+
+        let version = bytes.split_to(1)[0];
+        Ok((version, Self::decode_sync(bytes)?))
+    }
 }
 
 #[async_trait]
@@ -83,6 +106,9 @@ pub enum CodecError {
 
     #[display(fmt = "from string {}", _0)]
     FromStringUtf8(std::string::FromUtf8Error),
+
+    #[display(fmt = "missing version byte")]
+    MissingVersion,
 }
 
 impl Error for CodecError {}
@@ -41,6 +41,26 @@ fn test_codec() {
     test!(block, Pill, mock_pill, 100, 200);
 }
 
+#[test]
+fn test_versioned_codec() {
+    let stx = mock_sign_tx();
+
+    let bytes = stx.encode_versioned(2).unwrap();
+    assert_eq!(bytes[0], 2);
+    assert_eq!(bytes.slice(1..), stx.encode_sync().unwrap());
+
+    let (version, decoded) = SignedTransaction::decode_versioned(bytes).unwrap();
+    assert_eq!(version, 2);
+    assert_eq!(decoded, stx);
+
+    // A caller expecting another version branches on it instead of decoding
+    let (version, _) =
+        SignedTransaction::decode_versioned(stx.encode_versioned(1).unwrap()).unwrap();
+    assert_ne!(version, 2);
+
+    assert!(SignedTransaction::decode_versioned(Bytes::new()).is_err());
+}
+
 #[test]
 fn test_signed_tx_serialize_size() {
     let txs: Vec<Bytes> = (0..50_000)
@@ -29,10 +29,47 @@ pub enum FixedCodecError {
 
     #[display(fmt = "wrong bytes of u8")]
     DecodeUint8,
+
+    #[display(fmt = "wrong bytes of option")]
+    DecodeOption,
 }
 
 impl Error for FixedCodecError {}
 
+/// Length of bytes in rlp encoding, computed without encoding them.
+pub(crate) fn rlp_bytes_len(bytes: &[u8]) -> usize {
+    if bytes.len() == 1 && bytes[0] < 0x80 {
+        1
+    } else {
+        rlp_header_len(bytes.len()) + bytes.len()
+    }
+}
+
+/// Length of an unsigned integer in rlp encoding, it's encoded as big endian
+/// bytes without leading zeros.
+pub(crate) fn rlp_u64_len(val: u64) -> usize {
+    if val != 0 && val < 0x80 {
+        1
+    } else {
+        let bytes = 8 - val.leading_zeros() as usize / 8;
+        1 + bytes
+    }
+}
+
+/// Length of a list in rlp encoding whose items take `payload_len` bytes.
+pub(crate) fn rlp_list_len(payload_len: usize) -> usize {
+    rlp_header_len(payload_len) + payload_len
+}
+
+fn rlp_header_len(payload_len: usize) -> usize {
+    if payload_len < 56 {
+        1
+    } else {
+        let len_of_len = 8 - (payload_len as u64).leading_zeros() as usize / 8;
+        1 + len_of_len
+    }
+}
+
 impl From<FixedCodecError> for ProtocolError {
     fn from(err: FixedCodecError) -> ProtocolError {
         ProtocolError::new(ProtocolErrorKind::FixedCodec, Box::new(err))
@@ -121,6 +121,32 @@ impl FixedCodec for Vec<u8> {
     }
 }
 
+/// `None` is encoded as empty bytes, `Some` as a leading 1 followed by the
+/// encoded value, so that `Some` of an empty value is still distinguishable.
+impl<T: FixedCodec> FixedCodec for Option<T> {
+    fn encode_fixed(&self) -> ProtocolResult<Bytes> {
+        match self {
+            Some(value) => {
+                let mut buf = BytesMut::from([1u8].as_ref());
+                buf.extend_from_slice(value.encode_fixed()?.as_ref());
+                Ok(buf.freeze())
+            }
+            None => Ok(Bytes::new()),
+        }
+    }
+
+    fn decode_fixed(mut bytes: Bytes) -> ProtocolResult<Self> {
+        if bytes.is_empty() {
+            return Ok(None);
+        }
+
+        if bytes[0] != 1 {
+            return Err(FixedCodecError::DecodeOption.into());
+        }
+        Ok(Some(T::decode_fixed(bytes.split_off(1))?))
+    }
+}
+
 impl FixedCodec for Hex {
     fn encode_fixed(&self) -> ProtocolResult<bytes::Bytes> {
         let bytes = self.as_string_trim0x().as_bytes().to_vec();
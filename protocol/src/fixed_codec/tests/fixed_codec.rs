@@ -44,6 +44,21 @@ fn test_fixed_codec_primitive() {
         <String as FixedCodec>::decode_fixed(bs).unwrap(),
         "test".to_owned()
     );
+
+    let bs = Some(8u64).encode_fixed().unwrap();
+    assert_eq!(
+        <Option<u64> as FixedCodec>::decode_fixed(bs).unwrap(),
+        Some(8u64)
+    );
+
+    let bs = Some(String::new()).encode_fixed().unwrap();
+    assert_eq!(
+        <Option<String> as FixedCodec>::decode_fixed(bs).unwrap(),
+        Some(String::new())
+    );
+
+    let bs = None::<u64>.encode_fixed().unwrap();
+    assert_eq!(<Option<u64> as FixedCodec>::decode_fixed(bs).unwrap(), None);
 }
 
 #[test]
@@ -65,6 +80,26 @@ fn test_fixed_codec() {
     test_eq!(receipt, Receipt, mock_receipt);
 }
 
+#[test]
+fn test_raw_tx_encoded_len() {
+    let cycles = [0, 1, 0x7f, 0x80, 0xff, 0x100, u64::max_value()];
+    let payload_lens = [0, 1, 54, 55, 56, 255, 256, 70_000];
+
+    for (i, &len) in payload_lens.iter().enumerate() {
+        let mut raw = mock_raw_tx();
+        raw.request.payload = "a".repeat(len);
+        raw.cycles_limit = cycles[i % cycles.len()];
+        raw.cycles_price = cycles[(i + 1) % cycles.len()];
+        raw.timeout = cycles[(i + 2) % cycles.len()];
+
+        assert_eq!(raw.encoded_len(), raw.encode_fixed().unwrap().len());
+    }
+
+    let mut raw = mock_raw_tx();
+    raw.request.payload = "\u{7f}".to_owned();
+    assert_eq!(raw.encoded_len(), raw.encode_fixed().unwrap().len());
+}
+
 #[test]
 fn test_signed_tx_serialize_size() {
     let txs: Vec<Bytes> = (0..50_000)
@@ -1,6 +1,6 @@
 use bytes::BytesMut;
 
-use crate::fixed_codec::{FixedCodec, FixedCodecError};
+use crate::fixed_codec::{rlp_bytes_len, rlp_list_len, rlp_u64_len, FixedCodec, FixedCodecError};
 use crate::types::{Hash, RawTransaction, TransactionRequest};
 use crate::ProtocolResult;
 
@@ -50,6 +50,23 @@ impl rlp::Decodable for RawTransaction {
     }
 }
 
+impl RawTransaction {
+    /// Length of `encode_fixed` output, without actually encoding.
+    pub fn encoded_len(&self) -> usize {
+        let payload_len = rlp_bytes_len(self.chain_id.as_slice())
+            + rlp_u64_len(self.cycles_limit)
+            + rlp_u64_len(self.cycles_price)
+            + rlp_bytes_len(self.nonce.as_slice())
+            + rlp_bytes_len(self.request.method.as_bytes())
+            + rlp_bytes_len(self.request.service_name.as_bytes())
+            + rlp_bytes_len(self.request.payload.as_bytes())
+            + rlp_u64_len(self.timeout)
+            + rlp_list_len(rlp_bytes_len(self.sender.as_slice()));
+
+        rlp_list_len(payload_len)
+    }
+}
+
 impl FixedCodec for RawTransaction {
     fn encode_fixed(&self) -> ProtocolResult<bytes::Bytes> {
         Ok(bytes::Bytes::from(rlp::encode(self)))
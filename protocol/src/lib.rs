@@ -51,6 +51,13 @@ pub struct ProtocolError {
     error: Box<dyn Error + Send>,
 }
 
+impl ProtocolError {
+    /// Return the inner error if it's of type `E`.
+    pub fn downcast_ref<E: Error + 'static>(&self) -> Option<&E> {
+        self.error.downcast_ref::<E>()
+    }
+}
+
 impl From<ProtocolError> for Box<dyn Error + Send> {
     fn from(error: ProtocolError) -> Self {
         Box::new(error) as Box<dyn Error + Send>
@@ -1,7 +1,7 @@
 use async_trait::async_trait;
 
 use crate::traits::{Context, ServiceResponse};
-use crate::types::{Address, Block, BlockHeader, Hash, Receipt, SignedTransaction};
+use crate::types::{Address, Block, BlockHeader, Bytes, Hash, Receipt, SignedTransaction};
 use crate::ProtocolResult;
 
 #[async_trait]
@@ -12,12 +12,31 @@ pub trait APIAdapter: Send + Sync {
         signed_tx: SignedTransaction,
     ) -> ProtocolResult<()>;
 
+    /// Remove a pending transaction from mempool. The signature must be
+    /// signed over the cancel message of `tx_hash` by the transaction sender.
+    async fn cancel_transaction(
+        &self,
+        ctx: Context,
+        tx_hash: Hash,
+        pubkey: Bytes,
+        signature: Bytes,
+    ) -> ProtocolResult<()>;
+
     async fn get_block_by_height(
         &self,
         ctx: Context,
         height: Option<u64>,
     ) -> ProtocolResult<Option<Block>>;
 
+    async fn get_blocks_since(
+        &self,
+        ctx: Context,
+        from: u64,
+        limit: u64,
+    ) -> ProtocolResult<Vec<Block>>;
+
+    async fn get_latest_height(&self, ctx: Context) -> ProtocolResult<u64>;
+
     async fn get_block_header_by_height(
         &self,
         ctx: Context,
@@ -30,12 +49,34 @@ pub trait APIAdapter: Send + Sync {
         tx_hash: Hash,
     ) -> ProtocolResult<Option<Receipt>>;
 
+    /// Get receipts of transactions included in block at `height`, the result
+    /// is in the same order as the hashes.
+    async fn get_receipts_by_hashes(
+        &self,
+        ctx: Context,
+        height: u64,
+        tx_hashes: Vec<Hash>,
+    ) -> ProtocolResult<Vec<Option<Receipt>>>;
+
+    /// Get all receipts of block at `height`, in storage key order rather
+    /// than the order of transactions in block.
+    async fn get_block_receipts(&self, ctx: Context, height: u64) -> ProtocolResult<Vec<Receipt>>;
+
+    /// Get the transaction, if `height` is given, only the one included in
+    /// block at that height.
     async fn get_transaction_by_hash(
         &self,
         ctx: Context,
         tx_hash: Hash,
+        height: Option<u64>,
     ) -> ProtocolResult<Option<SignedTransaction>>;
 
+    async fn get_transactions_by_hashes(
+        &self,
+        ctx: Context,
+        tx_hashes: Vec<Hash>,
+    ) -> ProtocolResult<Vec<Option<SignedTransaction>>>;
+
     async fn query_service(
         &self,
         ctx: Context,
@@ -69,6 +69,14 @@ pub trait ServiceState {
 
     // Persist data from stash into MPT
     fn commit(&mut self) -> ProtocolResult<MerkleRoot>;
+
+    // Mark current cache as a savepoint and return its id. Savepoints are
+    // dropped on `revert_cache` and `stash`.
+    fn stash_savepoint(&mut self) -> ProtocolResult<usize>;
+
+    // Roll back data in the cache written after the savepoint, the savepoint
+    // and savepoints taken after it are released.
+    fn revert_to_savepoint(&mut self, savepoint: usize) -> ProtocolResult<()>;
 }
 
 pub trait ChainQuerier {
@@ -188,6 +196,23 @@ pub trait ServiceSDK {
     // Get a receipt by `tx_hash`
     // if not found on the chain, return None
     fn get_receipt_by_hash(&self, tx_hash: &Hash) -> Option<Receipt>;
+
+    // Take a savepoint of all service states and events emitted in `ctx`, so
+    // that a failed inner call, even into other services, can be rolled back
+    // without reverting the whole transaction
+    fn stash_savepoint(&mut self, ctx: &ServiceContext) -> Savepoint;
+
+    // Roll back writes made to service states and events emitted in `ctx`
+    // after the savepoint
+    fn revert_to_savepoint(&mut self, ctx: &ServiceContext, savepoint: Savepoint);
+}
+
+// Taken by `ServiceSDK::stash_savepoint`, `states` holds the savepoint id of
+// each service state and `event_index` the number of events emitted before it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Savepoint {
+    pub states:      Vec<usize>,
+    pub event_index: usize,
 }
 
 pub trait StoreMap<K: FixedCodec + PartialEq, V: FixedCodec> {
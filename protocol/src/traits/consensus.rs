@@ -1,4 +1,5 @@
 use std::collections::HashMap;
+use std::time::Instant;
 
 use async_trait::async_trait;
 use creep::Context;
@@ -23,6 +24,19 @@ pub struct NodeInfo {
     pub self_address: Address,
 }
 
+/// Height and local time of the latest block committed by consensus.
+#[derive(Debug, Clone, Copy)]
+pub struct CommitStatus {
+    pub height:       u64,
+    pub committed_at: Instant,
+}
+
+/// Consulted by the consensus adapter before proposing, to reorder or limit
+/// the transaction hashes packaged by mempool.
+pub trait ProposalSelector: Send + Sync {
+    fn select(&self, ctx: Context, height: u64, txs: MixedTxHashes) -> MixedTxHashes;
+}
+
 #[async_trait]
 pub trait Consensus: Send + Sync {
     /// Network set a received signed proposal to consensus.
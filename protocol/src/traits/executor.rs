@@ -1,3 +1,4 @@
+use std::collections::HashSet;
 use std::sync::Arc;
 
 use creep::Context;
@@ -22,6 +23,34 @@ pub struct ExecutorParams {
     pub proposer:     Address,
 }
 
+/// Node settings of an executor. They affect execution results, so nodes of a
+/// chain must agree on them. Defaults execute blocks the same as before any
+/// of them existed.
+#[derive(Debug, Clone, Default)]
+pub struct ExecutorConfig {
+    /// Transactions offering a lower cycles price fail without running.
+    pub min_cycles_price:          u64,
+    /// From this height on, `min_cycles_price` is enforced on execution.
+    /// Receipts are hashed into blocks, so it's off unless set.
+    pub min_cycles_price_height:   Option<u64>,
+    /// New transactions of these senders are rejected by mempool. It's node
+    /// local, so execution doesn't check it.
+    pub sender_blacklist:          Arc<HashSet<Address>>,
+    /// Cycles charged for each byte of key and value written into service
+    /// state by a transaction.
+    pub storage_cycles_per_byte:   u64,
+    /// From this height on, `storage_cycles_per_byte` is charged. Receipts
+    /// are hashed into blocks, so it's off unless set.
+    pub storage_cycles_height:     Option<u64>,
+    /// Execute transactions of different senders in parallel, the result is
+    /// the same as sequential execution.
+    pub parallel_exec:             bool,
+    /// From this height on, a transaction failing because it ran out of
+    /// cycles gets the out of cycles code whatever its service returned.
+    /// Receipts are hashed into blocks, so it's off unless set.
+    pub out_of_cycles_code_height: Option<u64>,
+}
+
 #[derive(Debug, Clone, Default)]
 pub struct ServiceResponse<T: Default> {
     pub code:          u64,
@@ -69,6 +98,7 @@ pub trait ExecutorFactory<DB: cita_trie::DB, S: Storage, Mapping: ServiceMapping
         db: Arc<DB>,
         storage: Arc<S>,
         mapping: Arc<Mapping>,
+        config: &ExecutorConfig,
     ) -> ProtocolResult<Box<dyn Executor>>;
 }
 
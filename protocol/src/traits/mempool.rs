@@ -5,6 +5,7 @@ use crate::types::{Hash, SignedTransaction};
 use crate::ProtocolResult;
 
 #[allow(dead_code)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub struct MixedTxHashes {
     pub order_tx_hashes:   Vec<Hash>,
     pub propose_tx_hashes: Vec<Hash>,
@@ -29,6 +30,11 @@ pub trait MemPool: Send + Sync {
 
     async fn flush(&self, ctx: Context, tx_hashes: &[Hash]) -> ProtocolResult<()>;
 
+    /// Remove a specific transaction from pool, for example, one found
+    /// invalid after insertion or cancelled by its sender. Return whether the
+    /// transaction was in pool.
+    async fn remove(&self, ctx: Context, tx_hash: Hash) -> ProtocolResult<bool>;
+
     async fn get_full_txs(
         &self,
         ctx: Context,
@@ -69,7 +75,14 @@ pub trait MemPoolAdapter: Send + Sync {
         tx: Box<SignedTransaction>,
     ) -> ProtocolResult<()>;
 
-    async fn check_transaction(&self, ctx: Context, tx: &SignedTransaction) -> ProtocolResult<()>;
+    /// Check the transaction as if it were packaged into block at `height`,
+    /// or into the next block if `height` is none.
+    async fn check_transaction(
+        &self,
+        ctx: Context,
+        tx: &SignedTransaction,
+        height: Option<u64>,
+    ) -> ProtocolResult<()>;
 
     async fn check_storage_exist(&self, ctx: Context, tx_hash: &Hash) -> ProtocolResult<()>;
 
@@ -82,6 +95,14 @@ pub trait MemPoolAdapter: Send + Sync {
         tx_hashes: &[Hash],
     ) -> ProtocolResult<Vec<Option<SignedTransaction>>>;
 
+    /// Transactions of the committed block at `height`, empty if there isn't
+    /// such a block.
+    async fn get_block_transactions(
+        &self,
+        ctx: Context,
+        height: u64,
+    ) -> ProtocolResult<Vec<SignedTransaction>>;
+
     fn report_good(&self, ctx: Context);
 
     fn set_args(&self, timeout_gap: u64, cycles_limit: u64, max_tx_size: u64);
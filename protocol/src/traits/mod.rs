@@ -8,21 +8,23 @@ mod storage;
 
 pub use api::APIAdapter;
 pub use binding::{
-    AdmissionControl, ChainQuerier, SDKFactory, Service, ServiceMapping, ServiceSDK, ServiceState,
-    StoreArray, StoreBool, StoreMap, StoreString, StoreUint64,
+    AdmissionControl, ChainQuerier, SDKFactory, Savepoint, Service, ServiceMapping, ServiceSDK,
+    ServiceState, StoreArray, StoreBool, StoreMap, StoreString, StoreUint64,
 };
 pub use consensus::{
-    CommonConsensusAdapter, Consensus, ConsensusAdapter, MessageTarget, NodeInfo, Synchronization,
-    SynchronizationAdapter,
+    CommitStatus, CommonConsensusAdapter, Consensus, ConsensusAdapter, MessageTarget, NodeInfo,
+    ProposalSelector, Synchronization, SynchronizationAdapter,
+};
+pub use executor::{
+    Executor, ExecutorConfig, ExecutorFactory, ExecutorParams, ExecutorResp, ServiceResponse,
 };
-pub use executor::{Executor, ExecutorFactory, ExecutorParams, ExecutorResp, ServiceResponse};
 pub use mempool::{MemPool, MemPoolAdapter, MixedTxHashes};
 pub use network::{
     Gossip, MessageCodec, MessageHandler, Network, PeerTag, PeerTrust, Priority, Rpc, TrustFeedback,
 };
 pub use storage::{
-    CommonStorage, IntoIteratorByRef, MaintenanceStorage, Storage, StorageAdapter,
-    StorageBatchModify, StorageCategory, StorageIterator, StorageSchema,
+    CommonStorage, IntoIteratorByRef, MaintenanceStorage, SelfCheckReport, Storage, StorageAdapter,
+    StorageBatch, StorageBatchModify, StorageCategory, StorageIterator, StorageSchema,
 };
 
 pub use creep::{Cloneable, Context};
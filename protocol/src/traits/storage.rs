@@ -1,14 +1,14 @@
 use async_trait::async_trait;
 use derive_more::Display;
 
-use crate::codec::ProtocolCodec;
+use crate::codec::{ProtocolCodec, ProtocolCodecSync};
 use crate::traits::Context;
 use crate::types::block::{Block, BlockHeader, Proof};
 use crate::types::receipt::Receipt;
 use crate::types::{Hash, SignedTransaction};
-use crate::ProtocolResult;
+use crate::{Bytes, ProtocolResult};
 
-#[derive(Debug, Copy, Clone, Display)]
+#[derive(Debug, Copy, Clone, Display, PartialEq, Eq, Hash)]
 pub enum StorageCategory {
     Block,
     BlockHeader,
@@ -40,6 +40,13 @@ pub trait CommonStorage: Send + Sync {
 
     async fn get_block(&self, ctx: Context, height: u64) -> ProtocolResult<Option<Block>>;
 
+    /// Check whether the block exists without fetching and decoding it.
+    async fn contains_block(&self, ctx: Context, height: u64) -> ProtocolResult<bool>;
+
+    /// Get at most `limit` consecutive blocks starting from `from` height,
+    /// stop at the first missing one.
+    async fn get_blocks(&self, ctx: Context, from: u64, limit: u64) -> ProtocolResult<Vec<Block>>;
+
     async fn get_block_header(
         &self,
         ctx: Context,
@@ -79,6 +86,37 @@ pub trait Storage: CommonStorage {
         hash: &Hash,
     ) -> ProtocolResult<Option<SignedTransaction>>;
 
+    /// Get transactions of any height by hashes, in the same order as hashes.
+    /// Looks up heights and then transactions each in one batched read.
+    async fn get_transactions_by_hashes(
+        &self,
+        ctx: Context,
+        hashes: &[Hash],
+    ) -> ProtocolResult<Vec<Option<SignedTransaction>>>;
+
+    /// Get the transaction only if it is included in block at `block_height`.
+    async fn get_transaction_at(
+        &self,
+        ctx: Context,
+        block_height: u64,
+        hash: &Hash,
+    ) -> ProtocolResult<Option<SignedTransaction>>;
+
+    /// Height of the block including the transaction and its index in the
+    /// block's ordered transaction hashes.
+    async fn get_transaction_location(
+        &self,
+        ctx: Context,
+        hash: &Hash,
+    ) -> ProtocolResult<Option<(u64, usize)>>;
+
+    /// Check whether the transaction exists without fetching and decoding it.
+    async fn contains_transaction(&self, ctx: Context, hash: &Hash) -> ProtocolResult<bool>;
+
+    /// Return hashes of committed transactions among `hashes`, in one batched
+    /// lookup.
+    async fn filter_existing(&self, ctx: Context, hashes: Vec<Hash>) -> ProtocolResult<Vec<Hash>>;
+
     async fn insert_receipts(
         &self,
         ctx: Context,
@@ -99,19 +137,124 @@ pub trait Storage: CommonStorage {
         hashes: Vec<Hash>,
     ) -> ProtocolResult<Vec<Option<Receipt>>>;
 
+    /// Get all receipts of a block, in storage key order rather than the
+    /// order of transactions in block.
+    async fn get_block_receipts(
+        &self,
+        ctx: Context,
+        block_height: u64,
+    ) -> ProtocolResult<Vec<Receipt>>;
+
     async fn update_latest_proof(&self, ctx: Context, proof: Proof) -> ProtocolResult<()>;
 
     async fn get_latest_proof(&self, ctx: Context) -> ProtocolResult<Proof>;
 }
 
 #[async_trait]
-pub trait MaintenanceStorage: CommonStorage {}
+pub trait MaintenanceStorage: CommonStorage {
+    /// Sample at most `sample` transaction hash to height mappings, and check
+    /// that each transaction is stored at the mapped height.
+    async fn self_check(&self, ctx: Context, sample: usize) -> ProtocolResult<SelfCheckReport>;
+
+    /// Flush written data to disk, so that files copied afterwards are
+    /// consistent.
+    async fn sync(&self, ctx: Context) -> ProtocolResult<()>;
+
+    /// Remove the block at `height` together with its header, transactions,
+    /// receipts and their hash to height mappings. Unlike `remove_block`,
+    /// nothing of the block is left behind.
+    async fn remove_block_full(&self, ctx: Context, height: u64) -> ProtocolResult<()>;
+
+    /// Compact keys from `from` to `to` of `category`, unbounded on a side if
+    /// none, so that space of removed data is reclaimed. Run it after bulk
+    /// removal, it can take long on a large range.
+    async fn compact_range(
+        &self,
+        ctx: Context,
+        category: StorageCategory,
+        from: Option<Bytes>,
+        to: Option<Bytes>,
+    ) -> ProtocolResult<()>;
+
+    /// Read the stored bytes of `key` in `category` as they are, without
+    /// decoding by any schema. For debugging and recovery tools only.
+    async fn get_raw(
+        &self,
+        ctx: Context,
+        category: StorageCategory,
+        key: Bytes,
+    ) -> ProtocolResult<Option<Bytes>>;
+
+    /// Write `value` of `key` in `category` as it is, bypassing schema
+    /// encoding and every consistency check.
+    ///
+    /// Dangerous: a malformed value breaks all later reads of the key, and
+    /// cached latest block and proof are not refreshed. Only use it in
+    /// maintenance mode with the node stopped.
+    async fn put_raw(
+        &self,
+        ctx: Context,
+        category: StorageCategory,
+        key: Bytes,
+        value: Bytes,
+    ) -> ProtocolResult<()>;
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SelfCheckReport {
+    /// Number of mappings checked.
+    pub checked:  usize,
+    /// Transaction hashes and heights which are mapped but not stored.
+    pub dangling: Vec<(Hash, u64)>,
+}
 
 pub enum StorageBatchModify<S: StorageSchema> {
     Remove,
     Insert(<S as StorageSchema>::Value),
 }
 
+/// Modifications of keys across schemas, written at once by
+/// `StorageAdapter::write_batch`.
+#[derive(Default)]
+pub struct StorageBatch {
+    modifies: Vec<(StorageCategory, Bytes, Option<Bytes>)>,
+}
+
+impl StorageBatch {
+    pub fn insert<S: StorageSchema>(
+        &mut self,
+        key: <S as StorageSchema>::Key,
+        val: <S as StorageSchema>::Value,
+    ) -> ProtocolResult<()> {
+        let modify = (S::category(), key.encode_sync()?, Some(val.encode_sync()?));
+        self.modifies.push(modify);
+        Ok(())
+    }
+
+    pub fn remove<S: StorageSchema>(
+        &mut self,
+        key: <S as StorageSchema>::Key,
+    ) -> ProtocolResult<()> {
+        self.modifies
+            .push((S::category(), key.encode_sync()?, None));
+        Ok(())
+    }
+
+    pub fn len(&self) -> usize {
+        self.modifies.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.modifies.is_empty()
+    }
+
+    /// Encoded keys and values in the order they were added, value is `None`
+    /// for a removal.
+    pub fn into_modifies(self) -> Vec<(StorageCategory, Bytes, Option<Bytes>)> {
+        self.modifies
+    }
+}
+
 #[async_trait]
 pub trait StorageAdapter: Send + Sync {
     async fn insert<S: StorageSchema>(
@@ -151,8 +294,28 @@ pub trait StorageAdapter: Send + Sync {
         vals: Vec<StorageBatchModify<S>>,
     ) -> ProtocolResult<()>;
 
+    /// Write all modifications of `batch` atomically, either all of them are
+    /// written or none is.
+    async fn write_batch(&self, batch: StorageBatch) -> ProtocolResult<()>;
+
     fn prepare_iter<'a, 'b: 'a, S: StorageSchema + 'static, P: AsRef<[u8]> + 'a>(
         &'b self,
         prefix: &'a P,
     ) -> ProtocolResult<Box<dyn IntoIteratorByRef<S> + 'a>>;
+
+    /// Flush buffered writes to disk, nothing to do for an in-memory adapter.
+    async fn flush(&self) -> ProtocolResult<()> {
+        Ok(())
+    }
+
+    /// Compact a key range of `category`, nothing to do for an in-memory
+    /// adapter.
+    async fn compact_range(
+        &self,
+        _category: StorageCategory,
+        _from: Option<Bytes>,
+        _to: Option<Bytes>,
+    ) -> ProtocolResult<()> {
+        Ok(())
+    }
 }
@@ -1,5 +1,6 @@
 pub(crate) mod block;
 pub(crate) mod genesis;
+pub(crate) mod page;
 pub(crate) mod primitive;
 pub(crate) mod receipt;
 pub(crate) mod service_context;
@@ -14,6 +15,7 @@ use crate::{ProtocolError, ProtocolErrorKind};
 pub use block::{Block, BlockHeader, Pill, Proof, Validator};
 pub use bytes::{Bytes, BytesMut};
 pub use genesis::{Genesis, ServiceParam};
+pub use page::{PageRequest, ServicePage, DEFAULT_PAGE_LIMIT};
 pub use primitive::{
     address_hrp, address_hrp_inited, init_address_hrp, Address, Hash, Hex, JsonString, MerkleRoot,
     Metadata, ValidatorExtend, GENESIS_HEIGHT, METADATA_KEY,
@@ -41,6 +43,9 @@ pub enum TypesError {
 
     #[display(fmt = "Invalid public key")]
     InvalidPublicKey,
+
+    #[display(fmt = "Paged payload should be a json object")]
+    InvalidPagePayload,
 }
 
 impl Error for TypesError {}
@@ -0,0 +1,117 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::types::TypesError;
+use crate::ProtocolResult;
+
+pub const DEFAULT_PAGE_LIMIT: u64 = 100;
+
+/// Paging arguments of a service list query. The api merges them into the
+/// json payload of `queryService`, so a list method takes them as its payload
+/// or flattens them into its own payload struct.
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PageRequest {
+    #[serde(default)]
+    pub offset: u64,
+    #[serde(default = "default_page_limit")]
+    pub limit:  u64,
+}
+
+impl Default for PageRequest {
+    fn default() -> Self {
+        PageRequest {
+            offset: 0,
+            limit:  DEFAULT_PAGE_LIMIT,
+        }
+    }
+}
+
+impl PageRequest {
+    /// Set `offset` and `limit` fields of a json object payload, an empty
+    /// payload is treated as an empty object.
+    pub fn merge_into_payload(&self, payload: &str) -> ProtocolResult<String> {
+        let mut obj = if payload.trim().is_empty() {
+            Map::new()
+        } else {
+            match serde_json::from_str::<Value>(payload) {
+                Ok(Value::Object(obj)) => obj,
+                _ => return Err(TypesError::InvalidPagePayload.into()),
+            }
+        };
+
+        obj.insert("offset".to_owned(), Value::from(self.offset));
+        obj.insert("limit".to_owned(), Value::from(self.limit));
+        Ok(Value::Object(obj).to_string())
+    }
+}
+
+/// Paginated envelope returned by a service list query. `next_cursor` is the
+/// offset of next page, none if this is the last one.
+#[derive(Deserialize, Serialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct ServicePage<T> {
+    pub items:       Vec<T>,
+    pub total:       u64,
+    pub next_cursor: Option<u64>,
+}
+
+impl<T: Clone> ServicePage<T> {
+    pub fn from_slice(all: &[T], page: PageRequest) -> Self {
+        let total = all.len() as u64;
+        let start = page.offset.min(total);
+        let end = start.saturating_add(page.limit).min(total);
+
+        ServicePage {
+            items: all[start as usize..end as usize].to_vec(),
+            total,
+            next_cursor: if end < total { Some(end) } else { None },
+        }
+    }
+}
+
+fn default_page_limit() -> u64 {
+    DEFAULT_PAGE_LIMIT
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{PageRequest, ServicePage};
+
+    #[test]
+    fn test_merge_page_into_payload() {
+        let page = PageRequest {
+            offset: 5,
+            limit:  2,
+        };
+
+        let payload = page.merge_into_payload("").unwrap();
+        assert_eq!(serde_json::from_str::<PageRequest>(&payload).unwrap(), page);
+
+        let payload = page
+            .merge_into_payload(r#"{"user": "alice", "offset": 1}"#)
+            .unwrap();
+        let value: serde_json::Value = serde_json::from_str(&payload).unwrap();
+        assert_eq!(value["user"], "alice");
+        assert_eq!(value["offset"], 5);
+        assert_eq!(value["limit"], 2);
+
+        assert!(page.merge_into_payload("\"alice\"").is_err());
+    }
+
+    #[test]
+    fn test_service_page_from_slice() {
+        let all: Vec<u64> = (0..25).collect();
+        let page = |offset, limit| ServicePage::from_slice(&all, PageRequest { offset, limit });
+
+        let first = page(0, 10);
+        assert_eq!(first.items, (0..10).collect::<Vec<_>>());
+        assert_eq!(first.total, 25);
+        assert_eq!(first.next_cursor, Some(10));
+
+        let last = page(20, 10);
+        assert_eq!(last.items, (20..25).collect::<Vec<_>>());
+        assert_eq!(last.next_cursor, None);
+
+        assert!(page(30, 10).items.is_empty());
+        assert_eq!(page(0, u64::max_value()).items.len(), 25);
+    }
+}
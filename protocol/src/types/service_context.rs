@@ -42,6 +42,7 @@ pub struct ServiceContext {
     timestamp:       u64,
     events:          Rc<RefCell<Vec<Event>>>,
     canceled:        Rc<RefCell<Option<Reason>>>,
+    out_of_cycles:   Rc<RefCell<bool>>,
 }
 
 impl ServiceContext {
@@ -61,6 +62,7 @@ impl ServiceContext {
             timestamp:       params.timestamp,
             events:          params.events,
             canceled:        Rc::new(RefCell::new(None)),
+            out_of_cycles:   Rc::new(RefCell::new(false)),
         }
     }
 
@@ -86,6 +88,7 @@ impl ServiceContext {
             timestamp: context.get_timestamp(),
             events: Rc::clone(&context.events),
             canceled: Rc::clone(&context.canceled),
+            out_of_cycles: Rc::clone(&context.out_of_cycles),
         }
     }
 
@@ -101,15 +104,32 @@ impl ServiceContext {
         self.events.borrow().clone()
     }
 
+    pub fn events_len(&self) -> usize {
+        self.events.borrow().len()
+    }
+
+    pub fn truncate_events(&self, len: usize) {
+        self.events.borrow_mut().truncate(len)
+    }
+
     pub fn sub_cycles(&self, cycles: u64) -> bool {
         if self.get_cycles_used() + cycles <= self.cycles_limit {
             *self.cycles_used.borrow_mut() = self.get_cycles_used() + cycles;
+            *self.out_of_cycles.borrow_mut() = false;
             true
         } else {
+            *self.out_of_cycles.borrow_mut() = true;
             false
         }
     }
 
+    /// Whether the latest `sub_cycles` call in this transaction, including
+    /// inner service calls, failed because the cycles limit was reached. A
+    /// failure caught by the caller is forgotten once it's charged again.
+    pub fn is_out_of_cycles(&self) -> bool {
+        *self.out_of_cycles.borrow()
+    }
+
     pub fn get_cycles_price(&self) -> u64 {
         self.cycles_price
     }
@@ -214,6 +234,11 @@ mod tests {
 
         ctx.sub_cycles(8);
         assert_eq!(ctx.get_cycles_used(), 18);
+        assert!(!ctx.is_out_of_cycles());
+
+        assert!(!ctx.sub_cycles(100));
+        assert_eq!(ctx.get_cycles_used(), 18);
+        assert!(ctx.is_out_of_cycles());
 
         assert_eq!(ctx.get_cycles_limit(), 100);
         assert_eq!(ctx.get_cycles_price(), 8);
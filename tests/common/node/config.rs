@@ -4,7 +4,9 @@ use std::path::PathBuf;
 
 use serde_derive::Deserialize;
 
-use core_mempool::{DEFAULT_BROADCAST_TXS_INTERVAL, DEFAULT_BROADCAST_TXS_SIZE};
+use core_mempool::{
+    DEFAULT_BROADCAST_CHANNEL_SIZE, DEFAULT_BROADCAST_TXS_INTERVAL, DEFAULT_BROADCAST_TXS_SIZE,
+};
 use protocol::types::Hex;
 
 #[derive(Debug, Deserialize)]
@@ -57,6 +59,10 @@ fn default_broadcast_txs_interval() -> u64 {
     DEFAULT_BROADCAST_TXS_INTERVAL
 }
 
+fn default_broadcast_channel_size() -> usize {
+    DEFAULT_BROADCAST_CHANNEL_SIZE
+}
+
 #[derive(Debug, Deserialize)]
 pub struct ConfigMempool {
     pub pool_size: u64,
@@ -65,6 +71,8 @@ pub struct ConfigMempool {
     pub broadcast_txs_size:     usize,
     #[serde(default = "default_broadcast_txs_interval")]
     pub broadcast_txs_interval: u64,
+    #[serde(default = "default_broadcast_channel_size")]
+    pub broadcast_channel_size: usize,
 }
 
 #[derive(Debug, Deserialize)]
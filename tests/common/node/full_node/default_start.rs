@@ -33,7 +33,7 @@ use core_consensus::{
     OverlordSynchronization, RichBlock, SignedTxsWAL,
 };
 use core_mempool::{
-    DefaultMemPoolAdapter, HashMemPool, MsgPushTxs, NewTxsHandler, PullTxsHandler,
+    BroadcastMode, DefaultMemPoolAdapter, HashMemPool, MsgPushTxs, NewTxsHandler, PullTxsHandler,
     END_GOSSIP_NEW_TXS, RPC_PULL_TXS, RPC_RESP_PULL_TXS,
 };
 use core_network::{DiagnosticEvent, NetworkConfig, NetworkService, PeerId, PeerIdExt};
@@ -201,9 +201,11 @@ pub async fn start<Mapping: 'static + ServiceMapping>(
             Arc::clone(&service_mapping),
             config.mempool.broadcast_txs_size,
             config.mempool.broadcast_txs_interval,
+            config.mempool.broadcast_channel_size,
+            BroadcastMode::Fixed,
         );
     let mempool =
-        Arc::new(HashMemPool::new(consts::MEMPOOL_POOL_SIZE, mempool_adapter, vec![]).await);
+        Arc::new(HashMemPool::new(consts::MEMPOOL_POOL_SIZE, None, mempool_adapter, vec![]).await);
 
     // self private key
     let hex_privkey = hex::decode(config.privkey.as_string_trim0x()).map_err(MainError::FromHex)?;
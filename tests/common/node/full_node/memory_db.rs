@@ -4,7 +4,8 @@ use protocol::{
     async_trait,
     codec::ProtocolCodecSync,
     traits::{
-        IntoIteratorByRef, StorageAdapter, StorageBatchModify, StorageIterator, StorageSchema,
+        IntoIteratorByRef, StorageAdapter, StorageBatch, StorageBatchModify, StorageIterator,
+        StorageSchema,
     },
     Bytes, ProtocolError, ProtocolErrorKind, ProtocolResult,
 };
@@ -256,6 +257,21 @@ impl StorageAdapter for MemoryDB {
         Ok(())
     }
 
+    async fn write_batch(&self, batch: StorageBatch) -> ProtocolResult<()> {
+        let mut db = self.db.write();
+
+        for (category, key, value) in batch.into_modifies() {
+            let db = db.entry(category.to_string()).or_insert_with(HashMap::new);
+
+            match value {
+                Some(value) => db.insert(key.to_vec(), value.to_vec()),
+                None => db.remove(&key.to_vec()),
+            };
+        }
+
+        Ok(())
+    }
+
     fn prepare_iter<'a, 'b: 'a, S: StorageSchema + 'static, P: AsRef<[u8]> + 'a>(
         &'b self,
         _prefix: &P,